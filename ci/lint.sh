@@ -7,4 +7,4 @@ npm run prettier-check
 echo "Checking build..."
 cargo +nightly clippy --all -- -D warnings
 echo "Running tests..."
-cargo +nightly test --all
\ No newline at end of file
+cargo +nightly test --all --all-features
\ No newline at end of file
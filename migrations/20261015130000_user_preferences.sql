@@ -0,0 +1,5 @@
+-- Add migration script here
+CREATE TABLE user_preferences (
+    id BIGINT PRIMARY KEY,
+    timezone TEXT
+);
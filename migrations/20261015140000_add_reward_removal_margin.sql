@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE guild_configs
+    ADD COLUMN reward_removal_margin SMALLINT;
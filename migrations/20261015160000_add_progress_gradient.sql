@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE custom_card ADD COLUMN progress_foreground_2 VARCHAR(7);
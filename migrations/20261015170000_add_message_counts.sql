@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE levels ADD COLUMN messages BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE guild_configs ADD COLUMN count_cooldown_messages BOOLEAN;
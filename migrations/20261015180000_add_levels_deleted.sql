@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE levels_deleted (
+    id BIGINT NOT NULL,
+    guild BIGINT NOT NULL,
+    xp BIGINT NOT NULL,
+    messages BIGINT NOT NULL DEFAULT 0,
+    deleted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (id, guild)
+);
+CREATE INDEX ON levels_deleted (deleted_at);
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE guild_configs ADD COLUMN unranked_card BOOLEAN;
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE guild_configs ADD COLUMN attachment_bonus_xp SMALLINT;
+ALTER TABLE guild_configs ADD COLUMN embed_bonus_xp SMALLINT;
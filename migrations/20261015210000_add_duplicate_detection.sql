@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE cooldowns ADD COLUMN last_content_hash BIGINT;
+ALTER TABLE guild_configs ADD COLUMN ignore_duplicate_messages BOOLEAN;
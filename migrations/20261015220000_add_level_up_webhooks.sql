@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE level_up_webhooks (
+    guild BIGINT PRIMARY KEY,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL
+);
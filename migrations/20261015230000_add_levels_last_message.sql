@@ -0,0 +1,4 @@
+-- Add migration script here
+-- Existing levels start counting from now, since we don't know when they were last active
+ALTER TABLE levels ADD COLUMN last_message TIMESTAMP NOT NULL DEFAULT NOW();
+CREATE INDEX ON levels (guild, last_message);
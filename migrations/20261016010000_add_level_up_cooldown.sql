@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE guild_configs ADD COLUMN level_up_cooldown INT2;
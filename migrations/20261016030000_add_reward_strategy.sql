@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE guild_configs ADD COLUMN reward_strategy TEXT;
+UPDATE guild_configs SET reward_strategy = 'one_at_a_time' WHERE one_at_a_time = TRUE;
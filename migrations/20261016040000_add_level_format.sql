@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE guild_configs ADD COLUMN level_style TEXT;
+ALTER TABLE guild_configs ADD COLUMN level_suffix TEXT;
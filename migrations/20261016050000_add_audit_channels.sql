@@ -0,0 +1,5 @@
+-- Add migration script here
+CREATE TABLE audit_channels (
+    guild BIGINT PRIMARY KEY,
+    channel BIGINT NOT NULL
+);
@@ -0,0 +1,11 @@
+-- Add migration script here
+ALTER TABLE user_preferences ADD COLUMN weekly_summary BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE user_preferences ADD COLUMN summary_sent_at TIMESTAMP;
+-- What each opted-in user's levels looked like when their last summary was sent
+CREATE TABLE weekly_summaries (
+    id BIGINT NOT NULL,
+    guild BIGINT NOT NULL,
+    xp BIGINT NOT NULL,
+    rank BIGINT NOT NULL,
+    PRIMARY KEY (id, guild)
+);
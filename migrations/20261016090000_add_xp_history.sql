@@ -0,0 +1,9 @@
+-- Add migration script here
+-- Each member's XP at the end of every UTC day they earned some, for /rank history
+CREATE TABLE xp_history (
+    id BIGINT NOT NULL,
+    guild BIGINT NOT NULL,
+    day DATE NOT NULL,
+    xp BIGINT NOT NULL,
+    PRIMARY KEY (id, guild, day)
+);
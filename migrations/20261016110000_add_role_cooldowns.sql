@@ -0,0 +1,8 @@
+-- Add migration script here
+-- Message cooldowns for members with specific roles, instead of the guild's cooldown
+CREATE TABLE role_cooldowns (
+    guild BIGINT NOT NULL,
+    role BIGINT NOT NULL,
+    cooldown INT2 NOT NULL,
+    PRIMARY KEY (guild, role)
+);
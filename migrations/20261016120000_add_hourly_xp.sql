@@ -0,0 +1,9 @@
+-- Add migration script here
+-- XP each member gained from messages in every UTC hour they earned some, for today's top gainers
+CREATE TABLE hourly_xp (
+    guild BIGINT NOT NULL,
+    hour TIMESTAMP NOT NULL,
+    id BIGINT NOT NULL,
+    gained BIGINT NOT NULL,
+    PRIMARY KEY (guild, hour, id)
+);
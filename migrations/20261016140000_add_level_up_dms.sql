@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE user_preferences ADD COLUMN level_up_dms BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,17 @@
+-- Add migration script here
+-- Guilds whose levels are reset on a schedule
+CREATE TABLE season_schedules (
+    guild BIGINT PRIMARY KEY,
+    cadence TEXT NOT NULL,
+    next_reset TIMESTAMP NOT NULL
+);
+CREATE INDEX ON season_schedules (next_reset);
+-- Final standings of every season that ended
+CREATE TABLE season_results (
+    guild BIGINT NOT NULL,
+    season INT NOT NULL,
+    id BIGINT NOT NULL,
+    xp BIGINT NOT NULL,
+    rank BIGINT NOT NULL,
+    PRIMARY KEY (guild, season, id)
+);
@@ -0,0 +1,11 @@
+-- Add migration script here
+ALTER TABLE guild_configs ADD COLUMN reaction_xp SMALLINT;
+ALTER TABLE guild_configs ADD COLUMN max_reaction_grants SMALLINT;
+-- Who has earned a message's author reaction XP already, so every member only counts once
+CREATE TABLE reaction_grants (
+    message BIGINT NOT NULL,
+    reactor BIGINT NOT NULL,
+    granted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (message, reactor)
+);
+CREATE INDEX ON reaction_grants (granted_at);
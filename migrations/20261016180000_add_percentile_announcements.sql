@@ -0,0 +1,9 @@
+-- Add migration script here
+ALTER TABLE guild_configs ADD COLUMN percentile_announcements SMALLINT;
+-- The narrowest "top X%" each member has been told they reached, so every step is only announced once
+CREATE TABLE percentile_announcements (
+    guild BIGINT NOT NULL,
+    id BIGINT NOT NULL,
+    percentile SMALLINT NOT NULL,
+    PRIMARY KEY (guild, id)
+);
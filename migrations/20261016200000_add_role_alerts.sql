@@ -0,0 +1,8 @@
+-- Add migration script here
+ALTER TABLE guild_configs ADD COLUMN role_alerts BOOLEAN;
+-- Reward roles the guild owner was already told the bot can't give out, so they're only told once
+CREATE TABLE role_alerts (
+    guild BIGINT NOT NULL,
+    role BIGINT NOT NULL,
+    PRIMARY KEY (guild, role)
+);
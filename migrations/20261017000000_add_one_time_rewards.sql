@@ -0,0 +1,16 @@
+-- Add migration script here
+-- Rewards which are handed out only once, the first time a member reaches their level
+CREATE TABLE one_time_rewards (
+    guild BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    requirement BIGINT NOT NULL,
+    PRIMARY KEY (guild, name)
+);
+-- Who already got each one-time reward. Resetting XP doesn't touch these, so nobody gets a reward twice.
+CREATE TABLE claimed_rewards (
+    guild BIGINT NOT NULL,
+    id BIGINT NOT NULL,
+    reward TEXT NOT NULL,
+    claimed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (guild, id, reward)
+);
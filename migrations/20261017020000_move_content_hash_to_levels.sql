@@ -0,0 +1,5 @@
+-- Add migration script here
+-- Cooldowns are cleaned up as they expire, levels aren't. The old hashes came from a hasher which
+-- isn't stable between builds, so they aren't worth carrying over.
+ALTER TABLE cooldowns DROP COLUMN last_content_hash;
+ALTER TABLE levels ADD COLUMN last_content_hash BIGINT;
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Levels tombstoned before this don't know when their member was last active
+ALTER TABLE levels_deleted ADD COLUMN last_message TIMESTAMP;
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Repeating a message only counts as a duplicate for a while after the last one
+ALTER TABLE levels ADD COLUMN last_content_at TIMESTAMP;
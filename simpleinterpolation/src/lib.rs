@@ -60,7 +60,10 @@ impl Interpolation {
     /// interpolation values from. Said values *must* be strings.
     /// # Errors
     /// If an interpolation value is not found, it is added to the [`RenderError`].
-    pub fn try_render(&self, args: &HashMap<Cow<str>, Cow<str>>) -> Result<String, RenderError> {
+    pub fn try_render(
+        &self,
+        args: &HashMap<Cow<str>, Cow<str>>,
+    ) -> Result<String, RenderError<'_>> {
         let mut output = self.output_string();
         for (raw, interpolation_key) in &self.parts {
             output.push_str(raw);
@@ -182,7 +185,7 @@ impl InterpolationCompiler {
             self.parts.push((to_push, ident));
         } else {
             self.next.push(ch);
-        };
+        }
         self.index += 1;
         Ok(())
     }
@@ -267,7 +270,7 @@ impl std::fmt::Display for RenderError<'_> {
                         write!(f, "{item}")?;
                     } else {
                         write!(f, "{item}, ")?;
-                    };
+                    }
                 }
                 Ok(())
             }
@@ -278,6 +281,7 @@ impl std::fmt::Display for RenderError<'_> {
 impl std::error::Error for RenderError<'_> {}
 
 #[cfg(test)]
+#[allow(clippy::literal_string_with_formatting_args)]
 mod tests {
     use std::collections::HashMap;
 
@@ -328,6 +332,16 @@ mod tests {
         );
     }
     #[test]
+    fn custom_emoji_pass_through() {
+        let template = "<:star:123456789012345678> {interpolation} <a:party:987654321098765432>";
+        let interpolation = Interpolation::new(template).unwrap();
+        assert_eq!(
+            interpolation.render(&get_example_args()),
+            "<:star:123456789012345678> Interpolation <a:party:987654321098765432>"
+        );
+        assert_eq!(template, interpolation.input_value());
+    }
+    #[test]
     fn basic_roundtrip() {
         let roundtrip = "This is an example string for {interpolation}!";
         let interpolation = Interpolation::new(roundtrip).unwrap();
@@ -13,7 +13,7 @@ extern crate tracing;
 fn main() -> Result<(), Error> {
     tracing_subscriber::fmt().json().init();
     info!(
-        version = xpd_common::CURRENT_GIT_SHA,
+        version = %xpd_common::build_info(env!("CARGO_PKG_VERSION")),
         "Starting experienced cleanup!"
     );
     let database_url = valk_utils::get_var("DATABASE_URL");
@@ -44,6 +44,21 @@ async fn async_main(database_url: &str) -> Result<(), Error> {
         info!(%guild, "Cleaned guild");
     }
     cleanup_cooldowns(&mut conn).await?;
+    let undo_days =
+        valk_utils::parse_var_or("RESET_UNDO_DAYS", xpd_common::DEFAULT_RESET_UNDO_DAYS);
+    let purged = xpd_database::purge_deleted_levels(&mut conn, undo_days).await?;
+    info!(
+        purged,
+        undo_days, "Purged reset levels past their undo window"
+    );
+    let pruned = xpd_database::prune_xp_history(&mut conn, xpd_common::XP_HISTORY_DAYS).await?;
+    info!(pruned, "Pruned old XP history");
+    let pruned = xpd_database::prune_hourly_xp(&mut conn, xpd_common::HOURLY_XP_HOURS).await?;
+    info!(pruned, "Pruned old hourly XP");
+    let pruned =
+        xpd_database::prune_reaction_grants(&mut conn, xpd_common::REACTION_XP_MAX_AGE_HOURS)
+            .await?;
+    info!(pruned, "Pruned old reaction grants");
     info!("Done!");
     Ok(())
 }
@@ -72,6 +87,21 @@ async fn cleanup_guild(
 ) -> Result<(), Error> {
     debug!(%guild, "Deleting guild configs");
     xpd_database::delete_guild_config(db.as_mut(), guild).await?;
+    debug!(%guild, "Deleting guild level-up webhook");
+    xpd_database::delete_level_up_webhook(db.as_mut(), guild).await?;
+    debug!(%guild, "Deleting guild audit channel");
+    xpd_database::delete_audit_channel(db.as_mut(), guild).await?;
+    debug!(%guild, "Deleting guild season schedule and results");
+    xpd_database::delete_season_schedule(db.as_mut(), guild).await?;
+    xpd_database::delete_season_results(db.as_mut(), guild).await?;
+    debug!(%guild, "Deleting guild percentile announcements");
+    xpd_database::delete_percentile_announcements(db.as_mut(), guild).await?;
+    debug!(%guild, "Deleting guild one-time rewards and claims");
+    xpd_database::delete_one_time_rewards(db.as_mut(), guild).await?;
+    debug!(%guild, "Deleting guild reward role alerts");
+    xpd_database::delete_role_alerts(db.as_mut(), guild).await?;
+    debug!(%guild, "Deleting guild role cooldowns");
+    xpd_database::delete_guild_role_cooldowns(db.as_mut(), guild).await?;
     debug!(%guild, "Deleting guild card customizations");
     xpd_database::delete_card_customizations(db.as_mut(), guild.cast()).await?;
     debug!(%guild, "Deleting guild rewards");
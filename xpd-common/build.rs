@@ -1,6 +1,7 @@
 use std::{
     process::{Command, ExitStatus},
     string::FromUtf8Error,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 fn main() {
@@ -13,6 +14,11 @@ fn main() {
     };
 
     println!("cargo:rustc-env=GIT_HASH_EXPERIENCED={}", commit_msg);
+
+    let built_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    println!("cargo:rustc-env=BUILD_TIMESTAMP_EXPERIENCED={built_at}");
 }
 
 fn get_sha() -> Result<String, Error> {
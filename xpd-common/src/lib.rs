@@ -3,12 +3,14 @@
 use std::{
     borrow::Cow,
     fmt::{Debug, Display, Formatter},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use simpleinterpolation::Interpolation;
 use twilight_cache_inmemory::ResourceType;
 use twilight_gateway::EventTypeFlags;
 use twilight_model::{
+    channel::message::AllowedMentions,
     gateway::Intents,
     guild::Member,
     id::{
@@ -20,8 +22,49 @@ use twilight_model::{
 };
 
 pub const CURRENT_GIT_SHA: &str = env!("GIT_HASH_EXPERIENCED");
+const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP_EXPERIENCED");
 pub const DISCORD_EPOCH_MS: i64 = 1_420_070_400_000;
 pub const DISCORD_EPOCH_SECS: i64 = DISCORD_EPOCH_MS / 1000;
+/// Accent color of experienced's own embeds
+pub const THEME_COLOR: u32 = 0x72_89DA;
+
+/// Which build of experienced is running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// Git commit the build was made from
+    pub sha: &'static str,
+    /// Unix timestamp of when `xpd-common` was compiled
+    pub built_at: u64,
+    /// Version of the binary which is running
+    pub version: &'static str,
+}
+
+impl Display for BuildInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{} (git-{})", self.version, self.sha)
+    }
+}
+
+/// Version, commit and build time of this build, for anything that reports which version is running.
+///
+/// Pass the binary's own `CARGO_PKG_VERSION` as `version`, since this crate's is unrelated to it.
+#[must_use]
+pub fn build_info(version: &'static str) -> BuildInfo {
+    BuildInfo {
+        sha: CURRENT_GIT_SHA,
+        built_at: BUILD_TIMESTAMP.parse().unwrap_or(0),
+        version,
+    }
+}
+
+/// Mentions which may only ping `user`, and never any roles, `@everyone` or `@here`.
+#[must_use]
+pub fn ping_only_user(user: Id<UserMarker>) -> AllowedMentions {
+    AllowedMentions {
+        users: vec![user],
+        ..AllowedMentions::default()
+    }
+}
 
 pub trait DisplayName {
     #[must_use]
@@ -64,6 +107,9 @@ pub struct MemberDisplayInfo {
     pub avatar: Option<ImageHash>,
     pub local_avatar: Option<ImageHash>,
     pub bot: bool,
+    /// `0` for users who moved to unique usernames, and `None` when only the member's nickname is
+    /// known, so there's no username to tag them with
+    pub discriminator: Option<u16>,
 }
 
 impl From<User> for MemberDisplayInfo {
@@ -76,6 +122,7 @@ impl From<User> for MemberDisplayInfo {
             avatar: value.avatar,
             local_avatar: None,
             bot: value.bot,
+            discriminator: Some(value.discriminator),
         }
     }
 }
@@ -90,6 +137,7 @@ impl From<Member> for MemberDisplayInfo {
             avatar: value.user.avatar,
             local_avatar: value.avatar,
             bot: value.user.bot,
+            discriminator: Some(value.user.discriminator),
         }
     }
 }
@@ -99,9 +147,62 @@ impl MemberDisplayInfo {
     pub fn with_nick(self, nick: Option<String>) -> Self {
         Self { nick, ..self }
     }
+
+    /// This member's unique tag, see [`format_user_tag`], or their display name if their
+    /// username isn't known
+    #[must_use]
+    pub fn tag(&self) -> String {
+        self.discriminator.map_or_else(
+            || self.display_name().to_string(),
+            |discriminator| user_tag(&self.name, discriminator),
+        )
+    }
+
+    /// URL of the avatar shown for this member, preferring their server avatar in `guild`.
+    #[must_use]
+    pub fn avatar_url(&self, guild: Option<Id<GuildMarker>>) -> String {
+        match (guild, self.local_avatar) {
+            (Some(guild), Some(hash)) => format!(
+                "https://cdn.discordapp.com/guilds/{guild}/users/{}/avatars/{hash}.png",
+                self.id
+            ),
+            _ => user_avatar_url(self.id, self.avatar),
+        }
+    }
+}
+
+/// A user's unique tag, which tells them apart from others with the same display name.
+///
+/// That's `@username` for users who moved to unique usernames, and `name#1234` for the
+/// legacy accounts which still have a discriminator.
+#[must_use]
+pub fn format_user_tag(user: &User) -> String {
+    user_tag(&user.name, user.discriminator)
+}
+
+fn user_tag(name: &str, discriminator: u16) -> String {
+    if discriminator == 0 {
+        format!("@{name}")
+    } else {
+        format!("{name}#{discriminator:04}")
+    }
+}
+
+/// URL of a user's global avatar, or the default avatar Discord gives them if they have none.
+#[must_use]
+pub fn user_avatar_url(user_id: Id<UserMarker>, avatar: Option<ImageHash>) -> String {
+    avatar.map_or_else(
+        || {
+            format!(
+                "https://cdn.discordapp.com/embed/avatars/{}.png",
+                (user_id.get() >> 22) % 6
+            )
+        },
+        |hash| format!("https://cdn.discordapp.com/avatars/{user_id}/{hash}.png"),
+    )
 }
 
-pub const TEMPLATE_VARIABLES: [&str; 9] = [
+pub const TEMPLATE_VARIABLES: [&str; 10] = [
     "user_id",
     "user_mention",
     "user_username",
@@ -111,11 +212,50 @@ pub const TEMPLATE_VARIABLES: [&str; 9] = [
     "level",
     "old_xp",
     "xp",
+    "earned_role",
 ];
 pub const DEFAULT_MAX_XP_PER_MESSAGE: i16 = 25;
 pub const DEFAULT_MIN_XP_PER_MESSAGE: i16 = 15;
 pub const DEFAULT_MESSAGE_COOLDOWN: i16 = 60;
 pub const MAX_MESSAGE_COOLDOWN: i16 = 28800;
+pub const MAX_CONTENT_BONUS_XP: i16 = 100;
+/// Attachments or embeds past this many on one message don't earn any more bonus XP
+pub const MAX_CONTENT_BONUS_ITEMS: usize = 3;
+/// How many days a guild reset can be undone for before the tombstoned levels are purged
+pub const DEFAULT_RESET_UNDO_DAYS: f64 = 7.0;
+/// How many reward roles one guild can have, unless the bot is configured otherwise.
+/// Every level-up checks each of a guild's rewards, so this keeps that cheap.
+pub const DEFAULT_MAX_REWARDS: usize = 50;
+/// `xp_lockdown_until` for a lockdown which only ends when it's turned off
+pub const XP_LOCKDOWN_INDEFINITE: i64 = i64::MAX;
+/// How many days of daily XP history are kept for `/rank history`
+pub const XP_HISTORY_DAYS: i32 = 90;
+/// How many hours of hourly XP are kept, enough to cover all of today in every timezone
+pub const HOURLY_XP_HOURS: i32 = 48;
+/// Reactions per message which earn XP, for guilds which haven't picked their own cap
+pub const DEFAULT_MAX_REACTION_GRANTS: i16 = 10;
+/// Reactions on messages older than this many hours don't earn XP, so old messages can't be farmed.
+/// Who already reacted is only remembered this long.
+pub const REACTION_XP_MAX_AGE_HOURS: i32 = 72;
+/// The "top X%" steps members can be told they've reached, from widest to narrowest
+pub const PERCENTILE_STEPS: [i16; 5] = [50, 25, 10, 5, 1];
+/// Guilds with fewer ranked members than this don't get percentile announcements,
+/// because being in the top 10% of a handful of members doesn't mean much.
+pub const PERCENTILE_MIN_MEMBERS: i64 = 20;
+
+/// The narrowest of the [`PERCENTILE_STEPS`] no wider than `widest` that someone ranked `rank`
+/// (starting at 1) out of `members` is in, if any.
+#[must_use]
+pub fn percentile_step(rank: i64, members: i64, widest: i16) -> Option<i16> {
+    if members < PERCENTILE_MIN_MEMBERS || rank < 1 {
+        return None;
+    }
+    PERCENTILE_STEPS
+        .into_iter()
+        .filter(|step| *step <= widest)
+        .take_while(|step| rank * 100 <= members * i64::from(*step))
+        .last()
+}
 
 #[derive(Default, Debug)]
 pub struct GuildConfig {
@@ -126,19 +266,375 @@ pub struct GuildConfig {
     pub min_xp_per_message: Option<i16>,
     pub max_xp_per_message: Option<i16>,
     pub cooldown: Option<i16>,
+    pub prune_deleted_rewards: Option<bool>,
+    /// How many levels below a reward's requirement a member may drop before losing it.
+    /// If unset, reward roles are never taken away for losing XP.
+    pub reward_removal_margin: Option<i16>,
+    /// XP members start with when they first earn XP in the guild.
+    pub starting_xp: Option<i64>,
+    /// Whether messages sent during the XP cooldown still add to members' message counts.
+    pub count_cooldown_messages: Option<bool>,
+    /// Whether /rank shows a card for members without XP instead of a message.
+    pub unranked_card: Option<bool>,
+    /// Bonus XP for each attachment on a message that earns XP.
+    pub attachment_bonus_xp: Option<i16>,
+    /// Bonus XP for each embed on a message that earns XP.
+    pub embed_bonus_xp: Option<i16>,
+    /// Whether a message identical to the member's last XP-earning message is denied XP.
+    pub ignore_duplicate_messages: Option<bool>,
+    /// Minimum seconds between level-up messages for one member, so big jumps don't spam the channel
+    pub level_up_cooldown: Option<i16>,
+    /// Whether level-ups only hand out reward roles, without a level-up message
+    pub silent_level_ups: Option<bool>,
+    /// How reward roles are handed out as members level past them.
+    /// Use [`GuildConfig::reward_strategy`] to read it, which falls back to `one_at_a_time`.
+    pub reward_strategy: Option<RewardStrategy>,
+    /// How levels are written on cards and level-up messages
+    pub level_style: Option<LevelStyle>,
+    /// Text shown right after levels on cards and level-up messages
+    pub level_suffix: Option<String>,
+    /// Whether level-up messages are sent as plain text or as an embed
+    pub level_up_style: Option<LevelUpStyle>,
+    /// XP every XP-earning message gives, instead of a random amount between the minimum and maximum.
+    /// Zero turns this off again.
+    pub xp_per_message: Option<i16>,
+    /// Unix time in seconds until which no one earns XP from messages. Zero means no lockdown,
+    /// and [`XP_LOCKDOWN_INDEFINITE`] one that lasts until it's turned off.
+    pub xp_lockdown_until: Option<i64>,
+    /// Whether bots get rank cards and can be given XP like any other member
+    pub rank_bots: Option<bool>,
+    /// XP a message's author earns for each member who reacts to it. Zero or unset turns reaction XP off.
+    pub reaction_xp: Option<i16>,
+    /// Most reactions on one message which earn its author XP
+    pub max_reaction_grants: Option<i16>,
+    /// Members are told when they reach the top this many percent of the guild, and each smaller
+    /// step of [`PERCENTILE_STEPS`] after that. Zero or unset turns percentile announcements off.
+    pub percentile_announcements: Option<i16>,
+    /// Slash commands members can't use in this guild, see `/commands`
+    pub disabled_commands: Option<Vec<String>>,
+    /// Whether the guild owner is sent a DM when a reward role can't be given out, once per role
+    pub role_alerts: Option<bool>,
+    /// Minutes members must have been in the guild before they start earning XP
+    pub join_grace: Option<i16>,
+    /// Days a member's Discord account must have existed before they start earning XP
+    pub min_account_age: Option<i16>,
+}
+
+impl GuildConfig {
+    /// The reward strategy in effect, honoring the legacy `one_at_a_time` flag if no strategy is set.
+    #[must_use]
+    pub const fn reward_strategy(&self) -> RewardStrategy {
+        match (self.reward_strategy, self.one_at_a_time) {
+            (Some(strategy), _) => strategy,
+            (None, Some(true)) => RewardStrategy::OneAtATime,
+            (None, _) => RewardStrategy::KeepAll,
+        }
+    }
+
+    /// The smallest and largest XP a message can earn, before content bonuses.
+    /// A flat `xp_per_message` wins over the range, which falls back to the defaults.
+    #[must_use]
+    pub fn message_xp_range(&self) -> (i16, i16) {
+        match self.xp_per_message {
+            Some(xp) if xp > 0 => (xp, xp),
+            _ => (
+                self.min_xp_per_message
+                    .unwrap_or(DEFAULT_MIN_XP_PER_MESSAGE),
+                self.max_xp_per_message
+                    .unwrap_or(DEFAULT_MAX_XP_PER_MESSAGE),
+            ),
+        }
+    }
+
+    /// Whether XP gain is locked down at `now`, in Unix seconds.
+    #[must_use]
+    pub fn xp_locked(&self, now: i64) -> bool {
+        self.xp_lockdown_until.is_some_and(|until| until > now)
+    }
+
+    /// Whether a member who joined at `joined_at` is still too new to earn XP at `now`, both in unix seconds
+    #[must_use]
+    pub fn in_join_grace(&self, joined_at: i64, now: i64) -> bool {
+        let grace = i64::from(self.join_grace.unwrap_or(0).max(0)) * 60;
+        joined_at + grace > now
+    }
+
+    /// Whether an account created at `created_at` is still too new to earn XP at `now`, both in unix seconds.
+    /// The creation time is in every user ID, so this never needs to ask Discord.
+    #[must_use]
+    pub fn account_too_new(&self, created_at: i64, now: i64) -> bool {
+        let min_age = i64::from(self.min_account_age.unwrap_or(0).max(0)) * 86_400;
+        created_at + min_age > now
+    }
+
+    /// Whether members can't use the slash command with this name here
+    #[must_use]
+    pub fn command_disabled(&self, name: &str) -> bool {
+        self.disabled_commands
+            .as_ref()
+            .is_some_and(|commands| commands.iter().any(|command| command == name))
+    }
+
+    fn display_disabled_commands(&self) -> Cow<'static, str> {
+        match self.disabled_commands.as_deref() {
+            None | Some([]) => Cow::Borrowed("none"),
+            Some(commands) => Cow::Owned(
+                commands
+                    .iter()
+                    .map(|name| format!("`/{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        }
+    }
+
+    fn display_percentile_announcements(&self) -> Cow<'static, str> {
+        let widest = self
+            .percentile_announcements
+            .and_then(|top| PERCENTILE_STEPS.into_iter().find(|step| *step <= top));
+        widest.map_or(Cow::Borrowed("off"), |widest| {
+            Cow::Owned(format!("from the top {widest}%"))
+        })
+    }
+
+    fn display_lockdown(&self) -> Cow<'static, str> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| {
+                i64::try_from(since.as_secs()).unwrap_or(i64::MAX)
+            });
+        match self.xp_lockdown_until {
+            Some(XP_LOCKDOWN_INDEFINITE) => Cow::Borrowed("on until turned off"),
+            Some(until) if until > now => Cow::Owned(format!("on until <t:{until}:f>")),
+            _ => Cow::Borrowed("off"),
+        }
+    }
+
+    /// A level written in this guild's chosen style, followed by its suffix.
+    #[must_use]
+    pub fn display_level(&self, level: u64) -> String {
+        let mut display = format_level(level, self.level_style.unwrap_or_default());
+        if let Some(suffix) = &self.level_suffix {
+            display.push_str(suffix);
+        }
+        display
+    }
+}
+
+/// How level-up messages are sent
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LevelUpStyle {
+    /// The message is sent as plain text.
+    #[default]
+    Plain,
+    /// The message is put in an embed with the member's avatar.
+    Embed,
+}
+
+impl LevelUpStyle {
+    /// The name this style is stored and shown as.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Plain => "plain",
+            Self::Embed => "embed",
+        }
+    }
+
+    /// Parses a stored style name, returning `None` for unknown names.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "plain" => Some(Self::Plain),
+            "embed" => Some(Self::Embed),
+            _ => None,
+        }
+    }
+}
+
+impl Display for LevelUpStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How levels are written for display
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LevelStyle {
+    /// Plain numbers, like `12`.
+    #[default]
+    Decimal,
+    /// Roman numerals, like `XII`, for levels [`MIN_ROMAN_LEVEL`] to [`MAX_ROMAN_LEVEL`].
+    Roman,
+}
+
+impl LevelStyle {
+    /// The name this style is stored and shown as.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Decimal => "decimal",
+            Self::Roman => "roman",
+        }
+    }
+
+    /// Parses a stored style name, returning `None` for unknown names.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "decimal" => Some(Self::Decimal),
+            "roman" => Some(Self::Roman),
+            _ => None,
+        }
+    }
+}
+
+/// How often a guild's levels are reset to start a new season
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SeasonCadence {
+    Weekly,
+    #[default]
+    Monthly,
+    Quarterly,
+}
+
+impl SeasonCadence {
+    /// The name this cadence is stored and shown as.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+            Self::Quarterly => "quarterly",
+        }
+    }
+
+    /// Parses a stored cadence name, returning `None` for unknown names.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "weekly" => Some(Self::Weekly),
+            "monthly" => Some(Self::Monthly),
+            "quarterly" => Some(Self::Quarterly),
+            _ => None,
+        }
+    }
+
+    /// The length of a season, as months and days.
+    /// Months are kept apart so seasons follow the calendar rather than a fixed day count.
+    #[must_use]
+    pub const fn interval(self) -> (i32, i32) {
+        match self {
+            Self::Weekly => (0, 7),
+            Self::Monthly => (1, 0),
+            Self::Quarterly => (3, 0),
+        }
+    }
+}
+
+impl Display for SeasonCadence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Roman numerals have no zero, so lower levels are written as plain numbers.
+pub const MIN_ROMAN_LEVEL: u64 = 1;
+/// Roman numerals past this need overlines, so higher levels are written as plain numbers.
+pub const MAX_ROMAN_LEVEL: u64 = 3999;
+/// Longest suffix a guild may put after levels, in characters
+pub const MAX_LEVEL_SUFFIX_LEN: usize = 16;
+
+/// Writes a level in the given style, falling back to decimal where the style can't show it.
+#[must_use]
+pub fn format_level(level: u64, style: LevelStyle) -> String {
+    match style {
+        LevelStyle::Roman if (MIN_ROMAN_LEVEL..=MAX_ROMAN_LEVEL).contains(&level) => {
+            to_roman(level)
+        }
+        LevelStyle::Decimal | LevelStyle::Roman => level.to_string(),
+    }
+}
+
+fn to_roman(mut value: u64) -> String {
+    const NUMERALS: [(u64, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut output = String::new();
+    for (amount, numeral) in NUMERALS {
+        while value >= amount {
+            output.push_str(numeral);
+            value -= amount;
+        }
+    }
+    output
+}
+
+/// How reward roles are handed out as members level past them
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RewardStrategy {
+    /// Members keep every reward role they have earned.
+    #[default]
+    KeepAll,
+    /// Members only keep their highest reward role.
+    OneAtATime,
+    /// Members keep every reward role except the one directly below their highest.
+    Ladder,
+}
+
+impl RewardStrategy {
+    /// The name this strategy is stored and shown as.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::KeepAll => "keep_all",
+            Self::OneAtATime => "one_at_a_time",
+            Self::Ladder => "ladder",
+        }
+    }
+
+    /// Parses a stored strategy name, returning `None` for unknown names.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "keep_all" => Some(Self::KeepAll),
+            "one_at_a_time" => Some(Self::OneAtATime),
+            "ladder" => Some(Self::Ladder),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RewardStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+const fn display_flag(flag: Option<bool>) -> &'static str {
+    match flag {
+        None => "unset",
+        Some(true) => "true",
+        Some(false) => "false",
+    }
 }
 
 impl Display for GuildConfig {
+    #[allow(clippy::too_many_lines)]
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        writeln!(
-            f,
-            "One reward role at a time: {}",
-            match self.one_at_a_time {
-                None => "unset",
-                Some(true) => "true",
-                Some(false) => "false",
-            }
-        )?;
+        writeln!(f, "Reward strategy: {}", self.reward_strategy())?;
         writeln!(
             f,
             "Level-up message: {}",
@@ -166,11 +662,97 @@ impl Display for GuildConfig {
             self.min_xp_per_message
                 .unwrap_or(DEFAULT_MIN_XP_PER_MESSAGE)
         )?;
-        write!(
+        writeln!(
             f,
             "Cooldown (seconds): {}",
             self.cooldown.unwrap_or(DEFAULT_MESSAGE_COOLDOWN)
         )?;
+        writeln!(
+            f,
+            "Remove deleted reward roles: {}",
+            display_flag(self.prune_deleted_rewards)
+        )?;
+        writeln!(
+            f,
+            "Reward removal margin (levels): {}",
+            self.reward_removal_margin
+                .map_or(Cow::Borrowed("never remove"), |v| Cow::Owned(v.to_string()))
+        )?;
+        writeln!(f, "Starting XP: {}", self.starting_xp.unwrap_or(0))?;
+        writeln!(
+            f,
+            "Count messages sent during cooldown: {}",
+            display_flag(self.count_cooldown_messages)
+        )?;
+        writeln!(
+            f,
+            "Show cards for unranked members: {}",
+            display_flag(self.unranked_card)
+        )?;
+        writeln!(
+            f,
+            "Attachment bonus XP: {}",
+            self.attachment_bonus_xp.unwrap_or(0)
+        )?;
+        writeln!(f, "Embed bonus XP: {}", self.embed_bonus_xp.unwrap_or(0))?;
+        writeln!(
+            f,
+            "Ignore duplicate messages: {}",
+            display_flag(self.ignore_duplicate_messages)
+        )?;
+        writeln!(
+            f,
+            "Level-up message cooldown (seconds): {}",
+            self.level_up_cooldown.unwrap_or(0)
+        )?;
+        writeln!(
+            f,
+            "Silent level-ups (roles only): {}",
+            display_flag(self.silent_level_ups)
+        )?;
+        writeln!(f, "Level format: level {}", self.display_level(12))?;
+        writeln!(
+            f,
+            "Level-up style: {}",
+            self.level_up_style.unwrap_or_default()
+        )?;
+        writeln!(
+            f,
+            "Flat XP per message: {}",
+            self.xp_per_message
+                .filter(|xp| *xp > 0)
+                .map_or(Cow::Borrowed("unset"), |xp| Cow::Owned(xp.to_string()))
+        )?;
+        writeln!(f, "XP lockdown: {}", self.display_lockdown())?;
+        writeln!(f, "Rank bots: {}", display_flag(self.rank_bots))?;
+        writeln!(f, "XP per reaction: {}", self.reaction_xp.unwrap_or(0))?;
+        writeln!(
+            f,
+            "Most reactions earning XP per message: {}",
+            self.max_reaction_grants
+                .unwrap_or(DEFAULT_MAX_REACTION_GRANTS)
+        )?;
+        writeln!(
+            f,
+            "Percentile announcements: {}",
+            self.display_percentile_announcements()
+        )?;
+        writeln!(f, "Disabled commands: {}", self.display_disabled_commands())?;
+        writeln!(
+            f,
+            "DM the owner about reward roles I can't give: {}",
+            display_flag(self.role_alerts)
+        )?;
+        writeln!(
+            f,
+            "Minutes new members wait before earning XP: {}",
+            self.join_grace.unwrap_or(0)
+        )?;
+        write!(
+            f,
+            "Days an account must exist before earning XP: {}",
+            self.min_account_age.unwrap_or(0)
+        )?;
         Ok(())
     }
 }
@@ -182,7 +764,14 @@ pub struct UserStatus {
     pub xp: i64,
 }
 
-#[derive(Debug)]
+/// A message cooldown, in seconds, for members with a role, overriding the guild's cooldown
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RoleCooldown {
+    pub role: Id<RoleMarker>,
+    pub cooldown: i16,
+}
+
+#[derive(Debug, Copy, Clone)]
 pub struct RoleReward {
     pub id: Id<RoleMarker>,
     pub requirement: i64,
@@ -194,6 +783,194 @@ pub fn compare_rewards_requirement(a: &RoleReward, b: &RoleReward) -> std::cmp::
     a.requirement.cmp(&b.requirement)
 }
 
+/// How a member's roles change to match the reward roles for their level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RewardRoleChanges {
+    /// Every role the member should hold afterwards
+    pub total_roles: Vec<Id<RoleMarker>>,
+    /// Reward roles which were added or taken away
+    pub changed_roles: Vec<Id<RoleMarker>>,
+    /// The highest reward role newly granted, if any
+    pub earned_role: Option<Id<RoleMarker>>,
+}
+
+impl RewardRoleChanges {
+    /// Work out a member's roles at `level`, following the guild's reward strategy and taking
+    /// away lapsed rewards if the guild opted into that.
+    ///
+    /// `rewards` must be sorted with [`compare_rewards_requirement`].
+    #[must_use]
+    pub fn new(
+        guild_config: &GuildConfig,
+        member_roles: &[Id<RoleMarker>],
+        rewards: &[RoleReward],
+        level: i64,
+    ) -> Self {
+        let mut roles = get_reward_idx(rewards, level).map_or_else(
+            || Self::unchanged(member_roles),
+            |reward_idx| get_role_changes(guild_config, member_roles, rewards, reward_idx),
+        );
+        roles.revoke_lapsed(guild_config, member_roles, rewards, level);
+        roles
+    }
+
+    fn unchanged(member_roles: &[Id<RoleMarker>]) -> Self {
+        Self {
+            total_roles: member_roles.to_vec(),
+            changed_roles: Vec::new(),
+            earned_role: None,
+        }
+    }
+
+    /// Take away reward roles the member has dropped too far below, if the guild opted into that.
+    /// The margin keeps members hovering around a requirement from having the role flap.
+    fn revoke_lapsed(
+        &mut self,
+        guild_config: &GuildConfig,
+        member_roles: &[Id<RoleMarker>],
+        rewards: &[RoleReward],
+        level: i64,
+    ) {
+        let Some(margin) = guild_config.reward_removal_margin else {
+            return;
+        };
+        let keep_below = level.saturating_add(margin.into());
+        self.total_roles.retain(|role| {
+            let lapsed = rewards
+                .iter()
+                .any(|reward| reward.id == *role && reward.requirement > keep_below);
+            if lapsed && member_roles.contains(role) {
+                self.changed_roles.push(*role);
+            }
+            !lapsed
+        });
+    }
+}
+
+fn get_reward_idx(rewards: &[RoleReward], level: i64) -> Option<usize> {
+    let mut reward_idx = None;
+    for (idx, data) in rewards.iter().enumerate() {
+        if data.requirement > level {
+            break;
+        }
+        reward_idx = Some(idx);
+    }
+    reward_idx
+}
+
+fn get_role_changes(
+    guild_config: &GuildConfig,
+    member_roles: &[Id<RoleMarker>],
+    rewards: &[RoleReward],
+    reward_idx: usize,
+) -> RewardRoleChanges {
+    // Rewards the member should hold, and lower rewards they have outgrown and should lose.
+    let (achieved_roles, outgrown_roles) = match guild_config.reward_strategy() {
+        RewardStrategy::KeepAll => (&rewards[..=reward_idx], &[][..]),
+        RewardStrategy::OneAtATime => (&rewards[reward_idx..=reward_idx], &rewards[..reward_idx]),
+        RewardStrategy::Ladder => (
+            &rewards[..=reward_idx],
+            &rewards[reward_idx.saturating_sub(1)..reward_idx],
+        ),
+    };
+    let outgrown = |id: &Id<RoleMarker>| outgrown_roles.iter().any(|v| v.id == *id);
+    let roles_to_add = achieved_roles
+        .iter()
+        .map(|v| v.id)
+        .filter(|id| !member_roles.contains(id) && !outgrown(id));
+    // Rewards are sorted, so the last achieved role the member doesn't have yet is the best new one.
+    let earned_role = roles_to_add.clone().next_back();
+
+    let mut changed_roles = Vec::with_capacity(8);
+
+    let total_roles = member_roles
+        .iter()
+        .copied()
+        .chain(roles_to_add)
+        // Outgrown roles are dropped, and both they and roles added in the chain
+        // are recorded in the changelist.
+        .filter(|v| {
+            let keeper = !outgrown(v);
+            if !keeper || !member_roles.contains(v) {
+                changed_roles.push(*v);
+            }
+            keeper
+        })
+        .collect();
+
+    RewardRoleChanges {
+        total_roles,
+        changed_roles,
+        earned_role,
+    }
+}
+
+/// The thousands separator used by a Discord locale, like `en-US` or `de`.
+/// Unknown or missing locales get a neutral non-breaking space.
+#[must_use]
+pub fn thousands_separator(locale: Option<&str>) -> char {
+    match locale.unwrap_or_default() {
+        "en-US" | "en-GB" | "es-419" | "hi" | "th" | "ja" | "ko" | "zh-CN" | "zh-TW" => ',',
+        "da" | "de" | "el" | "es-ES" | "hr" | "id" | "it" | "nl" | "pt-BR" | "ro" | "tr" | "vi" => {
+            '.'
+        }
+        _ => '\u{a0}',
+    }
+}
+
+/// The words for "rank" and "level" on rank cards in a Discord locale, like `en-US` or `de`.
+/// Locales without a translation get English.
+#[must_use]
+pub fn card_labels(locale: Option<&str>) -> (&'static str, &'static str) {
+    match locale.unwrap_or_default() {
+        "cs" => ("POŘADÍ", "ÚROVEŇ"),
+        "da" | "fr" | "no" => ("RANG", "NIVEAU"),
+        "de" | "nl" => ("RANG", "LEVEL"),
+        "es-ES" | "es-419" => ("RANGO", "NIVEL"),
+        "fi" => ("SIJA", "TASO"),
+        "it" => ("RANGO", "LIVELLO"),
+        "pl" => ("RANGA", "POZIOM"),
+        "pt-BR" => ("RANK", "NÍVEL"),
+        "ro" => ("RANG", "NIVEL"),
+        "sv-SE" => ("RANK", "NIVÅ"),
+        _ => ("RANK", "LEVEL"),
+    }
+}
+
+/// Format an integer with the thousands separator used by a Discord locale, see [`thousands_separator`].
+#[must_use]
+pub fn format_int(locale: Option<&str>, value: impl Into<i128>) -> String {
+    let separator = thousands_separator(locale);
+    let value: i128 = value.into();
+    let digits = value.unsigned_abs().to_string();
+    let mut output = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if value.is_negative() {
+        output.push('-');
+    }
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx != 0 && (digits.len() - idx).is_multiple_of(3) {
+            output.push(separator);
+        }
+        output.push(digit);
+    }
+    output
+}
+
+/// Decimals shown in percentages, unless a caller needs another precision
+pub const DEFAULT_PERCENTAGE_PRECISION: usize = 1;
+
+/// Format a fraction like `mee6::LevelInfo::percentage` as a percentage with `precision` decimals,
+/// clamped to 0-100%. Trailing zeros are kept, so `0.5` with one decimal is `50.0%`.
+#[must_use]
+pub fn format_percentage(fraction: f64, precision: usize) -> String {
+    let percent = if fraction.is_nan() {
+        0.0
+    } else {
+        (fraction * 100.0).clamp(0.0, 100.0)
+    };
+    format!("{percent:.precision$}%")
+}
+
 pub trait RequiredDiscordResources {
     fn required_intents() -> Intents;
     fn required_events() -> EventTypeFlags;
@@ -202,5 +979,436 @@ pub trait RequiredDiscordResources {
 
 pub enum EventBusMessage {
     InvalidateRewards(Id<GuildMarker>),
+    InvalidateRoleCooldowns(Id<GuildMarker>),
     UpdateConfig(Id<GuildMarker>, GuildConfig),
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_info_is_filled_in() {
+        let build = build_info("1.2.3");
+        assert_eq!(build.sha, CURRENT_GIT_SHA);
+        assert_eq!(build.version, "1.2.3");
+        assert!(build.built_at > 0);
+        assert_eq!(
+            build.to_string(),
+            format!("v{} (git-{CURRENT_GIT_SHA})", build.version)
+        );
+    }
+
+    #[test]
+    fn formats_percentages() {
+        assert_eq!(format_percentage(2.0 / 3.0, 1), "66.7%");
+        assert_eq!(format_percentage(2.0 / 3.0, 0), "67%");
+        assert_eq!(
+            format_percentage(0.5, DEFAULT_PERCENTAGE_PRECISION),
+            "50.0%"
+        );
+        assert_eq!(format_percentage(1.2, 1), "100.0%");
+        assert_eq!(format_percentage(-0.1, 1), "0.0%");
+        assert_eq!(format_percentage(f64::NAN, 2), "0.00%");
+    }
+
+    #[test]
+    fn percentile_steps() {
+        assert_eq!(percentile_step(1, 100, 50), Some(1));
+        assert_eq!(percentile_step(10, 100, 50), Some(10));
+        assert_eq!(percentile_step(11, 100, 50), Some(25));
+        assert_eq!(percentile_step(50, 100, 50), Some(50));
+        assert_eq!(percentile_step(51, 100, 50), None);
+        assert_eq!(percentile_step(30, 100, 10), None);
+        assert_eq!(percentile_step(1, 100, 10), Some(1));
+        assert_eq!(percentile_step(1, PERCENTILE_MIN_MEMBERS - 1, 50), None);
+    }
+
+    #[test]
+    fn formats_by_locale() {
+        assert_eq!(format_int(Some("en-US"), 1_234_567), "1,234,567");
+        assert_eq!(format_int(Some("de"), 1234), "1.234");
+        assert_eq!(format_int(Some("fr"), -1234), "-1\u{a0}234");
+        assert_eq!(format_int(None, 123), "123");
+        assert_eq!(
+            format_int(Some("en-US"), i64::MIN),
+            "-9,223,372,036,854,775,808"
+        );
+        assert_eq!(format_int(Some("en-US"), 0u64), "0");
+    }
+
+    #[test]
+    fn card_labels_by_locale() {
+        assert_eq!(card_labels(Some("fr")), ("RANG", "NIVEAU"));
+        assert_eq!(card_labels(Some("es-419")), card_labels(Some("es-ES")));
+        assert_eq!(card_labels(Some("ja")), ("RANK", "LEVEL"));
+        assert_eq!(card_labels(None), ("RANK", "LEVEL"));
+    }
+    #[test]
+    fn formats_levels() {
+        assert_eq!(format_level(12, LevelStyle::Decimal), "12");
+        assert_eq!(format_level(12, LevelStyle::Roman), "XII");
+        assert_eq!(format_level(1994, LevelStyle::Roman), "MCMXCIV");
+        assert_eq!(format_level(3999, LevelStyle::Roman), "MMMCMXCIX");
+        assert_eq!(format_level(0, LevelStyle::Roman), "0");
+        assert_eq!(format_level(4000, LevelStyle::Roman), "4000");
+
+        let config = GuildConfig {
+            level_style: Some(LevelStyle::Roman),
+            level_suffix: Some("★".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(config.display_level(4), "IV★");
+        assert_eq!(GuildConfig::default().display_level(4), "4");
+    }
+
+    #[test]
+    fn user_tags() {
+        let user = |name: &str, discriminator| User {
+            accent_color: None,
+            avatar: None,
+            avatar_decoration: None,
+            banner: None,
+            bot: false,
+            discriminator,
+            email: None,
+            flags: None,
+            global_name: Some("Display".to_string()),
+            id: Id::new(1),
+            locale: None,
+            mfa_enabled: None,
+            name: name.to_string(),
+            premium_type: None,
+            public_flags: None,
+            system: None,
+            verified: None,
+        };
+        assert_eq!(format_user_tag(&user("migrated", 0)), "@migrated");
+        assert_eq!(format_user_tag(&user("legacy", 42)), "legacy#0042");
+        assert_eq!(format_user_tag(&user("legacy", 1234)), "legacy#1234");
+        let member = MemberDisplayInfo::from(user("legacy", 7)).with_nick(Some("nick".to_string()));
+        assert_eq!(member.tag(), "legacy#0007");
+        assert_eq!(
+            MemberDisplayInfo::from(user("migrated", 0)).tag(),
+            "@migrated"
+        );
+        let nick_only = MemberDisplayInfo {
+            discriminator: None,
+            ..member
+        };
+        assert_eq!(nick_only.tag(), "nick");
+    }
+
+    #[test]
+    fn avatar_urls() {
+        let hash = ImageHash::new([1; 16], false);
+        let member = MemberDisplayInfo {
+            id: Id::new(1 << 22),
+            name: "user".to_string(),
+            global_name: None,
+            nick: None,
+            avatar: None,
+            local_avatar: Some(hash),
+            bot: false,
+            discriminator: Some(0),
+        };
+        assert_eq!(
+            member.avatar_url(None),
+            "https://cdn.discordapp.com/embed/avatars/1.png"
+        );
+        assert_eq!(
+            member.avatar_url(Some(Id::new(5))),
+            format!("https://cdn.discordapp.com/guilds/5/users/4194304/avatars/{hash}.png")
+        );
+    }
+
+    #[test]
+    fn flat_xp_overrides_range() {
+        let mut config = GuildConfig::default();
+        assert_eq!(
+            config.message_xp_range(),
+            (DEFAULT_MIN_XP_PER_MESSAGE, DEFAULT_MAX_XP_PER_MESSAGE)
+        );
+        config.min_xp_per_message = Some(5);
+        config.max_xp_per_message = Some(10);
+        assert_eq!(config.message_xp_range(), (5, 10));
+        config.xp_per_message = Some(40);
+        assert_eq!(config.message_xp_range(), (40, 40));
+        config.xp_per_message = Some(0);
+        assert_eq!(config.message_xp_range(), (5, 10));
+    }
+
+    #[test]
+    fn lockdowns_expire() {
+        let mut config = GuildConfig::default();
+        assert!(!config.xp_locked(1000));
+        config.xp_lockdown_until = Some(0);
+        assert!(!config.xp_locked(1000));
+        config.xp_lockdown_until = Some(2000);
+        assert!(config.xp_locked(1000));
+        assert!(!config.xp_locked(2000));
+        config.xp_lockdown_until = Some(XP_LOCKDOWN_INDEFINITE);
+        assert!(config.xp_locked(i64::MAX - 1));
+    }
+
+    #[test]
+    fn join_grace() {
+        let mut config = GuildConfig::default();
+        assert!(!config.in_join_grace(1000, 1000));
+        config.join_grace = Some(10);
+        assert!(config.in_join_grace(1000, 1599));
+        assert!(!config.in_join_grace(1000, 1600));
+    }
+
+    #[test]
+    fn min_account_age() {
+        let mut config = GuildConfig::default();
+        assert!(!config.account_too_new(1000, 1000));
+        config.min_account_age = Some(2);
+        assert!(config.account_too_new(1000, 1000 + 2 * 86_400 - 1));
+        assert!(!config.account_too_new(1000, 1000 + 2 * 86_400));
+        config.min_account_age = Some(-5);
+        assert!(!config.account_too_new(1000, 1000));
+    }
+
+    fn member_with_roles(roles: impl Into<Vec<Id<RoleMarker>>>) -> Vec<Id<RoleMarker>> {
+        roles.into()
+    }
+
+    // The legacy flag, which still selects the one-at-a-time strategy when no strategy is set
+    fn conf_one_at_time() -> GuildConfig {
+        GuildConfig {
+            one_at_a_time: Some(true),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn no_changes() {
+        let rewards = [RoleReward {
+            id: Id::new(1),
+            requirement: 2,
+        }];
+        let reward_idx = get_reward_idx(&rewards, 2).unwrap();
+        let member = member_with_roles([Id::new(1)]);
+        let changes = get_role_changes(&conf_one_at_time(), &member, &rewards, reward_idx);
+        assert_eq!(changes.changed_roles, Vec::<Id<RoleMarker>>::new());
+        assert_eq!(changes.total_roles, [Id::new(1)]);
+    }
+
+    #[test]
+    fn minecraft_discord() {
+        let rewards = [RoleReward {
+            id: Id::new(1),
+            requirement: 5,
+        }];
+        let reward_idx = get_reward_idx(&rewards, 5).unwrap();
+        let member = member_with_roles([]);
+        let changes = get_role_changes(&conf_one_at_time(), &member, &rewards, reward_idx);
+        assert_eq!(changes.changed_roles, [Id::new(1)]);
+        assert_eq!(changes.total_roles, [Id::new(1)]);
+    }
+
+    #[test]
+    fn add_one_role() {
+        let rewards = [
+            RoleReward {
+                id: Id::new(1),
+                requirement: 2,
+            },
+            RoleReward {
+                id: Id::new(2),
+                requirement: 10,
+            },
+        ];
+        let reward_idx = get_reward_idx(&rewards, 4).unwrap();
+        let member = member_with_roles([]);
+        let changes = get_role_changes(&conf_one_at_time(), &member, &rewards, reward_idx);
+        assert_eq!(changes.changed_roles, vec![Id::new(1)]);
+        assert_eq!(changes.total_roles, [Id::new(1)]);
+    }
+
+    const TEST_REWARDS: [RoleReward; 3] = [
+        RoleReward {
+            id: Id::new(1),
+            requirement: 2,
+        },
+        RoleReward {
+            id: Id::new(2),
+            requirement: 4,
+        },
+        RoleReward {
+            id: Id::new(3),
+            requirement: 10,
+        },
+    ];
+
+    #[test]
+    fn skip_roles() {
+        let reward_idx = get_reward_idx(&TEST_REWARDS, 10).unwrap();
+        let member = member_with_roles([]);
+        let changes = get_role_changes(&conf_one_at_time(), &member, &TEST_REWARDS, reward_idx);
+        assert_eq!(changes.changed_roles, [Id::new(3)]);
+        assert_eq!(changes.total_roles, [Id::new(3)]);
+    }
+    #[test]
+    fn stop_on_role() {
+        let reward_idx = get_reward_idx(&TEST_REWARDS, 5).unwrap();
+        let member = member_with_roles([Id::new(1)]);
+        let changes = get_role_changes(&conf_one_at_time(), &member, &TEST_REWARDS, reward_idx);
+        assert_eq!(changes.changed_roles, [Id::new(1), Id::new(2)]);
+        assert_eq!(changes.total_roles, [Id::new(2)]);
+    }
+
+    fn conf_strategy(strategy: RewardStrategy) -> GuildConfig {
+        GuildConfig {
+            reward_strategy: Some(strategy),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reward_strategies() {
+        let reward_idx = get_reward_idx(&TEST_REWARDS, 11).unwrap();
+        let member = member_with_roles([Id::new(1), Id::new(2)]);
+
+        let keep_all = conf_strategy(RewardStrategy::KeepAll);
+        let changes = get_role_changes(&keep_all, &member, &TEST_REWARDS, reward_idx);
+        assert_eq!(changes.changed_roles, [Id::new(3)]);
+        assert_eq!(changes.total_roles, [Id::new(1), Id::new(2), Id::new(3)]);
+
+        let one_at_a_time = conf_strategy(RewardStrategy::OneAtATime);
+        let changes = get_role_changes(&one_at_a_time, &member, &TEST_REWARDS, reward_idx);
+        assert_eq!(changes.changed_roles, [Id::new(1), Id::new(2), Id::new(3)]);
+        assert_eq!(changes.total_roles, [Id::new(3)]);
+
+        let ladder = conf_strategy(RewardStrategy::Ladder);
+        let changes = get_role_changes(&ladder, &member, &TEST_REWARDS, reward_idx);
+        assert_eq!(changes.changed_roles, [Id::new(2), Id::new(3)]);
+        assert_eq!(changes.total_roles, [Id::new(1), Id::new(3)]);
+        assert_eq!(changes.earned_role, Some(Id::new(3)));
+    }
+
+    #[test]
+    fn ladder_fills_in_skipped_roles() {
+        let reward_idx = get_reward_idx(&TEST_REWARDS, 11).unwrap();
+        let member = member_with_roles([]);
+        let ladder = conf_strategy(RewardStrategy::Ladder);
+        let changes = get_role_changes(&ladder, &member, &TEST_REWARDS, reward_idx);
+        assert_eq!(changes.changed_roles, [Id::new(1), Id::new(3)]);
+        assert_eq!(changes.total_roles, [Id::new(1), Id::new(3)]);
+    }
+
+    #[test]
+    fn strategy_overrides_legacy_flag() {
+        let config = GuildConfig {
+            one_at_a_time: Some(true),
+            reward_strategy: Some(RewardStrategy::KeepAll),
+            ..Default::default()
+        };
+        assert_eq!(config.reward_strategy(), RewardStrategy::KeepAll);
+        assert_eq!(
+            conf_one_at_time().reward_strategy(),
+            RewardStrategy::OneAtATime
+        );
+        assert_eq!(
+            GuildConfig::default().reward_strategy(),
+            RewardStrategy::KeepAll
+        );
+    }
+
+    #[test]
+    fn conf_many_doesnt_nuke() {
+        let reward_idx = get_reward_idx(&TEST_REWARDS, 5).unwrap();
+        let member = member_with_roles([Id::new(1)]);
+        let changes = get_role_changes(&GuildConfig::default(), &member, &TEST_REWARDS, reward_idx);
+        assert_eq!(changes.changed_roles, [Id::new(2)]);
+        assert_eq!(changes.total_roles, [Id::new(1), Id::new(2)]);
+    }
+
+    #[test]
+    fn conf_many_adds_many() {
+        let reward_idx = get_reward_idx(&TEST_REWARDS, 11).unwrap();
+        let member = member_with_roles([]);
+        let changes = get_role_changes(&GuildConfig::default(), &member, &TEST_REWARDS, reward_idx);
+        assert_eq!(changes.changed_roles, [Id::new(1), Id::new(2), Id::new(3)]);
+        assert_eq!(changes.total_roles, [Id::new(1), Id::new(2), Id::new(3)]);
+    }
+
+    #[test]
+    fn leave_alone_higher_roles() {
+        let reward_idx = get_reward_idx(&TEST_REWARDS, 3).unwrap();
+        let member = member_with_roles([Id::new(3)]);
+        let changes = get_role_changes(&GuildConfig::default(), &member, &TEST_REWARDS, reward_idx);
+        assert_eq!(changes.changed_roles, [Id::new(1)]);
+        assert_eq!(changes.total_roles, [Id::new(3), Id::new(1)]);
+    }
+
+    #[test]
+    fn earned_role_is_highest_new() {
+        let reward_idx = get_reward_idx(&TEST_REWARDS, 11).unwrap();
+        let member = member_with_roles([Id::new(1)]);
+        let changes = get_role_changes(&GuildConfig::default(), &member, &TEST_REWARDS, reward_idx);
+        assert_eq!(changes.earned_role, Some(Id::new(3)));
+    }
+
+    #[test]
+    fn no_earned_role_when_already_held() {
+        let reward_idx = get_reward_idx(&TEST_REWARDS, 5).unwrap();
+        let member = member_with_roles([Id::new(1), Id::new(2)]);
+        let changes = get_role_changes(&GuildConfig::default(), &member, &TEST_REWARDS, reward_idx);
+        assert_eq!(changes.earned_role, None);
+    }
+
+    fn conf_margin(margin: i16) -> GuildConfig {
+        GuildConfig {
+            reward_removal_margin: Some(margin),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn no_margin_keeps_lapsed_roles() {
+        let member = member_with_roles([Id::new(1), Id::new(2)]);
+        let mut changes = RewardRoleChanges::unchanged(&member);
+        changes.revoke_lapsed(&GuildConfig::default(), &member, &TEST_REWARDS, 0);
+        assert!(changes.changed_roles.is_empty());
+        assert_eq!(changes.total_roles, [Id::new(1), Id::new(2)]);
+    }
+
+    #[test]
+    fn margin_delays_removal() {
+        let member = member_with_roles([Id::new(1), Id::new(2)]);
+        let reward_idx = get_reward_idx(&TEST_REWARDS, 3).unwrap();
+
+        // Role 2 needs level 4, a margin of 1 keeps it at level 3...
+        let mut changes = get_role_changes(&conf_margin(1), &member, &TEST_REWARDS, reward_idx);
+        changes.revoke_lapsed(&conf_margin(1), &member, &TEST_REWARDS, 3);
+        assert!(changes.changed_roles.is_empty());
+
+        // ...but a margin of 0 takes it away immediately
+        let mut changes = get_role_changes(&conf_margin(0), &member, &TEST_REWARDS, reward_idx);
+        changes.revoke_lapsed(&conf_margin(0), &member, &TEST_REWARDS, 3);
+        assert_eq!(changes.changed_roles, [Id::new(2)]);
+        assert_eq!(changes.total_roles, [Id::new(1)]);
+    }
+
+    #[test]
+    fn penalties_follow_reward_strategy() {
+        let current = [Id::new(1), Id::new(3), Id::new(7)];
+        // A penalty bigger than the member's XP leaves them at level 0
+        let changes = RewardRoleChanges::new(&conf_margin(0), &current, &TEST_REWARDS, 0);
+        assert_eq!(changes.total_roles, [Id::new(7)]);
+        assert_eq!(changes.changed_roles, [Id::new(1), Id::new(3)]);
+
+        // Keeping one at a time hands the member back the reward they dropped to
+        let config = GuildConfig {
+            reward_strategy: Some(RewardStrategy::OneAtATime),
+            reward_removal_margin: Some(0),
+            ..Default::default()
+        };
+        let changes = RewardRoleChanges::new(&config, &current, &TEST_REWARDS, 5);
+        assert_eq!(changes.total_roles, [Id::new(7), Id::new(2)]);
+        assert_eq!(changes.changed_roles, [Id::new(1), Id::new(2), Id::new(3)]);
+    }
+}
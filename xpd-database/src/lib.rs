@@ -6,22 +6,29 @@
     clippy::missing_panics_doc
 )]
 
-#[cfg(test)]
+mod retry;
+mod store;
+#[cfg(all(test, feature = "database-tests"))]
 mod test;
 mod util;
 
 use std::{fmt::Display, ops::DerefMut};
 
+pub use retry::RetryPolicy;
 use simpleinterpolation::Interpolation;
 pub use sqlx::PgPool;
 use sqlx::{query, query_as, Acquire, PgConnection, Postgres};
+pub use store::{RetryingStore, XpStore};
 use tokio_stream::StreamExt;
 use twilight_model::id::{
-    marker::{ChannelMarker, GenericMarker, GuildMarker, RoleMarker, UserMarker},
+    marker::{ChannelMarker, GenericMarker, GuildMarker, MessageMarker, RoleMarker, UserMarker},
     Id,
 };
 use util::{db_to_id, id_to_db};
-use xpd_common::{GuildConfig, RoleReward, UserStatus};
+use xpd_common::{
+    GuildConfig, LevelStyle, LevelUpStyle, RewardStrategy, RoleCooldown, RoleReward, SeasonCadence,
+    UserStatus,
+};
 pub async fn guild_rewards<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
@@ -46,6 +53,25 @@ pub async fn guild_rewards<
     Ok(rewards)
 }
 
+/// Make other transactions wait to change this guild's rewards until this transaction ends.
+///
+/// Transactions can't see each other's new rows, so several adding rewards at once could each stay
+/// under the limit and go over it together. Only use this inside a transaction.
+pub async fn lock_guild_rewards<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild_id: Id<GuildMarker>,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!("SELECT FROM pg_advisory_xact_lock($1)", id_to_db(guild_id))
+        .execute(conn.as_mut())
+        .await?;
+    Ok(())
+}
+
 pub async fn guild_config<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
@@ -58,7 +84,8 @@ pub async fn guild_config<
     let config = query_as!(
         RawGuildConfig,
         "SELECT one_at_a_time, level_up_message, level_up_channel, ping_on_level_up,\
-                 max_xp_per_message, min_xp_per_message, message_cooldown \
+                 max_xp_per_message, min_xp_per_message, message_cooldown, prune_deleted_rewards, \
+                 reward_removal_margin, starting_xp, count_cooldown_messages, unranked_card, attachment_bonus_xp, embed_bonus_xp, ignore_duplicate_messages, level_up_cooldown, silent_level_ups, reward_strategy, level_style, level_suffix, level_up_style, xp_per_message, xp_lockdown_until, rank_bots, reaction_xp, max_reaction_grants, percentile_announcements, disabled_commands, role_alerts, join_grace, min_account_age \
                  FROM guild_configs WHERE id = $1",
         id_to_db(guild)
     )
@@ -70,6 +97,8 @@ pub async fn guild_config<
 }
 
 /// Add (or, when given a negative, subtract) some amount of XP from a user in a guild.
+///
+/// XP never drops below zero, so the returned total is always at least zero.
 pub async fn add_xp<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
@@ -82,9 +111,9 @@ pub async fn add_xp<
 ) -> Result<i64, Error> {
     let mut conn = conn.acquire().await?;
     let count = query!(
-        "INSERT INTO levels (id, guild, xp) VALUES ($1, $2, $3) \
+        "INSERT INTO levels (id, guild, xp) VALUES ($1, $2, GREATEST(0, $3::INT8)) \
                     ON CONFLICT (id, guild) \
-                    DO UPDATE SET xp=levels.xp+excluded.xp \
+                    DO UPDATE SET xp=GREATEST(0, levels.xp + $3::INT8) \
                     RETURNING xp",
         id_to_db(author),
         id_to_db(guild),
@@ -96,6 +125,80 @@ pub async fn add_xp<
     Ok(count)
 }
 
+/// Like [`add_xp`], but users without leveling data in this guild start at `starting_xp`
+/// instead of zero. Existing users are not affected by `starting_xp`.
+///
+/// This is what counted messages use, so it also adds one to the user's message count.
+pub async fn add_xp_with_start<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    author: Id<UserMarker>,
+    guild: Id<GuildMarker>,
+    amount: i64,
+    starting_xp: i64,
+) -> Result<i64, Error> {
+    let mut conn = conn.acquire().await?;
+    let count = query!(
+        "INSERT INTO levels (id, guild, xp, messages) VALUES ($1, $2, $3::INT8 + $4::INT8, 1) \
+                    ON CONFLICT (id, guild) \
+                    DO UPDATE SET xp=levels.xp+$3, messages=levels.messages+1, last_message=NOW() \
+                    RETURNING xp",
+        id_to_db(author),
+        id_to_db(guild),
+        amount,
+        starting_xp
+    )
+    .fetch_one(conn.as_mut())
+    .await?
+    .xp;
+    Ok(count)
+}
+
+/// Count a message without giving any XP for it. Users who have never earned XP are left alone.
+pub async fn count_message<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    author: Id<UserMarker>,
+    guild: Id<GuildMarker>,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!(
+        "UPDATE levels SET messages = messages + 1 WHERE id = $1 AND guild = $2",
+        id_to_db(author),
+        id_to_db(guild)
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+pub async fn user_messages<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+    guild: Id<GuildMarker>,
+) -> Result<Option<i64>, Error> {
+    let mut conn = conn.acquire().await?;
+    let messages = query!(
+        "SELECT messages FROM levels WHERE id = $1 AND guild = $2",
+        id_to_db(user),
+        id_to_db(guild)
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .map(|row| row.messages);
+    Ok(messages)
+}
+
 pub async fn set_xp<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
@@ -124,6 +227,49 @@ pub async fn set_xp<
     Ok(())
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ImportedXp {
+    /// The user's XP after the import
+    pub xp: i64,
+    /// True if the user had no leveling data in this guild before the import
+    pub inserted: bool,
+}
+
+pub async fn import_xp<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+    guild: Id<GuildMarker>,
+    amount: i64,
+    messages: Option<i64>,
+    overwrite: bool,
+) -> Result<ImportedXp, Error> {
+    let mut conn = conn.acquire().await?;
+    // xmax is only zero for freshly inserted rows, which is how we tell them from updates
+    let record = query!(
+        "INSERT INTO levels (id, guild, xp, messages) VALUES ($1, $2, $3, COALESCE($5::INT8, 0)) \
+            ON CONFLICT (id, guild) DO UPDATE \
+            SET xp = CASE WHEN $4 THEN excluded.xp ELSE levels.xp + excluded.xp END, \
+            messages = CASE WHEN $4 THEN COALESCE($5, levels.messages) \
+                ELSE levels.messages + COALESCE($5, 0) END \
+            RETURNING xp, (xmax = 0) AS \"inserted!\"",
+        id_to_db(user),
+        id_to_db(guild),
+        amount,
+        overwrite,
+        messages
+    )
+    .fetch_one(conn.as_mut())
+    .await?;
+    Ok(ImportedXp {
+        xp: record.xp,
+        inserted: record.inserted,
+    })
+}
+
 #[derive(Debug, Copy, Clone, Hash)]
 pub enum OnCooldown {
     Yes,
@@ -173,6 +319,64 @@ pub async fn set_cooldown<
     Ok(output)
 }
 
+/// Remember the content hash of a member's latest XP-earning message, returning the one it replaced
+/// if that was remembered within the last `window_secs` seconds.
+///
+/// This only updates existing levels, so members without any XP yet have nothing to compare against.
+pub async fn swap_content_hash<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+    guild: Id<GuildMarker>,
+    hash: i64,
+    window_secs: f64,
+) -> Result<Option<i64>, Error> {
+    let mut conn = conn.acquire().await?;
+    let previous = query!(
+        "UPDATE levels SET last_content_hash = $3, last_content_at = NOW() \
+            FROM (SELECT last_content_hash, last_content_at FROM levels \
+            WHERE guild = $1 AND id = $2 FOR UPDATE) AS old \
+            WHERE guild = $1 AND id = $2 \
+            RETURNING CASE WHEN old.last_content_at > NOW() - interval '1' second * $4 \
+            THEN old.last_content_hash END AS last_content_hash",
+        id_to_db(guild),
+        id_to_db(user),
+        hash,
+        window_secs
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .and_then(|row| row.last_content_hash);
+    Ok(previous)
+}
+
+/// When the user last earned XP for a message in this guild, in unix seconds. Unlike the cooldown,
+/// this is kept as long as their levels are.
+pub async fn last_active<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+    guild: Id<GuildMarker>,
+) -> Result<Option<i64>, Error> {
+    let mut conn = conn.acquire().await?;
+    let last_message = query!(
+        "SELECT EXTRACT(EPOCH FROM last_message)::INT8 AS \"last_message!\" \
+            FROM levels WHERE guild = $1 AND id = $2",
+        id_to_db(guild),
+        id_to_db(user),
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .map(|v| v.last_message);
+    Ok(last_message)
+}
+
 pub async fn get_last_message<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
@@ -218,14 +422,36 @@ pub async fn delete_levels_user_guild<
     user: Id<UserMarker>,
     guild: Id<GuildMarker>,
 ) -> Result<(), Error> {
-    let mut conn = conn.acquire().await?;
+    let mut txn = conn.begin().await?;
     query!(
         "DELETE FROM levels WHERE id = $1 AND guild = $2",
         id_to_db(user),
         id_to_db(guild)
     )
-    .execute(conn.as_mut())
+    .execute(txn.as_mut())
+    .await?;
+    query!(
+        "DELETE FROM xp_history WHERE id = $1 AND guild = $2",
+        id_to_db(user),
+        id_to_db(guild)
+    )
+    .execute(txn.as_mut())
+    .await?;
+    query!(
+        "DELETE FROM hourly_xp WHERE id = $1 AND guild = $2",
+        id_to_db(user),
+        id_to_db(guild)
+    )
+    .execute(txn.as_mut())
     .await?;
+    query!(
+        "DELETE FROM percentile_announcements WHERE id = $1 AND guild = $2",
+        id_to_db(user),
+        id_to_db(guild)
+    )
+    .execute(txn.as_mut())
+    .await?;
+    txn.commit().await?;
     Ok(())
 }
 
@@ -250,6 +476,91 @@ pub async fn count_with_higher_xp<
     Ok(count)
 }
 
+/// Like [`count_with_higher_xp`], but only counting the given users
+pub async fn count_with_higher_xp_among<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    users: &[Id<UserMarker>],
+    xp: i64,
+) -> Result<Option<i64>, Error> {
+    let mut conn = conn.acquire().await?;
+    let users: Vec<i64> = users.iter().copied().map(id_to_db).collect();
+    let count = query!(
+        "SELECT COUNT(*) as count FROM levels WHERE xp > $1 AND guild = $2 AND id = ANY($3)",
+        xp,
+        id_to_db(guild),
+        &users
+    )
+    .fetch_one(conn.as_mut())
+    .await?
+    .count;
+    Ok(count)
+}
+
+/// How many of `users` have levels in the guild
+pub async fn levels_in_guild_among<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    users: &[Id<UserMarker>],
+) -> Result<i64, Error> {
+    let mut conn = conn.acquire().await?;
+    let users: Vec<i64> = users.iter().copied().map(id_to_db).collect();
+    let count = query!(
+        "SELECT COUNT(*) as count FROM levels WHERE guild = $1 AND id = ANY($2)",
+        id_to_db(guild),
+        &users
+    )
+    .fetch_one(conn.as_mut())
+    .await?
+    .count
+    .unwrap_or(0);
+    Ok(count)
+}
+
+/// Get the XP and rank of several users in one query, in the same order as `users`.
+/// Users who have never earned XP get 0 XP, and rank after everyone else.
+pub async fn ranks_for_users<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    users: &[Id<UserMarker>],
+) -> Result<Vec<RankedUser>, Error> {
+    let mut conn = conn.acquire().await?;
+    let user_ids: Vec<i64> = users.iter().copied().map(id_to_db).collect();
+    let ranks = query!(
+        "WITH ranked AS ( \
+            SELECT id, xp, RANK() OVER (ORDER BY xp DESC) AS rank FROM levels WHERE guild = $1 \
+        ) SELECT wanted.id AS \"id!\", COALESCE(ranked.xp, 0) AS \"xp!\", \
+            COALESCE(ranked.rank, (SELECT COUNT(*) FROM ranked WHERE xp > 0) + 1) AS \"rank!\" \
+        FROM UNNEST($2::INT8[]) WITH ORDINALITY AS wanted(id, ordinality) \
+        LEFT JOIN ranked ON ranked.id = wanted.id \
+        ORDER BY wanted.ordinality",
+        id_to_db(guild),
+        &user_ids
+    )
+    .fetch_all(conn.as_mut())
+    .await?
+    .into_iter()
+    .map(|row| RankedUser {
+        id: db_to_id(row.id),
+        xp: row.xp,
+        rank: row.rank,
+    })
+    .collect();
+    Ok(ranks)
+}
+
 pub async fn levels_in_guild<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
@@ -309,6 +620,31 @@ pub async fn user_xp<
     Ok(xp)
 }
 
+/// A user's XP in a guild, which is 0 if they have none, and their 1-based position on its
+/// leaderboard, as `(xp, rank)`.
+pub async fn user_xp_and_rank<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    user: Id<UserMarker>,
+) -> Result<(i64, i64), Error> {
+    let mut conn = conn.acquire().await?;
+    let row = query!(
+        "SELECT COALESCE(levels.xp, 0) AS \"xp!\", \
+            (SELECT COUNT(*) FROM levels AS higher \
+            WHERE higher.guild = $2 AND higher.xp > COALESCE(levels.xp, 0)) + 1 AS \"rank!\" \
+            FROM (VALUES (1)) AS one LEFT JOIN levels ON levels.id = $1 AND levels.guild = $2",
+        id_to_db(user),
+        id_to_db(guild)
+    )
+    .fetch_one(conn.as_mut())
+    .await?;
+    Ok((row.xp, row.rank))
+}
+
 pub async fn get_all_levels<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
@@ -333,6 +669,32 @@ pub async fn get_all_levels<
     Ok(output)
 }
 
+/// Levels from guild resets that can still be undone
+pub async fn get_all_deleted_levels<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+) -> Result<Vec<UserStatus>, Error> {
+    let mut conn = conn.acquire().await?;
+    let levels = query!(
+        "SELECT guild, xp FROM levels_deleted WHERE id = $1",
+        id_to_db(user)
+    )
+    .fetch_all(conn.as_mut())
+    .await?
+    .into_iter()
+    .map(|v| UserStatus {
+        id: user,
+        guild: db_to_id(v.guild),
+        xp: v.xp,
+    })
+    .collect();
+    Ok(levels)
+}
+
 pub async fn card_customizations<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
@@ -372,211 +734,1666 @@ pub async fn delete_card_customizations<
     Ok(())
 }
 
-pub async fn delete_levels_user<
+pub async fn user_timezone<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
     A: Acquire<'a, Database = Postgres, Connection = D> + Send,
 >(
     conn: A,
-    id: Id<UserMarker>,
-) -> Result<u64, Error> {
+    user: Id<UserMarker>,
+) -> Result<Option<String>, Error> {
     let mut conn = conn.acquire().await?;
-    let rows = query!("DELETE FROM levels WHERE id = $1", id_to_db(id))
-        .execute(conn.as_mut())
-        .await?
-        .rows_affected();
-    Ok(rows)
+    let timezone = query!(
+        "SELECT timezone FROM user_preferences WHERE id = $1",
+        id_to_db(user)
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .and_then(|v| v.timezone);
+    Ok(timezone)
 }
 
-pub async fn delete_levels_guild<
+/// Everything a user has set with `/timezone`, `/rank notify` and `/weekly-summary`,
+/// or None if they never set any of it
+pub async fn user_preferences<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
     A: Acquire<'a, Database = Postgres, Connection = D> + Send,
 >(
     conn: A,
-    id: Id<GuildMarker>,
-) -> Result<u64, Error> {
+    user: Id<UserMarker>,
+) -> Result<Option<UserPreferences>, Error> {
     let mut conn = conn.acquire().await?;
-    let rows = query!("DELETE FROM levels WHERE guild = $1", id_to_db(id))
-        .execute(conn.as_mut())
-        .await?
-        .rows_affected();
-    Ok(rows)
+    let preferences = query!(
+        "SELECT timezone, level_up_dms, weekly_summary FROM user_preferences WHERE id = $1",
+        id_to_db(user)
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .map(|row| UserPreferences {
+        timezone: row.timezone,
+        level_up_dms: row.level_up_dms,
+        weekly_summary: row.weekly_summary,
+    });
+    Ok(preferences)
 }
 
-pub async fn ban_guild<
+pub async fn set_user_timezone<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
     A: Acquire<'a, Database = Postgres, Connection = D> + Send,
 >(
     conn: A,
-    id: Id<GuildMarker>,
-    duration: Option<f64>,
+    user: Id<UserMarker>,
+    timezone: Option<&str>,
 ) -> Result<(), Error> {
     let mut conn = conn.acquire().await?;
     query!(
-        "INSERT INTO guild_bans (id, expires) \
-                VALUES ($1, \
-                CASE WHEN $3 \
-                THEN NULL \
-                ELSE NOW() + interval '1' day * $2 END)",
+        "INSERT INTO user_preferences (id, timezone) VALUES ($1, $2) \
+            ON CONFLICT (id) DO UPDATE SET timezone = excluded.timezone",
+        id_to_db(user),
+        timezone
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_user_preferences<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+) -> Result<(), Error> {
+    let mut txn = conn.begin().await?;
+    query!("DELETE FROM user_preferences WHERE id = $1", id_to_db(user))
+        .execute(txn.as_mut())
+        .await?;
+    query!("DELETE FROM weekly_summaries WHERE id = $1", id_to_db(user))
+        .execute(txn.as_mut())
+        .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+pub async fn set_weekly_summary<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+    enabled: bool,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!(
+        "INSERT INTO user_preferences (id, weekly_summary) VALUES ($1, $2) \
+            ON CONFLICT (id) DO UPDATE SET weekly_summary = excluded.weekly_summary",
+        id_to_db(user),
+        enabled
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// Choose whether the user's own level-ups are sent to their DMs instead of announced publicly
+pub async fn set_level_up_dms<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+    enabled: bool,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!(
+        "INSERT INTO user_preferences (id, level_up_dms) VALUES ($1, $2) \
+            ON CONFLICT (id) DO UPDATE SET level_up_dms = excluded.level_up_dms",
+        id_to_db(user),
+        enabled
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// Whether the user asked for their level-ups to be sent to their DMs
+pub async fn level_up_dms<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+) -> Result<bool, Error> {
+    let mut conn = conn.acquire().await?;
+    let enabled = query!(
+        "SELECT level_up_dms FROM user_preferences WHERE id = $1",
+        id_to_db(user)
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .is_some_and(|row| row.level_up_dms);
+    Ok(enabled)
+}
+
+/// Users who opted into weekly summaries and haven't had one in the last week
+pub async fn due_weekly_summaries<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    limit: i64,
+) -> Result<Vec<Id<UserMarker>>, Error> {
+    let mut conn = conn.acquire().await?;
+    let users = query!(
+        "SELECT id FROM user_preferences WHERE weekly_summary \
+            AND (summary_sent_at IS NULL OR summary_sent_at < NOW() - interval '7 days') \
+            ORDER BY id LIMIT $1",
+        limit
+    )
+    .fetch_all(conn.as_mut())
+    .await?
+    .into_iter()
+    .map(|row| db_to_id(row.id))
+    .collect();
+    Ok(users)
+}
+
+/// Get a user's progress in every guild since their last summary, and make now the start of
+/// their next one.
+pub async fn take_weekly_summary<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+) -> Result<Vec<WeeklyProgress>, Error> {
+    let mut txn = conn.begin().await?;
+    let progress: Vec<WeeklyProgress> = query!(
+        "SELECT levels.guild, levels.xp, \
+            (SELECT COUNT(*) FROM levels higher \
+                WHERE higher.guild = levels.guild AND higher.xp > levels.xp) + 1 AS \"rank!\", \
+            weekly_summaries.xp AS \"previous_xp?\", weekly_summaries.rank AS \"previous_rank?\" \
+            FROM levels LEFT JOIN weekly_summaries \
+            ON weekly_summaries.id = levels.id AND weekly_summaries.guild = levels.guild \
+            WHERE levels.id = $1",
+        id_to_db(user)
+    )
+    .fetch_all(txn.as_mut())
+    .await?
+    .into_iter()
+    .map(|row| WeeklyProgress {
+        guild: db_to_id(row.guild),
+        xp: row.xp,
+        rank: row.rank,
+        previous: row.previous_xp.zip(row.previous_rank),
+    })
+    .collect();
+
+    let guilds: Vec<i64> = progress.iter().map(|p| id_to_db(p.guild)).collect();
+    let xp: Vec<i64> = progress.iter().map(|p| p.xp).collect();
+    let ranks: Vec<i64> = progress.iter().map(|p| p.rank).collect();
+    query!("DELETE FROM weekly_summaries WHERE id = $1", id_to_db(user))
+        .execute(txn.as_mut())
+        .await?;
+    query!(
+        "INSERT INTO weekly_summaries (id, guild, xp, rank) \
+            SELECT $1, * FROM UNNEST($2::INT8[], $3::INT8[], $4::INT8[])",
+        id_to_db(user),
+        &guilds,
+        &xp,
+        &ranks
+    )
+    .execute(txn.as_mut())
+    .await?;
+    query!(
+        "UPDATE user_preferences SET summary_sent_at = NOW() WHERE id = $1",
+        id_to_db(user)
+    )
+    .execute(txn.as_mut())
+    .await?;
+    txn.commit().await?;
+    Ok(progress)
+}
+
+/// Remember `xp` as the user's XP in this guild at the end of today (UTC).
+pub async fn record_xp_history<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+    guild: Id<GuildMarker>,
+    xp: i64,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!(
+        "INSERT INTO xp_history (id, guild, day, xp) \
+            VALUES ($1, $2, (NOW() AT TIME ZONE 'UTC')::DATE, $3) \
+            ON CONFLICT (id, guild, day) DO UPDATE SET xp = excluded.xp",
+        id_to_db(user),
+        id_to_db(guild),
+        xp
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// The user's last `limit` days of recorded XP in this guild, oldest first
+pub async fn xp_history<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+    guild: Id<GuildMarker>,
+    limit: i64,
+) -> Result<Vec<XpHistoryEntry>, Error> {
+    let mut conn = conn.acquire().await?;
+    let mut history: Vec<XpHistoryEntry> = query!(
+        "SELECT TO_CHAR(day, 'YYYY-MM-DD') AS \"day!\", xp FROM xp_history \
+            WHERE id = $1 AND guild = $2 ORDER BY xp_history.day DESC LIMIT $3",
+        id_to_db(user),
+        id_to_db(guild),
+        limit
+    )
+    .fetch_all(conn.as_mut())
+    .await?
+    .into_iter()
+    .map(|row| XpHistoryEntry {
+        day: row.day,
+        xp: row.xp,
+    })
+    .collect();
+    history.reverse();
+    Ok(history)
+}
+
+/// Where `user` stood in `guild` at the end of the UTC day containing `snapshot_time`.
+///
+/// `snapshot_time` is in seconds since the Unix epoch. Everyone is counted with the last XP
+/// recorded in the XP history for them on or before that day.
+///
+/// Returns `None` if nothing was recorded for `user` by then, like when they hadn't chatted in
+/// the days still kept in the history.
+pub async fn rank_at_snapshot<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    user: Id<UserMarker>,
+    snapshot_time: i64,
+) -> Result<Option<SnapshotRank>, Error> {
+    let mut conn = conn.acquire().await?;
+    let rank = query_as!(
+        SnapshotRank,
+        "WITH snapshot AS ( \
+            SELECT DISTINCT ON (id) id, xp FROM xp_history \
+            WHERE guild = $1 AND day <= (TO_TIMESTAMP($3::INT8) AT TIME ZONE 'UTC')::DATE \
+            ORDER BY id, day DESC \
+        ) \
+        SELECT TO_CHAR((TO_TIMESTAMP($3::INT8) AT TIME ZONE 'UTC')::DATE, 'YYYY-MM-DD') AS \"day!\", \
+            xp, \
+            (SELECT COUNT(*) FROM snapshot AS higher WHERE higher.xp > snapshot.xp) + 1 AS \"rank!\", \
+            (SELECT COUNT(*) FROM snapshot) AS \"members!\" \
+        FROM snapshot WHERE id = $2",
+        id_to_db(guild),
+        id_to_db(user),
+        snapshot_time
+    )
+    .fetch_optional(conn.as_mut())
+    .await?;
+    Ok(rank)
+}
+
+/// Delete XP history older than `keep_days` days
+pub async fn prune_xp_history<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    keep_days: i32,
+) -> Result<u64, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "DELETE FROM xp_history WHERE day < (NOW() AT TIME ZONE 'UTC')::DATE - $1::INT4",
+        keep_days
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows)
+}
+
+/// Add `gained` to the XP the user earned in this guild during the current hour (UTC).
+pub async fn record_hourly_xp<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+    guild: Id<GuildMarker>,
+    gained: i64,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!(
+        "INSERT INTO hourly_xp (guild, hour, id, gained) \
+            VALUES ($1, DATE_TRUNC('hour', NOW() AT TIME ZONE 'UTC'), $2, $3) \
+            ON CONFLICT (guild, hour, id) DO UPDATE SET gained = hourly_xp.gained + excluded.gained",
+        id_to_db(guild),
+        id_to_db(user),
+        gained
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// How many users have gained XP in this guild since the start of the UTC day
+pub async fn todays_gainers<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<i64, Error> {
+    let mut conn = conn.acquire().await?;
+    let count = query!(
+        "SELECT COUNT(DISTINCT id) AS \"count!\" FROM hourly_xp \
+            WHERE guild = $1 AND hour >= DATE_TRUNC('day', NOW() AT TIME ZONE 'UTC')",
+        id_to_db(guild)
+    )
+    .fetch_one(conn.as_mut())
+    .await?
+    .count;
+    Ok(count)
+}
+
+/// Users who gained the most XP in this guild since the start of the UTC day.
+/// The `xp` of each entry is what they gained today, not their total.
+pub async fn todays_top_gainers<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<UserStatus>, Error> {
+    let mut conn = conn.acquire().await?;
+    let gainers = query!(
+        "SELECT id, SUM(gained)::INT8 AS \"gained!\" FROM hourly_xp \
+            WHERE guild = $1 AND hour >= DATE_TRUNC('day', NOW() AT TIME ZONE 'UTC') \
+            GROUP BY id ORDER BY (SUM(gained), id) DESC LIMIT $2 OFFSET $3",
+        id_to_db(guild),
+        limit,
+        offset
+    )
+    .fetch_all(conn.as_mut())
+    .await?
+    .into_iter()
+    .map(|row| UserStatus {
+        id: db_to_id(row.id),
+        guild,
+        xp: row.gained,
+    })
+    .collect();
+    Ok(gainers)
+}
+
+/// The user's 1-indexed position among today's top gainers, if they gained any XP today.
+/// Matches the order of [`todays_top_gainers`].
+pub async fn todays_gainer_position<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    user: Id<UserMarker>,
+) -> Result<Option<i64>, Error> {
+    let mut conn = conn.acquire().await?;
+    let position = query!(
+        "WITH today AS (SELECT id, SUM(gained) AS gained FROM hourly_xp \
+            WHERE guild = $1 AND hour >= DATE_TRUNC('day', NOW() AT TIME ZONE 'UTC') GROUP BY id) \
+            SELECT (SELECT COUNT(*) FROM today ahead \
+            WHERE (ahead.gained, ahead.id) > (today.gained, today.id)) + 1 AS \"position!\" \
+            FROM today WHERE id = $2",
+        id_to_db(guild),
+        id_to_db(user)
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .map(|row| row.position);
+    Ok(position)
+}
+
+/// Delete hourly XP older than `keep_hours` hours
+pub async fn prune_hourly_xp<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    keep_hours: i32,
+) -> Result<u64, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "DELETE FROM hourly_xp \
+            WHERE hour < DATE_TRUNC('hour', NOW() AT TIME ZONE 'UTC') - MAKE_INTERVAL(hours => $1)",
+        keep_hours
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows)
+}
+
+/// Remember that `reactor` earned the author of `message` reaction XP, unless they already did
+/// or `max_grants` members already have. Returns whether the author should get XP.
+pub async fn grant_reaction<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    message: Id<MessageMarker>,
+    reactor: Id<UserMarker>,
+    max_grants: i16,
+) -> Result<bool, Error> {
+    let mut txn = conn.begin().await?;
+    // Reactions to the same message are counted one at a time, or several could all see the
+    // message under its limit and go over it together
+    query!("SELECT FROM pg_advisory_xact_lock($1)", id_to_db(message))
+        .execute(txn.as_mut())
+        .await?;
+    let rows = query!(
+        "INSERT INTO reaction_grants (message, reactor) SELECT $1, $2 \
+            WHERE (SELECT COUNT(*) FROM reaction_grants WHERE message = $1) < $3 \
+            ON CONFLICT (message, reactor) DO NOTHING",
+        id_to_db(message),
+        id_to_db(reactor),
+        i64::from(max_grants)
+    )
+    .execute(txn.as_mut())
+    .await?
+    .rows_affected();
+    txn.commit().await?;
+    Ok(rows > 0)
+}
+
+/// Forget reactions granted more than `keep_hours` hours ago
+pub async fn prune_reaction_grants<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    keep_hours: i32,
+) -> Result<u64, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "DELETE FROM reaction_grants WHERE granted_at < NOW() - MAKE_INTERVAL(hours => $1)",
+        keep_hours
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows)
+}
+
+/// Where `xp` would rank in a guild, as the number of members with more XP and the number of ranked members
+pub async fn guild_standing<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    xp: i64,
+) -> Result<(i64, i64), Error> {
+    let mut conn = conn.acquire().await?;
+    let row = query!(
+        "SELECT COUNT(*) FILTER (WHERE xp > $2) AS \"higher!\", COUNT(*) AS \"total!\" \
+            FROM levels WHERE guild = $1",
+        id_to_db(guild),
+        xp
+    )
+    .fetch_one(conn.as_mut())
+    .await?;
+    Ok((row.higher, row.total))
+}
+
+/// How many members of a guild are at each level, lowest level first.
+///
+/// Levels are found by `thresholds`, the ascending XP each level from 1 up starts at, so members
+/// past the last threshold are all counted at the highest level. Levels nobody is at are left out.
+pub async fn level_distribution<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    thresholds: &[i64],
+) -> Result<Vec<LevelCount>, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "SELECT width_bucket(xp, $2::INT8[]) AS \"level!\", COUNT(*) AS \"members!\" \
+            FROM levels WHERE guild = $1 GROUP BY 1 ORDER BY 1",
+        id_to_db(guild),
+        thresholds
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| LevelCount {
+            level: row.level.into(),
+            members: row.members,
+        })
+        .collect())
+}
+
+/// Remember that `user` reached the top `percentile` percent of `guild`, unless they were already
+/// told about that or a narrower percentile. Returns whether they should be told.
+pub async fn claim_percentile<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    user: Id<UserMarker>,
+    percentile: i16,
+) -> Result<bool, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "INSERT INTO percentile_announcements (guild, id, percentile) VALUES ($1, $2, $3) \
+            ON CONFLICT (guild, id) DO UPDATE SET percentile = excluded.percentile \
+            WHERE percentile_announcements.percentile > excluded.percentile",
+        id_to_db(guild),
+        id_to_db(user),
+        percentile
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows > 0)
+}
+
+pub async fn delete_percentile_announcements<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!(
+        "DELETE FROM percentile_announcements WHERE guild = $1",
+        id_to_db(guild)
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// Add a reward which is only handed out once per member, or change the level of one with the same name
+pub async fn add_one_time_reward<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    name: &str,
+    requirement: i64,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!(
+        "INSERT INTO one_time_rewards (guild, name, requirement) VALUES ($1, $2, $3) \
+            ON CONFLICT (guild, name) DO UPDATE SET requirement = excluded.requirement",
+        id_to_db(guild),
+        name,
+        requirement
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// Stop handing out a one-time reward. Who already claimed it is remembered, in case it's added back.
+/// Returns false if there was no such reward.
+pub async fn delete_one_time_reward<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    name: &str,
+) -> Result<bool, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "DELETE FROM one_time_rewards WHERE guild = $1 AND name = $2",
+        id_to_db(guild),
+        name
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows > 0)
+}
+
+/// A guild's one-time rewards and how many members claimed each, lowest level first
+pub async fn one_time_rewards<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<Vec<OneTimeReward>, Error> {
+    let mut conn = conn.acquire().await?;
+    let rewards = query_as!(
+        OneTimeReward,
+        "SELECT name, requirement, \
+            (SELECT COUNT(*) FROM claimed_rewards \
+                WHERE claimed_rewards.guild = one_time_rewards.guild \
+                AND claimed_rewards.reward = one_time_rewards.name) AS \"claims!\" \
+            FROM one_time_rewards WHERE guild = $1 ORDER BY requirement, name",
+        id_to_db(guild)
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    Ok(rewards)
+}
+
+/// Claim every one-time reward up to `level` that `user` hasn't claimed yet, in one statement so
+/// two level-ups at once can't both claim the same reward. Returns the names of the new claims.
+pub async fn claim_one_time_rewards<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    user: Id<UserMarker>,
+    level: i64,
+) -> Result<Vec<String>, Error> {
+    let mut conn = conn.acquire().await?;
+    let claimed = query!(
+        "INSERT INTO claimed_rewards (guild, id, reward) \
+            SELECT guild, $2, name FROM one_time_rewards WHERE guild = $1 AND requirement <= $3 \
+            ORDER BY requirement, name \
+            ON CONFLICT DO NOTHING RETURNING reward",
+        id_to_db(guild),
+        id_to_db(user),
+        level
+    )
+    .fetch_all(conn.as_mut())
+    .await?
+    .into_iter()
+    .map(|row| row.reward)
+    .collect();
+    Ok(claimed)
+}
+
+/// The one-time rewards `user` claimed in `guild`, oldest first
+pub async fn claimed_rewards<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    user: Id<UserMarker>,
+) -> Result<Vec<ClaimedReward>, Error> {
+    let mut conn = conn.acquire().await?;
+    let claimed = query_as!(
+        ClaimedReward,
+        "SELECT reward, EXTRACT(EPOCH FROM claimed_at)::INT8 AS \"claimed_at!\" \
+            FROM claimed_rewards WHERE guild = $1 AND id = $2 ORDER BY claimed_at, reward",
+        id_to_db(guild),
+        id_to_db(user)
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    Ok(claimed)
+}
+
+/// Delete a guild's one-time rewards along with everyone's claims
+pub async fn delete_one_time_rewards<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<(), Error> {
+    let mut txn = conn.begin().await?;
+    query!(
+        "DELETE FROM one_time_rewards WHERE guild = $1",
+        id_to_db(guild)
+    )
+    .execute(txn.as_mut())
+    .await?;
+    query!(
+        "DELETE FROM claimed_rewards WHERE guild = $1",
+        id_to_db(guild)
+    )
+    .execute(txn.as_mut())
+    .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Forget every one-time reward a user claimed, in every guild
+pub async fn delete_claimed_rewards_user<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    user: Id<UserMarker>,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!("DELETE FROM claimed_rewards WHERE id = $1", id_to_db(user))
+        .execute(conn.as_mut())
+        .await?;
+    Ok(())
+}
+
+/// Remember that the owner of `guild` was told the bot can't give out `role`.
+/// Returns false if they were already told about it.
+pub async fn claim_role_alert<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    role: Id<RoleMarker>,
+) -> Result<bool, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "INSERT INTO role_alerts (guild, role) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        id_to_db(guild),
+        id_to_db(role)
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows > 0)
+}
+
+pub async fn delete_role_alerts<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!("DELETE FROM role_alerts WHERE guild = $1", id_to_db(guild))
+        .execute(conn.as_mut())
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_levels_user<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    id: Id<UserMarker>,
+) -> Result<u64, Error> {
+    let mut txn = conn.begin().await?;
+    let rows = query!("DELETE FROM levels WHERE id = $1", id_to_db(id))
+        .execute(txn.as_mut())
+        .await?
+        .rows_affected();
+    query!("DELETE FROM xp_history WHERE id = $1", id_to_db(id))
+        .execute(txn.as_mut())
+        .await?;
+    query!("DELETE FROM hourly_xp WHERE id = $1", id_to_db(id))
+        .execute(txn.as_mut())
+        .await?;
+    query!("DELETE FROM season_results WHERE id = $1", id_to_db(id))
+        .execute(txn.as_mut())
+        .await?;
+    query!(
+        "DELETE FROM percentile_announcements WHERE id = $1",
+        id_to_db(id)
+    )
+    .execute(txn.as_mut())
+    .await?;
+    // Otherwise undoing a guild reset would bring back levels the user asked to have wiped
+    query!("DELETE FROM levels_deleted WHERE id = $1", id_to_db(id))
+        .execute(txn.as_mut())
+        .await?;
+    txn.commit().await?;
+    Ok(rows)
+}
+
+/// How many levels rows a guild reset moves per statement.
+/// Big guilds are reset in several short statements, rather than one that holds locks for a long time.
+const RESET_BATCH_SIZE: i64 = 1000;
+
+/// Reset every member's levels in a guild, which can be undone with `restore_levels_guild`.
+///
+/// The guild's XP history and percentile announcements are deleted outright, so past standings
+/// from before the reset are gone even if it's undone, and members can climb the percentiles again.
+pub async fn delete_levels_guild<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    id: Id<GuildMarker>,
+) -> Result<u64, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = delete_levels_guild_in_batches(conn.as_mut(), id, RESET_BATCH_SIZE).await?;
+    query!("DELETE FROM xp_history WHERE guild = $1", id_to_db(id))
+        .execute(conn.as_mut())
+        .await?;
+    query!("DELETE FROM hourly_xp WHERE guild = $1", id_to_db(id))
+        .execute(conn.as_mut())
+        .await?;
+    delete_percentile_announcements(conn.as_mut(), id).await?;
+    Ok(rows)
+}
+
+async fn delete_levels_guild_in_batches<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    id: Id<GuildMarker>,
+    batch_size: i64,
+) -> Result<u64, Error> {
+    let mut conn = conn.acquire().await?;
+    let mut total = 0;
+    loop {
+        // Levels are tombstoned rather than deleted, so a reset can be undone with `restore_levels_guild`
+        let rows = query!(
+            "WITH moved AS (DELETE FROM levels WHERE ctid IN \
+                (SELECT ctid FROM levels WHERE guild = $1 LIMIT $2) \
+                RETURNING id, guild, xp, messages, last_message) \
+                INSERT INTO levels_deleted (id, guild, xp, messages, last_message) \
+                SELECT id, guild, xp, messages, last_message FROM moved \
+                ON CONFLICT (id, guild) DO UPDATE SET \
+                xp = excluded.xp, messages = excluded.messages, \
+                last_message = excluded.last_message, deleted_at = NOW()",
+            id_to_db(id),
+            batch_size
+        )
+        .execute(conn.as_mut())
+        .await?
+        .rows_affected();
+        total += rows;
+        if rows < batch_size.unsigned_abs() {
+            return Ok(total);
+        }
+    }
+}
+
+/// Restore levels tombstoned by `delete_levels_guild` within the last `window_days` days.
+///
+/// Anything earned since the reset is kept and added on top of the restored levels, and members
+/// keep their message counts and when they were last active.
+pub async fn restore_levels_guild<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    id: Id<GuildMarker>,
+    window_days: f64,
+) -> Result<u64, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "WITH moved AS (DELETE FROM levels_deleted WHERE guild = $1 \
+            AND deleted_at > NOW() - interval '1' day * $2 \
+            RETURNING id, guild, xp, messages, last_message) \
+            INSERT INTO levels (id, guild, xp, messages, last_message) \
+            SELECT id, guild, xp, messages, COALESCE(last_message, NOW()) FROM moved \
+            ON CONFLICT (id, guild) DO UPDATE SET \
+            xp = levels.xp + excluded.xp, messages = levels.messages + excluded.messages, \
+            last_message = GREATEST(levels.last_message, excluded.last_message)",
+        id_to_db(id),
+        window_days
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows)
+}
+
+/// Permanently delete tombstoned levels older than `window_days` days
+pub async fn purge_deleted_levels<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    window_days: f64,
+) -> Result<u64, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "DELETE FROM levels_deleted WHERE deleted_at <= NOW() - interval '1' day * $1",
+        window_days
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows)
+}
+
+pub async fn ban_guild<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    id: Id<GuildMarker>,
+    duration: Option<f64>,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!(
+        "INSERT INTO guild_bans (id, expires) \
+                VALUES ($1, \
+                CASE WHEN $3 \
+                THEN NULL \
+                ELSE NOW() + interval '1' day * $2 END)",
         id_to_db(id),
         duration,
         duration.is_none()
     )
-    .execute(conn.as_mut())
-    .await?;
-    Ok(())
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+pub async fn pardon_guild<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    id: Id<GuildMarker>,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!("DELETE FROM guild_bans WHERE id = $1", id_to_db(id))
+        .execute(conn.as_mut())
+        .await?;
+    Ok(())
+}
+
+pub async fn is_guild_banned<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<bool, Error> {
+    let mut conn = conn.acquire().await?;
+    let banned = query!(
+        "SELECT id FROM guild_bans WHERE
+            ((expires > NOW()) OR (expires IS NULL))
+            AND id = $1 LIMIT 1",
+        id_to_db(guild)
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .is_some();
+    Ok(banned)
+}
+
+pub async fn update_card<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    id: Id<GenericMarker>,
+    update: &CardUpdate,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!(
+        "INSERT INTO custom_card (
+                username,
+                rank,
+                level,
+                border,
+                background,
+                progress_foreground,
+                progress_background,
+                foreground_xp_count,
+                background_xp_count,
+                font,
+                toy_image,
+                card_layout,
+                id,
+                progress_foreground_2,
+                subtitle,
+                progress_style,
+                card_size,
+                background_preset
+            ) VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, COALESCE($12, $13), $14, $15, $16, $17, $18, $19
+            ) ON CONFLICT (id) DO UPDATE SET
+                username = COALESCE($1, custom_card.username),
+                rank = COALESCE($2, custom_card.rank),
+                level = COALESCE($3, custom_card.level),
+                border = COALESCE($4, custom_card.border),
+                background = COALESCE($5, custom_card.background),
+                progress_foreground = COALESCE($6, custom_card.progress_foreground),
+                progress_background = COALESCE($7, custom_card.progress_background),
+                foreground_xp_count = COALESCE($8, custom_card.foreground_xp_count),
+                background_xp_count = COALESCE($9, custom_card.background_xp_count),
+                font = COALESCE($10, custom_card.font),
+                toy_image = COALESCE($11, custom_card.toy_image),
+                card_layout = COALESCE($12, custom_card.card_layout, $13),
+                progress_foreground_2 = COALESCE($15, custom_card.progress_foreground_2),
+                subtitle = COALESCE($16, custom_card.subtitle),
+                progress_style = COALESCE($17, custom_card.progress_style),
+                card_size = COALESCE($18, custom_card.card_size),
+                background_preset = COALESCE($19, custom_card.background_preset)",
+        update.username,
+        update.rank,
+        update.level,
+        update.border,
+        update.background,
+        update.progress_foreground,
+        update.progress_background,
+        update.foreground_xp_count,
+        update.background_xp_count,
+        update.font,
+        update.toy_image,
+        update.card_layout,
+        update.card_layout_default,
+        id_to_db(id),
+        update.progress_foreground_2,
+        update.subtitle,
+        update.progress_style,
+        update.card_size,
+        update.background_preset
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+pub async fn update_guild_config<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    cfg: UpdateGuildConfig,
+) -> Result<GuildConfig, Error> {
+    let mut conn = conn.acquire().await?;
+    let config = query_as!(
+                RawGuildConfig,
+                "INSERT INTO guild_configs (id, level_up_message, level_up_channel, ping_on_level_up, max_xp_per_message, min_xp_per_message, message_cooldown, one_at_a_time, prune_deleted_rewards, reward_removal_margin, starting_xp, count_cooldown_messages, unranked_card, attachment_bonus_xp, embed_bonus_xp, ignore_duplicate_messages, level_up_cooldown, silent_level_ups, reward_strategy, level_style, level_suffix, level_up_style, xp_per_message, xp_lockdown_until, rank_bots, reaction_xp, max_reaction_grants, percentile_announcements, disabled_commands, role_alerts, join_grace, min_account_age) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NULLIF($10::INT2, -1), $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32) \
+                ON CONFLICT (id) DO UPDATE SET \
+                level_up_message = COALESCE($2, guild_configs.level_up_message), \
+                level_up_channel = COALESCE($3, guild_configs.level_up_channel), \
+                ping_on_level_up = COALESCE($4, guild_configs.ping_on_level_up), \
+                max_xp_per_message = COALESCE($5, guild_configs.max_xp_per_message), \
+                min_xp_per_message = COALESCE($6, guild_configs.min_xp_per_message), \
+                message_cooldown = COALESCE($7, guild_configs.message_cooldown), \
+                one_at_a_time = COALESCE($8, guild_configs.one_at_a_time), \
+                prune_deleted_rewards = COALESCE($9, guild_configs.prune_deleted_rewards), \
+                reward_removal_margin = NULLIF(COALESCE($10, guild_configs.reward_removal_margin), -1), \
+                starting_xp = COALESCE($11, guild_configs.starting_xp), \
+                count_cooldown_messages = COALESCE($12, guild_configs.count_cooldown_messages), \
+                unranked_card = COALESCE($13, guild_configs.unranked_card), \
+                attachment_bonus_xp = COALESCE($14, guild_configs.attachment_bonus_xp), \
+                embed_bonus_xp = COALESCE($15, guild_configs.embed_bonus_xp), \
+                ignore_duplicate_messages = COALESCE($16, guild_configs.ignore_duplicate_messages), \
+                level_up_cooldown = COALESCE($17, guild_configs.level_up_cooldown), \
+                silent_level_ups = COALESCE($18, guild_configs.silent_level_ups), \
+                reward_strategy = COALESCE($19, guild_configs.reward_strategy), \
+                level_style = COALESCE($20, guild_configs.level_style), \
+                level_suffix = COALESCE($21, guild_configs.level_suffix), \
+                level_up_style = COALESCE($22, guild_configs.level_up_style), \
+                xp_per_message = COALESCE($23, guild_configs.xp_per_message), \
+                xp_lockdown_until = COALESCE($24, guild_configs.xp_lockdown_until), \
+                rank_bots = COALESCE($25, guild_configs.rank_bots), \
+                reaction_xp = COALESCE($26, guild_configs.reaction_xp), \
+                max_reaction_grants = COALESCE($27, guild_configs.max_reaction_grants), \
+                percentile_announcements = COALESCE($28, guild_configs.percentile_announcements), \
+                disabled_commands = COALESCE($29, guild_configs.disabled_commands), \
+                role_alerts = COALESCE($30, guild_configs.role_alerts), \
+                join_grace = COALESCE($31, guild_configs.join_grace), \
+                min_account_age = COALESCE($32, guild_configs.min_account_age) \
+                RETURNING one_at_a_time, level_up_message, level_up_channel, ping_on_level_up, \
+                max_xp_per_message, min_xp_per_message, message_cooldown, prune_deleted_rewards, \
+                reward_removal_margin, starting_xp, count_cooldown_messages, unranked_card, attachment_bonus_xp, embed_bonus_xp, ignore_duplicate_messages, level_up_cooldown, silent_level_ups, reward_strategy, level_style, level_suffix, level_up_style, xp_per_message, xp_lockdown_until, rank_bots, reaction_xp, max_reaction_grants, percentile_announcements, disabled_commands, role_alerts, join_grace, min_account_age",
+                id_to_db(guild),
+                cfg.level_up_message.map(|v| v),
+                cfg.level_up_channel.as_ref().map(|id| id_to_db(*id)),
+                cfg.ping_users,
+                cfg.max_xp_per_message,
+                cfg.min_xp_per_message,
+                cfg.message_cooldown,
+                cfg.one_at_a_time,
+                cfg.prune_deleted_rewards,
+                cfg.reward_removal_margin,
+                cfg.starting_xp,
+                cfg.count_cooldown_messages,
+                cfg.unranked_card,
+                cfg.attachment_bonus_xp,
+                cfg.embed_bonus_xp,
+                cfg.ignore_duplicate_messages,
+                cfg.level_up_cooldown,
+                cfg.silent_level_ups,
+                cfg.reward_strategy.map(RewardStrategy::as_str),
+                cfg.level_style.map(LevelStyle::as_str),
+                cfg.level_suffix,
+                cfg.level_up_style.map(LevelUpStyle::as_str),
+                cfg.xp_per_message,
+                cfg.xp_lockdown_until,
+                cfg.rank_bots,
+                cfg.reaction_xp,
+                cfg.max_reaction_grants,
+                cfg.percentile_announcements,
+                cfg.disabled_commands.as_deref(),
+                cfg.role_alerts,
+                cfg.join_grace,
+                cfg.min_account_age
+            )
+        .fetch_one(conn.as_mut())
+        .await?
+        .cook()?;
+    Ok(config)
+}
+
+/// Replace `target`'s config with a copy of `source`'s.
+///
+/// Channels belong to a single guild, so the level-up channel is replaced with `level_up_channel`.
+/// Returns false, leaving `target` untouched, if `source` has no config.
+pub async fn copy_guild_config<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    source: Id<GuildMarker>,
+    target: Id<GuildMarker>,
+    level_up_channel: Option<Id<ChannelMarker>>,
+) -> Result<bool, Error> {
+    let mut txn = conn.begin().await?;
+    query!("DELETE FROM guild_configs WHERE id = $1", id_to_db(target))
+        .execute(txn.as_mut())
+        .await?;
+    // Going through JSON copies every column, including ones added after this was written
+    let copied = query!(
+        "INSERT INTO guild_configs \
+            SELECT (jsonb_populate_record(NULL::guild_configs, to_jsonb(source) \
+                || jsonb_build_object('id', $2::INT8, 'level_up_channel', $3::INT8, \
+                'xp_lockdown_until', 0))).* \
+            FROM guild_configs source WHERE id = $1",
+        id_to_db(source),
+        id_to_db(target),
+        level_up_channel.map(id_to_db)
+    )
+    .execute(txn.as_mut())
+    .await?
+    .rows_affected();
+    if copied == 0 {
+        return Ok(false);
+    }
+    txn.commit().await?;
+    Ok(true)
 }
 
-pub async fn pardon_guild<
+pub async fn delete_guild_config<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
     A: Acquire<'a, Database = Postgres, Connection = D> + Send,
 >(
     conn: A,
-    id: Id<GuildMarker>,
+    guild: Id<GuildMarker>,
 ) -> Result<(), Error> {
     let mut conn = conn.acquire().await?;
-    query!("DELETE FROM guild_bans WHERE id = $1", id_to_db(id))
+    query!("DELETE FROM guild_configs WHERE id = $1", id_to_db(guild))
         .execute(conn.as_mut())
         .await?;
     Ok(())
 }
 
-pub async fn is_guild_banned<
+/// Add a command to a guild's disabled commands, or take it off again.
+///
+/// This only touches that one command, so commands toggled at the same time don't undo each other.
+/// Returns false if the command was already in the state asked for.
+pub async fn set_command_disabled<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
     A: Acquire<'a, Database = Postgres, Connection = D> + Send,
 >(
     conn: A,
     guild: Id<GuildMarker>,
+    name: &str,
+    disabled: bool,
 ) -> Result<bool, Error> {
     let mut conn = conn.acquire().await?;
-    let banned = query!(
-        "SELECT id FROM guild_bans WHERE
-            ((expires > NOW()) OR (expires IS NULL))
-            AND id = $1 LIMIT 1",
+    let rows = if disabled {
+        query!(
+            "INSERT INTO guild_configs (id, disabled_commands) VALUES ($1, ARRAY[$2::TEXT]) \
+                ON CONFLICT (id) DO UPDATE SET disabled_commands = \
+                array_append(COALESCE(guild_configs.disabled_commands, '{}'), $2) \
+                WHERE NOT $2 = ANY(COALESCE(guild_configs.disabled_commands, '{}'))",
+            id_to_db(guild),
+            name
+        )
+        .execute(conn.as_mut())
+        .await?
+    } else {
+        query!(
+            "UPDATE guild_configs SET disabled_commands = array_remove(disabled_commands, $2) \
+                WHERE id = $1 AND $2 = ANY(disabled_commands)",
+            id_to_db(guild),
+            name
+        )
+        .execute(conn.as_mut())
+        .await?
+    };
+    Ok(rows.rows_affected() > 0)
+}
+
+/// Guilds where nobody has earned XP in the last `days` days, least recently active first
+pub async fn stale_guilds<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    days: f64,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<StaleGuild>, Error> {
+    let mut conn = conn.acquire().await?;
+    let guilds = query!(
+        "SELECT guild, EXTRACT(EPOCH FROM MAX(last_message))::INT8 AS last_message \
+            FROM levels GROUP BY guild \
+            HAVING MAX(last_message) < NOW() - interval '1' day * $1 \
+            ORDER BY MAX(last_message) ASC, guild ASC LIMIT $2 OFFSET $3",
+        days,
+        limit,
+        offset
+    )
+    .fetch_all(conn.as_mut())
+    .await?
+    .into_iter()
+    .map(|row| StaleGuild {
+        id: db_to_id(row.guild),
+        last_message: row.last_message.unwrap_or(0),
+    })
+    .collect();
+    Ok(guilds)
+}
+
+pub async fn level_up_webhook<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<Option<LevelUpWebhook>, Error> {
+    let mut conn = conn.acquire().await?;
+    let webhook = query_as!(
+        LevelUpWebhook,
+        "SELECT url, secret FROM level_up_webhooks WHERE guild = $1",
+        id_to_db(guild)
+    )
+    .fetch_optional(conn.as_mut())
+    .await?;
+    Ok(webhook)
+}
+
+pub async fn set_level_up_webhook<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    webhook: &LevelUpWebhook,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!(
+        "INSERT INTO level_up_webhooks (guild, url, secret) VALUES ($1, $2, $3) \
+            ON CONFLICT (guild) DO UPDATE SET url = excluded.url, secret = excluded.secret",
+        id_to_db(guild),
+        webhook.url,
+        webhook.secret
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_level_up_webhook<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<bool, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "DELETE FROM level_up_webhooks WHERE guild = $1",
+        id_to_db(guild)
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows > 0)
+}
+
+/// The channel administrative XP changes in this guild are logged to, if any
+pub async fn audit_channel<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<Option<Id<ChannelMarker>>, Error> {
+    let mut conn = conn.acquire().await?;
+    let channel = query!(
+        "SELECT channel FROM audit_channels WHERE guild = $1",
         id_to_db(guild)
     )
     .fetch_optional(conn.as_mut())
     .await?
-    .is_some();
-    Ok(banned)
+    .map(|row| db_to_id(row.channel));
+    Ok(channel)
 }
 
-pub async fn update_card<
+pub async fn set_audit_channel<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
     A: Acquire<'a, Database = Postgres, Connection = D> + Send,
 >(
     conn: A,
-    id: Id<GenericMarker>,
-    update: &CardUpdate,
+    guild: Id<GuildMarker>,
+    channel: Id<ChannelMarker>,
 ) -> Result<(), Error> {
     let mut conn = conn.acquire().await?;
     query!(
-        "INSERT INTO custom_card (
-                username,
-                rank,
-                level,
-                border,
-                background,
-                progress_foreground,
-                progress_background,
-                foreground_xp_count,
-                background_xp_count,
-                font,
-                toy_image,
-                card_layout,
-                id
-            ) VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, COALESCE($12, $13), $14
-            ) ON CONFLICT (id) DO UPDATE SET
-                username = COALESCE($1, custom_card.username),
-                rank = COALESCE($2, custom_card.rank),
-                level = COALESCE($3, custom_card.level),
-                border = COALESCE($4, custom_card.border),
-                background = COALESCE($5, custom_card.background),
-                progress_foreground = COALESCE($6, custom_card.progress_foreground),
-                progress_background = COALESCE($7, custom_card.progress_background),
-                foreground_xp_count = COALESCE($8, custom_card.foreground_xp_count),
-                background_xp_count = COALESCE($9, custom_card.background_xp_count),
-                font = COALESCE($10, custom_card.font),
-                toy_image = COALESCE($11, custom_card.toy_image),
-                card_layout = COALESCE($12, custom_card.card_layout, $13)",
-        update.username,
-        update.rank,
-        update.level,
-        update.border,
-        update.background,
-        update.progress_foreground,
-        update.progress_background,
-        update.foreground_xp_count,
-        update.background_xp_count,
-        update.font,
-        update.toy_image,
-        update.card_layout,
-        update.card_layout_default,
-        id_to_db(id)
+        "INSERT INTO audit_channels (guild, channel) VALUES ($1, $2) \
+            ON CONFLICT (guild) DO UPDATE SET channel = excluded.channel",
+        id_to_db(guild),
+        id_to_db(channel)
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_audit_channel<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<bool, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "DELETE FROM audit_channels WHERE guild = $1",
+        id_to_db(guild)
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows > 0)
+}
+
+/// Reset a guild's levels every `cadence`, starting one season from now
+pub async fn set_season_schedule<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    cadence: SeasonCadence,
+) -> Result<SeasonSchedule, Error> {
+    let mut conn = conn.acquire().await?;
+    let (months, days) = cadence.interval();
+    let next_reset = query!(
+        "INSERT INTO season_schedules (guild, cadence, next_reset) \
+            VALUES ($1, $2, NOW() + MAKE_INTERVAL(months => $3, days => $4)) \
+            ON CONFLICT (guild) DO UPDATE SET \
+            cadence = excluded.cadence, next_reset = excluded.next_reset \
+            RETURNING EXTRACT(EPOCH FROM next_reset)::INT8 AS \"next_reset!\"",
+        id_to_db(guild),
+        cadence.as_str(),
+        months,
+        days
+    )
+    .fetch_one(conn.as_mut())
+    .await?
+    .next_reset;
+    Ok(SeasonSchedule {
+        cadence,
+        next_reset,
+    })
+}
+
+pub async fn season_schedule<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<Option<SeasonSchedule>, Error> {
+    let mut conn = conn.acquire().await?;
+    let schedule = query!(
+        "SELECT cadence, EXTRACT(EPOCH FROM next_reset)::INT8 AS \"next_reset!\" \
+            FROM season_schedules WHERE guild = $1",
+        id_to_db(guild)
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .map(|row| SeasonSchedule {
+        cadence: SeasonCadence::from_name(&row.cadence).unwrap_or_default(),
+        next_reset: row.next_reset,
+    });
+    Ok(schedule)
+}
+
+pub async fn delete_season_schedule<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<bool, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "DELETE FROM season_schedules WHERE guild = $1",
+        id_to_db(guild)
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows > 0)
+}
+
+/// Guilds whose current season is over, other than those in `skip`
+pub async fn due_season_resets<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    limit: i64,
+    skip: &[Id<GuildMarker>],
+) -> Result<Vec<Id<GuildMarker>>, Error> {
+    let mut conn = conn.acquire().await?;
+    let skip: Vec<i64> = skip.iter().copied().map(id_to_db).collect();
+    let guilds = query!(
+        "SELECT guild FROM season_schedules WHERE next_reset <= NOW() \
+            AND guild <> ALL($2) ORDER BY next_reset LIMIT $1",
+        limit,
+        &skip
+    )
+    .fetch_all(conn.as_mut())
+    .await?
+    .into_iter()
+    .map(|row| db_to_id(row.guild))
+    .collect();
+    Ok(guilds)
+}
+
+/// End a guild's season if it's due: archive the final standings to `season_results`,
+/// reset everyone's levels and schedule the next reset, all in one transaction.
+///
+/// Returns None if the guild has no schedule or its season isn't over yet.
+pub async fn end_season<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<Option<EndedSeason>, Error> {
+    let mut txn = conn.begin().await?;
+    // Locking the schedule keeps two workers from ending the same season twice
+    let Some(schedule) = query!(
+        "SELECT cadence FROM season_schedules \
+            WHERE guild = $1 AND next_reset <= NOW() FOR UPDATE",
+        id_to_db(guild)
+    )
+    .fetch_optional(txn.as_mut())
+    .await?
+    else {
+        return Ok(None);
+    };
+    let cadence = SeasonCadence::from_name(&schedule.cadence).unwrap_or_default();
+    let season = query!(
+        "SELECT COALESCE(MAX(season), 0) + 1 AS \"season!\" FROM season_results WHERE guild = $1",
+        id_to_db(guild)
     )
-    .execute(conn.as_mut())
-    .await?;
-    Ok(())
+    .fetch_one(txn.as_mut())
+    .await?
+    .season;
+    let archived = query!(
+        "INSERT INTO season_results (guild, season, id, xp, rank) \
+            SELECT guild, $2, id, xp, RANK() OVER (ORDER BY xp DESC) \
+            FROM levels WHERE guild = $1 AND xp > 0",
+        id_to_db(guild),
+        season
+    )
+    .execute(txn.as_mut())
+    .await?
+    .rows_affected();
+    delete_levels_guild(txn.as_mut(), guild).await?;
+    let (months, days) = cadence.interval();
+    // A bot that was down for a whole season skips ahead, instead of resetting over and over
+    let next_reset = query!(
+        "UPDATE season_schedules SET next_reset = CASE \
+            WHEN next_reset + MAKE_INTERVAL(months => $2, days => $3) > NOW() \
+            THEN next_reset + MAKE_INTERVAL(months => $2, days => $3) \
+            ELSE NOW() + MAKE_INTERVAL(months => $2, days => $3) END \
+            WHERE guild = $1 RETURNING EXTRACT(EPOCH FROM next_reset)::INT8 AS \"next_reset!\"",
+        id_to_db(guild),
+        months,
+        days
+    )
+    .fetch_one(txn.as_mut())
+    .await?
+    .next_reset;
+    txn.commit().await?;
+    Ok(Some(EndedSeason {
+        season,
+        archived,
+        next_reset,
+    }))
 }
 
-pub async fn update_guild_config<
+/// The best `limit` users of a guild's archived season
+pub async fn season_standings<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
     A: Acquire<'a, Database = Postgres, Connection = D> + Send,
 >(
     conn: A,
     guild: Id<GuildMarker>,
-    cfg: UpdateGuildConfig,
-) -> Result<GuildConfig, Error> {
+    season: i32,
+    limit: i64,
+) -> Result<Vec<RankedUser>, Error> {
     let mut conn = conn.acquire().await?;
-    let config = query_as!(
-                RawGuildConfig,
-                "INSERT INTO guild_configs (id, level_up_message, level_up_channel, ping_on_level_up, max_xp_per_message, min_xp_per_message, message_cooldown, one_at_a_time) \
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
-                ON CONFLICT (id) DO UPDATE SET \
-                level_up_message = COALESCE($2, guild_configs.level_up_message), \
-                level_up_channel = COALESCE($3, guild_configs.level_up_channel), \
-                ping_on_level_up = COALESCE($4, guild_configs.ping_on_level_up), \
-                max_xp_per_message = COALESCE($5, guild_configs.max_xp_per_message), \
-                min_xp_per_message = COALESCE($6, guild_configs.min_xp_per_message), \
-                message_cooldown = COALESCE($7, guild_configs.message_cooldown), \
-                one_at_a_time = COALESCE($8, guild_configs.one_at_a_time) \
-                RETURNING one_at_a_time, level_up_message, level_up_channel, ping_on_level_up, \
-                max_xp_per_message, min_xp_per_message, message_cooldown",
-                id_to_db(guild),
-                cfg.level_up_message.map(|v| v),
-                cfg.level_up_channel.as_ref().map(|id| id_to_db(*id)),
-                cfg.ping_users,
-                cfg.max_xp_per_message,
-                cfg.min_xp_per_message,
-                cfg.message_cooldown,
-                cfg.one_at_a_time
-            )
-        .fetch_one(conn.as_mut())
-        .await?
-        .cook()?;
-    Ok(config)
+    let users = query!(
+        "SELECT id, xp, rank FROM season_results WHERE guild = $1 AND season = $2 \
+            ORDER BY rank, id LIMIT $3",
+        id_to_db(guild),
+        season,
+        limit
+    )
+    .fetch_all(conn.as_mut())
+    .await?
+    .into_iter()
+    .map(|row| RankedUser {
+        id: db_to_id(row.id),
+        xp: row.xp,
+        rank: row.rank,
+    })
+    .collect();
+    Ok(users)
 }
 
-pub async fn delete_guild_config<
+pub async fn delete_season_results<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
     A: Acquire<'a, Database = Postgres, Connection = D> + Send,
 >(
     conn: A,
     guild: Id<GuildMarker>,
-) -> Result<(), Error> {
+) -> Result<u64, Error> {
     let mut conn = conn.acquire().await?;
-    query!("DELETE FROM guild_configs WHERE id = $1", id_to_db(guild))
-        .execute(conn.as_mut())
-        .await?;
-    Ok(())
+    let rows = query!(
+        "DELETE FROM season_results WHERE guild = $1",
+        id_to_db(guild)
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows)
 }
 
 pub async fn add_guild_cleanup<
@@ -642,6 +2459,33 @@ pub async fn get_active_guild_cleanups<
     Ok(output)
 }
 
+/// Where the user is listed on the leaderboard, counting from 1, or None if they aren't on it.
+///
+/// This follows the leaderboard's own ordering, so unlike the rank, users tied on XP are
+/// listed one after another.
+pub async fn leaderboard_position<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    user: Id<UserMarker>,
+) -> Result<Option<i64>, Error> {
+    let mut conn = conn.acquire().await?;
+    let position = query!(
+        "SELECT (SELECT COUNT(*) FROM levels ahead WHERE ahead.guild = levels.guild \
+            AND (ahead.xp, ahead.id) > (levels.xp, levels.id)) + 1 AS \"position!\" \
+            FROM levels WHERE guild = $1 AND id = $2",
+        id_to_db(guild),
+        id_to_db(user)
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .map(|row| row.position);
+    Ok(position)
+}
+
 pub async fn get_leaderboard_page<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
@@ -654,8 +2498,82 @@ pub async fn get_leaderboard_page<
 ) -> Result<Vec<UserStatus>, Error> {
     let mut conn = conn.acquire().await?;
     let mut users = query!(
-        "SELECT * FROM levels WHERE guild = $1 ORDER BY (xp, id) DESC LIMIT $2 OFFSET $3",
+        "SELECT id, xp FROM levels WHERE guild = $1 ORDER BY (xp, id) DESC LIMIT $2 OFFSET $3",
+        id_to_db(guild),
+        limit,
+        offset
+    )
+    .fetch(conn.as_mut());
+    let mut output = Vec::with_capacity(limit.try_into().unwrap_or(10));
+    while let Some(rec) = users.next().await.transpose()? {
+        let status = UserStatus {
+            id: db_to_id(rec.id),
+            guild,
+            xp: rec.xp,
+        };
+        output.push(status);
+    }
+    Ok(output)
+}
+
+/// Users whose XP is between `min` and `max` inclusive, highest first, along with how many
+/// users match in total.
+pub async fn users_in_xp_range<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    min: i64,
+    max: i64,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<UserStatus>, i64), Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "SELECT id, xp, COUNT(*) OVER () AS total FROM levels \
+            WHERE guild = $1 AND xp BETWEEN $2 AND $3 \
+            ORDER BY (xp, id) DESC LIMIT $4 OFFSET $5",
+        id_to_db(guild),
+        min,
+        max,
+        limit,
+        offset
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    let total = rows.first().and_then(|row| row.total).unwrap_or(0);
+    let users = rows
+        .into_iter()
+        .map(|row| UserStatus {
+            id: db_to_id(row.id),
+            guild,
+            xp: row.xp,
+        })
+        .collect();
+    Ok((users, total))
+}
+
+/// Like [`get_leaderboard_page`], but only ranking the given users
+pub async fn get_leaderboard_page_among<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    users: &[Id<UserMarker>],
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<UserStatus>, Error> {
+    let mut conn = conn.acquire().await?;
+    let users: Vec<i64> = users.iter().copied().map(id_to_db).collect();
+    let mut users = query!(
+        "SELECT id, xp FROM levels WHERE guild = $1 AND id = ANY($2) \
+            ORDER BY (xp, id) DESC LIMIT $3 OFFSET $4",
         id_to_db(guild),
+        &users,
         limit,
         offset
     )
@@ -672,6 +2590,94 @@ pub async fn get_leaderboard_page<
     Ok(output)
 }
 
+pub async fn guild_role_cooldowns<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<Vec<RoleCooldown>, Error> {
+    let mut conn = conn.acquire().await?;
+    let cooldowns = query!(
+        "SELECT role, cooldown FROM role_cooldowns WHERE guild = $1 ORDER BY role",
+        id_to_db(guild)
+    )
+    .fetch_all(conn.as_mut())
+    .await?
+    .into_iter()
+    .map(|row| RoleCooldown {
+        role: db_to_id(row.role),
+        cooldown: row.cooldown,
+    })
+    .collect();
+    Ok(cooldowns)
+}
+
+pub async fn set_role_cooldown<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    role: Id<RoleMarker>,
+    cooldown: i16,
+) -> Result<(), Error> {
+    let mut conn = conn.acquire().await?;
+    query!(
+        "INSERT INTO role_cooldowns (guild, role, cooldown) VALUES ($1, $2, $3) \
+            ON CONFLICT (guild, role) DO UPDATE SET cooldown = excluded.cooldown",
+        id_to_db(guild),
+        id_to_db(role),
+        cooldown
+    )
+    .execute(conn.as_mut())
+    .await?;
+    Ok(())
+}
+
+/// Returns whether the role had a cooldown override.
+pub async fn delete_role_cooldown<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+    role: Id<RoleMarker>,
+) -> Result<bool, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "DELETE FROM role_cooldowns WHERE guild = $1 AND role = $2",
+        id_to_db(guild),
+        id_to_db(role)
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows > 0)
+}
+
+pub async fn delete_guild_role_cooldowns<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<u64, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!(
+        "DELETE FROM role_cooldowns WHERE guild = $1",
+        id_to_db(guild)
+    )
+    .execute(conn.as_mut())
+    .await?
+    .rows_affected();
+    Ok(rows)
+}
+
 pub async fn add_reward_role<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
@@ -724,6 +2730,23 @@ pub async fn delete_reward_role<
     Ok(rows)
 }
 
+/// Delete every role reward in a guild, returning how many there were.
+pub async fn delete_guild_rewards<
+    'a,
+    D: DerefMut<Target = PgConnection> + Send,
+    A: Acquire<'a, Database = Postgres, Connection = D> + Send,
+>(
+    conn: A,
+    guild: Id<GuildMarker>,
+) -> Result<u64, Error> {
+    let mut conn = conn.acquire().await?;
+    let rows = query!("DELETE FROM role_rewards WHERE guild = $1", id_to_db(guild))
+        .execute(conn.as_mut())
+        .await?
+        .rows_affected();
+    Ok(rows)
+}
+
 pub async fn export_bulk_users<
     'a,
     D: DerefMut<Target = PgConnection> + Send,
@@ -731,25 +2754,133 @@ pub async fn export_bulk_users<
 >(
     conn: A,
     guild: Id<GuildMarker>,
-) -> Result<Vec<UserStatus>, Error> {
+) -> Result<Vec<ExportedUser>, Error> {
     let mut conn = conn.acquire().await?;
     let mut records = query!(
-        "SELECT id, xp FROM levels WHERE guild = $1",
+        "SELECT id, xp, messages FROM levels WHERE guild = $1",
         id_to_db(guild)
     )
     .fetch(conn.as_mut());
     let mut out = Vec::with_capacity(256);
     while let Some(Ok(rec)) = records.next().await {
-        let status = UserStatus {
+        let user = ExportedUser {
             id: db_to_id(rec.id),
-            guild,
             xp: rec.xp,
+            messages: rec.messages,
         };
-        out.push(status);
+        out.push(user);
     }
     Ok(out)
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RankedUser {
+    pub id: Id<UserMarker>,
+    pub xp: i64,
+    /// 1-based leaderboard position
+    pub rank: i64,
+}
+
+/// A user's levels in a guild now, and when their last weekly summary was sent
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct WeeklyProgress {
+    pub guild: Id<GuildMarker>,
+    pub xp: i64,
+    pub rank: i64,
+    /// XP and rank at the last summary, or None if they weren't in the guild yet
+    pub previous: Option<(i64, i64)>,
+}
+
+/// When a guild's levels are next reset, and how often
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SeasonSchedule {
+    pub cadence: SeasonCadence,
+    /// Unix timestamp of the next reset
+    pub next_reset: i64,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct EndedSeason {
+    /// 1-based number of the season that just ended
+    pub season: i32,
+    /// How many users' standings were archived
+    pub archived: u64,
+    /// Unix timestamp of the end of the new season
+    pub next_reset: i64,
+}
+
+/// A reward handed out only once per member, from [`one_time_rewards`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OneTimeReward {
+    pub name: String,
+    pub requirement: i64,
+    /// How many members have claimed it
+    pub claims: i64,
+}
+
+/// A one-time reward someone claimed, from [`claimed_rewards`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimedReward {
+    pub reward: String,
+    /// When it was claimed, in seconds since the Unix epoch
+    pub claimed_at: i64,
+}
+
+/// How many members are at a level, from [`level_distribution`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelCount {
+    pub level: i64,
+    pub members: i64,
+}
+
+/// A user's XP in a guild at the end of a day
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct XpHistoryEntry {
+    /// The UTC day, as `YYYY-MM-DD`
+    pub day: String,
+    pub xp: i64,
+}
+
+/// Someone's standing in a past snapshot of a guild, from [`rank_at_snapshot`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SnapshotRank {
+    /// The UTC day of the snapshot, as `YYYY-MM-DD`
+    pub day: String,
+    pub xp: i64,
+    pub rank: i64,
+    /// How many members were in the snapshot
+    pub members: i64,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct UserPreferences {
+    pub timezone: Option<String>,
+    /// Whether level-up messages are sent in DMs instead of the guild
+    pub level_up_dms: bool,
+    pub weekly_summary: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct StaleGuild {
+    pub id: Id<GuildMarker>,
+    /// Unix timestamp of the last time anyone earned XP in this guild
+    pub last_message: i64,
+}
+
+/// Where to send level-ups for a guild, and the secret their signatures are made with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelUpWebhook {
+    pub url: String,
+    pub secret: String,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ExportedUser {
+    pub id: Id<UserMarker>,
+    pub xp: i64,
+    pub messages: i64,
+}
+
 #[derive(Default)]
 pub struct UpdateGuildConfig {
     pub level_up_message: Option<String>,
@@ -759,6 +2890,31 @@ pub struct UpdateGuildConfig {
     pub min_xp_per_message: Option<i16>,
     pub message_cooldown: Option<i16>,
     pub one_at_a_time: Option<bool>,
+    pub prune_deleted_rewards: Option<bool>,
+    /// `-1` stops reward roles from being removed again, which is stored as NULL
+    pub reward_removal_margin: Option<i16>,
+    pub starting_xp: Option<i64>,
+    pub count_cooldown_messages: Option<bool>,
+    pub unranked_card: Option<bool>,
+    pub attachment_bonus_xp: Option<i16>,
+    pub embed_bonus_xp: Option<i16>,
+    pub ignore_duplicate_messages: Option<bool>,
+    pub level_up_cooldown: Option<i16>,
+    pub silent_level_ups: Option<bool>,
+    pub reward_strategy: Option<RewardStrategy>,
+    pub level_style: Option<LevelStyle>,
+    pub level_suffix: Option<String>,
+    pub level_up_style: Option<LevelUpStyle>,
+    pub xp_per_message: Option<i16>,
+    pub xp_lockdown_until: Option<i64>,
+    pub rank_bots: Option<bool>,
+    pub reaction_xp: Option<i16>,
+    pub max_reaction_grants: Option<i16>,
+    pub percentile_announcements: Option<i16>,
+    pub disabled_commands: Option<Vec<String>>,
+    pub role_alerts: Option<bool>,
+    pub join_grace: Option<i16>,
+    pub min_account_age: Option<i16>,
 }
 
 macro_rules! setter {
@@ -789,6 +2945,54 @@ impl UpdateGuildConfig {
 
     setter!(one_at_a_time, bool);
 
+    setter!(prune_deleted_rewards, bool);
+
+    setter!(reward_removal_margin, i16);
+
+    setter!(starting_xp, i64);
+
+    setter!(count_cooldown_messages, bool);
+
+    setter!(unranked_card, bool);
+
+    setter!(attachment_bonus_xp, i16);
+
+    setter!(embed_bonus_xp, i16);
+
+    setter!(ignore_duplicate_messages, bool);
+
+    setter!(level_up_cooldown, i16);
+
+    setter!(silent_level_ups, bool);
+
+    setter!(reward_strategy, RewardStrategy);
+
+    setter!(level_style, LevelStyle);
+
+    setter!(level_suffix, String);
+
+    setter!(level_up_style, LevelUpStyle);
+
+    setter!(xp_per_message, i16);
+
+    setter!(xp_lockdown_until, i64);
+
+    setter!(rank_bots, bool);
+
+    setter!(reaction_xp, i16);
+
+    setter!(max_reaction_grants, i16);
+
+    setter!(percentile_announcements, i16);
+
+    setter!(disabled_commands, Vec<String>);
+
+    setter!(role_alerts, bool);
+
+    setter!(join_grace, i16);
+
+    setter!(min_account_age, i16);
+
     #[must_use]
     pub fn new() -> Self {
         Self::default()
@@ -803,12 +3007,19 @@ pub struct CardUpdate {
     pub background: Option<String>,
     pub progress_background: Option<String>,
     pub progress_foreground: Option<String>,
+    pub progress_foreground_2: Option<String>,
     pub foreground_xp_count: Option<String>,
     pub background_xp_count: Option<String>,
     pub font: Option<String>,
     pub toy_image: Option<String>,
     pub card_layout: Option<String>,
     pub card_layout_default: String,
+    /// An empty string clears the subtitle
+    pub subtitle: Option<String>,
+    pub progress_style: Option<String>,
+    pub card_size: Option<String>,
+    /// An empty string clears the background image
+    pub background_preset: Option<String>,
 }
 
 pub struct RawCustomizations {
@@ -818,12 +3029,17 @@ pub struct RawCustomizations {
     pub border: Option<String>,
     pub background: Option<String>,
     pub progress_foreground: Option<String>,
+    pub progress_foreground_2: Option<String>,
     pub progress_background: Option<String>,
     pub background_xp_count: Option<String>,
     pub foreground_xp_count: Option<String>,
     pub font: Option<String>,
     pub toy_image: Option<String>,
     pub card_layout: String,
+    pub subtitle: Option<String>,
+    pub progress_style: Option<String>,
+    pub card_size: Option<String>,
+    pub background_preset: Option<String>,
     #[allow(dead_code)]
     id: I64Placeholder,
     #[allow(dead_code)]
@@ -854,6 +3070,30 @@ pub struct RawGuildConfig {
     pub min_xp_per_message: Option<i16>,
     pub max_xp_per_message: Option<i16>,
     pub message_cooldown: Option<i16>,
+    pub prune_deleted_rewards: Option<bool>,
+    pub reward_removal_margin: Option<i16>,
+    pub starting_xp: Option<i64>,
+    pub count_cooldown_messages: Option<bool>,
+    pub unranked_card: Option<bool>,
+    pub attachment_bonus_xp: Option<i16>,
+    pub embed_bonus_xp: Option<i16>,
+    pub ignore_duplicate_messages: Option<bool>,
+    pub level_up_cooldown: Option<i16>,
+    pub silent_level_ups: Option<bool>,
+    pub reward_strategy: Option<String>,
+    pub level_style: Option<String>,
+    pub level_suffix: Option<String>,
+    pub level_up_style: Option<String>,
+    pub xp_per_message: Option<i16>,
+    pub xp_lockdown_until: Option<i64>,
+    pub rank_bots: Option<bool>,
+    pub reaction_xp: Option<i16>,
+    pub max_reaction_grants: Option<i16>,
+    pub percentile_announcements: Option<i16>,
+    pub disabled_commands: Option<Vec<String>>,
+    pub role_alerts: Option<bool>,
+    pub join_grace: Option<i16>,
+    pub min_account_age: Option<i16>,
 }
 
 impl RawGuildConfig {
@@ -872,6 +3112,36 @@ impl RawGuildConfig {
             min_xp_per_message: self.min_xp_per_message,
             max_xp_per_message: self.max_xp_per_message,
             cooldown: self.message_cooldown,
+            prune_deleted_rewards: self.prune_deleted_rewards,
+            reward_removal_margin: self.reward_removal_margin,
+            starting_xp: self.starting_xp,
+            count_cooldown_messages: self.count_cooldown_messages,
+            unranked_card: self.unranked_card,
+            attachment_bonus_xp: self.attachment_bonus_xp,
+            embed_bonus_xp: self.embed_bonus_xp,
+            ignore_duplicate_messages: self.ignore_duplicate_messages,
+            level_up_cooldown: self.level_up_cooldown,
+            silent_level_ups: self.silent_level_ups,
+            reward_strategy: self
+                .reward_strategy
+                .as_deref()
+                .and_then(RewardStrategy::from_name),
+            level_style: self.level_style.as_deref().and_then(LevelStyle::from_name),
+            level_suffix: self.level_suffix,
+            level_up_style: self
+                .level_up_style
+                .as_deref()
+                .and_then(LevelUpStyle::from_name),
+            xp_per_message: self.xp_per_message,
+            xp_lockdown_until: self.xp_lockdown_until,
+            rank_bots: self.rank_bots,
+            reaction_xp: self.reaction_xp,
+            max_reaction_grants: self.max_reaction_grants,
+            percentile_announcements: self.percentile_announcements,
+            disabled_commands: self.disabled_commands,
+            role_alerts: self.role_alerts,
+            join_grace: self.join_grace,
+            min_account_age: self.min_account_age,
         };
         Ok(gc)
     }
@@ -0,0 +1,124 @@
+use std::{future::Future, time::Duration};
+
+use crate::Error;
+
+/// How reads are retried after database errors that usually clear up on their own, like a dropped connection.
+/// Only use this for queries which are safe to run twice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times to try, including the first. 1 never retries.
+    pub attempts: u32,
+    /// How long to wait before the first retry. This doubles after every retry.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(50),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Try the query once, and give up on any error
+    pub const NEVER: Self = Self {
+        attempts: 1,
+        backoff: Duration::ZERO,
+    };
+
+    /// Run `query` until it succeeds, fails with an error retrying won't fix, or runs out of attempts.
+    pub async fn run<T, F, Fut>(self, mut query: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut + Send,
+        Fut: Future<Output = Result<T, Error>> + Send,
+    {
+        let mut backoff = self.backoff;
+        let mut attempt = 1;
+        loop {
+            match query().await {
+                Err(error) if attempt < self.attempts && error.is_retryable() => {
+                    tracing::warn!(%error, attempt, "Retrying database read");
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Error {
+    /// Whether the same query might succeed if it's tried again, because the error was with the
+    /// connection rather than the query. Constraint violations and the like are never retryable.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        let Self::Database(error) = self else {
+            return false;
+        };
+        match error {
+            sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed => true,
+            sqlx::Error::Database(error) => error.code().is_some_and(|code| {
+                // Connection exceptions, the server shutting down or starting up,
+                // and transactions which lost a race with another one
+                code.starts_with("08")
+                    || matches!(
+                        code.as_ref(),
+                        "57P01" | "57P02" | "57P03" | "40001" | "40P01"
+                    )
+            }),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn only_connection_errors_are_retried() {
+        let policy = RetryPolicy {
+            attempts: 3,
+            backoff: Duration::ZERO,
+        };
+        let mut tries = 0;
+        let result = policy
+            .run(|| {
+                tries += 1;
+                let fail = tries < 3;
+                async move {
+                    if fail {
+                        Err(Error::Database(sqlx::Error::PoolTimedOut))
+                    } else {
+                        Ok(tries)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut tries = 0;
+        let result: Result<(), Error> = policy
+            .run(|| {
+                tries += 1;
+                async { Err(Error::Database(sqlx::Error::PoolTimedOut)) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(tries, 3);
+
+        // A missing row will still be missing the next time
+        let mut tries = 0;
+        let result: Result<(), Error> = policy
+            .run(|| {
+                tries += 1;
+                async { Err(Error::Database(sqlx::Error::RowNotFound)) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(tries, 1);
+    }
+}
@@ -0,0 +1,178 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use twilight_model::id::{
+    marker::{GuildMarker, UserMarker},
+    Id,
+};
+use xpd_common::UserStatus;
+
+use crate::{Error, RetryPolicy};
+
+/// The XP operations the leveling engine needs, so it can run on storage other than Postgres.
+///
+/// This is object-safe, so commands can take a `&dyn XpStore`.
+#[async_trait]
+pub trait XpStore: Send + Sync {
+    /// Get a user's XP in a guild, or `None` if they have never earned any.
+    async fn get_xp(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+    ) -> Result<Option<i64>, Error>;
+
+    /// Add XP to a user, returning their new total.
+    async fn add_xp(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+        amount: i64,
+    ) -> Result<i64, Error>;
+
+    /// Get the 1-based leaderboard position of a user. Users with no XP rank after everyone else.
+    async fn rank_for_user(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+    ) -> Result<i64, Error>;
+
+    /// Get a user's XP, which is 0 if they have none, and their leaderboard position in one read,
+    /// as `(xp, rank)`.
+    async fn xp_and_rank(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+    ) -> Result<(i64, i64), Error>;
+
+    /// Count the users on a guild's leaderboard.
+    async fn ranked_users(&self, guild: Id<GuildMarker>) -> Result<i64, Error>;
+
+    /// Get `limit` users from the leaderboard, skipping the first `offset`, highest XP first.
+    async fn top_n(
+        &self,
+        guild: Id<GuildMarker>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<UserStatus>, Error>;
+}
+
+#[async_trait]
+impl XpStore for PgPool {
+    async fn get_xp(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+    ) -> Result<Option<i64>, Error> {
+        crate::user_xp(self, guild, user).await
+    }
+
+    async fn add_xp(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+        amount: i64,
+    ) -> Result<i64, Error> {
+        crate::add_xp(self, user, guild, amount).await
+    }
+
+    async fn rank_for_user(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+    ) -> Result<i64, Error> {
+        Ok(self.xp_and_rank(user, guild).await?.1)
+    }
+
+    async fn xp_and_rank(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+    ) -> Result<(i64, i64), Error> {
+        crate::user_xp_and_rank(self, guild, user).await
+    }
+
+    async fn ranked_users(&self, guild: Id<GuildMarker>) -> Result<i64, Error> {
+        crate::levels_in_guild(self, guild).await
+    }
+
+    async fn top_n(
+        &self,
+        guild: Id<GuildMarker>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<UserStatus>, Error> {
+        crate::get_leaderboard_page(self, guild, limit, offset).await
+    }
+}
+
+/// A Postgres [`XpStore`] which retries reads that fail for reasons that usually clear up on their own.
+/// Adding XP isn't safe to repeat, so it's only ever tried once.
+#[derive(Clone, Debug)]
+pub struct RetryingStore {
+    pool: PgPool,
+    policy: RetryPolicy,
+}
+
+impl RetryingStore {
+    #[must_use]
+    pub const fn new(pool: PgPool, policy: RetryPolicy) -> Self {
+        Self { pool, policy }
+    }
+
+    #[must_use]
+    pub const fn policy(&self) -> RetryPolicy {
+        self.policy
+    }
+}
+
+#[async_trait]
+impl XpStore for RetryingStore {
+    async fn get_xp(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+    ) -> Result<Option<i64>, Error> {
+        self.policy.run(|| self.pool.get_xp(user, guild)).await
+    }
+
+    async fn add_xp(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+        amount: i64,
+    ) -> Result<i64, Error> {
+        self.pool.add_xp(user, guild, amount).await
+    }
+
+    async fn rank_for_user(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+    ) -> Result<i64, Error> {
+        self.policy
+            .run(|| self.pool.rank_for_user(user, guild))
+            .await
+    }
+
+    async fn xp_and_rank(
+        &self,
+        user: Id<UserMarker>,
+        guild: Id<GuildMarker>,
+    ) -> Result<(i64, i64), Error> {
+        self.policy.run(|| self.pool.xp_and_rank(user, guild)).await
+    }
+
+    async fn ranked_users(&self, guild: Id<GuildMarker>) -> Result<i64, Error> {
+        self.policy.run(|| self.pool.ranked_users(guild)).await
+    }
+
+    async fn top_n(
+        &self,
+        guild: Id<GuildMarker>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<UserStatus>, Error> {
+        self.policy
+            .run(|| self.pool.top_n(guild, limit, offset))
+            .await
+    }
+}
@@ -32,3 +32,929 @@ async fn find_deletes_returns_correctly(db: PgPool) -> Result<(), Box<dyn std::e
     assert!(!cleanups.contains(&Id::new(2)));
     Ok(())
 }
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn add_xp_sums_and_rolls_back(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    let guild = Id::new(2);
+
+    add_xp(&db, user, guild, 10).await?;
+    assert_eq!(add_xp(&db, user, guild, 15).await?, 25);
+
+    let mut txn = db.begin().await?;
+    assert_eq!(add_xp(txn.as_mut(), user, guild, 100).await?, 125);
+    txn.rollback().await?;
+
+    assert_eq!(user_xp(&db, guild, user).await?, Some(25));
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn penalties_stop_at_zero(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    let guild = Id::new(2);
+
+    add_xp(&db, user, guild, 100).await?;
+    assert_eq!(add_xp(&db, user, guild, -30).await?, 70);
+    assert_eq!(add_xp(&db, user, guild, -500).await?, 0);
+    assert_eq!(user_xp(&db, guild, user).await?, Some(0));
+    assert_eq!(add_xp(&db, Id::new(3), guild, -5).await?, 0);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn set_xp_zero_deletes(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    let guild = Id::new(2);
+
+    set_xp(&db, user, guild, 50).await?;
+    record_xp_history(&db, user, guild, 50).await?;
+    assert_eq!(user_xp(&db, guild, user).await?, Some(50));
+    set_xp(&db, user, guild, 0).await?;
+    assert_eq!(user_xp(&db, guild, user).await?, None);
+    assert!(xp_history(&db, user, guild, 10).await?.is_empty());
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn import_xp_reports_inserts(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    let guild = Id::new(2);
+
+    let first = import_xp(&db, user, guild, 10, None, false).await?;
+    assert_eq!(
+        first,
+        ImportedXp {
+            xp: 10,
+            inserted: true
+        }
+    );
+    let summed = import_xp(&db, user, guild, 5, None, false).await?;
+    assert_eq!(
+        summed,
+        ImportedXp {
+            xp: 15,
+            inserted: false
+        }
+    );
+    let overwritten = import_xp(&db, user, guild, 3, None, true).await?;
+    assert_eq!(
+        overwritten,
+        ImportedXp {
+            xp: 3,
+            inserted: false
+        }
+    );
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn starting_xp_only_seeds_new_users(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    let guild = Id::new(2);
+
+    assert_eq!(add_xp_with_start(&db, user, guild, 10, 100).await?, 110);
+    assert_eq!(add_xp_with_start(&db, user, guild, 10, 100).await?, 120);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn retrying_store_reads(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    add_xp(&db, Id::new(1), Id::new(2), 10).await?;
+    // A constraint violation is a problem with the query, not the connection
+    let duplicate = query!("INSERT INTO levels (id, guild, xp) VALUES (1, 2, 10)")
+        .execute(&db)
+        .await
+        .map_err(Error::from)
+        .unwrap_err();
+    assert!(!duplicate.is_retryable());
+
+    let store = RetryingStore::new(db, RetryPolicy::default());
+    assert_eq!(store.get_xp(Id::new(1), Id::new(2)).await?, Some(10));
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn postgres_xp_store(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let store: &dyn XpStore = &db;
+    let guild = Id::new(1);
+
+    store.add_xp(Id::new(2), guild, 50).await?;
+    store.add_xp(Id::new(3), guild, 100).await?;
+    assert_eq!(store.get_xp(Id::new(2), guild).await?, Some(50));
+    assert_eq!(store.get_xp(Id::new(4), guild).await?, None);
+    assert_eq!(store.rank_for_user(Id::new(3), guild).await?, 1);
+    assert_eq!(store.rank_for_user(Id::new(2), guild).await?, 2);
+    assert_eq!(store.rank_for_user(Id::new(4), guild).await?, 3);
+    assert_eq!(store.xp_and_rank(Id::new(2), guild).await?, (50, 2));
+    assert_eq!(store.xp_and_rank(Id::new(4), guild).await?, (0, 3));
+
+    let top: Vec<_> = store
+        .top_n(guild, 1, 1)
+        .await?
+        .iter()
+        .map(|v| v.id)
+        .collect();
+    assert_eq!(top, [Id::new(2)]);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn leaderboard_among_users(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    for (user, xp) in [(2, 300), (3, 200), (4, 100)] {
+        add_xp(&db, Id::new(user), guild, xp).await?;
+    }
+    let among = [Id::new(2), Id::new(4)];
+
+    let page: Vec<_> = get_leaderboard_page_among(&db, guild, &among, 10, 0)
+        .await?
+        .iter()
+        .map(|v| v.id)
+        .collect();
+    assert_eq!(page, among);
+    assert_eq!(
+        count_with_higher_xp_among(&db, guild, &among, 100).await?,
+        Some(1)
+    );
+    assert_eq!(levels_in_guild_among(&db, guild, &among).await?, 2);
+    assert_eq!(levels_in_guild_among(&db, guild, &[Id::new(9)]).await?, 0);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn message_counts(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    let guild = Id::new(2);
+
+    count_message(&db, user, guild).await?;
+    assert_eq!(user_messages(&db, user, guild).await?, None);
+
+    add_xp_with_start(&db, user, guild, 10, 0).await?;
+    add_xp_with_start(&db, user, guild, 10, 0).await?;
+    count_message(&db, user, guild).await?;
+    assert_eq!(user_messages(&db, user, guild).await?, Some(3));
+
+    import_xp(&db, user, guild, 0, Some(5), false).await?;
+    assert_eq!(user_messages(&db, user, guild).await?, Some(8));
+    import_xp(&db, user, guild, 20, Some(5), true).await?;
+    assert_eq!(user_messages(&db, user, guild).await?, Some(5));
+    import_xp(&db, user, guild, 20, None, true).await?;
+    assert_eq!(user_messages(&db, user, guild).await?, Some(5));
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn reset_can_be_undone(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    let guild = Id::new(2);
+
+    add_xp(&db, user, guild, 100).await?;
+    count_message(&db, user, guild).await?;
+    query!("UPDATE levels SET last_message = NOW() - interval '3 days'")
+        .execute(&db)
+        .await?;
+    let active = last_active(&db, user, guild).await?;
+    record_xp_history(&db, user, guild, 100).await?;
+    assert_eq!(delete_levels_guild(&db, guild).await?, 1);
+    assert!(xp_history(&db, user, guild, 10).await?.is_empty());
+    assert_eq!(user_xp(&db, guild, user).await?, None);
+    assert_eq!(levels_in_guild(&db, guild).await?, 0);
+
+    // XP earned after the reset is kept when restoring
+    add_xp(&db, user, guild, 5).await?;
+    assert_eq!(restore_levels_guild(&db, guild, 7.0).await?, 1);
+    assert_eq!(user_xp(&db, guild, user).await?, Some(105));
+    assert_eq!(user_messages(&db, user, guild).await?, Some(1));
+
+    // Someone who hasn't been back since keeps their old last activity
+    let other = Id::new(3);
+    add_xp(&db, other, guild, 10).await?;
+    query!("UPDATE levels SET last_message = NOW() - interval '3 days' WHERE id = 3")
+        .execute(&db)
+        .await?;
+    let other_active = last_active(&db, other, guild).await?;
+    delete_levels_guild(&db, guild).await?;
+    restore_levels_guild(&db, guild, 7.0).await?;
+    assert_eq!(last_active(&db, other, guild).await?, other_active);
+    assert!(last_active(&db, user, guild).await? > active);
+
+    // Wiping a user also wipes their tombstones, so undoing a reset can't bring them back
+    delete_levels_guild(&db, guild).await?;
+    assert_eq!(get_all_deleted_levels(&db, user).await?.len(), 1);
+    delete_levels_user(&db, user).await?;
+    assert!(get_all_deleted_levels(&db, user).await?.is_empty());
+    assert_eq!(restore_levels_guild(&db, guild, 7.0).await?, 1);
+    assert_eq!(user_xp(&db, guild, user).await?, None);
+
+    // Tombstones past the undo window are purged and can't be restored
+    delete_levels_guild(&db, guild).await?;
+    query!("UPDATE levels_deleted SET deleted_at = NOW() - interval '8 days'")
+        .execute(&db)
+        .await?;
+    assert_eq!(restore_levels_guild(&db, guild, 7.0).await?, 0);
+    assert_eq!(purge_deleted_levels(&db, 7.0).await?, 1);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn content_hash_swaps(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    let guild = Id::new(2);
+
+    assert_eq!(swap_content_hash(&db, user, guild, 5, 60.0).await?, None);
+    add_xp(&db, user, guild, 10).await?;
+    assert_eq!(swap_content_hash(&db, user, guild, 5, 60.0).await?, None);
+    assert_eq!(swap_content_hash(&db, user, guild, 6, 60.0).await?, Some(5));
+    assert_eq!(swap_content_hash(&db, user, guild, 6, 60.0).await?, Some(6));
+    // Cleaning up cooldowns doesn't forget the last message
+    delete_cooldowns_starting_before(&db, i64::MAX).await?;
+    assert_eq!(swap_content_hash(&db, user, guild, 6, 60.0).await?, Some(6));
+
+    // Once the window is over, the same message is new again
+    query!("UPDATE levels SET last_content_at = NOW() - interval '2 minutes'")
+        .execute(&db)
+        .await?;
+    assert_eq!(swap_content_hash(&db, user, guild, 6, 60.0).await?, None);
+    assert_eq!(swap_content_hash(&db, user, guild, 6, 60.0).await?, Some(6));
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn last_active_outlives_cooldowns(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    let guild = Id::new(2);
+    assert_eq!(last_active(&db, user, guild).await?, None);
+    set_cooldown(&db, user, guild, 100, 60).await?;
+    add_xp_with_start(&db, user, guild, 10, 0).await?;
+    delete_cooldowns_starting_before(&db, i64::MAX).await?;
+    assert_eq!(get_last_message(&db, user, guild).await?, None);
+    assert!(last_active(&db, user, guild)
+        .await?
+        .is_some_and(|at| at > 1_700_000_000));
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn finds_stale_guilds(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let active = Id::new(1);
+    let stale = Id::new(2);
+    add_xp_with_start(&db, Id::new(10), active, 10, 0).await?;
+    add_xp_with_start(&db, Id::new(10), stale, 10, 0).await?;
+    add_xp_with_start(&db, Id::new(11), stale, 10, 0).await?;
+    query!(
+        "UPDATE levels SET last_message = NOW() - interval '40 days' WHERE guild = $1",
+        id_to_db(stale)
+    )
+    .execute(&db)
+    .await?;
+
+    let guilds: Vec<_> = stale_guilds(&db, 30.0, 10, 0)
+        .await?
+        .iter()
+        .map(|guild| guild.id)
+        .collect();
+    assert_eq!(guilds, [stale]);
+    assert!(stale_guilds(&db, 30.0, 10, 1).await?.is_empty());
+    assert!(stale_guilds(&db, 50.0, 10, 0).await?.is_empty());
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn copies_guild_config(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let source = Id::new(1);
+    let target = Id::new(2);
+    let source_cfg = UpdateGuildConfig::new()
+        .level_up_message(Some("{user_mention} leveled up!".to_string()))
+        .level_up_channel(Some(Id::new(10)))
+        .starting_xp(Some(50));
+    update_guild_config(&db, source, source_cfg).await?;
+    update_guild_config(
+        &db,
+        target,
+        UpdateGuildConfig::new().message_cooldown(Some(5)),
+    )
+    .await?;
+
+    assert!(!copy_guild_config(&db, Id::new(3), target, None).await?);
+    assert_eq!(guild_config(&db, target).await?.unwrap().cooldown, Some(5));
+
+    update_guild_config(
+        &db,
+        source,
+        UpdateGuildConfig::new().xp_lockdown_until(Some(i64::MAX)),
+    )
+    .await?;
+    assert!(copy_guild_config(&db, source, target, Some(Id::new(20))).await?);
+    let copied = guild_config(&db, target).await?.unwrap();
+    assert_eq!(copied.starting_xp, Some(50));
+    assert_eq!(copied.cooldown, None);
+    assert_eq!(copied.level_up_channel, Some(Id::new(20)));
+    assert_eq!(copied.xp_lockdown_until, Some(0));
+    assert_eq!(
+        guild_config(&db, source).await?.unwrap().level_up_channel,
+        Some(Id::new(10))
+    );
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn reward_removal_can_be_turned_off(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    let margin = |margin| UpdateGuildConfig::new().reward_removal_margin(Some(margin));
+    let config = update_guild_config(&db, guild, margin(0)).await?;
+    assert_eq!(config.reward_removal_margin, Some(0));
+    let config = update_guild_config(&db, guild, margin(-1)).await?;
+    assert_eq!(config.reward_removal_margin, None);
+    // Turning it off for a guild without a config is fine too
+    let config = update_guild_config(&db, Id::new(2), margin(-1)).await?;
+    assert_eq!(config.reward_removal_margin, None);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn disabled_commands_are_kept(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    let disabled = vec!["leaderboard".to_string(), "whohas".to_string()];
+    update_guild_config(
+        &db,
+        guild,
+        UpdateGuildConfig::new().disabled_commands(Some(disabled.clone())),
+    )
+    .await?;
+    // Changing something else leaves them alone
+    let config =
+        update_guild_config(&db, guild, UpdateGuildConfig::new().starting_xp(Some(5))).await?;
+    assert_eq!(config.disabled_commands, Some(disabled));
+    assert!(config.command_disabled("whohas"));
+    assert!(!config.command_disabled("rank"));
+
+    let config = update_guild_config(
+        &db,
+        guild,
+        UpdateGuildConfig::new().disabled_commands(Some(Vec::new())),
+    )
+    .await?;
+    assert!(!config.command_disabled("whohas"));
+
+    let other = Id::new(2);
+    assert!(!set_command_disabled(&db, other, "rank", false).await?);
+    assert!(set_command_disabled(&db, other, "rank", true).await?);
+    assert!(!set_command_disabled(&db, other, "rank", true).await?);
+    assert!(set_command_disabled(&db, other, "whohas", true).await?);
+    assert!(set_command_disabled(&db, other, "rank", false).await?);
+    let config = guild_config(&db, other).await?.unwrap();
+    assert_eq!(config.disabled_commands, Some(vec!["whohas".to_string()]));
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn role_alerts_sent_once(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    assert!(claim_role_alert(&db, guild, Id::new(10)).await?);
+    assert!(!claim_role_alert(&db, guild, Id::new(10)).await?);
+    assert!(claim_role_alert(&db, guild, Id::new(11)).await?);
+    assert!(claim_role_alert(&db, Id::new(2), Id::new(10)).await?);
+    delete_role_alerts(&db, guild).await?;
+    assert!(claim_role_alert(&db, guild, Id::new(10)).await?);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn ranks_many_users(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    add_xp(&db, Id::new(10), guild, 50).await?;
+    add_xp(&db, Id::new(11), guild, 100).await?;
+    add_xp(&db, Id::new(12), guild, 50).await?;
+    add_xp(&db, Id::new(13), Id::new(2), 500).await?;
+
+    let users = [Id::new(12), Id::new(14), Id::new(11), Id::new(10)];
+    let ranks: Vec<_> = ranks_for_users(&db, guild, &users)
+        .await?
+        .iter()
+        .map(|user| (user.id, user.xp, user.rank))
+        .collect();
+    assert_eq!(
+        ranks,
+        [
+            (Id::new(12), 50, 2),
+            (Id::new(14), 0, 4),
+            (Id::new(11), 100, 1),
+            (Id::new(10), 50, 2),
+        ]
+    );
+    for user in users {
+        let rank = ranks.iter().find(|(id, _, _)| *id == user).unwrap().2;
+        assert_eq!(
+            (&db as &dyn XpStore).rank_for_user(user, guild).await?,
+            rank
+        );
+    }
+    assert!(ranks_for_users(&db, guild, &[]).await?.is_empty());
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn finds_users_in_xp_range(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    for (user, xp) in [(10, 5), (11, 50), (12, 100), (13, 150), (14, 100)] {
+        add_xp(&db, Id::new(user), guild, xp).await?;
+    }
+    add_xp(&db, Id::new(15), Id::new(2), 100).await?;
+
+    let (users, total) = users_in_xp_range(&db, guild, 50, 100, 2, 0).await?;
+    let users: Vec<_> = users.iter().map(|user| (user.id, user.xp)).collect();
+    assert_eq!(users, [(Id::new(14), 100), (Id::new(12), 100)]);
+    assert_eq!(total, 3);
+
+    let (users, total) = users_in_xp_range(&db, guild, 50, 100, 2, 2).await?;
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].id, Id::new(11));
+    assert_eq!(total, 3);
+
+    let (users, total) = users_in_xp_range(&db, guild, 1000, 2000, 2, 0).await?;
+    assert!(users.is_empty());
+    assert_eq!(total, 0);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn audit_channel_roundtrip(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    assert_eq!(audit_channel(&db, guild).await?, None);
+    set_audit_channel(&db, guild, Id::new(10)).await?;
+    set_audit_channel(&db, guild, Id::new(11)).await?;
+    assert_eq!(audit_channel(&db, guild).await?, Some(Id::new(11)));
+    assert_eq!(audit_channel(&db, Id::new(2)).await?, None);
+    assert!(delete_audit_channel(&db, guild).await?);
+    assert!(!delete_audit_channel(&db, guild).await?);
+    assert_eq!(audit_channel(&db, guild).await?, None);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn deletes_guild_rewards(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    add_reward_role(&db, Id::new(1), 5, Id::new(10)).await?;
+    add_reward_role(&db, Id::new(1), 10, Id::new(11)).await?;
+    add_reward_role(&db, Id::new(2), 5, Id::new(12)).await?;
+    assert_eq!(delete_guild_rewards(&db, Id::new(1)).await?, 2);
+    assert!(guild_rewards(&db, Id::new(1)).await?.is_empty());
+    assert_eq!(guild_rewards(&db, Id::new(2)).await?.len(), 1);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn reward_locks_wait_for_each_other(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    let mut first = db.begin().await?;
+    lock_guild_rewards(first.as_mut(), guild).await?;
+
+    let mut second = db.begin().await?;
+    let waiting = tokio::time::timeout(
+        std::time::Duration::from_millis(200),
+        lock_guild_rewards(second.as_mut(), guild),
+    )
+    .await;
+    assert!(waiting.is_err());
+    // Other guilds aren't held up
+    let mut other = db.begin().await?;
+    lock_guild_rewards(other.as_mut(), Id::new(2)).await?;
+    other.commit().await?;
+
+    first.commit().await?;
+    lock_guild_rewards(second.as_mut(), guild).await?;
+    second.commit().await?;
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn weekly_summaries(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    add_xp(&db, user, Id::new(10), 100).await?;
+    add_xp(&db, Id::new(2), Id::new(10), 300).await?;
+    assert!(due_weekly_summaries(&db, 10).await?.is_empty());
+
+    set_weekly_summary(&db, user, true).await?;
+    assert_eq!(due_weekly_summaries(&db, 10).await?, [user]);
+    let first = take_weekly_summary(&db, user).await?;
+    assert_eq!(
+        first,
+        [WeeklyProgress {
+            guild: Id::new(10),
+            xp: 100,
+            rank: 2,
+            previous: None
+        }]
+    );
+    assert!(due_weekly_summaries(&db, 10).await?.is_empty());
+
+    add_xp(&db, user, Id::new(10), 400).await?;
+    add_xp(&db, user, Id::new(11), 50).await?;
+    let mut second = take_weekly_summary(&db, user).await?;
+    second.sort_by_key(|progress| progress.guild);
+    assert_eq!(second[0].xp, 500);
+    assert_eq!(second[0].rank, 1);
+    assert_eq!(second[0].previous, Some((100, 2)));
+    assert_eq!(second[1].previous, None);
+
+    set_weekly_summary(&db, user, false).await?;
+    delete_user_preferences(&db, user).await?;
+    assert!(take_weekly_summary(&db, user)
+        .await?
+        .iter()
+        .all(|p| p.previous.is_none()));
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn xp_history_is_recorded(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    let guild = Id::new(10);
+    assert!(xp_history(&db, user, guild, 10).await?.is_empty());
+
+    query!(
+        "INSERT INTO xp_history (id, guild, day, xp) VALUES \
+            (1, 10, (NOW() AT TIME ZONE 'UTC')::DATE - 100, 5), \
+            (1, 10, (NOW() AT TIME ZONE 'UTC')::DATE - 2, 50), \
+            (1, 11, (NOW() AT TIME ZONE 'UTC')::DATE - 2, 70)"
+    )
+    .execute(&db)
+    .await?;
+    record_xp_history(&db, user, guild, 80).await?;
+    // Later grants on the same day replace that day's XP
+    record_xp_history(&db, user, guild, 120).await?;
+
+    let history = xp_history(&db, user, guild, 10).await?;
+    let xp: Vec<i64> = history.iter().map(|entry| entry.xp).collect();
+    assert_eq!(xp, [5, 50, 120]);
+    assert!(history[0].day < history[1].day);
+    assert_eq!(history[2].day.len(), "2026-01-01".len());
+    assert_eq!(xp_history(&db, user, guild, 2).await?[0].xp, 50);
+
+    assert_eq!(prune_xp_history(&db, 90).await?, 1);
+    assert_eq!(xp_history(&db, user, guild, 10).await?.len(), 2);
+
+    delete_levels_user(&db, user).await?;
+    assert!(xp_history(&db, user, guild, 10).await?.is_empty());
+    assert!(xp_history(&db, user, Id::new(11), 10).await?.is_empty());
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn ranks_at_snapshots(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(10);
+    query!(
+        "INSERT INTO xp_history (id, guild, day, xp) VALUES \
+            (1, 10, '2026-01-01', 100), \
+            (1, 10, '2026-01-05', 300), \
+            (2, 10, '2026-01-02', 200), \
+            (3, 10, '2026-01-06', 1000), \
+            (4, 11, '2026-01-01', 5000)"
+    )
+    .execute(&db)
+    .await?;
+    // 2026-01-03 12:00 UTC
+    let jan_3 = 1_767_441_600;
+
+    let rank = rank_at_snapshot(&db, guild, Id::new(1), jan_3)
+        .await?
+        .unwrap();
+    assert_eq!(rank.day, "2026-01-03");
+    assert_eq!((rank.xp, rank.rank, rank.members), (100, 2, 2));
+    let rank = rank_at_snapshot(&db, guild, Id::new(2), jan_3)
+        .await?
+        .unwrap();
+    assert_eq!((rank.xp, rank.rank, rank.members), (200, 1, 2));
+    // Not recorded yet, or not in this guild
+    assert!(rank_at_snapshot(&db, guild, Id::new(3), jan_3)
+        .await?
+        .is_none());
+    assert!(rank_at_snapshot(&db, guild, Id::new(4), jan_3)
+        .await?
+        .is_none());
+
+    let later = jan_3 + 4 * 86_400;
+    let rank = rank_at_snapshot(&db, guild, Id::new(1), later)
+        .await?
+        .unwrap();
+    assert_eq!((rank.xp, rank.rank, rank.members), (300, 2, 3));
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn role_cooldowns(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    assert!(guild_role_cooldowns(&db, guild).await?.is_empty());
+
+    set_role_cooldown(&db, guild, Id::new(20), 0).await?;
+    set_role_cooldown(&db, guild, Id::new(10), 120).await?;
+    set_role_cooldown(&db, guild, Id::new(10), 300).await?;
+    set_role_cooldown(&db, Id::new(2), Id::new(30), 5).await?;
+    assert_eq!(
+        guild_role_cooldowns(&db, guild).await?,
+        [
+            RoleCooldown {
+                role: Id::new(10),
+                cooldown: 300
+            },
+            RoleCooldown {
+                role: Id::new(20),
+                cooldown: 0
+            },
+        ]
+    );
+
+    assert!(delete_role_cooldown(&db, guild, Id::new(20)).await?);
+    assert!(!delete_role_cooldown(&db, guild, Id::new(20)).await?);
+    assert_eq!(delete_guild_role_cooldowns(&db, guild).await?, 1);
+    assert!(guild_role_cooldowns(&db, guild).await?.is_empty());
+    assert_eq!(guild_role_cooldowns(&db, Id::new(2)).await?.len(), 1);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn leaderboard_positions(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    add_xp(&db, Id::new(10), guild, 50).await?;
+    add_xp(&db, Id::new(11), guild, 100).await?;
+    add_xp(&db, Id::new(12), guild, 50).await?;
+    add_xp(&db, Id::new(13), Id::new(2), 500).await?;
+
+    let page = get_leaderboard_page(&db, guild, 10, 0).await?;
+    for (idx, user) in page.iter().enumerate() {
+        assert_eq!(
+            leaderboard_position(&db, guild, user.id).await?,
+            Some(i64::try_from(idx)? + 1)
+        );
+    }
+    assert_eq!(leaderboard_position(&db, guild, Id::new(13)).await?, None);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn todays_top_gainers_sum_hours(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(10);
+    query!(
+        "INSERT INTO hourly_xp (guild, hour, id, gained) VALUES \
+            (10, DATE_TRUNC('day', NOW() AT TIME ZONE 'UTC') - INTERVAL '1 hour', 1, 500), \
+            (10, DATE_TRUNC('day', NOW() AT TIME ZONE 'UTC') - INTERVAL '3 days', 2, 500), \
+            (10, DATE_TRUNC('day', NOW() AT TIME ZONE 'UTC'), 1, 20), \
+            (11, DATE_TRUNC('day', NOW() AT TIME ZONE 'UTC'), 3, 999)"
+    )
+    .execute(&db)
+    .await?;
+    record_hourly_xp(&db, Id::new(1), guild, 15).await?;
+    record_hourly_xp(&db, Id::new(2), guild, 30).await?;
+    record_hourly_xp(&db, Id::new(4), guild, 35).await?;
+
+    // Yesterday's and the other guild's XP don't count, and ties go to the higher ID
+    let gainers: Vec<(u64, i64)> = todays_top_gainers(&db, guild, 10, 0)
+        .await?
+        .iter()
+        .map(|user| (user.id.get(), user.xp))
+        .collect();
+    assert_eq!(gainers, [(4, 35), (1, 35), (2, 30)]);
+    assert_eq!(todays_gainers(&db, guild).await?, 3);
+    assert_eq!(
+        todays_top_gainers(&db, guild, 1, 2).await?[0].id,
+        Id::new(2)
+    );
+    assert_eq!(
+        todays_gainer_position(&db, guild, Id::new(1)).await?,
+        Some(2)
+    );
+    assert_eq!(todays_gainer_position(&db, guild, Id::new(3)).await?, None);
+
+    assert_eq!(prune_hourly_xp(&db, 48).await?, 1);
+    assert_eq!(todays_gainers(&db, guild).await?, 3);
+    delete_levels_user(&db, Id::new(1)).await?;
+    assert_eq!(todays_gainer_position(&db, guild, Id::new(1)).await?, None);
+
+    // Resets take away what members gained today, too
+    delete_levels_user_guild(&db, Id::new(2), guild).await?;
+    assert_eq!(todays_gainer_position(&db, guild, Id::new(2)).await?, None);
+    assert_eq!(todays_gainers(&db, guild).await?, 1);
+    delete_levels_guild(&db, guild).await?;
+    assert_eq!(todays_gainers(&db, guild).await?, 0);
+    assert_eq!(todays_gainers(&db, Id::new(11)).await?, 1);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn level_up_dm_preference(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    assert!(!level_up_dms(&db, user).await?);
+    set_user_timezone(&db, user, Some("Europe/Stockholm")).await?;
+    assert!(!level_up_dms(&db, user).await?);
+    set_level_up_dms(&db, user, true).await?;
+    assert!(level_up_dms(&db, user).await?);
+    // Other preferences are left alone
+    assert_eq!(
+        user_timezone(&db, user).await?.as_deref(),
+        Some("Europe/Stockholm")
+    );
+    set_level_up_dms(&db, user, false).await?;
+    assert!(!level_up_dms(&db, user).await?);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn user_preferences_together(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let user = Id::new(1);
+    assert_eq!(user_preferences(&db, user).await?, None);
+    set_level_up_dms(&db, user, true).await?;
+    set_weekly_summary(&db, user, true).await?;
+    assert_eq!(
+        user_preferences(&db, user).await?,
+        Some(UserPreferences {
+            timezone: None,
+            level_up_dms: true,
+            weekly_summary: true,
+        })
+    );
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn resets_count_every_batch(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(2);
+    for user in 1..=7 {
+        add_xp(&db, Id::new(user), guild, 10).await?;
+    }
+    add_xp(&db, Id::new(1), Id::new(3), 10).await?;
+
+    assert_eq!(delete_levels_guild_in_batches(&db, guild, 3).await?, 7);
+    assert_eq!(levels_in_guild(&db, guild).await?, 0);
+    assert_eq!(levels_in_guild(&db, Id::new(3)).await?, 1);
+    assert_eq!(restore_levels_guild(&db, guild, 7.0).await?, 7);
+
+    // A guild that fills the last batch exactly takes one more, empty, batch to finish
+    assert_eq!(delete_levels_guild_in_batches(&db, guild, 7).await?, 7);
+    assert_eq!(delete_levels_guild_in_batches(&db, guild, 3).await?, 0);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn end_season_archives_and_resets(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    add_xp(&db, Id::new(1), guild, 50).await?;
+    add_xp(&db, Id::new(2), guild, 80).await?;
+    add_xp(&db, Id::new(3), guild, 50).await?;
+    add_xp(&db, Id::new(4), Id::new(2), 10).await?;
+
+    // No schedule, nothing to end
+    assert_eq!(end_season(&db, guild).await?, None);
+    let schedule = set_season_schedule(&db, guild, SeasonCadence::Weekly).await?;
+    assert_eq!(
+        season_schedule(&db, guild).await?.map(|s| s.cadence),
+        Some(SeasonCadence::Weekly)
+    );
+    // Not due yet
+    assert!(due_season_resets(&db, 10, &[]).await?.is_empty());
+    assert_eq!(end_season(&db, guild).await?, None);
+
+    query!("UPDATE season_schedules SET next_reset = NOW() - interval '1 hour'")
+        .execute(&db)
+        .await?;
+    assert_eq!(due_season_resets(&db, 10, &[]).await?, [guild]);
+    assert!(due_season_resets(&db, 10, &[guild]).await?.is_empty());
+    let ended = end_season(&db, guild).await?.ok_or("season didn't end")?;
+    assert_eq!(ended.season, 1);
+    assert_eq!(ended.archived, 3);
+    // The next season starts where the last one ended, not when the worker got to it
+    assert!(ended.next_reset < schedule.next_reset);
+    assert!(ended.next_reset > schedule.next_reset - 2 * 60 * 60);
+    assert!(due_season_resets(&db, 10, &[]).await?.is_empty());
+    assert_eq!(levels_in_guild(&db, guild).await?, 0);
+    assert_eq!(levels_in_guild(&db, Id::new(2)).await?, 1);
+
+    let standings: Vec<(u64, i64)> = season_standings(&db, guild, 1, 10)
+        .await?
+        .iter()
+        .map(|user| (user.id.get(), user.rank))
+        .collect();
+    assert_eq!(standings, [(2, 1), (1, 2), (3, 2)]);
+
+    // The next season is numbered after the last one
+    add_xp(&db, Id::new(1), guild, 5).await?;
+    query!("UPDATE season_schedules SET next_reset = NOW() - interval '1 hour'")
+        .execute(&db)
+        .await?;
+    assert_eq!(end_season(&db, guild).await?.map(|s| s.season), Some(2));
+    assert_eq!(season_standings(&db, guild, 2, 10).await?.len(), 1);
+
+    assert!(delete_season_schedule(&db, guild).await?);
+    assert_eq!(delete_season_results(&db, guild).await?, 4);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn reaction_grants_once_per_reactor(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let message = Id::new(10);
+    assert!(grant_reaction(&db, message, Id::new(1), 2).await?);
+    // Reacting again, even with another emoji, doesn't count twice
+    assert!(!grant_reaction(&db, message, Id::new(1), 2).await?);
+    assert!(grant_reaction(&db, message, Id::new(2), 2).await?);
+    // The cap is reached
+    assert!(!grant_reaction(&db, message, Id::new(3), 2).await?);
+    // Other messages have their own cap
+    assert!(grant_reaction(&db, Id::new(11), Id::new(3), 2).await?);
+    // Reactions racing each other still stop at the cap
+    let racing: Vec<_> = (20..30)
+        .map(|reactor| {
+            let db = db.clone();
+            tokio::spawn(async move { grant_reaction(&db, Id::new(12), Id::new(reactor), 2).await })
+        })
+        .collect();
+    let mut granted = 0;
+    for grant in racing {
+        granted += usize::from(grant.await??);
+    }
+    assert_eq!(granted, 2);
+
+    assert_eq!(prune_reaction_grants(&db, 1).await?, 0);
+    query!("UPDATE reaction_grants SET granted_at = NOW() - interval '2 hours' WHERE message = 10")
+        .execute(&db)
+        .await?;
+    assert_eq!(prune_reaction_grants(&db, 1).await?, 2);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn percentiles_announced_once(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    for (user, xp) in [(1, 300), (2, 200), (3, 100)] {
+        add_xp(&db, Id::new(user), guild, xp).await?;
+    }
+    assert_eq!(guild_standing(&db, guild, 200).await?, (1, 3));
+    assert_eq!(guild_standing(&db, guild, 1000).await?, (0, 3));
+    assert_eq!(guild_standing(&db, Id::new(2), 10).await?, (0, 0));
+
+    let user = Id::new(2);
+    assert!(claim_percentile(&db, guild, user, 25).await?);
+    assert!(!claim_percentile(&db, guild, user, 25).await?);
+    // Falling back to a wider percentile isn't news
+    assert!(!claim_percentile(&db, guild, user, 50).await?);
+    assert!(claim_percentile(&db, guild, user, 10).await?);
+    // Every guild is tracked separately
+    assert!(claim_percentile(&db, Id::new(2), user, 50).await?);
+
+    delete_percentile_announcements(&db, guild).await?;
+    assert!(claim_percentile(&db, guild, user, 50).await?);
+
+    // Resetting the member's or the whole guild's XP lets them climb again
+    delete_levels_user_guild(&db, user, guild).await?;
+    assert!(claim_percentile(&db, guild, user, 50).await?);
+    delete_levels_guild(&db, guild).await?;
+    assert!(claim_percentile(&db, guild, user, 50).await?);
+    assert!(!claim_percentile(&db, Id::new(2), user, 50).await?);
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../migrations/")]
+async fn one_time_rewards_claimed_once(db: PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    let guild = Id::new(1);
+    let user = Id::new(10);
+    add_one_time_reward(&db, guild, "Color token", 5).await?;
+    add_one_time_reward(&db, guild, "Shoutout", 10).await?;
+    add_one_time_reward(&db, Id::new(2), "Elsewhere", 1).await?;
+
+    assert!(claim_one_time_rewards(&db, guild, user, 4)
+        .await?
+        .is_empty());
+    assert_eq!(
+        claim_one_time_rewards(&db, guild, user, 5).await?,
+        ["Color token"]
+    );
+    // Losing XP and earning it back doesn't hand it out again
+    delete_levels_user_guild(&db, user, guild).await?;
+    assert_eq!(
+        claim_one_time_rewards(&db, guild, user, 12).await?,
+        ["Shoutout"]
+    );
+    assert!(claim_one_time_rewards(&db, guild, user, 12)
+        .await?
+        .is_empty());
+
+    let claimed: Vec<String> = claimed_rewards(&db, guild, user)
+        .await?
+        .into_iter()
+        .map(|claim| claim.reward)
+        .collect();
+    assert_eq!(claimed, ["Color token", "Shoutout"]);
+    let rewards = one_time_rewards(&db, guild).await?;
+    assert_eq!(rewards.len(), 2);
+    assert!(rewards.iter().all(|reward| reward.claims == 1));
+
+    assert!(delete_one_time_reward(&db, guild, "Shoutout").await?);
+    assert!(!delete_one_time_reward(&db, guild, "Shoutout").await?);
+    delete_claimed_rewards_user(&db, user).await?;
+    assert_eq!(
+        claim_one_time_rewards(&db, guild, user, 12).await?,
+        ["Color token"]
+    );
+    delete_one_time_rewards(&db, guild).await?;
+    assert!(one_time_rewards(&db, guild).await?.is_empty());
+    assert!(claimed_rewards(&db, guild, user).await?.is_empty());
+    Ok(())
+}
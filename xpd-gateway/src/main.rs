@@ -3,7 +3,7 @@
 #[macro_use]
 extern crate tracing;
 
-use std::{collections::HashMap, env::VarError, sync::Arc};
+use std::{collections::HashMap, env::VarError, sync::Arc, time::Duration};
 
 use base64::{
     engine::{GeneralPurpose as Base64Engine, GeneralPurposeConfig as Base64Config},
@@ -32,18 +32,17 @@ use twilight_model::{
     gateway::ShardId,
     id::{marker::GuildMarker, Id},
 };
-use xpd_common::RequiredDiscordResources;
+use xpd_common::{BuildInfo, RequiredDiscordResources};
+use xpd_database::RetryPolicy;
 use xpd_listener::XpdListener;
-use xpd_slash::XpdSlash;
+use xpd_slash::{ImportSyncConfig, SlashConfig, XpdSlash};
 use xpd_util::LogError;
 
 #[tokio::main]
 async fn main() {
     let tracer_shutdown = init_tracing();
-    info!(
-        version = xpd_common::CURRENT_GIT_SHA,
-        "Starting experienced!"
-    );
+    let build = xpd_common::build_info(env!("CARGO_PKG_VERSION"));
+    info!(version = %build, "Starting experienced!");
 
     let token = valk_utils::get_var("DISCORD_TOKEN");
     let pg = valk_utils::get_var("DATABASE_URL");
@@ -90,6 +89,16 @@ async fn main() {
         .build()
         .unwrap();
 
+    // Level-up webhooks go wherever guild admins point them, so they must not follow redirects
+    // to addresses which were never checked, and only ever connect to the public addresses their host resolves to
+    let webhooks = reqwest::Client::builder()
+        .user_agent("randomairborne/experienced")
+        .https_only(true)
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(xpd_util::PublicResolver))
+        .build()
+        .unwrap();
+
     let cache_resource_types =
         XpdListener::required_cache_types() | XpdSlash::required_cache_types();
     let cache = Arc::new(
@@ -106,6 +115,7 @@ async fn main() {
         db.clone(),
         client.clone(),
         cache.clone(),
+        webhooks,
         task_tracker.clone(),
         bot_id,
     );
@@ -139,7 +149,10 @@ async fn main() {
         control_guild,
         owners,
         event_bus_tx,
+        slash_config(build),
     );
+    slash.start_weekly_summaries(shutdown.clone());
+    slash.start_seasons(shutdown.clone());
     let config = Config::new(token.clone(), intents);
     let shards: Vec<Shard> =
         twilight_gateway::create_recommended(&client, config, |_, builder| builder.build())
@@ -258,6 +271,7 @@ async fn handle_event(
             );
         }
         Event::MessageCreate(msg) => listener.save(*msg).await?,
+        Event::ReactionAdd(reaction) => listener.save_reaction(*reaction).await?,
         Event::GuildCreate(guild_add) => {
             if xpd_database::is_guild_banned(&db, guild_add.id).await? {
                 debug!(
@@ -293,6 +307,67 @@ impl<S> Filter<S> for PrefixFilter {
     }
 }
 
+/// Read the operator's settings for slash commands, falling back to the defaults for anything unset
+fn slash_config(build: BuildInfo) -> SlashConfig {
+    let defaults = SlashConfig::new(build);
+    SlashConfig {
+        import_sync: import_sync_config(),
+        reset_undo_days: valk_utils::parse_var_or("RESET_UNDO_DAYS", defaults.reset_undo_days),
+        max_rewards: valk_utils::parse_var_or("MAX_REWARDS", defaults.max_rewards),
+        avatar_hosts: avatar_hosts(defaults.avatar_hosts),
+        card_format: valk_utils::parse_var_or("CARD_FORMAT", defaults.card_format),
+        db_retry: db_retry_policy(),
+        build,
+    }
+}
+
+/// Read the post-import reward sync tuning, falling back to the defaults for anything unset
+fn import_sync_config() -> ImportSyncConfig {
+    let defaults = ImportSyncConfig::default();
+    let interval_ms = valk_utils::parse_var_or(
+        "IMPORT_SYNC_INTERVAL_MS",
+        u64::try_from(defaults.interval.as_millis()).unwrap_or(u64::MAX),
+    );
+    ImportSyncConfig {
+        batch_size: valk_utils::parse_var_or("IMPORT_SYNC_BATCH_SIZE", defaults.batch_size),
+        interval: Duration::from_millis(interval_ms),
+        concurrency: valk_utils::parse_var_or("IMPORT_SYNC_CONCURRENCY", defaults.concurrency),
+        max_running_imports: valk_utils::parse_var_or(
+            "IMPORT_MAX_RUNNING",
+            defaults.max_running_imports,
+        ),
+    }
+}
+
+/// Read how database reads are retried, falling back to the defaults for anything unset
+fn db_retry_policy() -> RetryPolicy {
+    let defaults = RetryPolicy::default();
+    let backoff_ms = valk_utils::parse_var_or(
+        "DB_RETRY_BACKOFF_MS",
+        u64::try_from(defaults.backoff.as_millis()).unwrap_or(u64::MAX),
+    );
+    RetryPolicy {
+        // Zero attempts would never run the query at all
+        attempts: valk_utils::parse_var_or("DB_READ_ATTEMPTS", defaults.attempts).max(1),
+        backoff: Duration::from_millis(backoff_ms),
+    }
+}
+
+/// Read the comma-separated list of hosts card images may be downloaded from
+fn avatar_hosts(defaults: Vec<String>) -> Vec<String> {
+    std::env::var("AVATAR_HOSTS").map_or_else(
+        |_| defaults,
+        |hosts| {
+            hosts
+                .split(',')
+                .map(str::trim)
+                .filter(|host| !host.is_empty())
+                .map(ToOwned::to_owned)
+                .collect()
+        },
+    )
+}
+
 #[must_use]
 fn init_tracing() -> Option<LoggerProvider> {
     let logger = std::env::var("OTLP_ENDPOINT").ok().map(|v| make_otlp(&v));
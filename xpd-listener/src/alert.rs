@@ -0,0 +1,111 @@
+use twilight_model::{
+    channel::message::AllowedMentions,
+    id::{
+        marker::{GuildMarker, RoleMarker},
+        Id,
+    },
+};
+use xpd_common::GuildConfig;
+use xpd_util::{CanAddRole, LogError};
+
+use crate::{Error, XpdListenerInner};
+
+impl XpdListenerInner {
+    /// Log which of `roles` the bot can't give out or take away and why, and tell the guild owner
+    /// about each of them once, if the guild wants that.
+    pub(crate) async fn report_unmanageable_roles(
+        &self,
+        guild_id: Id<GuildMarker>,
+        guild_config: &GuildConfig,
+        roles: &[Id<RoleMarker>],
+    ) {
+        let mut blocked: Vec<(Id<RoleMarker>, &'static str)> = roles
+            .iter()
+            .filter_map(|role| {
+                match xpd_util::can_manage_roles(&self.cache, self.bot_id, guild_id, &[*role]) {
+                    Ok(CanAddRole::Yes) => None,
+                    Ok(reason) => Some((*role, reason_text(Some(reason)))),
+                    Err(_) => Some((*role, reason_text(None))),
+                }
+            })
+            .collect();
+        if blocked.is_empty() {
+            // The cache thought every role was fine, but Discord disagreed
+            blocked = roles
+                .iter()
+                .map(|role| (*role, reason_text(None)))
+                .collect();
+        }
+        for (role, reason) in blocked {
+            let name = self
+                .cache
+                .role(role)
+                .map_or_else(|| role.to_string(), |role| role.name.clone());
+            warn!(guild = ?guild_id, ?role, name, reason, "Can't give out reward role");
+            if guild_config.role_alerts.unwrap_or(false) {
+                self.alert_owner(guild_id, role, &name, reason)
+                    .await
+                    .log_error("Failed to tell guild owner about reward role");
+            }
+        }
+    }
+
+    async fn alert_owner(
+        &self,
+        guild_id: Id<GuildMarker>,
+        role: Id<RoleMarker>,
+        name: &str,
+        reason: &str,
+    ) -> Result<(), Error> {
+        let Some((owner, guild_name)) = self
+            .cache
+            .guild(guild_id)
+            .map(|guild| (guild.owner_id(), guild.name().to_owned()))
+        else {
+            return Ok(());
+        };
+        if !xpd_database::claim_role_alert(&self.db, guild_id, role).await? {
+            return Ok(());
+        }
+        debug!(guild = ?guild_id, ?role, ?owner, "Telling guild owner about reward role");
+        let content = format!(
+            "I can't give members the reward role **{name}** in **{guild_name}**, because {reason}. \
+            You can fix this in Server Settings → Roles.\n\
+            -# You won't be told about this role again. Turn these messages off with `/rewards alerts enabled:False`."
+        );
+        let channel = self
+            .http
+            .create_private_channel(owner)
+            .await?
+            .model()
+            .await?;
+        match self
+            .http
+            .create_message(channel.id)
+            .allowed_mentions(Some(&AllowedMentions::default()))
+            .content(&content)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(source)
+                if xpd_util::discord_error_code(&source) == Some(xpd_util::CANNOT_MESSAGE_USER) =>
+            {
+                debug!(
+                    ?owner,
+                    "Skipping reward role alert for owner with closed DMs"
+                );
+                Ok(())
+            }
+            Err(source) => Err(source.into()),
+        }
+    }
+}
+
+fn reason_text(reason: Option<CanAddRole>) -> &'static str {
+    match reason {
+        Some(CanAddRole::NoManageRoles) => "I don't have the Manage Roles permission",
+        Some(CanAddRole::HighestRoleIsLowerRoleThanTarget) => "it's above my highest role",
+        Some(CanAddRole::RoleIsManaged) => "it's managed by an integration",
+        Some(CanAddRole::Yes) | None => "Discord says I'm missing permissions for it",
+    }
+}
@@ -1,4 +1,6 @@
-use std::{ops::Deref, sync::Arc};
+#![allow(clippy::result_large_err)]
+
+use std::{ops::Deref, sync::Arc, time::Instant};
 
 use dashmap::DashMap;
 use tokio_util::task::TaskTracker;
@@ -11,10 +13,15 @@ use twilight_model::{
         Id,
     },
 };
-use xpd_common::{EventBusMessage, GuildConfig, RequiredDiscordResources, RoleReward};
+use xpd_common::{
+    EventBusMessage, GuildConfig, RequiredDiscordResources, RoleCooldown, RoleReward,
+};
 use xpd_database::PgPool;
 
+mod alert;
 mod message;
+mod reaction;
+mod webhook;
 
 #[macro_use]
 extern crate tracing;
@@ -27,10 +34,13 @@ impl XpdListener {
         db: PgPool,
         http: Arc<twilight_http::Client>,
         cache: Arc<InMemoryCache>,
+        web: reqwest::Client,
         tasks: TaskTracker,
         me: Id<UserMarker>,
     ) -> Self {
-        Self(Arc::new(XpdListenerInner::new(db, http, cache, tasks, me)))
+        Self(Arc::new(XpdListenerInner::new(
+            db, http, cache, web, tasks, me,
+        )))
     }
 }
 
@@ -60,10 +70,14 @@ pub struct XpdListenerInner {
     db: PgPool,
     http: Arc<twilight_http::Client>,
     cache: Arc<InMemoryCache>,
-    #[allow(unused)]
+    /// Client for requests that aren't to Discord, like level-up webhooks
+    web: reqwest::Client,
     task_tracker: TaskTracker,
     configs: DashMap<Id<GuildMarker>, Arc<GuildConfig>>,
     rewards: DashMap<Id<GuildMarker>, Arc<Vec<RoleReward>>>,
+    role_cooldowns: DashMap<Id<GuildMarker>, Arc<Vec<RoleCooldown>>>,
+    /// When each member was last sent a level-up message, for guilds with a level-up cooldown
+    level_ups: DashMap<(Id<GuildMarker>, Id<UserMarker>), Instant>,
     bot_id: Id<UserMarker>,
 }
 
@@ -72,6 +86,7 @@ impl XpdListenerInner {
         db: PgPool,
         http: Arc<twilight_http::Client>,
         cache: Arc<InMemoryCache>,
+        web: reqwest::Client,
         task_tracker: TaskTracker,
         bot_id: Id<UserMarker>,
     ) -> Self {
@@ -83,7 +98,10 @@ impl XpdListenerInner {
             http,
             configs,
             rewards,
+            role_cooldowns: DashMap::new(),
+            level_ups: DashMap::new(),
             cache,
+            web,
             task_tracker,
             bot_id,
         }
@@ -92,6 +110,10 @@ impl XpdListenerInner {
     pub async fn bus(&self, msg: EventBusMessage) {
         let res = match msg {
             EventBusMessage::InvalidateRewards(id) => self.invalidate_rewards(id).await,
+            EventBusMessage::InvalidateRoleCooldowns(id) => {
+                self.role_cooldowns.remove(&id);
+                Ok(())
+            }
             EventBusMessage::UpdateConfig(id, guild_config) => self.update_config(id, guild_config),
         };
         match res {
@@ -141,11 +163,23 @@ impl XpdListenerInner {
         self.rewards.insert(guild_id, new_copy.clone());
         Ok(new_copy)
     }
+
+    pub async fn get_role_cooldowns(
+        &self,
+        guild_id: Id<GuildMarker>,
+    ) -> Result<Arc<Vec<RoleCooldown>>, Error> {
+        if let Some(cooldowns) = self.role_cooldowns.get(&guild_id) {
+            return Ok(Arc::clone(&cooldowns));
+        }
+        let cooldowns = Arc::new(xpd_database::guild_role_cooldowns(&self.db, guild_id).await?);
+        self.role_cooldowns.insert(guild_id, cooldowns.clone());
+        Ok(cooldowns)
+    }
 }
 
 impl RequiredDiscordResources for XpdListenerInner {
     fn required_intents() -> Intents {
-        Intents::GUILDS | Intents::GUILD_MESSAGES
+        Intents::GUILDS | Intents::GUILD_MESSAGES | Intents::GUILD_MESSAGE_REACTIONS
     }
 
     fn required_events() -> EventTypeFlags {
@@ -163,6 +197,7 @@ impl RequiredDiscordResources for XpdListenerInner {
             | EventTypeFlags::THREAD_LIST_SYNC
             | EventTypeFlags::THREAD_DELETE
             | EventTypeFlags::MESSAGE_CREATE
+            | EventTypeFlags::REACTION_ADD
     }
 
     fn required_cache_types() -> ResourceType {
@@ -178,6 +213,8 @@ impl RequiredDiscordResources for XpdListenerInner {
 pub enum Error {
     #[error("Discord error")]
     Twilight(#[from] twilight_http::Error),
+    #[error("Discord sent a response we couldn't read")]
+    DeserializeBody(#[from] twilight_http::response::DeserializeBodyError),
     #[error("database fetch fail: {0}")]
     DatabaseAbstraction(#[from] xpd_database::Error),
     #[error("simpleinterpolation failed")]
@@ -1,8 +1,13 @@
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use rand::Rng;
 use twilight_model::{
-    channel::message::AllowedMentions,
+    channel::message::{AllowedMentions, Embed},
     gateway::payload::incoming::MessageCreate,
     guild::PartialMember,
     id::{
@@ -10,12 +15,16 @@ use twilight_model::{
         Id,
     },
 };
+use twilight_util::builder::embed::{EmbedBuilder, ImageSource};
 use xpd_common::{
-    DisplayName, GuildConfig, RoleReward, DEFAULT_MAX_XP_PER_MESSAGE, DEFAULT_MESSAGE_COOLDOWN,
-    DEFAULT_MIN_XP_PER_MESSAGE,
+    DisplayName, GuildConfig, LevelUpStyle, MemberDisplayInfo, RewardRoleChanges, RoleCooldown,
+    RoleReward, DEFAULT_MESSAGE_COOLDOWN, DISCORD_EPOCH_SECS, MAX_CONTENT_BONUS_ITEMS,
+    MAX_CONTENT_BONUS_XP,
 };
 
-use crate::{Error, XpdListenerInner};
+use xpd_util::LogError;
+
+use crate::{webhook::LevelUpPayload, Error, XpdListenerInner};
 
 type RoleList = Vec<Id<RoleMarker>>;
 
@@ -40,22 +49,36 @@ impl XpdListenerInner {
             return Err(Error::NoMember);
         };
 
-        let this_message_sts = xpd_util::snowflake_to_timestamp(msg.id);
-
         let guild_config = self.get_guild_config(guild_id).await?;
-        let config_max_xp_per_msg = guild_config
-            .max_xp_per_message
-            .unwrap_or(DEFAULT_MAX_XP_PER_MESSAGE);
-        let config_min_xp_per_msg = guild_config
-            .min_xp_per_message
-            .unwrap_or(DEFAULT_MIN_XP_PER_MESSAGE);
+        let this_message_sts = xpd_util::snowflake_to_timestamp(msg.id);
+        if guild_config.xp_locked(DISCORD_EPOCH_SECS + this_message_sts) {
+            debug!(guild = ?guild_id, "Skipping message in guild with XP locked down");
+            return Ok(());
+        }
+        if member.joined_at.is_some_and(|joined_at| {
+            guild_config.in_join_grace(joined_at.as_secs(), DISCORD_EPOCH_SECS + this_message_sts)
+        }) {
+            debug!(guild = ?guild_id, user = ?msg.author.id, "Skipping message from member still in join grace period");
+            return Ok(());
+        }
+        if guild_config.account_too_new(
+            DISCORD_EPOCH_SECS + xpd_util::snowflake_to_timestamp(msg.author.id),
+            DISCORD_EPOCH_SECS + this_message_sts,
+        ) {
+            debug!(guild = ?guild_id, user = ?msg.author.id, "Skipping message from account younger than the minimum age");
+            return Ok(());
+        }
+        let (config_min_xp_per_msg, config_max_xp_per_msg) = guild_config.message_xp_range();
 
         // if the last message timestamp plus the cooldown period is larger than the current sent at epoch,
         // we want to return immediately because the "expiry time" is still in the future
-        let cooldown: i64 = guild_config
-            .cooldown
-            .unwrap_or(DEFAULT_MESSAGE_COOLDOWN)
-            .into();
+        let role_cooldowns = self.get_role_cooldowns(guild_id).await?;
+        let cooldown: i64 = role_cooldown(&role_cooldowns, &member.roles, |role| {
+            self.cache.role(role).map(|role| role.position)
+        })
+        .or(guild_config.cooldown)
+        .unwrap_or(DEFAULT_MESSAGE_COOLDOWN)
+        .into();
         if xpd_database::set_cooldown(
             &self.db,
             msg.author.id,
@@ -66,24 +89,63 @@ impl XpdListenerInner {
         .await?
         .was_on_cooldown()
         {
+            if guild_config.count_cooldown_messages.unwrap_or(false) {
+                xpd_database::count_message(&self.db, msg.author.id, guild_id).await?;
+            }
             return Ok(());
         }
 
-        let xp_added: i64 = if config_max_xp_per_msg == config_min_xp_per_msg {
+        if guild_config.ignore_duplicate_messages.unwrap_or(false) {
+            if let Some(hash) = content_hash(&msg.content) {
+                let previous = xpd_database::swap_content_hash(
+                    &self.db,
+                    msg.author.id,
+                    guild_id,
+                    hash,
+                    DUPLICATE_MESSAGE_WINDOW.as_secs_f64(),
+                )
+                .await?;
+                if previous == Some(hash) {
+                    debug!(user = ?msg.author.id, "Ignoring duplicate message");
+                    return Ok(());
+                }
+            }
+        }
+
+        let base_xp: i64 = if config_max_xp_per_msg == config_min_xp_per_msg {
             config_max_xp_per_msg
         } else {
             rand::thread_rng().gen_range(config_min_xp_per_msg..=config_max_xp_per_msg)
         }
         .into();
+        let xp_added =
+            base_xp + content_bonus(&guild_config, msg.attachments.len(), msg.embeds.len());
 
-        let xp_i64 = xpd_database::add_xp(&self.db, msg.author.id, guild_id, xp_added).await?;
-        let xp = u64::try_from(xp_i64).unwrap_or(0);
-        let old_xp = u64::try_from(xp_i64 - xp_added).unwrap_or(0);
-
-        let level_info = mee6::LevelInfo::new(xp);
-        let old_level_info = mee6::LevelInfo::new(old_xp);
+        let starting_xp = guild_config.starting_xp.unwrap_or(0);
+        let xp_i64 = xpd_database::add_xp_with_start(
+            &self.db,
+            msg.author.id,
+            guild_id,
+            xp_added,
+            starting_xp,
+        )
+        .await?;
+        // The XP is already saved, so losing a history point shouldn't stop the level-up
+        xpd_database::record_xp_history(&self.db, msg.author.id, guild_id, xp_i64)
+            .await
+            .log_error("Failed to record XP history");
+        xpd_database::record_hourly_xp(&self.db, msg.author.id, guild_id, xp_added)
+            .await
+            .log_error("Failed to record hourly XP");
+        let mut outcome = GrantOutcome::new(
+            u64::try_from(xp_i64 - xp_added).unwrap_or(0),
+            u64::try_from(xp_i64).unwrap_or(0),
+        );
 
         let rewards = self.get_guild_rewards(guild_id).await?;
+        let rewards = self
+            .skip_deleted_rewards(guild_id, &guild_config, &rewards)
+            .await?;
 
         debug!(
             ?rewards,
@@ -91,27 +153,51 @@ impl XpdListenerInner {
             "Got & sorted rewards for guild"
         );
 
-        let user_level: i64 = level_info.level().try_into().unwrap_or(-1);
-        let old_user_level: i64 = old_level_info.level().try_into().unwrap_or(-1);
-
-        debug!(user = ?msg.author.id, channel = ?msg.channel_id, old_xp, new_xp = xp, user_level, old_user_level, config = ?guild_config, "Preparing to update user");
-
-        if user_level > old_user_level {
-            self.congratulate_user(&guild_config, &msg, user_level, old_user_level, xp, old_xp)
-                .await?;
+        debug!(user = ?msg.author.id, channel = ?msg.channel_id, ?outcome, config = ?guild_config, "Preparing to update user");
+
+        if let Some(roles) = self
+            .add_user_role(
+                guild_id,
+                &guild_config,
+                msg.author.id,
+                member,
+                &rewards,
+                outcome.new_level,
+            )
+            .await?
+        {
+            outcome.roles_changed = roles.changed_roles;
+            outcome.earned_role = roles.earned_role;
+        }
+        if outcome.leveled_up() {
+            self.send_level_up_webhook(LevelUpPayload {
+                guild_id,
+                user_id: msg.author.id,
+                old_level: outcome.old_level,
+                new_level: outcome.new_level,
+                xp: outcome.xp_after,
+            });
+            if guild_config.silent_level_ups.unwrap_or(false) {
+                debug!(user = ?msg.author.id, ?guild_id, roles_changed = ?outcome.roles_changed, "Leveled up silently");
+            } else if self.level_up_cooldown_over(guild_id, msg.author.id, &guild_config) {
+                self.congratulate_user(&guild_config, &msg, &outcome)
+                    .await?;
+            }
+            // The XP is already saved, so a failure here shouldn't look like the message failed
+            self.claim_one_time_rewards(guild_id, &guild_config, &msg, outcome.new_level)
+                .await
+                .log_error("Failed to hand out one-time rewards");
+            // Ranking the member against the whole guild is too slow to do for every message
+            if let Some(widest) = guild_config.percentile_announcements.filter(|top| *top > 0) {
+                self.announce_percentile(guild_id, &guild_config, &msg, widest, xp_i64)
+                    .await
+                    .log_error("Failed to announce percentile");
+            }
         }
-        self.add_user_role(
-            guild_id,
-            &guild_config,
-            msg.author.id,
-            member,
-            &rewards,
-            user_level,
-        )
-        .await?;
         Ok(())
     }
 
+    /// Returns the role changes that were made, if any.
     #[tracing::instrument(skip(self, member))]
     async fn add_user_role(
         &self,
@@ -121,12 +207,12 @@ impl XpdListenerInner {
         member: &PartialMember,
         rewards: &[RoleReward],
         user_level: i64,
-    ) -> Result<(), Error> {
-        let Some(reward_idx) = get_reward_idx(rewards, user_level) else {
-            // This ensures we don't delete roles or otherwise edit them if none are earned.
-            return Ok(());
-        };
-        let roles = get_role_changes(guild_config, member, rewards, reward_idx);
+    ) -> Result<Option<RewardRoleChanges>, Error> {
+        let roles = RewardRoleChanges::new(guild_config, &member.roles, rewards, user_level);
+        if roles.changed_roles.is_empty() {
+            // This ensures we don't make useless requests or edit roles if nothing changed.
+            return Ok(None);
+        }
 
         // make sure we don't make useless error requests to the API
         let can_update_roles = xpd_util::can_manage_roles(
@@ -138,34 +224,105 @@ impl XpdListenerInner {
         .can_update_roles();
         if can_update_roles {
             debug!(user = ?user_id, old = ?member.roles, new = ?roles, "Updating roles for user");
-            self.http
+            match self
+                .http
                 .update_guild_member(guild_id, user_id)
                 .roles(&roles.total_roles)
-                .await?;
+                .await
+            {
+                Ok(_) => Ok(Some(roles)),
+                // The cache can lag behind a role deletion, so this is not an error worth bubbling up.
+                Err(source)
+                    if xpd_util::discord_error_code(&source) == Some(xpd_util::UNKNOWN_ROLE) =>
+                {
+                    warn!(user = ?user_id, guild = ?guild_id, new = ?roles, "Discord rejected a deleted reward role");
+                    Ok(None)
+                }
+                Err(source)
+                    if xpd_util::discord_error_code(&source)
+                        == Some(xpd_util::MISSING_PERMISSIONS) =>
+                {
+                    self.report_unmanageable_roles(guild_id, guild_config, &roles.changed_roles)
+                        .await;
+                    Ok(None)
+                }
+                Err(source) => Err(source.into()),
+            }
         } else {
             warn!(user = ?user_id, old = ?member.roles, new = ?roles, "Could not update roles for user");
+            self.report_unmanageable_roles(guild_id, guild_config, &roles.changed_roles)
+                .await;
+            Ok(None)
         }
-        Ok(())
+    }
+
+    /// Rewards for roles which were deleted on Discord can never be granted, so leave them out.
+    /// Depending on the guild config, they are also removed from the database.
+    async fn skip_deleted_rewards<'a>(
+        &self,
+        guild_id: Id<GuildMarker>,
+        guild_config: &GuildConfig,
+        rewards: &'a [RoleReward],
+    ) -> Result<Cow<'a, [RoleReward]>, Error> {
+        // If the guild isn't cached yet, a missing role doesn't mean it was deleted.
+        if self.cache.guild(guild_id).is_none() {
+            return Ok(Cow::Borrowed(rewards));
+        }
+        let (live, deleted) =
+            partition_deleted_rewards(rewards, |role| self.cache.role(role).is_some());
+        if deleted.is_empty() {
+            return Ok(Cow::Borrowed(rewards));
+        }
+
+        if guild_config.prune_deleted_rewards.is_some_and(|v| v) {
+            for role in &deleted {
+                warn!(guild = ?guild_id, ?role, "Removing reward for deleted role");
+                xpd_database::delete_reward_role(&self.db, guild_id, None, Some(*role)).await?;
+            }
+            self.invalidate_rewards(guild_id).await?;
+        } else {
+            warn!(guild = ?guild_id, roles = ?deleted, "Guild has rewards for deleted roles");
+        }
+        Ok(Cow::Owned(live))
+    }
+
+    /// Whether this member may be sent another level-up message yet. If they may, their cooldown restarts.
+    fn level_up_cooldown_over(
+        &self,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+        guild_config: &GuildConfig,
+    ) -> bool {
+        let cooldown = level_up_cooldown(guild_config);
+        if cooldown.is_zero() {
+            return true;
+        }
+        let now = Instant::now();
+        if self
+            .level_ups
+            .get(&(guild_id, user_id))
+            .is_some_and(|last| now.duration_since(*last) < cooldown)
+        {
+            return false;
+        }
+        if self.level_ups.len() >= LEVEL_UP_PRUNE_THRESHOLD {
+            self.level_ups
+                .retain(|_, last| now.duration_since(*last) < MAX_LEVEL_UP_COOLDOWN);
+        }
+        self.level_ups.insert((guild_id, user_id), now);
+        true
     }
 
     async fn congratulate_user(
         &self,
         guild_config: &GuildConfig,
         msg: &MessageCreate,
-        user_level: i64,
-        old_user_level: i64,
-        xp: u64,
-        old_xp: u64,
+        outcome: &GrantOutcome,
     ) -> Result<(), Error> {
         let Some(template) = guild_config.level_up_message.as_ref() else {
             return Ok(());
         };
         let target_channel = guild_config.level_up_channel.unwrap_or(msg.channel_id);
-        debug!(user = ?msg.author.id, channel = ?msg.channel_id, ?target_channel, old = old_user_level, new = user_level, "Congratulating user");
-        if !xpd_util::can_create_message(&self.cache, self.bot_id, target_channel)? {
-            warn!(channel = ?msg.channel_id, user = ?msg.author.id, guild = ?msg.guild_id, "Could not congratulate user");
-            return Ok(());
-        }
         let mention = format!("<@{}>", msg.author.id);
         // this is horrible but i love it.
         let author_id_str = &mention[2..=mention.len() - 2];
@@ -176,182 +333,363 @@ impl XpdListenerInner {
             .and_then(|v| v.nick.as_deref().map(Cow::Borrowed))
             .unwrap_or_else(|| Cow::Borrowed(msg.author.display_name()));
 
+        let display_level =
+            |level: i64| guild_config.display_level(u64::try_from(level).unwrap_or(0));
+        let earned_role = outcome
+            .earned_role
+            .map_or_else(String::new, |role| format!("<@&{role}>"));
+
         let map: HashMap<Cow<str>, Cow<str>> = HashMap::from([
             (Cow::Borrowed("user_id"), Cow::Borrowed(author_id_str)),
             ("user_mention".into(), mention.as_str().into()),
             ("user_username".into(), msg.author.name.as_str().into()),
             ("user_display_name".into(), msg.author.display_name().into()),
             ("user_nickname".into(), nickname),
-            ("old_level".into(), old_user_level.to_string().into()),
-            ("level".into(), user_level.to_string().into()),
-            ("old_xp".into(), xp.to_string().into()),
-            ("xp".into(), old_xp.to_string().into()),
+            ("old_level".into(), display_level(outcome.old_level).into()),
+            ("level".into(), display_level(outcome.new_level).into()),
+            ("old_xp".into(), outcome.xp_before.to_string().into()),
+            ("xp".into(), outcome.xp_after.to_string().into()),
+            ("earned_role".into(), earned_role.as_str().into()),
         ]);
         let message = template.render(&map);
 
+        if xpd_database::level_up_dms(&self.db, msg.author.id).await? {
+            return self.dm_level_up(msg, &message).await;
+        }
+        debug!(user = ?msg.author.id, channel = ?msg.channel_id, ?target_channel, old = outcome.old_level, new = outcome.new_level, "Congratulating user");
+        if !xpd_util::can_create_message(&self.cache, self.bot_id, target_channel)? {
+            warn!(channel = ?msg.channel_id, user = ?msg.author.id, guild = ?msg.guild_id, "Could not congratulate user");
+            return Ok(());
+        }
+
+        // Role mentions are never allowed to ping, the earned role is only shown for flair.
         let allowed_mentions = if let Some(false) = guild_config.ping_on_level_up {
             AllowedMentions::default()
         } else {
             AllowedMentions {
                 replied_user: true,
-                users: vec![msg.author.id],
-                ..AllowedMentions::default()
+                ..xpd_common::ping_only_user(msg.author.id)
             }
         };
 
-        let mut congratulatory_msg = self.http.create_message(target_channel);
+        let mut congratulatory_msg = self
+            .http
+            .create_message(target_channel)
+            .allowed_mentions(Some(&allowed_mentions));
         if target_channel == msg.channel_id {
             // only reply to a message if it's in the same channel
             congratulatory_msg = congratulatory_msg.reply(msg.id);
         }
-        congratulatory_msg
+        match guild_config.level_up_style.unwrap_or_default() {
+            LevelUpStyle::Plain => congratulatory_msg.content(&message).await?,
+            LevelUpStyle::Embed => {
+                let embed = level_up_embed(msg, message);
+                // Mentions in embeds never ping, so the mention goes in the message itself
+                congratulatory_msg
+                    .content(&mention)
+                    .embeds(&[embed])
+                    .await?
+            }
+        };
+        Ok(())
+    }
+
+    /// Tell the author they've reached a new "top X%" of the guild, if they have.
+    /// Every step is only announced once, even if they drop out of it and climb back.
+    async fn announce_percentile(
+        &self,
+        guild_id: Id<GuildMarker>,
+        guild_config: &GuildConfig,
+        msg: &MessageCreate,
+        widest: i16,
+        xp: i64,
+    ) -> Result<(), Error> {
+        // Steps aren't claimed either, so members still hear about them if level-ups stop being silent
+        if guild_config.silent_level_ups.unwrap_or(false) {
+            return Ok(());
+        }
+        let (higher, members) = xpd_database::guild_standing(&self.db, guild_id, xp).await?;
+        let Some(step) = xpd_common::percentile_step(higher + 1, members, widest) else {
+            return Ok(());
+        };
+        let target_channel = guild_config.level_up_channel.unwrap_or(msg.channel_id);
+        if !xpd_util::can_create_message(&self.cache, self.bot_id, target_channel)? {
+            warn!(channel = ?target_channel, user = ?msg.author.id, ?guild_id, "Could not announce percentile");
+            return Ok(());
+        }
+        if !xpd_database::claim_percentile(&self.db, guild_id, msg.author.id, step).await? {
+            return Ok(());
+        }
+        debug!(user = ?msg.author.id, ?guild_id, step, "Announcing percentile");
+        let allowed_mentions = if let Some(false) = guild_config.ping_on_level_up {
+            AllowedMentions::default()
+        } else {
+            xpd_common::ping_only_user(msg.author.id)
+        };
+        let content = format!(
+            "<@{}> is now in the top {step}% of the server!",
+            msg.author.id
+        );
+        let mut announcement = self
+            .http
+            .create_message(target_channel)
             .allowed_mentions(Some(&allowed_mentions))
-            .content(&message)
-            .await?;
+            .content(&content);
+        if target_channel == msg.channel_id {
+            announcement = announcement.reply(msg.id);
+        }
+        announcement.await?;
+        Ok(())
+    }
+
+    /// Claim the one-time rewards the author just reached, and tell them so the guild's staff can
+    /// hand them out. They stay claimed even if this can't be sent, `/rewards claims` still lists them.
+    async fn claim_one_time_rewards(
+        &self,
+        guild_id: Id<GuildMarker>,
+        guild_config: &GuildConfig,
+        msg: &MessageCreate,
+        level: i64,
+    ) -> Result<(), Error> {
+        let claimed =
+            xpd_database::claim_one_time_rewards(&self.db, guild_id, msg.author.id, level).await?;
+        if claimed.is_empty() {
+            return Ok(());
+        }
+        if guild_config.silent_level_ups.unwrap_or(false) {
+            debug!(user = ?msg.author.id, ?guild_id, ?claimed, "Claimed one-time rewards silently");
+            return Ok(());
+        }
+        let target_channel = guild_config.level_up_channel.unwrap_or(msg.channel_id);
+        if !xpd_util::can_create_message(&self.cache, self.bot_id, target_channel)? {
+            warn!(channel = ?target_channel, user = ?msg.author.id, ?guild_id, "Could not announce one-time rewards");
+            return Ok(());
+        }
+        debug!(user = ?msg.author.id, ?guild_id, ?claimed, "Announcing one-time rewards");
+        let allowed_mentions = if let Some(false) = guild_config.ping_on_level_up {
+            AllowedMentions::default()
+        } else {
+            xpd_common::ping_only_user(msg.author.id)
+        };
+        let content = format!("<@{}> earned {}!", msg.author.id, reward_list(&claimed));
+        let mut announcement = self
+            .http
+            .create_message(target_channel)
+            .allowed_mentions(Some(&allowed_mentions))
+            .content(&content);
+        if target_channel == msg.channel_id {
+            announcement = announcement.reply(msg.id);
+        }
+        announcement.await?;
         Ok(())
     }
-}
 
-fn get_reward_idx(rewards: &[RoleReward], user_level: i64) -> Option<usize> {
-    let mut reward_idx = None;
-    for (idx, data) in rewards.iter().enumerate() {
-        if data.requirement > user_level {
-            break;
+    /// Send a level-up message to the member who leveled up, for members who opted out of public ones
+    async fn dm_level_up(&self, msg: &MessageCreate, message: &str) -> Result<(), Error> {
+        debug!(user = ?msg.author.id, guild = ?msg.guild_id, "Congratulating user in DMs");
+        let guild_name = msg
+            .guild_id
+            .and_then(|guild_id| self.cache.guild(guild_id))
+            .map_or_else(|| "a server".to_string(), |guild| guild.name().to_owned());
+        let content = format!(
+            "{message}\n-# Sent from {guild_name}. Get level-ups there again with `/rank notify:False`"
+        );
+        let channel = self
+            .http
+            .create_private_channel(msg.author.id)
+            .await?
+            .model()
+            .await?;
+        match self
+            .http
+            .create_message(channel.id)
+            .allowed_mentions(Some(&AllowedMentions::default()))
+            .content(&content)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(source)
+                if xpd_util::discord_error_code(&source) == Some(xpd_util::CANNOT_MESSAGE_USER) =>
+            {
+                debug!(user = ?msg.author.id, "Skipping level-up DM for user with closed DMs");
+                Ok(())
+            }
+            Err(source) => Err(source.into()),
         }
-        reward_idx = Some(idx);
     }
-    reward_idx
 }
 
-#[derive(Debug)]
-struct RoleChangeList {
-    total_roles: RoleList,
-    changed_roles: RoleList,
+/// Embeds use the member's profile accent color when Discord sent it, or this otherwise.
+const LEVEL_UP_EMBED_COLOR: u32 = 0x007F_FFD4;
+
+fn level_up_embed(msg: &MessageCreate, message: String) -> Embed {
+    let member = msg.member.as_ref();
+    let info = MemberDisplayInfo {
+        local_avatar: member.and_then(|member| member.avatar),
+        ..MemberDisplayInfo::from(msg.author.clone())
+            .with_nick(member.and_then(|member| member.nick.clone()))
+    };
+    let mut embed = EmbedBuilder::new()
+        .description(message)
+        .color(msg.author.accent_color.unwrap_or(LEVEL_UP_EMBED_COLOR));
+    if let Ok(thumbnail) = ImageSource::url(info.avatar_url(msg.guild_id)) {
+        embed = embed.thumbnail(thumbnail);
+    }
+    embed.build()
 }
 
-fn get_role_changes(
-    guild_config: &GuildConfig,
-    member: &PartialMember,
+fn partition_deleted_rewards(
     rewards: &[RoleReward],
-    reward_idx: usize,
-) -> RoleChangeList {
-    let one_at_a_time = guild_config.one_at_a_time.is_some_and(|v| v);
-
-    let previous_role = rewards[reward_idx.saturating_sub(1)].id;
-    let achieved_roles = if one_at_a_time {
-        &rewards[reward_idx..=reward_idx]
-    } else {
-        &rewards[..=reward_idx]
-    };
-    let roles_to_add = achieved_roles.iter().filter_map(|v| {
-        if !member.roles.contains(&v.id) {
-            Some(v.id)
+    role_exists: impl Fn(Id<RoleMarker>) -> bool,
+) -> (Vec<RoleReward>, RoleList) {
+    let mut live = Vec::with_capacity(rewards.len());
+    let mut deleted = RoleList::new();
+    for reward in rewards {
+        if role_exists(reward.id) {
+            live.push(*reward);
         } else {
-            None
+            deleted.push(reward.id);
         }
-    });
-
-    let mut changed_roles = Vec::with_capacity(8);
-
-    let total_roles: RoleList = member
-        .roles
-        .iter()
-        .copied()
-        .chain(roles_to_add)
-        // if we're not doing one at a time, we always return true.
-        // If the reward index is 0, we won't be removing any roles ever.
-        // Otherwise, we return true if v is not the previous role.
-        // If we're removing it, or the member didn't have it before
-        // because it was added in the chain, we also add it to the changelist.
-        // If we return false, we want to know that we are REMOVING that role.
-        .filter(|v| {
-            let keeper = !one_at_a_time || reward_idx == 0 || *v != previous_role;
-            if !keeper || !member.roles.contains(v) {
-                changed_roles.push(*v);
-            };
-            keeper
-        })
-        .collect();
-
-    RoleChangeList {
-        total_roles,
-        changed_roles,
     }
+    (live, deleted)
 }
 
-#[cfg(test)]
-mod tests {
-    use twilight_model::guild::MemberFlags;
+/// The level-up cooldown can't be longer than this, so older entries can always be forgotten
+const MAX_LEVEL_UP_COOLDOWN: Duration = Duration::from_secs(i16::MAX.unsigned_abs() as u64);
+/// How many members' last level-up times are tracked before expired ones are cleared out
+const LEVEL_UP_PRUNE_THRESHOLD: usize = 10_000;
+/// How long after a message sending it again earns no XP, when duplicates are ignored
+const DUPLICATE_MESSAGE_WINDOW: Duration = Duration::from_secs(60 * 60);
 
-    use super::*;
+fn level_up_cooldown(guild_config: &GuildConfig) -> Duration {
+    let seconds = guild_config.level_up_cooldown.unwrap_or(0).max(0);
+    Duration::from_secs(seconds.unsigned_abs().into())
+}
 
-    fn member_with_roles(roles: impl Into<RoleList>) -> PartialMember {
-        PartialMember {
-            avatar: None,
-            communication_disabled_until: None,
-            deaf: false,
-            flags: MemberFlags::empty(),
-            joined_at: None,
-            mute: false,
-            nick: None,
-            permissions: None,
-            premium_since: None,
-            roles: roles.into(),
-            user: None,
+/// Everything that happened to a member when they were granted XP for a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GrantOutcome {
+    xp_before: u64,
+    xp_after: u64,
+    old_level: i64,
+    new_level: i64,
+    /// Reward roles which were added or taken away
+    roles_changed: RoleList,
+    /// The highest reward role newly granted, if any
+    earned_role: Option<Id<RoleMarker>>,
+}
+
+impl GrantOutcome {
+    fn new(xp_before: u64, xp_after: u64) -> Self {
+        let level = |xp| mee6::LevelInfo::new(xp).level().try_into().unwrap_or(-1);
+        Self {
+            xp_before,
+            xp_after,
+            old_level: level(xp_before),
+            new_level: level(xp_after),
+            roles_changed: RoleList::new(),
+            earned_role: None,
         }
     }
 
-    // Non-one at a time only changes the behavior to not remove the previous role
-    fn conf_one_at_time() -> GuildConfig {
-        GuildConfig {
-            one_at_a_time: Some(true),
-            ..Default::default()
-        }
+    const fn leveled_up(&self) -> bool {
+        self.new_level > self.old_level
     }
+}
 
-    #[test]
-    fn no_changes() {
-        let rewards = [RoleReward {
-            id: Id::new(1),
-            requirement: 2,
-        }];
-        let reward_idx = get_reward_idx(&rewards, 2).unwrap();
-        let member = member_with_roles([Id::new(1)]);
-        let changes = get_role_changes(&conf_one_at_time(), &member, &rewards, reward_idx);
-        assert_eq!(changes.changed_roles, RoleList::new());
-        assert_eq!(changes.total_roles, [Id::new(1)]);
+/// Hash of a message's text with case and whitespace normalized, so trivially edited
+/// copies of a message hash the same. Messages with no text have no hash.
+///
+/// This is 64-bit FNV-1a, since the hashes are stored and must match across builds and restarts.
+fn content_hash(content: &str) -> Option<i64> {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01b3;
+    // never appears in UTF-8, so it can't be confused with the text of a word
+    const WORD_END: u8 = 0xff;
+
+    let mut words = content.split_whitespace().peekable();
+    words.peek()?;
+    let mut hash = FNV_OFFSET_BASIS;
+    for word in words {
+        for byte in word.to_lowercase().bytes().chain([WORD_END]) {
+            hash = (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
     }
+    Some(hash.cast_signed())
+}
 
-    #[test]
-    fn minecraft_discord() {
-        let rewards = [RoleReward {
-            id: Id::new(1),
-            requirement: 5,
-        }];
-        let reward_idx = get_reward_idx(&rewards, 5).unwrap();
-        let member = member_with_roles([]);
-        let changes = get_role_changes(&conf_one_at_time(), &member, &rewards, reward_idx);
-        assert_eq!(changes.changed_roles, [Id::new(1)]);
-        assert_eq!(changes.total_roles, [Id::new(1)]);
+/// Extra XP for the attachments and embeds on a message, which only counts a few of each
+/// so that spamming images isn't worth more than the guild intended.
+fn content_bonus(config: &GuildConfig, attachments: usize, embeds: usize) -> i64 {
+    let bonus_for = |bonus: Option<i16>, count: usize| {
+        let bonus = i64::from(bonus.unwrap_or(0).clamp(0, MAX_CONTENT_BONUS_XP));
+        let count = i64::try_from(count.min(MAX_CONTENT_BONUS_ITEMS)).unwrap_or(0);
+        bonus * count
+    };
+    bonus_for(config.attachment_bonus_xp, attachments) + bonus_for(config.embed_bonus_xp, embeds)
+}
+
+/// Name one-time rewards in a sentence, like "**a**, **b** and **c**"
+fn reward_list(rewards: &[String]) -> String {
+    let bold: Vec<String> = rewards.iter().map(|name| format!("**{name}**")).collect();
+    match bold.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {last}", rest.join(", ")),
+        None => String::new(),
     }
+}
+
+/// The cooldown override of the highest of the member's roles that has one.
+/// Roles missing from the cache rank below every other role.
+fn role_cooldown(
+    overrides: &[RoleCooldown],
+    member_roles: &[Id<RoleMarker>],
+    position: impl Fn(Id<RoleMarker>) -> Option<i64>,
+) -> Option<i16> {
+    overrides
+        .iter()
+        .filter(|cooldown| member_roles.contains(&cooldown.role))
+        // Roles in the same position are ordered by ID in Discord, lowest first
+        .max_by_key(|cooldown| (position(cooldown.role), Reverse(cooldown.role)))
+        .map(|cooldown| cooldown.cooldown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
-    fn add_one_role() {
-        let rewards = [
-            RoleReward {
-                id: Id::new(1),
-                requirement: 2,
+    fn highest_role_cooldown_wins() {
+        let overrides = [
+            RoleCooldown {
+                role: Id::new(1),
+                cooldown: 0,
+            },
+            RoleCooldown {
+                role: Id::new(2),
+                cooldown: 300,
             },
-            RoleReward {
-                id: Id::new(2),
-                requirement: 10,
+            RoleCooldown {
+                role: Id::new(3),
+                cooldown: 30,
             },
         ];
-        let reward_idx = get_reward_idx(&rewards, 4).unwrap();
-        let member = member_with_roles([]);
-        let changes = get_role_changes(&conf_one_at_time(), &member, &rewards, reward_idx);
-        assert_eq!(changes.changed_roles, vec![Id::new(1)]);
-        assert_eq!(changes.total_roles, [Id::new(1)]);
+        let positions = |role: Id<RoleMarker>| match role.get() {
+            1 => Some(10),
+            2 | 3 => Some(5),
+            _ => None,
+        };
+        assert_eq!(role_cooldown(&overrides, &[], positions), None);
+        assert_eq!(role_cooldown(&overrides, &[Id::new(9)], positions), None);
+        assert_eq!(
+            role_cooldown(&overrides, &[Id::new(2), Id::new(1)], positions),
+            Some(0)
+        );
+        assert_eq!(
+            role_cooldown(&overrides, &[Id::new(3), Id::new(2)], positions),
+            Some(300)
+        );
+        assert_eq!(role_cooldown(&overrides, &[Id::new(3)], |_| None), Some(30));
     }
 
     const TEST_REWARDS: [RoleReward; 3] = [
@@ -370,46 +708,88 @@ mod tests {
     ];
 
     #[test]
-    fn skip_roles() {
-        let reward_idx = get_reward_idx(&TEST_REWARDS, 10).unwrap();
-        let member = member_with_roles([]);
-        let changes = get_role_changes(&conf_one_at_time(), &member, &TEST_REWARDS, reward_idx);
-        assert_eq!(changes.changed_roles, [Id::new(3)]);
-        assert_eq!(changes.total_roles, [Id::new(3)]);
+    fn deleted_rewards_are_split_out() {
+        let (live, deleted) = partition_deleted_rewards(&TEST_REWARDS, |id| id != Id::new(2));
+        let live: RoleList = live.iter().map(|v| v.id).collect();
+        assert_eq!(live, [Id::new(1), Id::new(3)]);
+        assert_eq!(deleted, [Id::new(2)]);
     }
+
     #[test]
-    fn stop_on_role() {
-        let reward_idx = get_reward_idx(&TEST_REWARDS, 5).unwrap();
-        let member = member_with_roles([Id::new(1)]);
-        let changes = get_role_changes(&conf_one_at_time(), &member, &TEST_REWARDS, reward_idx);
-        assert_eq!(changes.changed_roles, [Id::new(1), Id::new(2)]);
-        assert_eq!(changes.total_roles, [Id::new(2)]);
+    fn content_bonus_is_capped() {
+        let config = GuildConfig {
+            attachment_bonus_xp: Some(5),
+            embed_bonus_xp: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(content_bonus(&GuildConfig::default(), 2, 2), 0);
+        assert_eq!(content_bonus(&config, 0, 0), 0);
+        assert_eq!(content_bonus(&config, 2, 1), 12);
+        assert_eq!(content_bonus(&config, 50, 0), 15);
+        let config = GuildConfig {
+            attachment_bonus_xp: Some(i16::MAX),
+            ..Default::default()
+        };
+        assert_eq!(content_bonus(&config, 1, 0), 100);
     }
 
     #[test]
-    fn conf_many_doesnt_nuke() {
-        let reward_idx = get_reward_idx(&TEST_REWARDS, 5).unwrap();
-        let member = member_with_roles([Id::new(1)]);
-        let changes = get_role_changes(&GuildConfig::default(), &member, &TEST_REWARDS, reward_idx);
-        assert_eq!(changes.changed_roles, [Id::new(2)]);
-        assert_eq!(changes.total_roles, [Id::new(1), Id::new(2)]);
+    fn duplicate_content_hashes() {
+        assert_eq!(content_hash("Hello  World"), content_hash("hello world\n"));
+        assert_ne!(content_hash("hello world"), content_hash("hello there"));
+        assert_ne!(content_hash("ab c"), content_hash("a bc"));
+        assert_eq!(content_hash(" \n "), None);
+        // stored hashes have to stay valid between builds
+        assert_eq!(
+            content_hash("hello"),
+            Some(0xa9bd_73cc_a220_c59c_u64.cast_signed())
+        );
     }
 
     #[test]
-    fn conf_many_adds_many() {
-        let reward_idx = get_reward_idx(&TEST_REWARDS, 11).unwrap();
-        let member = member_with_roles([]);
-        let changes = get_role_changes(&GuildConfig::default(), &member, &TEST_REWARDS, reward_idx);
-        assert_eq!(changes.changed_roles, [Id::new(1), Id::new(2), Id::new(3)]);
-        assert_eq!(changes.total_roles, [Id::new(1), Id::new(2), Id::new(3)]);
+    fn grant_outcome_levels() {
+        let first_level = mee6::xp_needed_for_level(1);
+
+        let outcome = GrantOutcome::new(0, first_level - 1);
+        assert_eq!((outcome.old_level, outcome.new_level), (0, 0));
+        assert!(!outcome.leveled_up());
+
+        let outcome = GrantOutcome::new(first_level - 1, first_level);
+        assert_eq!((outcome.old_level, outcome.new_level), (0, 1));
+        assert!(outcome.leveled_up());
+
+        let outcome = GrantOutcome::new(first_level, first_level + 1);
+        assert!(!outcome.leveled_up());
+
+        let outcome = GrantOutcome::new(0, mee6::xp_needed_for_level(5));
+        assert_eq!((outcome.old_level, outcome.new_level), (0, 5));
+        assert!(outcome.leveled_up());
+        assert!(outcome.roles_changed.is_empty());
     }
 
     #[test]
-    fn leave_alone_higher_roles() {
-        let reward_idx = get_reward_idx(&TEST_REWARDS, 3).unwrap();
-        let member = member_with_roles([Id::new(3)]);
-        let changes = get_role_changes(&GuildConfig::default(), &member, &TEST_REWARDS, reward_idx);
-        assert_eq!(changes.changed_roles, [Id::new(1)]);
-        assert_eq!(changes.total_roles, [Id::new(3), Id::new(1)]);
+    fn level_up_cooldowns() {
+        let config = |seconds| GuildConfig {
+            level_up_cooldown: Some(seconds),
+            ..Default::default()
+        };
+        assert_eq!(level_up_cooldown(&GuildConfig::default()), Duration::ZERO);
+        assert_eq!(level_up_cooldown(&config(-5)), Duration::ZERO);
+        assert_eq!(level_up_cooldown(&config(30)), Duration::from_secs(30));
+        assert_eq!(level_up_cooldown(&config(i16::MAX)), MAX_LEVEL_UP_COOLDOWN);
+    }
+
+    #[test]
+    fn reward_lists() {
+        let names = |names: &[&str]| names.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(reward_list(&names(&["Token"])), "**Token**");
+        assert_eq!(
+            reward_list(&names(&["Token", "Flag"])),
+            "**Token** and **Flag**"
+        );
+        assert_eq!(
+            reward_list(&names(&["Token", "Flag", "Hat"])),
+            "**Token**, **Flag** and **Hat**"
+        );
     }
 }
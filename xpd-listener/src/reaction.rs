@@ -0,0 +1,93 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use twilight_model::gateway::payload::incoming::ReactionAdd;
+use xpd_common::{DEFAULT_MAX_REACTION_GRANTS, DISCORD_EPOCH_SECS, REACTION_XP_MAX_AGE_HOURS};
+
+use xpd_util::LogError;
+
+use crate::{Error, XpdListenerInner};
+
+impl XpdListenerInner {
+    /// Give the author of a message XP for a reaction, if the guild turned reaction XP on.
+    ///
+    /// Like `/xp add`, this doesn't hand out reward roles or send level-up messages,
+    /// those happen the next time the author earns XP from a message.
+    #[tracing::instrument(skip(self, reaction), fields(message = ?reaction.message_id))]
+    pub async fn save_reaction(&self, reaction: ReactionAdd) -> Result<(), Error> {
+        let (Some(guild_id), Some(author)) = (reaction.guild_id, reaction.message_author_id) else {
+            return Ok(());
+        };
+        if author == reaction.user_id
+            || reaction
+                .member
+                .as_ref()
+                .is_some_and(|member| member.user.bot)
+        {
+            return Ok(());
+        }
+        let guild_config = self.get_guild_config(guild_id).await?;
+        let Some(xp) = guild_config.reaction_xp.filter(|xp| *xp > 0) else {
+            return Ok(());
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs().try_into().unwrap_or(i64::MAX));
+        if guild_config.xp_locked(now) {
+            debug!(guild = ?guild_id, "Skipping reaction in guild with XP locked down");
+            return Ok(());
+        }
+        if reaction.member.as_ref().is_some_and(|member| {
+            member
+                .joined_at
+                .is_some_and(|joined_at| guild_config.in_join_grace(joined_at.as_secs(), now))
+        }) {
+            debug!(guild = ?guild_id, user = ?reaction.user_id, "Skipping reaction from member still in join grace period");
+            return Ok(());
+        }
+        if guild_config.account_too_new(
+            DISCORD_EPOCH_SECS + xpd_util::snowflake_to_timestamp(reaction.user_id),
+            now,
+        ) {
+            debug!(guild = ?guild_id, user = ?reaction.user_id, "Skipping reaction from account younger than the minimum age");
+            return Ok(());
+        }
+        let sent_at = DISCORD_EPOCH_SECS + xpd_util::snowflake_to_timestamp(reaction.message_id);
+        if now - sent_at > i64::from(REACTION_XP_MAX_AGE_HOURS) * 60 * 60 {
+            return Ok(());
+        }
+        // Reactions don't say whether the author is a bot, but bots and webhooks never earn XP from
+        // their own messages, so only authors who already have XP can earn it from reactions.
+        if !guild_config.rank_bots.unwrap_or(false)
+            && xpd_database::user_xp(&self.db, guild_id, author)
+                .await?
+                .is_none()
+        {
+            return Ok(());
+        }
+        let max_grants = guild_config
+            .max_reaction_grants
+            .unwrap_or(DEFAULT_MAX_REACTION_GRANTS);
+        if !xpd_database::grant_reaction(
+            &self.db,
+            reaction.message_id,
+            reaction.user_id,
+            max_grants,
+        )
+        .await?
+        {
+            return Ok(());
+        }
+        let xp = i64::from(xp);
+        let starting_xp = guild_config.starting_xp.unwrap_or(0);
+        let total =
+            xpd_database::add_xp_with_start(&self.db, author, guild_id, xp, starting_xp).await?;
+        xpd_database::record_xp_history(&self.db, author, guild_id, total)
+            .await
+            .log_error("Failed to record XP history");
+        xpd_database::record_hourly_xp(&self.db, author, guild_id, xp)
+            .await
+            .log_error("Failed to record hourly XP");
+        debug!(user = ?author, reactor = ?reaction.user_id, xp, "Gave reaction XP");
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use twilight_model::id::{
+    marker::{GuildMarker, UserMarker},
+    Id,
+};
+use xpd_database::LevelUpWebhook;
+
+use crate::XpdListenerInner;
+
+/// Header carrying the hex-encoded HMAC-SHA256 of the request body, keyed with the guild's secret
+pub const SIGNATURE_HEADER: &str = "X-Experienced-Signature";
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+const WEBHOOK_ATTEMPTS: u32 = 3;
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct LevelUpPayload {
+    pub guild_id: Id<GuildMarker>,
+    pub user_id: Id<UserMarker>,
+    pub old_level: i64,
+    pub new_level: i64,
+    pub xp: u64,
+}
+
+impl XpdListenerInner {
+    /// Tell the guild's level-up webhook about a level-up, if it has one.
+    /// This runs in the background so a slow endpoint never holds up leveling.
+    pub(crate) fn send_level_up_webhook(&self, payload: LevelUpPayload) {
+        let db = self.db.clone();
+        let web = self.web.clone();
+        self.task_tracker.spawn(async move {
+            let webhook = match xpd_database::level_up_webhook(&db, payload.guild_id).await {
+                Ok(Some(webhook)) => webhook,
+                Ok(None) => return,
+                Err(source) => {
+                    error!(?source, guild = ?payload.guild_id, "Could not fetch level-up webhook");
+                    return;
+                }
+            };
+            if let Err(source) = post_webhook(&web, &webhook, &payload).await {
+                warn!(?source, guild = ?payload.guild_id, "Level-up webhook failed");
+            }
+        });
+    }
+}
+
+async fn post_webhook(
+    web: &reqwest::Client,
+    webhook: &LevelUpWebhook,
+    payload: &LevelUpPayload,
+) -> Result<(), WebhookError> {
+    // The host is checked again for every request, because what it resolves to can change after it's saved.
+    // The webhook client's resolver catches it changing again before we connect, but never sees IP literals.
+    let url = reqwest::Url::parse(&webhook.url).map_err(|_| WebhookError::InvalidUrl)?;
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return Err(WebhookError::NoHost);
+    };
+    xpd_util::resolve_public_host(host, port).await?;
+    let body = serde_json::to_vec(payload)?;
+    let signature = sign(webhook.secret.as_bytes(), &body);
+    let mut attempt = 1;
+    loop {
+        let result = web
+            .post(url.clone())
+            .timeout(WEBHOOK_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, &signature)
+            .body(body.clone())
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        match result {
+            Ok(_) => return Ok(()),
+            // Only server errors and timeouts are worth trying again, anything else won't change
+            Err(source)
+                if attempt < WEBHOOK_ATTEMPTS
+                    && (source.is_timeout()
+                        || source
+                            .status()
+                            .is_some_and(|status| status.is_server_error())) =>
+            {
+                debug!(?source, attempt, "Retrying level-up webhook");
+                tokio::time::sleep(WEBHOOK_RETRY_DELAY * attempt).await;
+                attempt += 1;
+            }
+            Err(source) => return Err(source.into()),
+        }
+    }
+}
+
+/// Hex-encoded HMAC-SHA256 of `body`
+#[must_use]
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[derive(Debug, thiserror::Error)]
+enum WebhookError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Could not serialize payload: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid webhook URL")]
+    InvalidUrl,
+    #[error("Webhook URL has no host")]
+    NoHost,
+    #[error("Webhook host is not allowed: {0}")]
+    Host(#[from] xpd_util::PublicHostError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_like_rfc_4231() {
+        assert_eq!(
+            sign(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn payload_ids_are_strings() {
+        let payload = LevelUpPayload {
+            guild_id: Id::new(1),
+            user_id: Id::new(2),
+            old_level: 3,
+            new_level: 4,
+            xp: 500,
+        };
+        assert_eq!(
+            serde_json::to_string(&payload).unwrap(),
+            r#"{"guild_id":"1","user_id":"2","old_level":3,"new_level":4,"xp":500}"#
+        );
+    }
+}
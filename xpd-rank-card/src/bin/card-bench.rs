@@ -7,12 +7,15 @@ const VALK_PFP: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAQAAAAEABA
 fn main() {
     let state = SvgState::new("xpd-card-resources").unwrap();
     let context = Context {
-        level: 694,
+        level: "694".to_string(),
         rank: 124,
         name: "Testy McTestington".to_string(),
         percentage: 30,
         current: 124,
         needed: 213,
+        messages: None,
+        separator: xpd_rank_card::DEFAULT_SEPARATOR,
+        labels: xpd_rank_card::Labels::default(),
         customizations: state.default_customizations().clone(),
         avatar: VALK_PFP.to_string(),
     };
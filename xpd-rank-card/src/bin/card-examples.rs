@@ -1,6 +1,9 @@
 use std::thread::JoinHandle;
 
-use xpd_rank_card::{customizations::Customizations, *};
+use xpd_rank_card::{
+    customizations::{Color, Customizations},
+    *,
+};
 
 const VALK_PFP: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAQAAAAEABAMAAACuXLVVAAAAIGNIUk0AAHomAACAhAAA+gAAAIDoAAB1MAAA6mAAADqYAAAXcJy6UTwAAAAYUExURXG0zgAAAFdXV6ampoaGhr6zpHxfQ2VPOt35dJcAAAABYktHRAH/Ai3eAAAAB3RJTUUH5wMDFSE5W/eo1AAAAQtJREFUeNrt1NENgjAUQFFXYAVWYAVXcAVXYH0hoQlpSqGY2Dae82WE9971x8cDAAAAAAAAAAAAAAAAAADgR4aNAAEC/jNgPTwuBAgQ8J8B69FpI0CAgL4DhozczLgjQICAPgPCkSkjtXg/I0CAgD4Dzg4PJ8YEAQIE9BEQLyg5cEWYFyBAQHsBVxcPN8U7BAgQ0FbAlcNhcLohjkn+egECBFQPKPE8cXpQgAABzQXkwsIfUElwblaAAAF9BeyP3Z396rgAAQJ+EvCqTIAAAfUD3pUJECCgvYB5kfp89N28yR3J7RQgQED9gPjhfmG8/Oh56r1UYOpdAQIEtBFwtLBUyY7wrgABAqoHfABW2cbX3ElRgQAAACV0RVh0ZGF0ZTpjcmVhdGUAMjAyMy0wMy0wM1QyMTozMzo1NiswMDowMNpnAp0AAAAldEVYdGRhdGU6bW9kaWZ5ADIwMjMtMDMtMDNUMjE6MzM6NTYrMDA6MDCrOrohAAAAKHRFWHRkYXRlOnRpbWVzdGFtcAAyMDIzLTAzLTAzVDIxOjMzOjU3KzAwOjAwWliQSgAAAABJRU5ErkJggg==";
 
@@ -9,6 +12,7 @@ fn main() {
     std::fs::create_dir_all("rendered-cards").unwrap();
     render_classic_l().unwrap();
     render_classic_r().unwrap();
+    render_classic_gradient().unwrap();
     render_vertical().unwrap();
     render_vertical_procedural();
 }
@@ -25,12 +29,15 @@ fn render_classic_l() -> Result<(), Error> {
         ..state.customizations_for("classic.svg").unwrap().clone()
     };
     let context = Context {
-        level: 1,
+        level: "1".to_string(),
         rank: 1,
         name: "Testy McTestington".to_string(),
         percentage: xp,
         current: xp,
         needed: 100 - xp,
+        messages: None,
+        separator: xpd_rank_card::DEFAULT_SEPARATOR,
+        labels: xpd_rank_card::Labels::default(),
         customizations,
         avatar: VALK_PFP.to_string(),
     };
@@ -44,15 +51,19 @@ fn render_classic_r() -> Result<(), Error> {
     let xp = 51;
     let customizations = Customizations {
         toy: Some("cow.png".to_string()),
+        subtitle: Some("Professional tester".to_string()),
         ..state.customizations_for("classic.svg").unwrap().clone()
     };
     let context = Context {
-        level: 1,
+        level: "1".to_string(),
         rank: 1,
         name: "Testy McTestington".to_string(),
         percentage: xp,
         current: xp,
         needed: 100 - xp,
+        messages: None,
+        separator: xpd_rank_card::DEFAULT_SEPARATOR,
+        labels: xpd_rank_card::Labels::default(),
         customizations,
         avatar: VALK_PFP.to_string(),
     };
@@ -61,20 +72,49 @@ fn render_classic_r() -> Result<(), Error> {
     Ok(())
 }
 
+fn render_classic_gradient() -> Result<(), Error> {
+    let state = new_state();
+    let xp = 75;
+    let customizations = Customizations {
+        progress_foreground_2: Some(Color::new(230, 200, 40)),
+        ..state.customizations_for("classic.svg").unwrap().clone()
+    };
+    let context = Context {
+        level: "5".to_string(),
+        rank: 3,
+        name: "Testy McTestington".to_string(),
+        percentage: xp,
+        current: xp,
+        needed: 100 - xp,
+        messages: Some(12_345),
+        separator: xpd_rank_card::DEFAULT_SEPARATOR,
+        labels: xpd_rank_card::Labels::default(),
+        customizations,
+        avatar: VALK_PFP.to_string(),
+    };
+    let output = state.sync_render(&context)?;
+    std::fs::write("rendered-cards/renderer_test_classic_gradient.png", output).unwrap();
+    Ok(())
+}
+
 fn render_vertical() -> Result<(), Error> {
     let state = new_state();
     let xp = 99;
     let customizations = Customizations {
         font: "Montserrat-Alt1".to_string(),
+        subtitle: Some("they/them".to_string()),
         ..state.customizations_for("vertical.svg").unwrap().clone()
     };
     let context = Context {
-        level: 420,
+        level: "420".to_string(),
         rank: 100_000,
         name: "Testy McTestington".to_string(),
         percentage: xp,
         current: xp,
         needed: 100 - xp,
+        messages: Some(1_234),
+        separator: xpd_rank_card::DEFAULT_SEPARATOR,
+        labels: xpd_rank_card::Labels::default(),
         customizations,
         avatar: VALK_PFP.to_string(),
     };
@@ -92,12 +132,15 @@ fn render_vertical_procedural() {
         let spawn = std::thread::spawn(move || {
             let state = new_state();
             let context = Context {
-                level: 69,
+                level: "69".to_string(),
                 rank: 1_000_000,
                 name: "Testy McTestington".to_string(),
                 percentage: xp,
                 current: xp,
                 needed: 100 - xp,
+                messages: None,
+                separator: xpd_rank_card::DEFAULT_SEPARATOR,
+                labels: xpd_rank_card::Labels::default(),
                 customizations: state.customizations_for("vertical.svg").unwrap().clone(),
                 avatar: VALK_PFP.to_string(),
             };
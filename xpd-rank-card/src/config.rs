@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use serde::Deserialize;
 
-use crate::customizations::Customizations;
+use crate::customizations::{Color, Customizations};
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct Config {
@@ -10,6 +10,9 @@ pub struct Config {
     pub fonts: Vec<ConfigItem>,
     pub toys: Vec<ConfigItem>,
     pub cards: Vec<CardItem>,
+    /// Images cards can use as their background, instead of a solid color
+    #[serde(default)]
+    pub backgrounds: Vec<ConfigItem>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
@@ -22,6 +25,9 @@ pub struct ConfigItem {
     pub file: PathBuf,
     pub internal_name: String,
     pub display_name: String,
+    /// Average color of a background image, which text contrast is checked against
+    #[serde(default)]
+    pub color: Option<Color>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
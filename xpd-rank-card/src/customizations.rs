@@ -10,71 +10,270 @@ pub struct Customizations {
     pub border: Color,
     pub background: Color,
     pub progress_foreground: Color,
+    /// Where the completed part of the progress bar fades to, if it's a gradient
+    #[serde(default)]
+    pub progress_foreground_2: Option<Color>,
     pub progress_background: Color,
     pub background_xp_count: Color,
     pub foreground_xp_count: Color,
     pub font: String,
     pub toy: Option<String>,
     pub internal_name: String,
+    /// Short user-chosen text shown under the name, like pronouns
+    #[serde(default)]
+    pub subtitle: Option<String>,
+    #[serde(default)]
+    pub progress_style: ProgressStyle,
+    #[serde(default)]
+    pub size: CardSize,
+    /// A bundled background image drawn over the background color
+    #[serde(default)]
+    pub background_preset: Option<String>,
 }
 
-macro_rules! add_output {
-    ($f:expr, $name:expr, $val:expr, $default:expr) => {
-        write!($f, "{}: `{}`", $name, $val)?;
-        if $val == $default {
-            writeln!($f, " (default)")?;
-        } else {
-            writeln!($f)?;
-        };
-    };
+/// The shape of the ends of the progress bar
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressStyle {
+    #[default]
+    Rounded,
+    Square,
 }
 
-impl Customizations {
-    /// Generate a user representation of their customizations
-    /// # Errors
-    /// Never, realistically. Just uses write! internally.
-    pub fn display(&self, defaults: &Self) -> Result<String, std::fmt::Error> {
-        use std::fmt::Write;
-        let mut f = String::with_capacity(256);
-        add_output!(f, "Important text", self.username, defaults.username);
-        add_output!(f, "Rank", self.rank, defaults.rank);
-        add_output!(f, "Level", self.level, defaults.level);
-        add_output!(f, "Border", self.border, defaults.border);
-        add_output!(f, "Background", self.background, defaults.background);
-        add_output!(
-            f,
-            "Progress bar completed",
-            self.progress_foreground,
-            defaults.progress_foreground
-        );
-        add_output!(
-            f,
-            "Progress bar remaining",
-            self.progress_background,
-            defaults.progress_background
-        );
-        add_output!(
-            f,
-            "Progress bar foreground overlay",
-            self.foreground_xp_count,
-            defaults.foreground_xp_count
-        );
-        add_output!(
-            f,
-            "Progress bar background overlay",
-            self.background_xp_count,
-            defaults.background_xp_count
-        );
-        add_output!(f, "Font", self.font, defaults.font);
-        writeln!(
+impl ProgressStyle {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Rounded => "rounded",
+            Self::Square => "square",
+        }
+    }
+}
+
+impl std::str::FromStr for ProgressStyle {
+    type Err = UnknownProgressStyle;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rounded" => Ok(Self::Rounded),
+            "square" => Ok(Self::Square),
+            _ => Err(UnknownProgressStyle),
+        }
+    }
+}
+
+impl std::fmt::Display for ProgressStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Progress bar style must be either `rounded` or `square`!")]
+pub struct UnknownProgressStyle;
+
+/// How big the rendered card image is. Every size keeps the layout, just scaled.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CardSize {
+    /// Three quarters of the normal size, for narrow screens
+    Small,
+    #[default]
+    Normal,
+    /// One and a half times the normal size, for big screens
+    Large,
+}
+
+impl CardSize {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Small => "small",
+            Self::Normal => "normal",
+            Self::Large => "large",
+        }
+    }
+
+    /// How much the card template is scaled by at this size
+    #[must_use]
+    pub const fn scale(self) -> f32 {
+        match self {
+            Self::Small => 0.75,
+            Self::Normal => 1.0,
+            Self::Large => 1.5,
+        }
+    }
+}
+
+impl std::str::FromStr for CardSize {
+    type Err = UnknownCardSize;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "small" => Ok(Self::Small),
+            "normal" => Ok(Self::Normal),
+            "large" => Ok(Self::Large),
+            _ => Err(UnknownCardSize),
+        }
+    }
+}
+
+impl std::fmt::Display for CardSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Card size must be `small`, `normal` or `large`!")]
+pub struct UnknownCardSize;
+
+/// Minimum contrast ratio between card text and what it's drawn on.
+/// Card text is large, so this is the WCAG AA requirement for large text.
+pub const MIN_CARD_TEXT_CONTRAST: f64 = 3.0;
+
+/// A piece of card text which is hard to read on the surface it's drawn on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LowContrast {
+    pub text: &'static str,
+    pub surface: &'static str,
+    pub ratio: f64,
+}
+
+impl std::fmt::Display for LowContrast {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
             f,
-            "Toy: `{}`",
-            self.toy
-                .as_ref()
-                .map_or_else(|| "None".to_owned(), ToString::to_string)
-        )?;
-        add_output!(f, "Card", self.internal_name, defaults.internal_name);
-        Ok(f)
+            "{} on {} has a contrast ratio of {:.1}:1",
+            self.text, self.surface, self.ratio
+        )
+    }
+}
+
+/// One setting of a card, as shown to users by [`Customizations::settings`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardSetting {
+    pub name: &'static str,
+    pub value: String,
+    /// Whether this is what the card would have if nothing was customized
+    pub default: bool,
+}
+
+impl CardSetting {
+    fn new<T: ToString + PartialEq>(name: &'static str, value: &T, default: &T) -> Self {
+        Self {
+            name,
+            value: value.to_string(),
+            default: value == default,
+        }
+    }
+
+    fn optional<T: ToString + PartialEq>(
+        name: &'static str,
+        value: Option<&T>,
+        default: Option<&T>,
+    ) -> Self {
+        Self {
+            name,
+            value: value.map_or_else(|| "None".to_owned(), ToString::to_string),
+            default: value == default,
+        }
+    }
+}
+
+impl Customizations {
+    /// Every setting of the card in the order they're shown, marking the ones left at `defaults`
+    #[must_use]
+    pub fn settings(&self, defaults: &Self) -> Vec<CardSetting> {
+        vec![
+            CardSetting::new("Important text", &self.username, &defaults.username),
+            CardSetting::new("Rank", &self.rank, &defaults.rank),
+            CardSetting::new("Level", &self.level, &defaults.level),
+            CardSetting::new("Border", &self.border, &defaults.border),
+            CardSetting::new("Background", &self.background, &defaults.background),
+            CardSetting::new(
+                "Progress bar completed",
+                &self.progress_foreground,
+                &defaults.progress_foreground,
+            ),
+            CardSetting::optional(
+                "Progress bar gradient end",
+                self.progress_foreground_2.as_ref(),
+                defaults.progress_foreground_2.as_ref(),
+            ),
+            CardSetting::new(
+                "Progress bar remaining",
+                &self.progress_background,
+                &defaults.progress_background,
+            ),
+            CardSetting::new(
+                "Progress bar foreground overlay",
+                &self.foreground_xp_count,
+                &defaults.foreground_xp_count,
+            ),
+            CardSetting::new(
+                "Progress bar background overlay",
+                &self.background_xp_count,
+                &defaults.background_xp_count,
+            ),
+            CardSetting::new("Font", &self.font, &defaults.font),
+            CardSetting::optional("Toy", self.toy.as_ref(), defaults.toy.as_ref()),
+            CardSetting::new("Card", &self.internal_name, &defaults.internal_name),
+            CardSetting::optional(
+                "Subtitle",
+                self.subtitle.as_ref(),
+                defaults.subtitle.as_ref(),
+            ),
+            CardSetting::new(
+                "Progress bar ends",
+                &self.progress_style,
+                &defaults.progress_style,
+            ),
+            CardSetting::new("Size", &self.size, &defaults.size),
+            CardSetting::optional(
+                "Background image",
+                self.background_preset.as_ref(),
+                defaults.background_preset.as_ref(),
+            ),
+        ]
+    }
+
+    /// Find text which contrasts less than `min_ratio` with the surface it's drawn on
+    #[must_use]
+    pub fn low_contrast(&self, min_ratio: f64) -> Vec<LowContrast> {
+        let pairs = [
+            (
+                "Important text",
+                self.username,
+                "Background",
+                self.background,
+            ),
+            ("Rank", self.rank, "Background", self.background),
+            ("Level", self.level, "Background", self.background),
+            (
+                "Progress bar foreground overlay",
+                self.foreground_xp_count,
+                "Progress bar completed",
+                self.progress_foreground,
+            ),
+            (
+                "Progress bar background overlay",
+                self.background_xp_count,
+                "Progress bar remaining",
+                self.progress_background,
+            ),
+        ];
+        pairs
+            .into_iter()
+            .filter_map(|(text, text_color, surface, surface_color)| {
+                let ratio = text_color.contrast_ratio(surface_color);
+                (ratio < min_ratio).then_some(LowContrast {
+                    text,
+                    surface,
+                    ratio,
+                })
+            })
+            .collect()
     }
 }
 
@@ -92,7 +291,8 @@ impl Color {
     pub fn from_hex(hex: &impl AsRef<str>) -> Result<Self, Error> {
         let hex = hex.as_ref();
         let hex = hex.trim_start_matches('#');
-        if hex.len() != 6 {
+        // non-ascii input could make us slice in the middle of a char
+        if hex.len() != 6 || !hex.is_ascii() {
             return Err(Error::InvalidLength);
         }
         Ok(Self {
@@ -106,6 +306,62 @@ impl Color {
     pub const fn new(red: u8, green: u8, blue: u8) -> Self {
         Self { red, green, blue }
     }
+
+    /// Create a color from HSL, with hue in degrees and saturation and lightness from 0 to 1.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64) -> Self {
+        let saturation = saturation.clamp(0.0, 1.0);
+        let lightness = lightness.clamp(0.0, 1.0);
+        let chroma = (1.0 - 2.0f64.mul_add(lightness, -1.0).abs()) * saturation;
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let second = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (red, green, blue) = match sector as u8 {
+            0 => (chroma, second, 0.0),
+            1 => (second, chroma, 0.0),
+            2 => (0.0, chroma, second),
+            3 => (0.0, second, chroma),
+            4 => (second, 0.0, chroma),
+            _ => (chroma, 0.0, second),
+        };
+        let offset = lightness - chroma / 2.0;
+        let channel = |v: f64| ((v + offset) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Self::new(channel(red), channel(green), channel(blue))
+    }
+
+    /// The WCAG relative luminance of this color, from 0 (black) to 1 (white).
+    #[must_use]
+    pub fn relative_luminance(self) -> f64 {
+        let linear = |channel: u8| {
+            let srgb = f64::from(channel) / 255.0;
+            if srgb <= 0.040_45 {
+                srgb / 12.92
+            } else {
+                ((srgb + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.0722f64.mul_add(
+            linear(self.blue),
+            0.2126f64.mul_add(linear(self.red), 0.7152 * linear(self.green)),
+        )
+    }
+
+    /// The WCAG contrast ratio between two colors, from 1 (identical) to 21 (black on white).
+    #[must_use]
+    pub fn contrast_ratio(self, other: Self) -> f64 {
+        let (a, b) = (self.relative_luminance(), other.relative_luminance());
+        let (light, dark) = if a > b { (a, b) } else { (b, a) };
+        (light + 0.05) / (dark + 0.05)
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = Error;
+
+    /// Same as [`Color::from_hex`], so colors can be `.parse()`d (for example from env vars)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(&s)
+    }
 }
 
 impl std::fmt::Display for Color {
@@ -195,6 +451,67 @@ mod test {
         color: Color,
     }
 
+    #[test]
+    fn hsl_conversion() {
+        assert_eq!(Color::from_hsl(0.0, 1.0, 0.5), Color::new(255, 0, 0));
+        assert_eq!(Color::from_hsl(120.0, 1.0, 0.5), Color::new(0, 255, 0));
+        assert_eq!(Color::from_hsl(240.0, 1.0, 0.25), Color::new(0, 0, 128));
+        assert_eq!(Color::from_hsl(480.0, 0.0, 1.0), Color::new(255, 255, 255));
+    }
+
+    #[test]
+    fn contrast_ratios() {
+        let black = Color::new(0, 0, 0);
+        let white = Color::new(255, 255, 255);
+        assert!((black.contrast_ratio(white) - 21.0).abs() < 0.01);
+        assert!((white.contrast_ratio(black) - 21.0).abs() < 0.01);
+        assert!((white.contrast_ratio(white) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn low_contrast_pairs() {
+        let black = Color::new(0, 0, 0);
+        let white = Color::new(255, 255, 255);
+        let mut customizations = Customizations {
+            username: white,
+            rank: white,
+            level: white,
+            border: white,
+            background: black,
+            progress_foreground: white,
+            progress_foreground_2: None,
+            progress_background: black,
+            background_xp_count: white,
+            foreground_xp_count: black,
+            font: String::new(),
+            toy: None,
+            internal_name: String::new(),
+            subtitle: None,
+            progress_style: ProgressStyle::Rounded,
+            size: CardSize::Normal,
+            background_preset: None,
+        };
+        assert!(customizations
+            .low_contrast(MIN_CARD_TEXT_CONTRAST)
+            .is_empty());
+
+        customizations.rank = Color::new(20, 20, 20);
+        let low = customizations.low_contrast(MIN_CARD_TEXT_CONTRAST);
+        assert_eq!(low.len(), 1);
+        assert_eq!(low[0].text, "Rank");
+        assert_eq!(low[0].surface, "Background");
+    }
+
+    #[test]
+    fn parse_matches_from_hex() {
+        let parsed: Color = "#ff0080".parse().unwrap();
+        assert_eq!(parsed, Color::new(0xff, 0x00, 0x80));
+        assert_eq!("ff0080".parse::<Color>().unwrap(), parsed);
+        assert!("#ff00".parse::<Color>().is_err());
+        assert!("#gg0000".parse::<Color>().is_err());
+        assert!("ééé".parse::<Color>().is_err());
+    }
+
     #[test]
     fn basic_roundtrip() {
         let mut shared_serialize_string = String::with_capacity(32);
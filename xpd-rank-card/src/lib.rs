@@ -3,7 +3,10 @@
 mod config;
 pub mod customizations;
 
-use std::{collections::HashMap, ops::Deref, path::Path, sync::Arc, time::Instant};
+use std::{
+    collections::HashMap, fmt::Display, ops::Deref, path::Path, str::FromStr, sync::Arc,
+    time::Instant,
+};
 
 use customizations::Customizations;
 use rayon::ThreadPoolBuilder;
@@ -20,8 +23,8 @@ pub use crate::config::{CardItem, Config, ConfigItem, NameableItem};
 /// the card.
 #[derive(serde::Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct Context {
-    /// Level of the user for display
-    pub level: u64,
+    /// Level of the user, already formatted for display
+    pub level: String,
     /// Rank of the user for display
     pub rank: i64,
     /// Username
@@ -32,12 +35,78 @@ pub struct Context {
     pub current: u64,
     /// Total XP needed to complete this level
     pub needed: u64,
+    /// How many messages the user has sent, if it should be shown
+    pub messages: Option<u64>,
+    /// Character between groups of thousands, like `,` in `12,345`.
+    /// Cards whose font has no glyph for it use a comma instead.
+    pub separator: char,
+    /// Names of the stats on the card.
+    /// Cards whose font can't draw all of them use the English ones instead.
+    pub labels: Labels,
     /// Customization data
     pub customizations: customizations::Customizations,
     /// Base64-encoded PNG string.
     pub avatar: String,
 }
 
+/// Names of the stats shown on a card, in the language of whoever is looking at it
+#[derive(serde::Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Labels {
+    pub rank: String,
+    pub level: String,
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Self {
+            rank: "RANK".to_string(),
+            level: "LEVEL".to_string(),
+        }
+    }
+}
+
+/// The image format cards are encoded in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CardFormat {
+    #[default]
+    Png,
+    /// Lossless WebP, which comes out smaller than PNG for the same card
+    WebP,
+}
+
+impl CardFormat {
+    /// File extension for images in this format, without the dot
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::WebP => "webp",
+        }
+    }
+}
+
+impl FromStr for CardFormat {
+    type Err = UnknownCardFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "png" => Ok(Self::Png),
+            "webp" => Ok(Self::WebP),
+            _ => Err(UnknownCardFormat),
+        }
+    }
+}
+
+impl Display for CardFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Card format must be either `png` or `webp`!")]
+pub struct UnknownCardFormat;
+
 #[derive(Clone)]
 pub struct SvgState(pub Arc<InnerSvgState>);
 
@@ -55,11 +124,18 @@ impl SvgState {
     /// # Errors
     /// Errors on [`resvg`](https://docs.rs/resvg) library failure. This will almost always be a library bug.
     pub async fn render(&self, data: Context) -> Result<Vec<u8>, Error> {
+        self.render_as(data, CardFormat::Png).await
+    }
+
+    /// Like [`SvgState::render`], but encodes the image as `format`.
+    /// # Errors
+    /// Errors on [`resvg`](https://docs.rs/resvg) library failure, or if encoding fails.
+    pub async fn render_as(&self, data: Context, format: CardFormat) -> Result<Vec<u8>, Error> {
         let cloned_self = self.clone();
         let (send, recv) = tokio::sync::oneshot::channel();
-        debug!("starting async render of SVG");
+        debug!(%format, "starting async render of SVG");
         self.threads.spawn(move || {
-            send.send(cloned_self.sync_render(&data)).ok();
+            send.send(cloned_self.sync_render_as(&data, format)).ok();
         });
         recv.await?
     }
@@ -109,8 +185,11 @@ impl InnerSvgState {
         let mut defaults = HashMap::with_capacity(config.cards.len());
 
         let mut tera = Tera::default();
-        tera.autoescape_on(vec!["svg", "html", "xml", "htm"]);
+        // Names and subtitles come from users, so every template is escaped, whatever it's called.
+        tera.autoescape_on(vec![""]);
+        tera.set_escape_fn(escape_xml);
         tera.register_filter("integerhumanize", int_humanize);
+        tera.register_filter("integergroup", int_group);
 
         let mut template_files = Vec::with_capacity(config.cards.len());
         for card in &config.cards {
@@ -136,10 +215,12 @@ impl InnerSvgState {
             .thread_name(|i| format!("svg-renderer-{i}"))
             .build()?;
 
+        // Toys and backgrounds are both looked up by the name the template gives as the href
         let images = config
             .toys
-            .clone()
-            .into_iter()
+            .iter()
+            .chain(&config.backgrounds)
+            .cloned()
             .map(|v| ConfigItem {
                 file: data_dir.join(&v.file),
                 ..v
@@ -168,17 +249,72 @@ impl InnerSvgState {
     /// # Errors
     /// Errors if tera has a problem
     pub fn render_svg(&self, context: &Context) -> Result<String, Error> {
-        let ctx = tera::Context::from_serialize(context)?;
+        let mut ctx = tera::Context::from_serialize(context)?;
+        ctx.insert("separator", &self.card_separator(context));
+        ctx.insert("labels", &self.card_labels(context));
         Ok(self
             .tera
             .render(&context.customizations.internal_name, &ctx)?)
     }
 
+    /// The thousands separator to use on this card, falling back to a comma when its font can't draw the chosen one.
+    fn card_separator(&self, context: &Context) -> char {
+        if self.font_can_draw(context, [context.separator]) {
+            context.separator
+        } else {
+            DEFAULT_SEPARATOR
+        }
+    }
+
+    /// The stat names to use on this card, falling back to English when its font can't draw the chosen ones.
+    fn card_labels(&self, context: &Context) -> Labels {
+        let labels = &context.labels;
+        if self.font_can_draw(context, labels.rank.chars().chain(labels.level.chars())) {
+            labels.clone()
+        } else {
+            Labels::default()
+        }
+    }
+
+    /// Whether the card's font has a glyph for every one of `chars`
+    fn font_can_draw(&self, context: &Context, chars: impl IntoIterator<Item = char>) -> bool {
+        let mut chars = chars.into_iter();
+        let query = Query {
+            families: &[Family::Name(&context.customizations.font)],
+            ..Query::default()
+        };
+        let has_glyphs = self.fontdb.query(&query).and_then(|id| {
+            self.fontdb.with_face_data(id, |data, index| {
+                ttf_parser::Face::parse(data, index)
+                    .is_ok_and(|face| chars.all(|c| face.glyph_index(c).is_some()))
+            })
+        });
+        has_glyphs.unwrap_or(false)
+    }
+
     /// Render the PNG for a card.
     /// # Errors
     /// Errors if tera has a problem, or resvg does.
     pub fn sync_render(&self, context: &Context) -> Result<Vec<u8>, Error> {
+        self.sync_render_as(context, CardFormat::Png)
+    }
+
+    /// Render a card, encoded as `format`.
+    /// # Errors
+    /// Errors if tera has a problem, or resvg does, or encoding fails.
+    pub fn sync_render_as(&self, context: &Context, format: CardFormat) -> Result<Vec<u8>, Error> {
         let start = Instant::now();
+        let pixmap = self.render_pixmap(context)?;
+        let image = encode(&pixmap, format)?;
+        debug!(
+            micros_taken = start.elapsed().as_micros(),
+            %format,
+            "Rendered SVG image"
+        );
+        Ok(image)
+    }
+
+    fn render_pixmap(&self, context: &Context) -> Result<resvg::tiny_skia::Pixmap, Error> {
         let svg = self.render_svg(context)?;
         let resolve_data =
             Box::new(
@@ -199,25 +335,26 @@ impl InnerSvgState {
                 resolve_string,
             },
             image_rendering: ImageRendering::OptimizeSpeed,
-            font_family: context.customizations.font.to_string(),
+            font_family: context.customizations.font.clone(),
             fontdb: self.fontdb.clone(),
             ..Default::default()
         };
         let tree = resvg::usvg::Tree::from_str(&svg, &opt)?;
-        let pixmap_size = tree.size().to_int_size();
+        // Scaling the whole template keeps text and the avatar where they belong at any size
+        let scale = context.customizations.size.scale();
+        let pixmap_size = tree
+            .size()
+            .to_int_size()
+            .scale_by(scale)
+            .ok_or(Error::PixmapCreation)?;
         let mut pixmap = resvg::tiny_skia::Pixmap::new(pixmap_size.width(), pixmap_size.height())
             .ok_or(Error::PixmapCreation)?;
         resvg::render(
             &tree,
-            resvg::tiny_skia::Transform::default(),
+            resvg::tiny_skia::Transform::from_scale(scale, scale),
             &mut pixmap.as_mut(),
         );
-        let png = pixmap.encode_png()?;
-        debug!(
-            micros_taken = start.elapsed().as_micros(),
-            "Rendered SVG image"
-        );
-        Ok(png)
+        Ok(pixmap)
     }
 
     #[must_use]
@@ -231,11 +368,83 @@ impl InnerSvgState {
     }
 }
 
+fn encode(pixmap: &resvg::tiny_skia::Pixmap, format: CardFormat) -> Result<Vec<u8>, Error> {
+    match format {
+        CardFormat::Png => Ok(pixmap.encode_png()?),
+        CardFormat::WebP => {
+            // tiny-skia keeps colors premultiplied by alpha, WebP wants them straight
+            let rgba: Vec<u8> = pixmap
+                .pixels()
+                .iter()
+                .flat_map(|pixel| {
+                    let color = pixel.demultiply();
+                    [color.red(), color.green(), color.blue(), color.alpha()]
+                })
+                .collect();
+            let mut webp = Vec::new();
+            image_webp::WebPEncoder::new(&mut webp).encode(
+                &rgba,
+                pixmap.width(),
+                pixmap.height(),
+                image_webp::ColorType::Rgba8,
+            )?;
+            Ok(webp)
+        }
+    }
+}
+
 fn config_item_tuple(ci: ConfigItem) -> Result<(String, Arc<Vec<u8>>), NewSvgStateError> {
     let data = std::fs::read(&ci.file)?;
     Ok((ci.internal_name, Arc::new(data)))
 }
 
+/// Escape text so it's safe in SVG text and attribute values alike.
+/// Characters which XML can't represent at all, like most control characters, are dropped.
+fn escape_xml(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            '\t' | '\n' | '\r' => output.push(c),
+            '\u{0}'..='\u{1F}' | '\u{FFFE}' | '\u{FFFF}' => {}
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+/// Thousands separator for cards that don't pick one, and for fonts that can't draw the one they picked
+pub const DEFAULT_SEPARATOR: char = ',';
+
+/// Numbers this big are too long to fit on a card once grouped, so they're humanized instead
+const GROUPED_BELOW: u64 = 1_000_000;
+
+/// Group an integer's digits in thousands with the `sep` argument, like `12,345`.
+/// Numbers too long for that to fit are humanized like [`int_humanize`] does.
+fn int_group(v: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let Some(num) = v.as_u64().filter(|num| *num < GROUPED_BELOW) else {
+        return int_humanize(v, args);
+    };
+    let separator = args
+        .get("sep")
+        .and_then(Value::as_str)
+        .and_then(|sep| sep.chars().next())
+        .unwrap_or(DEFAULT_SEPARATOR);
+    let digits = num.to_string();
+    let mut output = String::with_capacity(digits.len() + digits.len() / 3);
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx != 0 && (digits.len() - idx).is_multiple_of(3) {
+            output.push(separator);
+        }
+        output.push(digit);
+    }
+    Ok(Value::String(output))
+}
+
 #[allow(clippy::unnecessary_wraps)]
 fn int_humanize(v: &Value, _hm: &HashMap<String, Value>) -> tera::Result<Value> {
     let num = if let Value::Number(num) = v {
@@ -272,6 +481,8 @@ pub enum Error {
     ParseInt(#[from] std::num::ParseIntError),
     #[error("Pixmap error: {0}")]
     Pixmap(#[from] png::EncodingError),
+    #[error("WebP encoding error: {0}")]
+    WebP(#[from] image_webp::EncodingError),
     #[error("Render result fetching error: {0}")]
     Recv(#[from] tokio::sync::oneshot::error::RecvError),
     #[error("Pixmap Creation error!")]
@@ -295,3 +506,343 @@ pub enum NewSvgStateError {
     #[error("Default customization (name `{0}`) not found")]
     InvalidDefault(String),
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NASTY_NAMES: [&str; 6] = [
+        "<script>alert(1)</script>",
+        "Tom & Jerry &amp;",
+        "\"quoted\" 'name'",
+        "🦀 Ferris ✨",
+        "]]><!-- </text></svg>",
+        "null\u{0}byte\u{1b}",
+    ];
+
+    #[test]
+    fn card_formats() {
+        assert_eq!("webp".parse::<CardFormat>().unwrap(), CardFormat::WebP);
+        assert_eq!("PNG".parse::<CardFormat>().unwrap(), CardFormat::Png);
+        assert!("gif".parse::<CardFormat>().is_err());
+        assert_eq!(CardFormat::default().extension(), "png");
+
+        let mut pixmap = resvg::tiny_skia::Pixmap::new(4, 3).unwrap();
+        pixmap.fill(resvg::tiny_skia::Color::from_rgba8(200, 100, 50, 128));
+        let png = encode(&pixmap, CardFormat::Png).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        let webp = encode(&pixmap, CardFormat::WebP).unwrap();
+        assert!(webp.starts_with(b"RIFF"));
+        assert_eq!(&webp[8..12], b"WEBP");
+    }
+
+    #[test]
+    fn groups_integers() {
+        let group = |num: u64, sep: &str| {
+            let args = HashMap::from([("sep".to_string(), Value::from(sep))]);
+            int_group(&Value::from(num), &args).unwrap()
+        };
+        assert_eq!(group(999, ","), "999");
+        assert_eq!(group(12_345, ","), "12,345");
+        assert_eq!(group(999_999, "."), "999.999");
+        // Too long to fit grouped
+        assert_eq!(group(1_234_567, ","), "1.235m");
+        assert_eq!(
+            int_group(&Value::from(1234), &HashMap::new()).unwrap(),
+            "1,234"
+        );
+    }
+
+    #[test]
+    fn separators_need_glyphs() {
+        let state = SvgState::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../xpd-card-resources"
+        ))
+        .unwrap();
+        let mut context = Context {
+            level: "1".to_string(),
+            rank: 12_345,
+            name: "Separator".to_string(),
+            percentage: 50,
+            current: 50,
+            needed: 100,
+            messages: None,
+            separator: '.',
+            labels: Labels::default(),
+            customizations: state.default_customizations().clone(),
+            avatar: String::new(),
+        };
+        assert_eq!(state.card_separator(&context), '.');
+        assert!(state.render_svg(&context).unwrap().contains("12.345"));
+        context.separator = '🦀';
+        assert_eq!(state.card_separator(&context), DEFAULT_SEPARATOR);
+    }
+
+    #[test]
+    fn labels_need_glyphs() {
+        let state = SvgState::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../xpd-card-resources"
+        ))
+        .unwrap();
+        let mut context = Context {
+            level: "1".to_string(),
+            rank: 1,
+            name: "Labels".to_string(),
+            percentage: 50,
+            current: 50,
+            needed: 100,
+            messages: None,
+            separator: DEFAULT_SEPARATOR,
+            labels: Labels {
+                rank: "RANG".to_string(),
+                level: "NIVEAU".to_string(),
+            },
+            customizations: state.default_customizations().clone(),
+            avatar: String::new(),
+        };
+        let svg = state.render_svg(&context).unwrap();
+        assert!(svg.contains("RANG:") && svg.contains("NIVEAU:"), "{svg}");
+        context.labels.level = "🦀".to_string();
+        assert_eq!(state.card_labels(&context), Labels::default());
+    }
+
+    #[test]
+    fn escapes_xml() {
+        assert_eq!(
+            escape_xml(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos;&lt;/a&gt;"
+        );
+        assert_eq!(escape_xml("🦀\tok\u{0}\u{7}"), "🦀\tok");
+        assert_eq!(escape_xml("plain/text #123"), "plain/text #123");
+    }
+
+    #[test]
+    fn progress_styles() {
+        let state = SvgState::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../xpd-card-resources"
+        ))
+        .unwrap();
+        for card in &state.config().cards {
+            assert_eq!(
+                card.customizations.progress_style,
+                customizations::ProgressStyle::Rounded
+            );
+            let mut context = Context {
+                level: "1".to_string(),
+                rank: 1,
+                name: "Progress".to_string(),
+                percentage: 50,
+                current: 50,
+                needed: 100,
+                messages: None,
+                separator: DEFAULT_SEPARATOR,
+                labels: Labels::default(),
+                customizations: card.customizations.clone(),
+                avatar: String::new(),
+            };
+            let rounded = state.render_svg(&context).unwrap();
+            assert!(!rounded.contains(r#"rx="0""#), "{rounded}");
+            context.customizations.progress_style = customizations::ProgressStyle::Square;
+            let square = state.render_svg(&context).unwrap();
+            assert_eq!(square.matches(r#"rx="0" ry="0""#).count(), 2, "{square}");
+        }
+        assert_eq!(
+            "square".parse::<customizations::ProgressStyle>().unwrap(),
+            customizations::ProgressStyle::Square
+        );
+        assert!("wavy".parse::<customizations::ProgressStyle>().is_err());
+    }
+
+    /// A 200x100 avatar, red in the middle square and blue on both sides
+    fn wide_avatar() -> String {
+        use base64::Engine;
+        let mut pixmap = resvg::tiny_skia::Pixmap::new(200, 100).unwrap();
+        for (idx, pixel) in pixmap.pixels_mut().iter_mut().enumerate() {
+            let x = idx % 200;
+            *pixel = if (50..150).contains(&x) {
+                resvg::tiny_skia::PremultipliedColorU8::from_rgba(255, 0, 0, 255)
+            } else {
+                resvg::tiny_skia::PremultipliedColorU8::from_rgba(0, 0, 255, 255)
+            }
+            .unwrap();
+        }
+        let png = pixmap.encode_png().unwrap();
+        format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(png)
+        )
+    }
+
+    #[test]
+    fn avatars_are_circular_center_crops() {
+        let state = SvgState::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../xpd-card-resources"
+        ))
+        .unwrap();
+        let avatar = wide_avatar();
+        for card in &state.config().cards {
+            // Top-left corner of the avatar's box, its center, and a point just inside the top of its circle
+            let (corner, center, top) = match card.customizations.internal_name.as_str() {
+                "classic.svg" => ((62, 52), (150, 140), (150, 55)),
+                "vertical.svg" => ((42, 122), (190, 270), (190, 125)),
+                other => panic!("No avatar geometry for card {other}"),
+            };
+            let context = Context {
+                level: "1".to_string(),
+                rank: 1,
+                name: "Avatar".to_string(),
+                percentage: 50,
+                current: 50,
+                needed: 100,
+                messages: None,
+                separator: DEFAULT_SEPARATOR,
+                labels: Labels::default(),
+                customizations: card.customizations.clone(),
+                avatar: avatar.clone(),
+            };
+            let pixmap = state.render_pixmap(&context).unwrap();
+            let rgb = |(x, y): (u32, u32)| {
+                let pixel = pixmap.pixel(x, y).unwrap().demultiply();
+                (pixel.red(), pixel.green(), pixel.blue())
+            };
+            let name = &card.customizations.internal_name;
+            assert_eq!(rgb(center), (255, 0, 0), "{name}");
+            // Letterboxing would leave the top of the circle empty, and squashing would make it blue
+            assert_eq!(rgb(top), (255, 0, 0), "{name}");
+            // The corners of the box are clipped off
+            assert_ne!(rgb(corner), (255, 0, 0), "{name}");
+            assert_ne!(rgb(corner), (0, 0, 255), "{name}");
+        }
+    }
+
+    #[test]
+    fn card_sizes() {
+        let state = SvgState::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../xpd-card-resources"
+        ))
+        .unwrap();
+        let avatar = wide_avatar();
+        for card in &state.config().cards {
+            let mut context = Context {
+                level: "1".to_string(),
+                rank: 1,
+                name: "Size".to_string(),
+                percentage: 50,
+                current: 50,
+                needed: 100,
+                messages: None,
+                separator: DEFAULT_SEPARATOR,
+                labels: Labels::default(),
+                customizations: card.customizations.clone(),
+                avatar: avatar.clone(),
+            };
+            let normal = state.render_pixmap(&context).unwrap();
+            context.customizations.size = customizations::CardSize::Large;
+            let large = state.render_pixmap(&context).unwrap();
+            let name = &card.customizations.internal_name;
+            assert_eq!(large.width(), normal.width() * 3 / 2, "{name}");
+            assert_eq!(large.height(), normal.height() * 3 / 2, "{name}");
+            // The avatar's center moves along with everything else
+            let center = match name.as_str() {
+                "classic.svg" => (150, 140),
+                "vertical.svg" => (190, 270),
+                other => panic!("No avatar geometry for card {other}"),
+            };
+            let pixel = large
+                .pixel(center.0 * 3 / 2, center.1 * 3 / 2)
+                .unwrap()
+                .demultiply();
+            assert_eq!((pixel.red(), pixel.green(), pixel.blue()), (255, 0, 0));
+        }
+        assert_eq!(
+            "small".parse::<customizations::CardSize>().unwrap(),
+            customizations::CardSize::Small
+        );
+        assert!("huge".parse::<customizations::CardSize>().is_err());
+    }
+
+    #[test]
+    fn background_presets() {
+        let state = SvgState::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../xpd-card-resources"
+        ))
+        .unwrap();
+        assert!(!state.config().backgrounds.is_empty());
+        assert!(state
+            .config()
+            .backgrounds
+            .iter()
+            .all(|bg| bg.color.is_some()));
+        for card in &state.config().cards {
+            let mut context = Context {
+                level: "1".to_string(),
+                rank: 1,
+                name: "Background".to_string(),
+                percentage: 50,
+                current: 50,
+                needed: 100,
+                messages: None,
+                separator: DEFAULT_SEPARATOR,
+                labels: Labels::default(),
+                customizations: card.customizations.clone(),
+                avatar: String::new(),
+            };
+            // Just inside the top of the card, clear of everything drawn on the background
+            let rgb = |pixmap: &resvg::tiny_skia::Pixmap| {
+                let pixel = pixmap.pixel(100, 30).unwrap().demultiply();
+                customizations::Color::new(pixel.red(), pixel.green(), pixel.blue())
+            };
+            let plain = rgb(&state.render_pixmap(&context).unwrap());
+            assert_eq!(plain, card.customizations.background);
+            for preset in &state.config().backgrounds {
+                context.customizations.background_preset = Some(preset.internal_name.clone());
+                let with_image = rgb(&state.render_pixmap(&context).unwrap());
+                assert_ne!(with_image, plain, "{}", preset.internal_name);
+            }
+        }
+    }
+
+    #[test]
+    fn renders_nasty_names() {
+        let state = SvgState::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../xpd-card-resources"
+        ))
+        .unwrap();
+        for card in &state.config().cards {
+            for name in NASTY_NAMES {
+                let context = Context {
+                    level: "1".to_string(),
+                    rank: 1,
+                    name: name.to_string(),
+                    percentage: 50,
+                    current: 50,
+                    needed: 100,
+                    messages: None,
+                    separator: DEFAULT_SEPARATOR,
+                    labels: Labels::default(),
+                    customizations: Customizations {
+                        subtitle: Some(name.to_string()),
+                        ..card.customizations.clone()
+                    },
+                    avatar: String::new(),
+                };
+                let svg = state.render_svg(&context).unwrap();
+                assert!(!svg.contains("<script>"), "{svg}");
+                let tree = resvg::usvg::Tree::from_str(&svg, &resvg::usvg::Options::default());
+                assert!(
+                    tree.is_ok(),
+                    "{name:?} broke {}",
+                    card.customizations.internal_name
+                );
+                state.sync_render(&context).unwrap();
+            }
+        }
+    }
+}
@@ -17,6 +17,10 @@ pub enum AdminCommand {
     Leave(AdminCommandLeave),
     #[command(name = "resetguild")]
     ResetGuild(AdminCommandResetGuild),
+    #[command(name = "undoreset")]
+    UndoReset(AdminCommandUndoReset),
+    #[command(name = "recomputelevels")]
+    RecomputeLevels(AdminCommandRecomputeLevels),
     #[command(name = "resetuser")]
     ResetUser(AdminCommandResetUser),
     #[command(name = "setnick")]
@@ -25,12 +29,26 @@ pub enum AdminCommand {
     BanGuild(AdminCommandBanGuild),
     #[command(name = "pardonguild")]
     PardonGuild(AdminCommandPardonGuild),
+    #[command(name = "bulkbanguilds")]
+    BulkBanGuilds(AdminCommandBulkBanGuilds),
+    #[command(name = "bulkpardonguilds")]
+    BulkPardonGuilds(AdminCommandBulkPardonGuilds),
     #[command(name = "guildstats")]
     GuildStats(AdminCommandGuildStats),
+    #[command(name = "staleguilds")]
+    StaleGuilds(AdminCommandStaleGuilds),
     #[command(name = "stats")]
     Stats(AdminCommandStats),
     #[command(name = "inspectcooldown")]
     InspectCooldown(AdminCommandInspectCooldown),
+    #[command(name = "xprange")]
+    XpRange(AdminCommandXpRange),
+    #[command(name = "recomputerank")]
+    RecomputeRank(AdminCommandRecomputeRank),
+    #[command(name = "inspectuser")]
+    InspectUser(AdminCommandInspectUser),
+    #[command(name = "imports")]
+    Imports(AdminCommandImports),
 }
 
 impl AdminCommand {
@@ -54,6 +72,26 @@ pub struct AdminCommandResetGuild {
     pub guild: String,
 }
 
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "undoreset",
+    desc = "Restore the levels of a recently reset guild"
+)]
+pub struct AdminCommandUndoReset {
+    #[command(desc = "Guild to restore")]
+    pub guild: String,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "recomputelevels",
+    desc = "Recompute every member's level in a guild and re-sync their reward roles"
+)]
+pub struct AdminCommandRecomputeLevels {
+    #[command(desc = "Guild to recompute")]
+    pub guild: String,
+}
+
 #[derive(CommandModel, CreateCommand)]
 #[command(
     name = "guildstats",
@@ -64,6 +102,23 @@ pub struct AdminCommandGuildStats {
     pub guild: String,
 }
 
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "staleguilds",
+    desc = "List guilds where nobody has earned XP recently"
+)]
+pub struct AdminCommandStaleGuilds {
+    #[command(
+        desc = "How many days without activity makes a guild stale",
+        min_value = 1
+    )]
+    pub days: i64,
+    #[command(desc = "Page of results to show", min_value = 1)]
+    pub page: Option<i64>,
+    #[command(desc = "Reset the levels of every guild on this page. This can be undone.")]
+    pub reset: Option<bool>,
+}
+
 #[derive(CommandModel, CreateCommand)]
 #[command(name = "stats", desc = "Get some basic stats about the bot in general")]
 pub struct AdminCommandStats;
@@ -91,6 +146,25 @@ pub struct AdminCommandPardonGuild {
     pub guild: String,
 }
 
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "bulkbanguilds",
+    desc = "Ban many guilds from using the bot at once"
+)]
+pub struct AdminCommandBulkBanGuilds {
+    #[command(desc = "Guild IDs to ban, separated by spaces, commas or newlines")]
+    pub guilds: String,
+    #[command(desc = "How many days to ban for")]
+    pub duration: Option<f64>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(name = "bulkpardonguilds", desc = "Unban many guilds at once")]
+pub struct AdminCommandBulkPardonGuilds {
+    #[command(desc = "Guild IDs to pardon, separated by spaces, commas or newlines")]
+    pub guilds: String,
+}
+
 #[derive(CommandModel, CreateCommand)]
 #[command(name = "setnick", desc = "Set the bot's nickname in a guild")]
 pub struct AdminCommandSetNick {
@@ -108,3 +182,48 @@ pub struct AdminCommandInspectCooldown {
     #[command(desc = "User ID")]
     pub user: Id<UserMarker>,
 }
+
+#[derive(CommandModel, CreateCommand)]
+#[command(name = "xprange", desc = "List users in a guild with XP in a range")]
+pub struct AdminCommandXpRange {
+    #[command(desc = "Guild to search")]
+    pub guild: String,
+    #[command(desc = "Lowest XP to include", min_value = 0)]
+    pub min: i64,
+    #[command(desc = "Highest XP to include", min_value = 0)]
+    pub max: i64,
+    #[command(desc = "Page of results to show", min_value = 1, max_value = 50)]
+    pub page: Option<i64>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "recomputerank",
+    desc = "Work out a user's rank every way it's calculated, and check they agree"
+)]
+pub struct AdminCommandRecomputeRank {
+    #[command(desc = "Guild to check the rank in")]
+    pub guild: String,
+    #[command(desc = "User ID")]
+    pub user: Id<UserMarker>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "inspectuser",
+    desc = "Show everything stored about a user, in every guild"
+)]
+pub struct AdminCommandInspectUser {
+    #[command(desc = "User ID")]
+    pub user: Id<UserMarker>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "imports",
+    desc = "Pause or resume level imports, or see whether they're paused"
+)]
+pub struct AdminCommandImports {
+    #[command(desc = "Leave empty to just see the status")]
+    pub paused: Option<bool>,
+}
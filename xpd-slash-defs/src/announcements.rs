@@ -0,0 +1,36 @@
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::guild::Permissions;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "announcements",
+    desc = "Celebrate members' progress beyond leveling up",
+    dm_permission = false,
+    default_permissions = "Self::default_permissions"
+)]
+pub enum AnnouncementsCommand {
+    #[command(name = "percentiles")]
+    Percentiles(AnnouncementsCommandPercentiles),
+}
+
+impl AnnouncementsCommand {
+    #[inline]
+    const fn default_permissions() -> Permissions {
+        Permissions::ADMINISTRATOR
+    }
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "percentiles",
+    desc = "Announce when members reach a top percentage of the server",
+    dm_permission = false
+)]
+pub struct AnnouncementsCommandPercentiles {
+    #[command(
+        desc = "Widest top % to announce, or 0 to turn this off",
+        min_value = 0,
+        max_value = 50
+    )]
+    pub top: i64,
+}
@@ -3,7 +3,7 @@ use twilight_interactions::command::{
     AutocompleteValue, CommandModel, CommandOption, CreateCommand, CreateOption, ResolvedUser,
 };
 use twilight_model::guild::Permissions;
-use xpd_rank_card::customizations::Color;
+use xpd_rank_card::customizations::{CardSize, Color, ProgressStyle};
 
 #[derive(CommandModel, CreateCommand)]
 #[command(
@@ -19,6 +19,10 @@ pub enum CardCommand {
     Fetch(CardCommandFetch),
     #[command(name = "edit")]
     Edit(CardCommandEdit),
+    #[command(name = "random")]
+    Random(CardCommandRandom),
+    #[command(name = "preset")]
+    Preset(CardCommandPreset),
 }
 
 #[derive(CommandModel, CreateCommand)]
@@ -66,6 +70,35 @@ pub struct CardCommandFetch {
 )]
 pub struct GuildCardCommandFetch;
 
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "random",
+    desc = "Roll a random color scheme for your card, and save it if you like it."
+)]
+pub struct CardCommandRandom;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "preset",
+    desc = "Set every color on your card at once from a ready-made theme."
+)]
+pub struct CardCommandPreset {
+    #[command(desc = "Which theme to use")]
+    pub preset: CardPreset,
+}
+
+#[derive(CommandOption, CreateOption, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardPreset {
+    #[option(name = "Dark", value = "dark")]
+    Dark,
+    #[option(name = "Light", value = "light")]
+    Light,
+    #[option(name = "Ocean", value = "ocean")]
+    Ocean,
+    #[option(name = "Forest", value = "forest")]
+    Forest,
+}
+
 #[derive(CommandModel, CreateCommand)]
 #[command(
     name = "edit",
@@ -84,6 +117,11 @@ pub struct CardCommandEdit {
     pub level: Option<ColorOption>,
     #[command(desc = "What color to use for the progress bar's filled part")]
     pub progress_foreground: Option<ColorOption>,
+    #[command(
+        desc = "What color the progress bar's filled part fades into, for a gradient. NULL removes it",
+        max_length = 7
+    )]
+    pub progress_foreground_2: Option<String>,
     #[command(desc = "What color to use for the progress bar's empty part")]
     pub progress_background: Option<ColorOption>,
     #[command(desc = "What color to use for the xp count when in the progress bar's filled part")]
@@ -96,6 +134,22 @@ pub struct CardCommandEdit {
     pub toy_image: Option<String>,
     #[command(desc = "What layout to use for the card", autocomplete = true)]
     pub card_layout: Option<String>,
+    #[command(
+        desc = "Short text shown under the name, like pronouns. NULL removes it",
+        max_length = 32
+    )]
+    pub subtitle: Option<String>,
+    #[command(desc = "Whether the progress bar has rounded or square ends")]
+    pub progress_style: Option<ProgressStyleOption>,
+    #[command(desc = "How big the card image is, smaller fits phones better")]
+    pub card_size: Option<CardSizeOption>,
+    #[command(
+        desc = "What background image to use in the card, instead of a solid color",
+        autocomplete = true
+    )]
+    pub background_preset: Option<String>,
+    #[command(desc = "Refuse changes which make text hard to read, instead of only warning")]
+    pub reject_low_contrast: Option<bool>,
 }
 
 #[derive(CommandModel, Debug)]
@@ -107,6 +161,10 @@ pub enum CardCommandAutocomplete {
     Fetch(NoAutocomplete),
     #[command(name = "reset")]
     Reset(NoAutocomplete),
+    #[command(name = "random")]
+    Random(NoAutocomplete),
+    #[command(name = "preset")]
+    Preset(NoAutocomplete),
 }
 
 #[derive(CommandModel, Debug)]
@@ -115,6 +173,7 @@ pub struct CardCommandEditAutocomplete {
     pub font: AutocompleteValue<String>,
     pub toy_image: AutocompleteValue<String>,
     pub card_layout: AutocompleteValue<String>,
+    pub background_preset: AutocompleteValue<String>,
 }
 
 #[derive(CommandModel, Debug)]
@@ -173,3 +232,40 @@ impl CreateOption for ColorOption {
         }
     }
 }
+
+#[derive(CommandOption, CreateOption, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressStyleOption {
+    #[option(name = "Rounded", value = "rounded")]
+    Rounded,
+    #[option(name = "Square", value = "square")]
+    Square,
+}
+
+impl From<ProgressStyleOption> for ProgressStyle {
+    fn from(value: ProgressStyleOption) -> Self {
+        match value {
+            ProgressStyleOption::Rounded => Self::Rounded,
+            ProgressStyleOption::Square => Self::Square,
+        }
+    }
+}
+
+#[derive(CommandOption, CreateOption, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardSizeOption {
+    #[option(name = "Small", value = "small")]
+    Small,
+    #[option(name = "Normal", value = "normal")]
+    Normal,
+    #[option(name = "Large", value = "large")]
+    Large,
+}
+
+impl From<CardSizeOption> for CardSize {
+    fn from(value: CardSizeOption) -> Self {
+        match value {
+            CardSizeOption::Small => Self::Small,
+            CardSizeOption::Normal => Self::Normal,
+            CardSizeOption::Large => Self::Large,
+        }
+    }
+}
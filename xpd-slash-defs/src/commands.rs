@@ -0,0 +1,55 @@
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::guild::Permissions;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "commands",
+    desc = "Turn Experienced's commands on and off in this server",
+    dm_permission = false,
+    default_permissions = "Self::default_permissions"
+)]
+pub enum CommandsCommand {
+    #[command(name = "disable")]
+    Disable(CommandsCommandDisable),
+    #[command(name = "enable")]
+    Enable(CommandsCommandEnable),
+    #[command(name = "list")]
+    List(CommandsCommandList),
+}
+
+impl CommandsCommand {
+    #[inline]
+    const fn default_permissions() -> Permissions {
+        Permissions::ADMINISTRATOR
+    }
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "disable",
+    desc = "Stop members from using a command here",
+    dm_permission = false
+)]
+pub struct CommandsCommandDisable {
+    #[command(desc = "Name of the command, like leaderboard", max_length = 32)]
+    pub command: String,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "enable",
+    desc = "Let members use a disabled command again",
+    dm_permission = false
+)]
+pub struct CommandsCommandEnable {
+    #[command(desc = "Name of the command, like leaderboard", max_length = 32)]
+    pub command: String,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "list",
+    desc = "See which commands are disabled here",
+    dm_permission = false
+)]
+pub struct CommandsCommandList;
@@ -1,5 +1,9 @@
-use twilight_interactions::command::{CommandModel, CreateCommand};
-use twilight_model::{application::interaction::InteractionChannel, guild::Permissions};
+use twilight_interactions::command::{CommandModel, CommandOption, CreateCommand, CreateOption};
+use twilight_model::{
+    application::interaction::InteractionChannel,
+    channel::Attachment,
+    guild::{Permissions, Role},
+};
 
 #[derive(CommandModel, CreateCommand)]
 #[command(
@@ -20,6 +24,32 @@ pub enum ConfigCommand {
     Levels(ConfigCommandLevels),
     #[command(name = "perms_checkup")]
     PermsCheckup(ConfigCommandPermsCheckup),
+    #[command(name = "starting-xp")]
+    StartingXp(ConfigCommandStartingXp),
+    #[command(name = "xp-per-message")]
+    XpPerMessage(ConfigCommandXpPerMessage),
+    #[command(name = "reaction-xp")]
+    ReactionXp(ConfigCommandReactionXp),
+    #[command(name = "role-cooldown")]
+    RoleCooldown(ConfigCommandRoleCooldown),
+    #[command(name = "content-bonus")]
+    ContentBonus(ConfigCommandContentBonus),
+    #[command(name = "webhook")]
+    Webhook(ConfigCommandWebhook),
+    #[command(name = "levelup-message")]
+    LevelUpMessage(ConfigCommandLevelUpMessage),
+    #[command(name = "levelup-style")]
+    LevelUpStyle(ConfigCommandLevelUpStyle),
+    #[command(name = "copy-from")]
+    CopyFrom(ConfigCommandCopyFrom),
+    #[command(name = "audit-channel")]
+    AuditChannel(ConfigCommandAuditChannel),
+    #[command(name = "season")]
+    Season(ConfigCommandSeason),
+    #[command(name = "export")]
+    Export(ConfigCommandExport),
+    #[command(name = "import")]
+    Import(ConfigCommandImport),
 }
 
 impl ConfigCommand {
@@ -64,6 +94,94 @@ pub struct ConfigCommandLevels {
         max_value = 28800
     )]
     pub message_cooldown: Option<i64>,
+    #[command(desc = "Count messages sent during the cooldown towards members' message totals")]
+    pub count_cooldown_messages: Option<bool>,
+    #[command(desc = "Don't give XP for a message identical to the member's last one")]
+    pub ignore_duplicate_messages: Option<bool>,
+    #[command(desc = "Show a rank card for members without XP instead of a message")]
+    pub unranked_card: Option<bool>,
+    #[command(desc = "Give bots rank cards and let them be given XP, like any other member")]
+    pub rank_bots: Option<bool>,
+    #[command(
+        desc = "How many seconds must pass between level-up messages for the same member",
+        min_value = 0,
+        max_value = 28800
+    )]
+    pub level_up_cooldown: Option<i64>,
+    #[command(desc = "Only hand out reward roles on level-up, without sending a level-up message")]
+    pub silent_level_ups: Option<bool>,
+    #[command(desc = "How levels are written on rank cards and in level-up messages")]
+    pub level_style: Option<LevelStyleOption>,
+    #[command(
+        desc = "Text to show right after levels, like a star. Set to NULL to remove it",
+        max_length = 16,
+        min_length = 1
+    )]
+    pub level_suffix: Option<String>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "levelup-style",
+    desc = "Choose whether level-up messages are plain text or an embed"
+)]
+pub struct ConfigCommandLevelUpStyle {
+    #[command(desc = "How to send level-up messages")]
+    pub style: LevelUpStyleOption,
+}
+
+#[derive(CommandOption, CreateOption, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LevelUpStyleOption {
+    #[option(name = "Plain text", value = "plain")]
+    Plain,
+    #[option(name = "Embed with the member's avatar", value = "embed")]
+    Embed,
+}
+
+impl From<LevelUpStyleOption> for xpd_common::LevelUpStyle {
+    fn from(value: LevelUpStyleOption) -> Self {
+        match value {
+            LevelUpStyleOption::Plain => Self::Plain,
+            LevelUpStyleOption::Embed => Self::Embed,
+        }
+    }
+}
+
+#[derive(CommandOption, CreateOption, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LevelStyleOption {
+    #[option(name = "Numbers (12)", value = "decimal")]
+    Decimal,
+    #[option(name = "Roman numerals (XII)", value = "roman")]
+    Roman,
+}
+
+impl From<LevelStyleOption> for xpd_common::LevelStyle {
+    fn from(value: LevelStyleOption) -> Self {
+        match value {
+            LevelStyleOption::Decimal => Self::Decimal,
+            LevelStyleOption::Roman => Self::Roman,
+        }
+    }
+}
+
+#[derive(CommandOption, CreateOption, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RewardStrategyOption {
+    #[option(name = "Keep all earned roles", value = "keep_all")]
+    KeepAll,
+    #[option(name = "Only the highest role", value = "one_at_a_time")]
+    OneAtATime,
+    #[option(name = "All but the role just below the highest", value = "ladder")]
+    Ladder,
+}
+
+impl From<RewardStrategyOption> for xpd_common::RewardStrategy {
+    fn from(value: RewardStrategyOption) -> Self {
+        match value {
+            RewardStrategyOption::KeepAll => Self::KeepAll,
+            RewardStrategyOption::OneAtATime => Self::OneAtATime,
+            RewardStrategyOption::Ladder => Self::Ladder,
+        }
+    }
 }
 
 #[derive(CommandModel, CreateCommand)]
@@ -75,8 +193,148 @@ pub struct ConfigCommandLevels {
 pub struct ConfigCommandRewards {
     #[command(desc = "Remove all existing Experienced-managed roles when assigning a new one")]
     pub one_at_a_time: Option<bool>,
+    #[command(desc = "Which earned reward roles members keep, overriding one_at_a_time")]
+    pub reward_strategy: Option<RewardStrategyOption>,
+    #[command(
+        desc = "Remove role rewards whose role was deleted, instead of only warning about them"
+    )]
+    pub prune_deleted_rewards: Option<bool>,
+    #[command(
+        desc = "Levels under a reward's requirement before its role is taken, -1 for never",
+        min_value = -1,
+        max_value = 1000
+    )]
+    pub reward_removal_margin: Option<i64>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "content-bonus",
+    desc = "Give extra XP for messages with attachments or embeds"
+)]
+pub struct ConfigCommandContentBonus {
+    #[command(
+        desc = "Extra XP for each attachment, up to 3 per message (Default 0)",
+        min_value = 0,
+        max_value = 100
+    )]
+    pub attachment_xp: Option<i64>,
+    #[command(
+        desc = "Extra XP for each embed, up to 3 per message (Default 0)",
+        min_value = 0,
+        max_value = 100
+    )]
+    pub embed_xp: Option<i64>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(name = "webhook", desc = "Send level-ups to an external service")]
+pub struct ConfigCommandWebhook {
+    #[command(desc = "HTTPS URL to POST level-ups to", max_length = 512)]
+    pub url: Option<String>,
+    #[command(desc = "Stop sending level-ups to the webhook")]
+    pub remove: Option<bool>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "role-cooldown",
+    desc = "Give members with a role a different XP cooldown, or list the roles that have one"
+)]
+pub struct ConfigCommandRoleCooldown {
+    #[command(desc = "Role to change the cooldown of")]
+    pub role: Option<Role>,
+    #[command(
+        desc = "Seconds between messages that earn XP, 0 for no cooldown",
+        min_value = 0,
+        max_value = 28800
+    )]
+    pub cooldown: Option<i64>,
+    #[command(desc = "Use the server's cooldown for this role again")]
+    pub remove: Option<bool>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "audit-channel",
+    desc = "Log manual XP changes, and who made them, to a channel"
+)]
+pub struct ConfigCommandAuditChannel {
+    #[command(desc = "Channel to log XP changes in", channel_types = "guild_text")]
+    pub channel: Option<InteractionChannel>,
+    #[command(desc = "Stop logging XP changes")]
+    pub remove: Option<bool>,
 }
 
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "season",
+    desc = "Reset everyone's levels on a schedule, keeping the final standings of each season"
+)]
+pub struct ConfigCommandSeason {
+    #[command(desc = "How often to start a new season. Leave empty to see the current schedule")]
+    pub cadence: Option<SeasonCadenceOption>,
+}
+
+#[derive(CommandOption, CreateOption, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeasonCadenceOption {
+    #[option(name = "Every week", value = "weekly")]
+    Weekly,
+    #[option(name = "Every month", value = "monthly")]
+    Monthly,
+    #[option(name = "Every three months", value = "quarterly")]
+    Quarterly,
+    #[option(name = "Never (stop seasons)", value = "off")]
+    Off,
+}
+
+impl From<SeasonCadenceOption> for Option<xpd_common::SeasonCadence> {
+    fn from(value: SeasonCadenceOption) -> Self {
+        match value {
+            SeasonCadenceOption::Weekly => Some(xpd_common::SeasonCadence::Weekly),
+            SeasonCadenceOption::Monthly => Some(xpd_common::SeasonCadence::Monthly),
+            SeasonCadenceOption::Quarterly => Some(xpd_common::SeasonCadence::Quarterly),
+            SeasonCadenceOption::Off => None,
+        }
+    }
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "export",
+    desc = "Download this server's config and role rewards as a JSON backup"
+)]
+pub struct ConfigCommandExport;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "import",
+    desc = "Replace this server's config and role rewards with a backup from /config export"
+)]
+pub struct ConfigCommandImport {
+    #[command(desc = "JSON backup made with /config export")]
+    pub file: Attachment,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "copy-from",
+    desc = "Replace this server's config with a copy of another server's"
+)]
+pub struct ConfigCommandCopyFrom {
+    #[command(desc = "ID of the server to copy from. You must own both servers")]
+    pub source: String,
+    #[command(desc = "Also copy role rewards, matching roles by name")]
+    pub rewards: Option<bool>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "levelup-message",
+    desc = "Edit the level-up message in a larger text box"
+)]
+pub struct ConfigCommandLevelUpMessage;
+
 #[derive(CommandModel, CreateCommand)]
 #[command(name = "reset", desc = "Reset your guild's configuration")]
 pub struct ConfigCommandReset;
@@ -91,3 +349,51 @@ pub struct ConfigCommandGet;
     desc = "See if Experienced has the proper permissions in your server"
 )]
 pub struct ConfigCommandPermsCheckup;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "starting-xp",
+    desc = "Set how much XP new members start with. Existing members are not affected."
+)]
+pub struct ConfigCommandStartingXp {
+    #[command(
+        desc = "XP given to members the first time they earn XP (Default 0)",
+        min_value = 0,
+        max_value = 10_000_000
+    )]
+    pub xp: i64,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "xp-per-message",
+    desc = "Give the same XP for every message, instead of a random amount"
+)]
+pub struct ConfigCommandXpPerMessage {
+    #[command(
+        desc = "XP for each message, or 0 to go back to the minimum and maximum",
+        min_value = 0,
+        max_value = 32767
+    )]
+    pub xp: i64,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "reaction-xp",
+    desc = "Give members XP when others react to their messages"
+)]
+pub struct ConfigCommandReactionXp {
+    #[command(
+        desc = "XP for each member who reacts to a message, or 0 to turn reaction XP off",
+        min_value = 0,
+        max_value = 1000
+    )]
+    pub xp: i64,
+    #[command(
+        desc = "Most reactions on one message which give XP (Default 10)",
+        min_value = 1,
+        max_value = 100
+    )]
+    pub max_per_message: Option<i64>,
+}
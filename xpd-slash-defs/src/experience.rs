@@ -17,6 +17,8 @@ pub enum XpCommand {
     Reset(XpCommandReset),
     #[command(name = "set")]
     Set(XpCommandSet),
+    #[command(name = "lockdown")]
+    Lockdown(XpCommandLockdown),
 }
 
 impl XpCommand {
@@ -75,3 +77,20 @@ pub struct XpCommandSet {
     #[command(desc = "value to set their current XP to", min_value = 1)]
     pub xp: i64,
 }
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "lockdown",
+    desc = "Stop everyone from earning XP, for example during a raid",
+    dm_permission = false
+)]
+pub struct XpCommandLockdown {
+    #[command(desc = "Whether XP gain should be locked down")]
+    pub enabled: bool,
+    #[command(
+        desc = "Lift the lockdown by itself after this many minutes",
+        min_value = 1,
+        max_value = 10080
+    )]
+    pub minutes: Option<i64>,
+}
@@ -1,4 +1,5 @@
 use twilight_interactions::command::{CommandModel, CreateCommand, ResolvedUser};
+use twilight_model::guild::Role;
 
 #[derive(CommandModel, CreateCommand)]
 #[command(
@@ -13,6 +14,12 @@ pub struct LeaderboardCommand {
     pub page: Option<i64>,
     #[command(desc = "Want to show this off to everyone?")]
     pub show_off: Option<bool>,
+    #[command(desc = "Only rank members with this role")]
+    pub role: Option<Role>,
+    #[command(desc = "Jump to the page you're on")]
+    pub me: Option<bool>,
+    #[command(desc = "Rank by XP gained today (UTC) instead")]
+    pub today: Option<bool>,
 }
 
 #[derive(CommandModel, CreateCommand)]
@@ -26,4 +33,37 @@ pub struct RankCommand {
     pub user: Option<ResolvedUser>,
     #[command(desc = "Show off this card publicly")]
     pub showoff: Option<bool>,
+    #[command(desc = "Send just the card image, without any text")]
+    pub card_only: Option<bool>,
+    #[command(desc = "Show how their XP changed over the last few days instead")]
+    pub history: Option<bool>,
+    #[command(
+        desc = "Show their rank from this many days ago instead",
+        min_value = 1,
+        max_value = 90
+    )]
+    pub days_ago: Option<i64>,
+    #[command(desc = "Estimate how many messages until their next level instead")]
+    pub estimate: Option<bool>,
+    #[command(desc = "Get your own level-ups in DMs instead of announced in the server")]
+    pub notify: Option<bool>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "whohas",
+    desc = "List the members at a level",
+    dm_permission = false
+)]
+pub struct WhoHasCommand {
+    #[command(
+        desc = "Level to list the members at",
+        min_value = 0,
+        max_value = 10000
+    )]
+    pub level: i64,
+    #[command(desc = "Page to jump to", min_value = 1)]
+    pub page: Option<i64>,
+    #[command(desc = "Want to show this off to everyone?")]
+    pub show_off: Option<bool>,
 }
@@ -1,11 +1,17 @@
 pub mod admin;
+pub mod announcements;
 pub mod card;
+pub mod commands;
 pub mod config;
 pub mod experience;
 pub mod gdpr;
 pub mod levels;
 pub mod manage;
+pub mod newcomers;
 pub mod rewards;
+pub mod setup;
+pub mod summary;
+pub mod timezone;
 
 use admin::AdminCommand;
 use rewards::RewardsCommand;
@@ -16,12 +22,18 @@ use twilight_model::{
 };
 
 use crate::{
+    announcements::AnnouncementsCommand,
     card::{CardCommand, GuildCardCommand},
+    commands::CommandsCommand,
     config::ConfigCommand,
     experience::XpCommand,
     gdpr::GdprCommand,
-    levels::{LeaderboardCommand, RankCommand},
+    levels::{LeaderboardCommand, RankCommand, WhoHasCommand},
     manage::ManageCommand,
+    newcomers::NewcomersCommand,
+    setup::SetupCommand,
+    summary::WeeklySummaryCommand,
+    timezone::TimezoneCommand,
 };
 
 #[derive(CommandModel, CreateCommand)]
@@ -32,18 +44,34 @@ use crate::{
 )]
 pub struct HelpCommand;
 
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "about",
+    desc = "See which version of experienced is running, and where to learn more",
+    dm_permission = true
+)]
+pub struct AboutCommand;
+
 pub fn get_commands() -> Vec<Command> {
     vec![
         XpCommand::create_command().into(),
         RankCommand::create_command().into(),
         CardCommand::create_command().into(),
         HelpCommand::create_command().into(),
+        AboutCommand::create_command().into(),
         GdprCommand::create_command().into(),
         ManageCommand::create_command().into(),
         ConfigCommand::create_command().into(),
         GuildCardCommand::create_command().into(),
         LeaderboardCommand::create_command().into(),
         RewardsCommand::create_command().into(),
+        TimezoneCommand::create_command().into(),
+        WeeklySummaryCommand::create_command().into(),
+        SetupCommand::create_command().into(),
+        WhoHasCommand::create_command().into(),
+        CommandsCommand::create_command().into(),
+        NewcomersCommand::create_command().into(),
+        AnnouncementsCommand::create_command().into(),
         context_cmd("Get level", CommandType::User),
         context_cmd("Get author level", CommandType::Message),
     ]
@@ -76,18 +104,25 @@ fn ensure_limits_match() {
     use twilight_model::application::command::CommandOptionValue;
     let cmd = ConfigCommand::create_command();
     eprintln!("{cmd:?}");
-    let levels_cmd = cmd.options.iter().find(|v| v.name == "levels").unwrap();
-    let levels_cmd_opts = levels_cmd.options.as_ref().unwrap();
-    let cooldown_value = levels_cmd_opts
-        .iter()
-        .find(|v| v.name == "message_cooldown")
-        .unwrap()
-        .max_value
-        .unwrap();
-    assert_eq!(
-        cooldown_value,
-        CommandOptionValue::Integer(xpd_common::MAX_MESSAGE_COOLDOWN.into())
-    );
+    // Cooldowns are cleaned up after the longest one allowed, so no cooldown may be longer
+    for (subcommand, option) in [
+        ("levels", "message_cooldown"),
+        ("role-cooldown", "cooldown"),
+    ] {
+        let sub_cmd = cmd.options.iter().find(|v| v.name == subcommand).unwrap();
+        let sub_cmd_opts = sub_cmd.options.as_ref().unwrap();
+        let cooldown_value = sub_cmd_opts
+            .iter()
+            .find(|v| v.name == option)
+            .unwrap()
+            .max_value
+            .unwrap();
+        assert_eq!(
+            cooldown_value,
+            CommandOptionValue::Integer(xpd_common::MAX_MESSAGE_COOLDOWN.into()),
+            "{subcommand} {option}"
+        );
+    }
 }
 
 #[test]
@@ -16,6 +16,8 @@ pub enum ManageCommand {
     Import(ManageCommandImport),
     #[command(name = "export")]
     Export(ManageCommandExport),
+    #[command(name = "distribution")]
+    Distribution(ManageCommandDistribution),
 }
 
 impl ManageCommand {
@@ -47,7 +49,7 @@ pub struct ManageCommandResetGuild {
     dm_permission = false
 )]
 pub struct ManageCommandImport {
-    #[command(desc = "Leveling JSON file")]
+    #[command(desc = "Leveling JSON file, or a CSV file with `user_id,xp` columns")]
     pub levels: Attachment,
     #[command(desc = "Overwrite, rather then summing with previous leveling data")]
     pub overwrite: Option<bool>,
@@ -60,3 +62,11 @@ pub struct ManageCommandImport {
     dm_permission = false
 )]
 pub struct ManageCommandExport;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "distribution",
+    desc = "Show how many members are at each level in this server",
+    dm_permission = false
+)]
+pub struct ManageCommandDistribution;
@@ -0,0 +1,45 @@
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::guild::Permissions;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "newcomers",
+    desc = "Keep members who just arrived from earning XP right away",
+    dm_permission = false,
+    default_permissions = "Self::default_permissions"
+)]
+pub enum NewcomersCommand {
+    #[command(name = "join-grace")]
+    JoinGrace(NewcomersCommandJoinGrace),
+    #[command(name = "min-account-age")]
+    MinAccountAge(NewcomersCommandMinAccountAge),
+}
+
+impl NewcomersCommand {
+    #[inline]
+    const fn default_permissions() -> Permissions {
+        Permissions::ADMINISTRATOR
+    }
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "join-grace",
+    desc = "Minutes new members must wait before they earn XP",
+    dm_permission = false
+)]
+pub struct NewcomersCommandJoinGrace {
+    #[command(desc = "0 to earn XP right away", min_value = 0, max_value = 10080)]
+    pub minutes: i64,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "min-account-age",
+    desc = "Days an account must exist before it earns XP",
+    dm_permission = false
+)]
+pub struct NewcomersCommandMinAccountAge {
+    #[command(desc = "0 lets any account earn XP", min_value = 0, max_value = 365)]
+    pub days: i64,
+}
@@ -2,6 +2,7 @@ use twilight_interactions::command::{CommandModel, CreateCommand};
 use twilight_model::{
     guild::{Permissions, Role},
     id::{marker::RoleMarker, Id},
+    user::User,
 };
 
 #[derive(CommandModel, CreateCommand)]
@@ -11,6 +12,7 @@ use twilight_model::{
     dm_permission = false,
     default_permissions = "Self::default_permissions"
 )]
+#[allow(clippy::large_enum_variant)]
 pub enum RewardsCommand {
     #[command(name = "add")]
     Add(RewardsCommandAdd),
@@ -18,6 +20,16 @@ pub enum RewardsCommand {
     Remove(RewardsCommandRemove),
     #[command(name = "list")]
     List(RewardsCommandList),
+    #[command(name = "bulk")]
+    Bulk(RewardsCommandBulk),
+    #[command(name = "alerts")]
+    Alerts(RewardsCommandAlerts),
+    #[command(name = "once-add")]
+    OnceAdd(RewardsCommandOnceAdd),
+    #[command(name = "once-remove")]
+    OnceRemove(RewardsCommandOnceRemove),
+    #[command(name = "claims")]
+    Claims(RewardsCommandClaims),
 }
 
 impl RewardsCommand {
@@ -60,3 +72,94 @@ pub struct RewardsCommandRemove {
     dm_permission = false
 )]
 pub struct RewardsCommandList;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "bulk",
+    desc = "Add several leveling rewards at once",
+    dm_permission = false
+)]
+pub struct RewardsCommandBulk {
+    #[command(desc = "Role 1 to grant")]
+    pub role_1: Option<Role>,
+    #[command(desc = "Level to grant role 1 at", min_value = 1)]
+    pub level_1: Option<i64>,
+    #[command(desc = "Role 2 to grant")]
+    pub role_2: Option<Role>,
+    #[command(desc = "Level to grant role 2 at", min_value = 1)]
+    pub level_2: Option<i64>,
+    #[command(desc = "Role 3 to grant")]
+    pub role_3: Option<Role>,
+    #[command(desc = "Level to grant role 3 at", min_value = 1)]
+    pub level_3: Option<i64>,
+    #[command(desc = "Role 4 to grant")]
+    pub role_4: Option<Role>,
+    #[command(desc = "Level to grant role 4 at", min_value = 1)]
+    pub level_4: Option<i64>,
+    #[command(desc = "Role 5 to grant")]
+    pub role_5: Option<Role>,
+    #[command(desc = "Level to grant role 5 at", min_value = 1)]
+    pub level_5: Option<i64>,
+    #[command(desc = "Remove every other reward, instead of keeping them alongside these")]
+    pub replace: Option<bool>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "alerts",
+    desc = "DM the server owner once for each reward role I can't give out",
+    dm_permission = false
+)]
+pub struct RewardsCommandAlerts {
+    #[command(desc = "Whether to send these DMs. Leave empty to see if they're on")]
+    pub enabled: Option<bool>,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "once-add",
+    desc = "Add a reward each member only gets once, the first time they reach a level",
+    dm_permission = false
+)]
+pub struct RewardsCommandOnceAdd {
+    #[command(desc = "What the reward is, like \"Custom color\"", max_length = 64)]
+    pub name: String,
+    #[command(desc = "What level to grant the reward at", min_value = 1)]
+    pub level: i64,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "once-remove",
+    desc = "Stop granting a one-time reward",
+    dm_permission = false
+)]
+pub struct RewardsCommandOnceRemove {
+    #[command(desc = "The name of the reward to remove", max_length = 64)]
+    pub name: String,
+}
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "claims",
+    desc = "See who claimed one-time rewards",
+    dm_permission = false
+)]
+pub struct RewardsCommandClaims {
+    #[command(desc = "Whose claimed rewards to show. Leave empty to see every reward")]
+    pub user: Option<User>,
+}
+
+impl RewardsCommandBulk {
+    /// Every role and level option, in order. Either half of a pair may be missing.
+    #[must_use]
+    pub fn pairs(&self) -> [(Option<Id<RoleMarker>>, Option<i64>); 5] {
+        [
+            (self.role_1.as_ref().map(|role| role.id), self.level_1),
+            (self.role_2.as_ref().map(|role| role.id), self.level_2),
+            (self.role_3.as_ref().map(|role| role.id), self.level_3),
+            (self.role_4.as_ref().map(|role| role.id), self.level_4),
+            (self.role_5.as_ref().map(|role| role.id), self.level_5),
+        ]
+    }
+}
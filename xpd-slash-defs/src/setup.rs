@@ -0,0 +1,18 @@
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::guild::Permissions;
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "setup",
+    desc = "Walk through the most important settings for this server",
+    dm_permission = false,
+    default_permissions = "Self::default_permissions"
+)]
+pub struct SetupCommand;
+
+impl SetupCommand {
+    #[inline]
+    const fn default_permissions() -> Permissions {
+        Permissions::ADMINISTRATOR
+    }
+}
@@ -0,0 +1,12 @@
+use twilight_interactions::command::{CommandModel, CreateCommand};
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "weekly-summary",
+    desc = "Get a DM every week with the XP, levels and ranks you gained",
+    dm_permission = true
+)]
+pub struct WeeklySummaryCommand {
+    #[command(desc = "Whether to send you weekly summaries")]
+    pub enabled: bool,
+}
@@ -0,0 +1,22 @@
+use twilight_interactions::command::{AutocompleteValue, CommandModel, CreateCommand};
+
+#[derive(CommandModel, CreateCommand)]
+#[command(
+    name = "timezone",
+    desc = "Set the timezone Experienced uses to show you times",
+    dm_permission = true
+)]
+pub struct TimezoneCommand {
+    #[command(
+        desc = "Timezone name, like Europe/Stockholm. Leave this out to go back to UTC.",
+        autocomplete = true,
+        max_length = 64
+    )]
+    pub timezone: Option<String>,
+}
+
+#[derive(CommandModel, Debug)]
+#[command(autocomplete = true)]
+pub struct TimezoneCommandAutocomplete {
+    pub timezone: AutocompleteValue<String>,
+}
@@ -1,58 +1,127 @@
-use std::{borrow::Cow, fmt::Display};
+use std::{
+    borrow::Cow,
+    fmt::{Display, Write},
+};
 
-use twilight_model::id::{
-    marker::{GuildMarker, UserMarker},
-    Id,
+use serde::Serialize;
+use sqlx::Connection;
+use twilight_model::{
+    http::attachment::Attachment,
+    id::{
+        marker::{GuildMarker, UserMarker},
+        Id,
+    },
 };
 use twilight_util::builder::embed::EmbedBuilder;
-use xpd_common::{CURRENT_GIT_SHA, DEFAULT_MESSAGE_COOLDOWN, DISCORD_EPOCH_SECS};
+use xpd_common::{DEFAULT_MESSAGE_COOLDOWN, DISCORD_EPOCH_SECS};
+use xpd_database::XpStore;
+use xpd_rank_card::customizations::Customizations;
 use xpd_slash_defs::admin::{
-    self, AdminCommand, AdminCommandBanGuild, AdminCommandGuildStats, AdminCommandInspectCooldown,
-    AdminCommandLeave, AdminCommandPardonGuild, AdminCommandResetGuild, AdminCommandResetUser,
-    AdminCommandSetNick,
+    self, AdminCommand, AdminCommandBanGuild, AdminCommandBulkBanGuilds,
+    AdminCommandBulkPardonGuilds, AdminCommandGuildStats, AdminCommandImports,
+    AdminCommandInspectCooldown, AdminCommandInspectUser, AdminCommandLeave,
+    AdminCommandPardonGuild, AdminCommandRecomputeLevels, AdminCommandRecomputeRank,
+    AdminCommandResetGuild, AdminCommandResetUser, AdminCommandSetNick, AdminCommandStaleGuilds,
+    AdminCommandUndoReset, AdminCommandXpRange,
 };
 
-use crate::{Error, SlashState, XpdSlashResponse};
+use crate::{
+    dispatch::Respondable,
+    http::{DiscordHttp, GuildCountsCache},
+    Error, SlashState, XpdSlashResponse,
+};
 
 pub async fn process_admin(
     data: AdminCommand,
     guild_id: Id<GuildMarker>,
+    respondable: Respondable,
     invoker: Id<UserMarker>,
+    locale: Option<&str>,
     state: SlashState,
 ) -> Result<XpdSlashResponse, Error> {
     if guild_id != state.control_guild {
         return Err(Error::NotControlGuild);
-    };
+    }
     if !state.owners.contains(&invoker) {
         return Err(Error::NotControlUser);
     }
     let contents = match data {
-        AdminCommand::Leave(lg) => leave_guild(state, lg).await,
+        AdminCommand::Leave(lg) => leave_guild(state.client.as_ref(), lg).await,
         AdminCommand::ResetGuild(rg) => reset_guild(state, rg).await,
+        AdminCommand::UndoReset(ur) => undo_reset(state, ur).await,
+        AdminCommand::RecomputeLevels(rl) => recompute_levels(state, rl, respondable).await,
         AdminCommand::ResetUser(ru) => reset_user(state, ru).await,
-        AdminCommand::SetNick(sn) => set_nick(state, sn).await,
+        AdminCommand::SetNick(sn) => set_nick(state.client.as_ref(), sn).await,
         AdminCommand::BanGuild(bg) => ban_guild(state, bg).await,
         AdminCommand::PardonGuild(pg) => pardon_guild(state, pg).await,
-        AdminCommand::GuildStats(gs) => get_guild_stats(state, gs).await,
-        AdminCommand::Stats(admin::AdminCommandStats) => get_bot_stats(state).await,
+        AdminCommand::BulkBanGuilds(bg) => bulk_ban_guilds(state, bg).await,
+        AdminCommand::BulkPardonGuilds(pg) => bulk_pardon_guilds(state, pg).await,
+        AdminCommand::GuildStats(gs) => get_guild_stats(state, gs, locale).await,
+        AdminCommand::StaleGuilds(sg) => stale_guilds(state, sg).await,
+        AdminCommand::Stats(admin::AdminCommandStats) => get_bot_stats(state, locale).await,
         AdminCommand::InspectCooldown(ic) => inspect_cooldown(state, ic).await,
+        AdminCommand::XpRange(xr) => xp_range(state, xr, locale).await,
+        AdminCommand::RecomputeRank(rr) => recompute_rank(state, rr).await,
+        AdminCommand::InspectUser(iu) => return inspect_user(state, iu).await,
+        AdminCommand::Imports(im) => Ok(imports(&state, &im)),
     }?;
     Ok(XpdSlashResponse::new()
         .ephemeral(true)
         .embeds([EmbedBuilder::new().description(contents).build()]))
 }
 
-async fn leave_guild(state: SlashState, leave: AdminCommandLeave) -> Result<String, Error> {
-    let guild: Id<GuildMarker> = leave.guild.parse()?;
-    state.client.leave_guild(guild).await?;
+async fn leave_guild(http: &impl DiscordHttp, leave: AdminCommandLeave) -> Result<String, Error> {
+    let guild = parse_guild_id(&leave.guild)?;
+    http.leave_guild(guild).await?;
     Ok(format!("Left guild {guild}"))
 }
 
 async fn reset_guild(state: SlashState, leave: AdminCommandResetGuild) -> Result<String, Error> {
-    let guild: Id<GuildMarker> = leave.guild.parse()?;
+    let guild = parse_guild_id(&leave.guild)?;
     let rows = xpd_database::delete_levels_guild(&state.db, guild).await?;
     Ok(format!(
-        "Reset levels for guild {guild}. It had {rows} users worth of data."
+        "Reset levels for guild {guild}. It had {rows} users worth of data. \
+        This can be undone with `/admin undoreset` for {} days.",
+        state.reset_undo_days
+    ))
+}
+
+async fn undo_reset(state: SlashState, undo: AdminCommandUndoReset) -> Result<String, Error> {
+    let guild = parse_guild_id(&undo.guild)?;
+    let rows = xpd_database::restore_levels_guild(&state.db, guild, state.reset_undo_days).await?;
+    Ok(format!(
+        "Restored levels for guild {guild}. {rows} users worth of data were recovered."
+    ))
+}
+
+async fn recompute_levels(
+    state: SlashState,
+    recompute: AdminCommandRecomputeLevels,
+    respondable: Respondable,
+) -> Result<String, Error> {
+    let guild = parse_guild_id(&recompute.guild)?;
+    let users: Vec<(Id<UserMarker>, i64)> = xpd_database::export_bulk_users(&state.db, guild)
+        .await?
+        .into_iter()
+        .map(|user| (user.id, user.xp))
+        .collect();
+    let total = users.len();
+    state.clone().spawn(async move {
+        let text = match crate::manager::sync_guild_rewards(&state, guild, users).await {
+            Ok(updated) => format!(
+                "Recomputed levels for {total} members of {guild}. {updated} had reward roles added."
+            ),
+            Err(source) => {
+                error!(?source, ?guild, "Failed to recompute levels");
+                format!("Failed to recompute levels for {guild}: {source}")
+            }
+        };
+        let response = XpdSlashResponse::with_embed_text(text).ephemeral(true);
+        state.send_followup(response, respondable.token()).await;
+    });
+    Ok(format!(
+        "Recomputing levels for {total} members of {guild}. \
+        Reward roles are synced in the background, and I'll follow up when it's done."
     ))
 }
 
@@ -65,13 +134,9 @@ async fn reset_user(state: SlashState, leave: AdminCommandResetUser) -> Result<S
     ))
 }
 
-async fn set_nick(state: SlashState, nick: AdminCommandSetNick) -> Result<String, Error> {
-    let guild: Id<GuildMarker> = nick.guild.parse()?;
-    state
-        .client
-        .update_current_member(guild)
-        .nick(nick.name.as_deref())
-        .await?;
+async fn set_nick(http: &impl DiscordHttp, nick: AdminCommandSetNick) -> Result<String, Error> {
+    let guild = parse_guild_id(&nick.guild)?;
+    http.set_own_nick(guild, nick.name.as_deref()).await?;
     Ok(format!(
         "Set nickname to {} in {guild}",
         nick.name.unwrap_or_else(|| "{default}".to_string())
@@ -79,55 +144,249 @@ async fn set_nick(state: SlashState, nick: AdminCommandSetNick) -> Result<String
 }
 
 async fn ban_guild(state: SlashState, ban: AdminCommandBanGuild) -> Result<String, Error> {
-    let guild: Id<GuildMarker> = ban.guild.parse()?;
+    let guild = parse_guild_id(&ban.guild)?;
     xpd_database::ban_guild(&state.db, guild, ban.duration).await?;
     Ok(format!("Banned guild {guild}"))
 }
 
 async fn pardon_guild(state: SlashState, pardon: AdminCommandPardonGuild) -> Result<String, Error> {
-    let guild: Id<GuildMarker> = pardon.guild.parse()?;
+    let guild = parse_guild_id(&pardon.guild)?;
     xpd_database::pardon_guild(&state.db, guild).await?;
     Ok(format!("Pardoned guild {guild}"))
 }
 
-async fn get_guild_stats(state: SlashState, gs: AdminCommandGuildStats) -> Result<String, Error> {
-    let guild_id: Id<GuildMarker> = gs.guild.parse()?;
+async fn bulk_ban_guilds(
+    state: SlashState,
+    ban: AdminCommandBulkBanGuilds,
+) -> Result<String, Error> {
+    let (guilds, mut lines) = parse_guild_list(&ban.guilds);
+    let mut banned = 0;
+    // Each guild gets a savepoint, so one failure doesn't undo the rest
+    let mut txn = state.db.begin().await?;
+    for guild in &guilds {
+        let mut savepoint = txn.begin().await?;
+        match xpd_database::ban_guild(savepoint.as_mut(), *guild, ban.duration).await {
+            Ok(()) => {
+                savepoint.commit().await?;
+                banned += 1;
+                lines.push(format!("Banned `{guild}`"));
+            }
+            Err(source) => lines.push(format!("Failed to ban `{guild}`: {source}")),
+        }
+    }
+    txn.commit().await?;
+    Ok(bulk_report(
+        &format!("Banned {banned} of {} guilds.", guilds.len()),
+        &lines,
+    ))
+}
+
+async fn bulk_pardon_guilds(
+    state: SlashState,
+    pardon: AdminCommandBulkPardonGuilds,
+) -> Result<String, Error> {
+    let (guilds, mut lines) = parse_guild_list(&pardon.guilds);
+    let mut pardoned = 0;
+    let mut txn = state.db.begin().await?;
+    for guild in &guilds {
+        let mut savepoint = txn.begin().await?;
+        match xpd_database::pardon_guild(savepoint.as_mut(), *guild).await {
+            Ok(()) => {
+                savepoint.commit().await?;
+                pardoned += 1;
+                lines.push(format!("Pardoned `{guild}`"));
+            }
+            Err(source) => lines.push(format!("Failed to pardon `{guild}`: {source}")),
+        }
+    }
+    txn.commit().await?;
+    Ok(bulk_report(
+        &format!("Pardoned {pardoned} of {} guilds.", guilds.len()),
+        &lines,
+    ))
+}
+
+/// Parse a guild ID the way people tend to paste them: with spaces, in `<...>`,
+/// or as part of a `discord.com/channels/...` link.
+pub fn parse_guild_id(input: &str) -> Result<Id<GuildMarker>, Error> {
+    let trimmed = input.trim();
+    let unwrapped = trimmed
+        .strip_prefix('<')
+        .and_then(|rest| rest.strip_suffix('>'))
+        .map_or(trimmed, |inner| {
+            inner.trim_start_matches(['#', '@', '!', '&'])
+        });
+    let id = unwrapped
+        .split_once("/channels/")
+        .map_or(unwrapped, |(_, path)| {
+            path.split('/').next().unwrap_or_default()
+        });
+    id.parse::<u64>()
+        .ok()
+        .and_then(Id::new_checked)
+        .ok_or_else(|| Error::InvalidGuildId(input.to_string()))
+}
+
+/// Split a list of guild IDs on whitespace and commas.
+/// Returns the unique valid IDs, and a note for every entry that wasn't one.
+fn parse_guild_list(list: &str) -> (Vec<Id<GuildMarker>>, Vec<String>) {
+    let mut guilds = Vec::new();
+    let mut notes = Vec::new();
+    for item in list
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|item| !item.is_empty())
+    {
+        match parse_guild_id(item) {
+            Ok(guild) if !guilds.contains(&guild) => guilds.push(guild),
+            Ok(_) => {}
+            Err(_) => notes.push(format!("Skipped `{item}`, it is not a guild ID")),
+        }
+    }
+    (guilds, notes)
+}
+
+/// Embed descriptions can be at most 4096 characters
+const REPORT_LIMIT: usize = 4000;
+
+fn bulk_report(summary: &str, lines: &[String]) -> String {
+    let mut report = summary.to_string();
+    for (idx, line) in lines.iter().enumerate() {
+        if report.len() + line.len() + 1 > REPORT_LIMIT {
+            // writing to a String can't fail
+            let _ = write!(report, "\n...and {} more", lines.len() - idx);
+            break;
+        }
+        report.push('\n');
+        report.push_str(line);
+    }
+    report
+}
+
+async fn get_guild_stats(
+    state: SlashState,
+    gs: AdminCommandGuildStats,
+    locale: Option<&str>,
+) -> Result<String, Error> {
+    let guild_id = parse_guild_id(&gs.guild)?;
     let levels = xpd_database::levels_in_guild(&state.db, guild_id).await?;
+    describe_guild(
+        state.client.as_ref(),
+        &state.guild_counts,
+        guild_id,
+        levels,
+        locale,
+    )
+    .await
+}
 
-    let guild = state
-        .client
-        .guild(guild_id)
-        .with_counts(true)
-        .await?
-        .model()
-        .await?;
+async fn describe_guild(
+    http: &impl DiscordHttp,
+    counts: &GuildCountsCache,
+    guild_id: Id<GuildMarker>,
+    levels: i64,
+    locale: Option<&str>,
+) -> Result<String, Error> {
+    let levels = xpd_common::format_int(locale, levels);
+    let guild = counts.get(http, guild_id).await?;
 
     let large = if guild.large { "large" } else { "" };
     let name = &guild.name;
-    let online = fmt_opt_u64(guild.approximate_presence_count);
-    let members = fmt_opt_u64(guild.approximate_member_count);
+    let online = fmt_opt_u64(locale, guild.online);
+    let members = fmt_opt_u64(locale, guild.members);
 
     Ok(format!(
         "{levels} levels in database for {large} guild {name}. Roughly {online} members online of {members} total members.",
     ))
 }
 
-fn fmt_opt_u64(item: Option<u64>) -> impl Display {
-    item.map_or_else(|| Cow::Borrowed("unknown"), |v| Cow::Owned(v.to_string()))
+const STALE_GUILDS_PER_PAGE: i64 = 20;
+
+async fn stale_guilds(
+    state: SlashState,
+    listing: AdminCommandStaleGuilds,
+) -> Result<String, Error> {
+    let page = listing.page.unwrap_or(1);
+    #[allow(clippy::cast_precision_loss)]
+    let days = listing.days as f64;
+    let guilds = xpd_database::stale_guilds(
+        &state.db,
+        days,
+        STALE_GUILDS_PER_PAGE,
+        (page - 1) * STALE_GUILDS_PER_PAGE,
+    )
+    .await?;
+    if guilds.is_empty() {
+        return Ok(format!(
+            "No stale guilds on page {page}. Every other guild earned XP in the last {} days.",
+            listing.days
+        ));
+    }
+
+    let mut lines = Vec::with_capacity(guilds.len() + 1);
+    if listing.reset.is_some_and(|reset| reset) {
+        let mut txn = state.db.begin().await?;
+        for guild in &guilds {
+            let rows = xpd_database::delete_levels_guild(txn.as_mut(), guild.id).await?;
+            lines.push(format!("Reset `{}` ({rows} users)", guild.id));
+        }
+        txn.commit().await?;
+        lines.push(format!(
+            "These resets can be undone with `/admin undoreset` for {} days.",
+            state.reset_undo_days
+        ));
+    } else {
+        for guild in &guilds {
+            lines.push(format!(
+                "`{}` last active <t:{}:R>",
+                guild.id, guild.last_message
+            ));
+        }
+        lines.push(format!("Page {page}, use `page:{}` for more.", page + 1));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn fmt_opt_u64(locale: Option<&str>, item: Option<u64>) -> impl Display {
+    item.map_or_else(
+        || Cow::Borrowed("unknown"),
+        |v| Cow::Owned(xpd_common::format_int(locale, v)),
+    )
 }
 
-async fn get_bot_stats(state: SlashState) -> Result<String, Error> {
+async fn get_bot_stats(state: SlashState, locale: Option<&str>) -> Result<String, Error> {
     let levels_held = xpd_database::total_levels(&state.db).await?;
+    let levels_held = xpd_common::format_int(locale, levels_held);
+    let build = state.build;
     Ok(format!(
-        "Roughly {levels_held} levels in database. Bot version `git-{CURRENT_GIT_SHA}`"
+        "Roughly {levels_held} levels in database. Bot version `{build}`, built <t:{}:R>",
+        build.built_at
     ))
 }
 
+fn imports(state: &SlashState, imports: &AdminCommandImports) -> String {
+    let was_paused = imports.paused.map(|paused| state.import_pause.set(paused));
+    let paused = state.import_pause.is_paused();
+    let change = match (was_paused, paused) {
+        (Some(false), true) => "Paused imports. ",
+        (Some(true), false) => "Resumed imports. ",
+        _ => "",
+    };
+    let status = if paused {
+        "New imports are refused, and reward syncs wait before their next batch"
+    } else {
+        "Imports are running normally"
+    };
+    format!(
+        "{change}{status}. {} guild(s) are importing right now.",
+        state.running_imports.count()
+    )
+}
+
 async fn inspect_cooldown(
     state: SlashState,
     inspect: AdminCommandInspectCooldown,
 ) -> Result<String, Error> {
-    let guild: Id<GuildMarker> = inspect.guild.parse()?;
+    let guild = parse_guild_id(&inspect.guild)?;
     let last_message_ts = xpd_database::get_last_message(&state.db, inspect.user, guild)
         .await?
         .ok_or(Error::NoLastMessage)?;
@@ -140,3 +399,392 @@ async fn inspect_cooldown(
         "Last message detected <t:{unix_lm_timestamp}:R>. Guild cooldown {guild_cooldown}s."
     ))
 }
+
+async fn recompute_rank(
+    state: SlashState,
+    recompute: AdminCommandRecomputeRank,
+) -> Result<String, Error> {
+    let guild = parse_guild_id(&recompute.guild)?;
+    let user = recompute.user;
+    let xp = xpd_database::user_xp(&state.db, guild, user).await?;
+    let rank = XpStore::rank_for_user(&state.db, user, guild).await?;
+    let batch_rank = xpd_database::ranks_for_users(&state.db, guild, &[user])
+        .await?
+        .first()
+        .map(|ranked| ranked.rank);
+    let position = xpd_database::leaderboard_position(&state.db, guild, user).await?;
+    let check = RankCheck {
+        xp,
+        rank,
+        batch_rank,
+        position,
+    };
+    Ok(format!("<@{user}> in `{guild}`\n{}", check.report()))
+}
+
+/// A user's rank as worked out by each of the queries that compute one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RankCheck {
+    xp: Option<i64>,
+    /// What `/rank` shows, counting users with more XP
+    rank: i64,
+    /// What the batch query used for role-filtered leaderboards returns
+    batch_rank: Option<i64>,
+    /// Where the leaderboard lists them, which breaks ties by user ID
+    position: Option<i64>,
+}
+
+impl RankCheck {
+    fn report(&self) -> String {
+        let mut report = String::new();
+        let _ = writeln!(
+            report,
+            "XP: {}",
+            self.xp
+                .map_or(Cow::Borrowed("none"), |xp| xp.to_string().into())
+        );
+        let _ = writeln!(report, "Rank (`/rank`): {}", self.rank);
+        let _ = writeln!(
+            report,
+            "Rank (batch query): {}",
+            self.batch_rank
+                .map_or(Cow::Borrowed("missing"), |rank| rank.to_string().into())
+        );
+        let _ = writeln!(
+            report,
+            "Leaderboard position: {}",
+            self.position
+                .map_or(Cow::Borrowed("not listed"), |position| position
+                    .to_string()
+                    .into())
+        );
+        report.push_str("Cached rank: none, ranks are always computed from the database\n");
+
+        let mut problems = Vec::new();
+        if self.batch_rank != Some(self.rank) {
+            problems.push("the batch query disagrees with `/rank`");
+        }
+        match (self.xp, self.position) {
+            (Some(_), None) => problems.push("they have XP but aren't on the leaderboard"),
+            (None, Some(_)) => problems.push("they have no XP but are on the leaderboard"),
+            // Users tied on XP share a rank, but are listed one after another
+            (Some(_), Some(position)) if position < self.rank => {
+                problems.push("they're listed above their rank");
+            }
+            _ => {}
+        }
+        if problems.is_empty() {
+            report.push_str("**Everything agrees.**");
+            if self.position.is_some_and(|position| position > self.rank) {
+                let _ = write!(
+                    report,
+                    " They're listed {} place(s) below their rank because of users tied on XP.",
+                    self.position.unwrap_or(self.rank) - self.rank
+                );
+            }
+        } else {
+            let _ = write!(report, "**Mismatch:** {}.", problems.join(", "));
+        }
+        report
+    }
+}
+
+/// Everything stored about a user, for support. Message content is never stored, so it can't show up here.
+#[derive(Serialize)]
+struct UserRecord {
+    user: Id<UserMarker>,
+    levels: Vec<GuildRecord>,
+    /// The user's own card, or None if they never customized it
+    card: Option<Customizations>,
+    preferences: Option<UserPreferencesRecord>,
+}
+
+#[derive(Serialize)]
+struct GuildRecord {
+    guild: Id<GuildMarker>,
+    xp: i64,
+    level: u64,
+}
+
+#[derive(Serialize)]
+struct UserPreferencesRecord {
+    timezone: Option<String>,
+    level_up_dms: bool,
+    weekly_summary: bool,
+}
+
+async fn inspect_user(
+    state: SlashState,
+    inspect: AdminCommandInspectUser,
+) -> Result<XpdSlashResponse, Error> {
+    let user = inspect.user;
+    let mut levels: Vec<GuildRecord> = xpd_database::get_all_levels(&state.db, user)
+        .await?
+        .into_iter()
+        .map(|status| GuildRecord {
+            guild: status.guild,
+            xp: status.xp,
+            level: mee6::LevelInfo::new(u64::try_from(status.xp).unwrap_or(0)).level(),
+        })
+        .collect();
+    levels.sort_by_key(|record| std::cmp::Reverse(record.xp));
+    let card = xpd_database::card_customizations(&state.db, &[user.cast()])
+        .await?
+        .map(|raw| crate::levels::resolve_customizations(&state, Some(raw)))
+        .transpose()?;
+    let preferences = xpd_database::user_preferences(&state.db, user)
+        .await?
+        .map(|preferences| UserPreferencesRecord {
+            timezone: preferences.timezone,
+            level_up_dms: preferences.level_up_dms,
+            weekly_summary: preferences.weekly_summary,
+        });
+    let record = UserRecord {
+        user,
+        levels,
+        card,
+        preferences,
+    };
+    Ok(record_response(&record)?.ephemeral(true))
+}
+
+/// Show a record as JSON in an embed, or as a file if it doesn't fit in one
+fn record_response(record: &UserRecord) -> Result<XpdSlashResponse, Error> {
+    let json = serde_json::to_string_pretty(record)?;
+    let summary = format!(
+        "<@{}> has XP in {} guild(s).",
+        record.user,
+        record.levels.len()
+    );
+    let description = format!("{summary}\n```json\n{json}\n```");
+    if description.len() <= REPORT_LIMIT {
+        return Ok(
+            XpdSlashResponse::new().embeds([EmbedBuilder::new().description(description).build()])
+        );
+    }
+    let file = Attachment::from_bytes(format!("user-{}.json", record.user), json.into_bytes(), 1);
+    Ok(XpdSlashResponse::with_embed_text(summary).attachments([file]))
+}
+
+const XP_RANGE_PER_PAGE: i64 = 20;
+/// Results past this many are never listed, narrow the range instead
+const XP_RANGE_MAX_RESULTS: i64 = 1000;
+
+async fn xp_range(
+    state: SlashState,
+    search: AdminCommandXpRange,
+    locale: Option<&str>,
+) -> Result<String, Error> {
+    let guild = parse_guild_id(&search.guild)?;
+    if search.min > search.max {
+        return Err(Error::InvalidXpRange);
+    }
+    let page = search.page.unwrap_or(1).max(1);
+    let offset = (page - 1) * XP_RANGE_PER_PAGE;
+    if offset >= XP_RANGE_MAX_RESULTS {
+        return Err(Error::XpRangeTooDeep(XP_RANGE_MAX_RESULTS));
+    }
+    let (users, total) = xpd_database::users_in_xp_range(
+        &state.db,
+        guild,
+        search.min,
+        search.max,
+        XP_RANGE_PER_PAGE,
+        offset,
+    )
+    .await?;
+    if users.is_empty() {
+        return Ok(format!(
+            "No users on page {page} with between {} and {} XP.",
+            search.min, search.max
+        ));
+    }
+
+    let mut lines = Vec::with_capacity(users.len() + 1);
+    for user in &users {
+        let level = mee6::LevelInfo::new(u64::try_from(user.xp).unwrap_or(0)).level();
+        let name = crate::members::member_tag(&state, guild, user.id).await;
+        lines.push(format!(
+            "**{name}** (<@{}>) - {} XP, level {}",
+            user.id,
+            xpd_common::format_int(locale, user.xp),
+            xpd_common::format_int(locale, level)
+        ));
+    }
+    let shown = total.min(XP_RANGE_MAX_RESULTS);
+    let pages = (shown + XP_RANGE_PER_PAGE - 1) / XP_RANGE_PER_PAGE;
+    lines.push(format!(
+        "Page {page} of {pages}, {} users match.",
+        xpd_common::format_int(locale, total)
+    ));
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::{mock::MockHttp, GuildCounts};
+
+    #[test]
+    fn rank_checks() {
+        let check = RankCheck {
+            xp: Some(100),
+            rank: 2,
+            batch_rank: Some(2),
+            position: Some(2),
+        };
+        assert!(check.report().ends_with("**Everything agrees.**"));
+        let tied = RankCheck {
+            position: Some(4),
+            ..check
+        };
+        assert!(tied
+            .report()
+            .ends_with("listed 2 place(s) below their rank because of users tied on XP."));
+        let unranked = RankCheck {
+            xp: None,
+            rank: 5,
+            batch_rank: Some(5),
+            position: None,
+        };
+        assert!(unranked
+            .report()
+            .contains("Leaderboard position: not listed"));
+        assert!(unranked.report().ends_with("**Everything agrees.**"));
+
+        let broken = RankCheck {
+            batch_rank: Some(3),
+            position: Some(1),
+            ..check
+        };
+        assert!(broken.report().ends_with(
+            "**Mismatch:** the batch query disagrees with `/rank`, they're listed above their rank."
+        ));
+    }
+
+    #[test]
+    fn guild_id_parsing() {
+        let guild = Id::new(123);
+        for input in [
+            "123",
+            " 123\n",
+            "<123>",
+            "<#123>",
+            "https://discord.com/channels/123/456",
+            "https://discord.com/channels/123",
+        ] {
+            assert_eq!(parse_guild_id(input).unwrap(), guild, "{input:?}");
+        }
+        for input in ["", "0", "abc", "-5", "https://discord.com/channels/@me/456"] {
+            assert!(
+                matches!(parse_guild_id(input), Err(Error::InvalidGuildId(echo)) if echo == input),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn guild_list_parsing() {
+        let (guilds, notes) = parse_guild_list("1, 2\n3 nope  2,,0");
+        assert_eq!(guilds, [Id::new(1), Id::new(2), Id::new(3)]);
+        assert_eq!(notes.len(), 2);
+        assert!(notes[0].contains("nope"));
+    }
+
+    #[test]
+    fn big_records_are_attached() {
+        let record = |guilds: u64| UserRecord {
+            user: Id::new(1),
+            levels: (1..=guilds)
+                .map(|guild| GuildRecord {
+                    guild: Id::new(guild),
+                    xp: 100,
+                    level: 1,
+                })
+                .collect(),
+            card: None,
+            preferences: None,
+        };
+        let small = record_response(&record(2)).unwrap();
+        assert!(small.attachments.is_none());
+        let description = small.embeds.unwrap()[0].description.clone().unwrap();
+        assert!(description.contains("\"xp\": 100"));
+
+        let big = record_response(&record(200)).unwrap();
+        let attachments = big.attachments.unwrap();
+        assert_eq!(attachments[0].filename, "user-1.json");
+        assert!(big.embeds.unwrap()[0]
+            .description
+            .as_deref()
+            .unwrap()
+            .contains("200 guild(s)"));
+    }
+
+    #[test]
+    fn report_stays_within_embed() {
+        let lines = vec!["x".repeat(100); 100];
+        let report = bulk_report("summary", &lines);
+        assert!(report.len() <= REPORT_LIMIT + 32);
+        assert!(report.ends_with("more"));
+    }
+
+    #[tokio::test]
+    async fn leaving_guilds() {
+        let http = MockHttp::default();
+        let leave = |guild: &str| AdminCommandLeave {
+            guild: guild.to_string(),
+        };
+        assert_eq!(
+            leave_guild(&http, leave("<123>")).await.unwrap(),
+            "Left guild 123"
+        );
+        assert!(matches!(
+            leave_guild(&http, leave("nope")).await,
+            Err(Error::InvalidGuildId(_))
+        ));
+        assert_eq!(*http.left.lock().unwrap(), [Id::new(123)]);
+    }
+
+    #[tokio::test]
+    async fn setting_nicknames() {
+        let http = MockHttp::default();
+        let nick = |name: Option<&str>| AdminCommandSetNick {
+            guild: "5".to_string(),
+            name: name.map(ToOwned::to_owned),
+        };
+        let set = set_nick(&http, nick(Some("levels"))).await.unwrap();
+        assert_eq!(set, "Set nickname to levels in 5");
+        let reset = set_nick(&http, nick(None)).await.unwrap();
+        assert_eq!(reset, "Set nickname to {default} in 5");
+        assert_eq!(
+            *http.nicks.lock().unwrap(),
+            [(Id::new(5), Some("levels".to_string())), (Id::new(5), None)]
+        );
+    }
+
+    #[tokio::test]
+    async fn describing_guilds() {
+        let mut http = MockHttp::default();
+        http.guilds.insert(
+            Id::new(5),
+            GuildCounts {
+                name: "Levels".to_string(),
+                large: true,
+                online: Some(1200),
+                members: None,
+            },
+        );
+        let counts = GuildCountsCache::default();
+        let description = describe_guild(&http, &counts, Id::new(5), 3000, Some("en-US"))
+            .await
+            .unwrap();
+        assert_eq!(
+            description,
+            "3,000 levels in database for large guild Levels. \
+            Roughly 1,200 members online of unknown total members."
+        );
+        assert!(describe_guild(&http, &counts, Id::new(6), 0, None)
+            .await
+            .is_err());
+    }
+}
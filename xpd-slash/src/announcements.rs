@@ -0,0 +1,35 @@
+use twilight_model::id::{marker::GuildMarker, Id};
+use xpd_common::{PERCENTILE_MIN_MEMBERS, PERCENTILE_STEPS};
+use xpd_database::UpdateGuildConfig;
+use xpd_slash_defs::announcements::{AnnouncementsCommand, AnnouncementsCommandPercentiles};
+
+use crate::{Error, SlashState, XpdSlashResponse};
+
+pub async fn process_announcements(
+    command: AnnouncementsCommand,
+    guild_id: Id<GuildMarker>,
+    state: SlashState,
+) -> Result<XpdSlashResponse, Error> {
+    let message = match command {
+        AnnouncementsCommand::Percentiles(p) => process_percentiles(state, guild_id, p).await?,
+    };
+    Ok(XpdSlashResponse::with_embed_text(message).ephemeral(true))
+}
+
+async fn process_percentiles(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: AnnouncementsCommandPercentiles,
+) -> Result<String, Error> {
+    let top: i16 = options.top.try_into()?;
+    let new_cfg = UpdateGuildConfig::new().percentile_announcements(Some(top));
+    let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+    state.update_config(guild_id, config).await;
+    let Some(widest) = PERCENTILE_STEPS.into_iter().find(|step| *step <= top) else {
+        return Ok("Members will no longer be told when they reach a top percentage.".to_string());
+    };
+    Ok(format!(
+        "Members will now be told in the level-up channel when they reach the top {widest}% \
+        of the server, and each step after that, once the server has {PERCENTILE_MIN_MEMBERS} ranked members."
+    ))
+}
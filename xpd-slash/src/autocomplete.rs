@@ -8,7 +8,7 @@ use twilight_model::{
 };
 use twilight_util::builder::InteractionResponseDataBuilder;
 use xpd_rank_card::NameableItem;
-use xpd_slash_defs::card::CardCommandAutocomplete;
+use xpd_slash_defs::{card::CardCommandAutocomplete, timezone::TimezoneCommandAutocomplete};
 
 use crate::{manage_card::CUSTOM_CARD_NULL_SENTINEL, Error, SlashState};
 
@@ -30,14 +30,14 @@ pub fn autocomplete_inner(
     data: CommandData,
 ) -> Result<InteractionResponse, Error> {
     debug!(options = ?data, "Got autocomplete");
-    let choices = match data.name.as_str() {
-        "card" => card_autocomplete(data, state)?.into_iter(),
-        "guild-card" => card_autocomplete(data, state)?.into_iter(),
+    let choices: Vec<CommandOptionChoice> = match data.name.as_str() {
+        "card" | "guild-card" => card_autocomplete(data, state)?.into_iter().collect(),
+        "timezone" => timezone_autocomplete(data)?,
         _ => return Err(Error::NoAutocompleteForCommand),
     };
 
     let ird = InteractionResponseDataBuilder::new()
-        .choices(choices.take(25))
+        .choices(choices.into_iter().take(25))
         .build();
     Ok(InteractionResponse {
         kind: InteractionResponseType::ApplicationCommandAutocompleteResult,
@@ -45,6 +45,14 @@ pub fn autocomplete_inner(
     })
 }
 
+fn timezone_autocomplete(data: CommandData) -> Result<Vec<CommandOptionChoice>, Error> {
+    let autocomplete = TimezoneCommandAutocomplete::from_interaction(data.into())?;
+    let AutocompleteValue::Focused(input) = autocomplete.timezone else {
+        return Ok(Vec::new());
+    };
+    Ok(crate::timezone::timezone_choices(&input))
+}
+
 fn card_autocomplete(
     data: CommandData,
     state: &SlashState,
@@ -58,10 +66,19 @@ fn card_autocomplete(
     let fonts = choices(&edit.font, &state.svg.config().fonts, false);
     let cards = choices(&edit.card_layout, &state.svg.config().cards, false);
     let toys = choices(&edit.toy_image, &state.svg.config().toys, true);
+    let backgrounds = choices(
+        &edit.background_preset,
+        &state.svg.config().backgrounds,
+        true,
+    );
 
-    debug!(interaction = ?edit, ?fonts, ?cards, ?toys, "picked out some choices");
+    debug!(interaction = ?edit, ?fonts, ?cards, ?toys, ?backgrounds, "picked out some choices");
 
-    let choice_chain = fonts.into_iter().chain(toys).chain(cards);
+    let choice_chain = fonts
+        .into_iter()
+        .chain(toys)
+        .chain(cards)
+        .chain(backgrounds);
     Ok(choice_chain)
 }
 
@@ -0,0 +1,706 @@
+use std::{collections::BTreeMap, fmt::Write, ops::RangeInclusive};
+
+use http_body_util::{BodyExt, Limited};
+use serde::{Deserialize, Serialize};
+use twilight_interactions::command::CreateCommand;
+use twilight_model::{
+    application::command::{CommandOption, CommandOptionValue},
+    channel::{Attachment, ChannelType},
+    http::attachment::Attachment as HttpAttachment,
+    id::{
+        marker::{ChannelMarker, GuildMarker, RoleMarker},
+        Id,
+    },
+};
+use xpd_common::{GuildConfig, LevelStyle, LevelUpStyle, RewardStrategy, RoleReward};
+use xpd_database::UpdateGuildConfig;
+use xpd_slash_defs::{
+    announcements::AnnouncementsCommand, config::ConfigCommand, newcomers::NewcomersCommand,
+};
+
+use crate::{Error, SlashState, XpdSlashResponse};
+
+/// Version of the config backup format. Adding fields doesn't need a new version,
+/// because missing fields are read as unset. Renaming or removing them does.
+pub const CONFIG_EXPORT_VERSION: u32 = 1;
+/// Backups are a few kilobytes, anything much bigger isn't one
+const MAX_CONFIG_IMPORT_SIZE: usize = 1024 * 64;
+
+/// A guild's config and role rewards, as written to a backup file.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct ConfigExport {
+    pub version: u32,
+    #[serde(default)]
+    pub config: ExportedConfig,
+    #[serde(default)]
+    pub rewards: Vec<ExportedReward>,
+    /// Fields this version doesn't know, which are reported and ignored on import
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, serde_json::Value>,
+}
+
+/// Every setting is optional, unset ones are left out of the file.
+/// Enums and templates are stored as the text they're configured with.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ExportedConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level_up_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level_up_channel: Option<Id<ChannelMarker>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ping_on_level_up: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_xp_per_message: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_xp_per_message: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_cooldown: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prune_deleted_rewards: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reward_removal_margin: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starting_xp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count_cooldown_messages: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unranked_card: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment_bonus_xp: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embed_bonus_xp: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_duplicate_messages: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level_up_cooldown: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub silent_level_ups: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reward_strategy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level_style: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level_suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level_up_style: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xp_per_message: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank_bots: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reaction_xp: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_reaction_grants: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percentile_announcements: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled_commands: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role_alerts: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub join_grace: Option<i16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_account_age: Option<i16>,
+    /// Obsolete, replaced by `reward_strategy`. Only read from hand-written backups.
+    #[serde(skip_serializing)]
+    pub one_at_a_time: Option<bool>,
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ExportedReward {
+    pub role: Id<RoleMarker>,
+    pub level: i64,
+}
+
+impl ConfigExport {
+    #[must_use]
+    pub fn new(config: &GuildConfig, rewards: &[RoleReward]) -> Self {
+        let mut rewards: Vec<ExportedReward> = rewards
+            .iter()
+            .map(|reward| ExportedReward {
+                role: reward.id,
+                level: reward.requirement,
+            })
+            .collect();
+        rewards.sort_unstable_by_key(|reward| (reward.level, reward.role));
+        Self {
+            version: CONFIG_EXPORT_VERSION,
+            config: ExportedConfig::from(config),
+            rewards,
+            unknown: BTreeMap::new(),
+        }
+    }
+
+    /// Read a backup file, which must be from this version of the format or an older one.
+    /// # Errors
+    /// If the file isn't a config backup, or is from a newer version of the bot.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let export: Self = serde_json::from_slice(data).map_err(|_| Error::InvalidConfigBackup)?;
+        if export.version == 0 || export.version > CONFIG_EXPORT_VERSION {
+            return Err(Error::UnsupportedConfigBackupVersion(export.version));
+        }
+        Ok(export)
+    }
+
+    /// Fields in the backup which this version ignores or only partly understands.
+    #[must_use]
+    pub fn notes(&self) -> Vec<String> {
+        let mut notes: Vec<String> = self
+            .unknown
+            .keys()
+            .chain(self.config.unknown.keys())
+            .map(|field| format!("Ignored `{field}`, which isn't a setting I know about."))
+            .collect();
+        if self.config.one_at_a_time.is_some() {
+            notes.push(
+                "`one_at_a_time` is obsolete, and was read as the matching `reward_strategy`."
+                    .to_string(),
+            );
+        }
+        notes
+    }
+}
+
+impl ExportedConfig {
+    /// Check every setting, and turn them into a config update.
+    /// The level-up channel is left out, because it has to be checked against the guild.
+    /// # Errors
+    /// If any setting has a value that can't be configured.
+    pub fn into_update(self) -> Result<UpdateGuildConfig, Error> {
+        self.check_bounds()?;
+        if let Some(message) = &self.level_up_message {
+            crate::config::validate_level_up_message(message)?;
+        }
+        let reward_strategy = match (self.reward_strategy, self.one_at_a_time) {
+            (Some(name), _) => Some(parse_setting(
+                "reward_strategy",
+                name,
+                RewardStrategy::from_name,
+            )?),
+            (None, Some(true)) => Some(RewardStrategy::OneAtATime),
+            (None, _) => None,
+        };
+        let level_style = self
+            .level_style
+            .map(|name| parse_setting("level_style", name, LevelStyle::from_name))
+            .transpose()?;
+        let level_up_style = self
+            .level_up_style
+            .map(|name| parse_setting("level_up_style", name, LevelUpStyle::from_name))
+            .transpose()?;
+        Ok(UpdateGuildConfig {
+            level_up_message: self.level_up_message,
+            level_up_channel: None,
+            ping_users: self.ping_on_level_up,
+            max_xp_per_message: self.max_xp_per_message,
+            min_xp_per_message: self.min_xp_per_message,
+            message_cooldown: self.message_cooldown,
+            one_at_a_time: None,
+            prune_deleted_rewards: self.prune_deleted_rewards,
+            reward_removal_margin: self.reward_removal_margin,
+            starting_xp: self.starting_xp,
+            count_cooldown_messages: self.count_cooldown_messages,
+            unranked_card: self.unranked_card,
+            attachment_bonus_xp: self.attachment_bonus_xp,
+            embed_bonus_xp: self.embed_bonus_xp,
+            ignore_duplicate_messages: self.ignore_duplicate_messages,
+            level_up_cooldown: self.level_up_cooldown,
+            silent_level_ups: self.silent_level_ups,
+            reward_strategy,
+            level_style,
+            level_suffix: crate::config::process_level_suffix(self.level_suffix)?,
+            level_up_style,
+            xp_per_message: self.xp_per_message,
+            // Lockdowns are a reaction to what's happening right now, not a setting to restore
+            xp_lockdown_until: None,
+            rank_bots: self.rank_bots,
+            reaction_xp: self.reaction_xp,
+            max_reaction_grants: self.max_reaction_grants,
+            percentile_announcements: self.percentile_announcements,
+            disabled_commands: self.disabled_commands,
+            role_alerts: self.role_alerts,
+            join_grace: self.join_grace,
+            min_account_age: self.min_account_age,
+        })
+    }
+
+    /// Hold numeric settings to the bounds of the command options that set them, and
+    /// disabled commands to the ones `/commands disable` accepts.
+    fn check_bounds(&self) -> Result<(), Error> {
+        let bounded = [
+            (
+                "min_xp_per_message",
+                self.min_xp_per_message,
+                "levels",
+                "min_xp_per_message",
+            ),
+            (
+                "max_xp_per_message",
+                self.max_xp_per_message,
+                "levels",
+                "max_xp_per_message",
+            ),
+            (
+                "message_cooldown",
+                self.message_cooldown,
+                "levels",
+                "message_cooldown",
+            ),
+            (
+                "level_up_cooldown",
+                self.level_up_cooldown,
+                "levels",
+                "level_up_cooldown",
+            ),
+            (
+                "reward_removal_margin",
+                self.reward_removal_margin,
+                "rewards",
+                "reward_removal_margin",
+            ),
+            (
+                "attachment_bonus_xp",
+                self.attachment_bonus_xp,
+                "content-bonus",
+                "attachment_xp",
+            ),
+            (
+                "embed_bonus_xp",
+                self.embed_bonus_xp,
+                "content-bonus",
+                "embed_xp",
+            ),
+            (
+                "xp_per_message",
+                self.xp_per_message,
+                "xp-per-message",
+                "xp",
+            ),
+            ("reaction_xp", self.reaction_xp, "reaction-xp", "xp"),
+            (
+                "max_reaction_grants",
+                self.max_reaction_grants,
+                "reaction-xp",
+                "max_per_message",
+            ),
+            (
+                "percentile_announcements",
+                self.percentile_announcements,
+                "percentiles",
+                "top",
+            ),
+            ("join_grace", self.join_grace, "join-grace", "minutes"),
+            (
+                "min_account_age",
+                self.min_account_age,
+                "min-account-age",
+                "days",
+            ),
+        ]
+        .map(|(field, value, subcommand, option)| {
+            (field, value.map(i64::from), subcommand, option)
+        });
+        let starting_xp = ("starting_xp", self.starting_xp, "starting-xp", "xp");
+        let options = settings_subcommands();
+        for (field, value, subcommand, option) in bounded.into_iter().chain([starting_xp]) {
+            let Some(value) = value else {
+                continue;
+            };
+            if !option_range(&options, subcommand, option)
+                .is_some_and(|range| range.contains(&value))
+            {
+                return Err(Error::BackupSettingOutOfRange(field, value));
+            }
+        }
+        for name in self.disabled_commands.iter().flatten() {
+            if crate::commands::command_name(name).ok().as_ref() != Some(name) {
+                return Err(Error::InvalidBackupSetting(
+                    "disabled_commands",
+                    name.clone(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Settings subcommands of every command that sets them, which have different names
+fn settings_subcommands() -> Vec<CommandOption> {
+    [
+        ConfigCommand::create_command(),
+        NewcomersCommand::create_command(),
+        AnnouncementsCommand::create_command(),
+    ]
+    .into_iter()
+    .flat_map(|command| command.options)
+    .collect()
+}
+
+/// The values a settings subcommand's integer option accepts
+fn option_range(
+    options: &[CommandOption],
+    subcommand: &str,
+    option: &str,
+) -> Option<RangeInclusive<i64>> {
+    let option = options
+        .iter()
+        .find(|candidate| candidate.name == subcommand)?
+        .options
+        .as_deref()?
+        .iter()
+        .find(|candidate| candidate.name == option)?;
+    let bound = |value: Option<CommandOptionValue>, unbounded| match value {
+        Some(CommandOptionValue::Integer(value)) => Some(value),
+        None => Some(unbounded),
+        Some(CommandOptionValue::Number(_)) => None,
+    };
+    Some(bound(option.min_value, i64::MIN)?..=bound(option.max_value, i64::MAX)?)
+}
+
+fn parse_setting<T>(
+    field: &'static str,
+    value: String,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Result<T, Error> {
+    parse(&value).ok_or(Error::InvalidBackupSetting(field, value))
+}
+
+impl From<&GuildConfig> for ExportedConfig {
+    fn from(config: &GuildConfig) -> Self {
+        Self {
+            level_up_message: config
+                .level_up_message
+                .as_ref()
+                .map(simpleinterpolation::Interpolation::input_value),
+            level_up_channel: config.level_up_channel,
+            ping_on_level_up: config.ping_on_level_up,
+            min_xp_per_message: config.min_xp_per_message,
+            max_xp_per_message: config.max_xp_per_message,
+            message_cooldown: config.cooldown,
+            prune_deleted_rewards: config.prune_deleted_rewards,
+            reward_removal_margin: config.reward_removal_margin,
+            starting_xp: config.starting_xp,
+            count_cooldown_messages: config.count_cooldown_messages,
+            unranked_card: config.unranked_card,
+            attachment_bonus_xp: config.attachment_bonus_xp,
+            embed_bonus_xp: config.embed_bonus_xp,
+            ignore_duplicate_messages: config.ignore_duplicate_messages,
+            level_up_cooldown: config.level_up_cooldown,
+            silent_level_ups: config.silent_level_ups,
+            // The legacy one_at_a_time flag is folded into the strategy it stands for
+            reward_strategy: (config.reward_strategy.is_some() || config.one_at_a_time.is_some())
+                .then(|| config.reward_strategy().as_str().to_string()),
+            level_style: config.level_style.map(|style| style.as_str().to_string()),
+            level_suffix: config.level_suffix.clone(),
+            level_up_style: config
+                .level_up_style
+                .map(|style| style.as_str().to_string()),
+            xp_per_message: config.xp_per_message,
+            rank_bots: config.rank_bots,
+            reaction_xp: config.reaction_xp,
+            max_reaction_grants: config.max_reaction_grants,
+            percentile_announcements: config.percentile_announcements,
+            disabled_commands: config.disabled_commands.clone(),
+            role_alerts: config.role_alerts,
+            join_grace: config.join_grace,
+            min_account_age: config.min_account_age,
+            one_at_a_time: None,
+            unknown: BTreeMap::new(),
+        }
+    }
+}
+
+pub async fn export_config(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+) -> Result<XpdSlashResponse, Error> {
+    let config = xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .unwrap_or_default();
+    let rewards = xpd_database::guild_rewards(&state.db, guild_id).await?;
+    let export = ConfigExport::new(&config, &rewards);
+    let file = serde_json::to_vec_pretty(&export)?;
+    let attachment = HttpAttachment::from_bytes(format!("config-{guild_id}.json"), file, 1);
+    Ok(XpdSlashResponse::with_embed_text(format!(
+        "Here's this server's config, including {} role rewards. \
+        Restore it with `/config import`.",
+        export.rewards.len()
+    ))
+    .attachments([attachment])
+    .ephemeral(true))
+}
+
+/// Replace this guild's config and rewards with a backup's, all at once or not at all.
+pub async fn import_config(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    file: Attachment,
+) -> Result<String, Error> {
+    if usize::try_from(file.size).map_or(true, |size| size > MAX_CONFIG_IMPORT_SIZE) {
+        return Err(Error::InvalidConfigBackup);
+    }
+    let request = state.http.get(file.url).send().await?;
+    request.error_for_status_ref()?;
+    let body = Limited::new(reqwest::Body::from(request), MAX_CONFIG_IMPORT_SIZE)
+        .collect()
+        .await
+        .map_err(|_| Error::RawHttpBody)?
+        .to_bytes();
+
+    let export = ConfigExport::parse(&body)?;
+    let mut notes = export.notes();
+    let level_up_channel = export.config.level_up_channel;
+    let mut update = export.config.into_update()?;
+    if let Some(channel) = level_up_channel {
+        if is_guild_text_channel(&state, guild_id, channel) {
+            update.level_up_channel = Some(channel);
+        } else {
+            notes.push(format!(
+                "The level-up channel <#{channel}> isn't a text channel in this server, \
+                so level-ups will be sent where members level up."
+            ));
+        }
+    }
+
+    let mut txn = state.db.begin().await?;
+    let before = crate::rewards::count_rewards_locked(txn.as_mut(), guild_id).await?;
+    // Backups don't have lockdowns, but importing one mustn't lift a lockdown that's on right now
+    update.xp_lockdown_until = xpd_database::guild_config(txn.as_mut(), guild_id)
+        .await?
+        .and_then(|config| config.xp_lockdown_until);
+    xpd_database::delete_guild_config(txn.as_mut(), guild_id).await?;
+    let config = xpd_database::update_guild_config(txn.as_mut(), guild_id, update).await?;
+    crate::config::validate_config(&config)?;
+    xpd_database::delete_guild_rewards(txn.as_mut(), guild_id).await?;
+    let mut imported_rewards = 0;
+    for reward in &export.rewards {
+        if reward.level < 1 {
+            return Err(Error::BackupSettingOutOfRange("rewards", reward.level));
+        }
+        if is_guild_role(&state, guild_id, reward.role) {
+            xpd_database::add_reward_role(txn.as_mut(), guild_id, reward.level, reward.role)
+                .await?;
+            imported_rewards += 1;
+        } else {
+            notes.push(format!(
+                "Skipped the level {} reward, because role {} isn't in this server.",
+                reward.level, reward.role
+            ));
+        }
+    }
+    let rewards = xpd_database::guild_rewards(txn.as_mut(), guild_id)
+        .await?
+        .len();
+    crate::rewards::check_reward_limit(&state, before, rewards)?;
+    txn.commit().await?;
+
+    state.update_config(guild_id, config).await;
+    state.invalidate_rewards(guild_id).await;
+    let mut msg = format!("Imported config, including {imported_rewards} role rewards.");
+    for note in notes {
+        let _ = write!(msg, "\n- {note}");
+    }
+    Ok(msg)
+}
+
+fn is_guild_text_channel(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    channel: Id<ChannelMarker>,
+) -> bool {
+    state.cache.channel(channel).is_some_and(|channel| {
+        channel.guild_id == Some(guild_id) && channel.kind == ChannelType::GuildText
+    })
+}
+
+fn is_guild_role(state: &SlashState, guild_id: Id<GuildMarker>, role: Id<RoleMarker>) -> bool {
+    role != guild_id.cast()
+        && state
+            .cache
+            .role(role)
+            .is_some_and(|role| role.guild_id() == guild_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use simpleinterpolation::Interpolation;
+    use xpd_common::{LevelStyle, RewardStrategy};
+
+    use super::*;
+
+    #[test]
+    fn exports_only_set_fields() {
+        let config = GuildConfig {
+            level_up_message: Some(Interpolation::new("gg {user_mention}, \\{hi}").unwrap()),
+            cooldown: Some(30),
+            level_style: Some(LevelStyle::Roman),
+            one_at_a_time: Some(true),
+            ..GuildConfig::default()
+        };
+        let rewards = [
+            RoleReward {
+                id: Id::new(9),
+                requirement: 10,
+            },
+            RoleReward {
+                id: Id::new(8),
+                requirement: 5,
+            },
+        ];
+        let export = serde_json::to_value(ConfigExport::new(&config, &rewards)).unwrap();
+        assert_eq!(
+            export,
+            serde_json::json!({
+                "version": CONFIG_EXPORT_VERSION,
+                "config": {
+                    "level_up_message": "gg {user_mention}, \\{hi}",
+                    "message_cooldown": 30,
+                    "reward_strategy": RewardStrategy::OneAtATime.as_str(),
+                    "level_style": "roman",
+                },
+                "rewards": [{"role": "8", "level": 5}, {"role": "9", "level": 10}],
+            })
+        );
+    }
+
+    #[test]
+    fn older_exports_still_read() {
+        let export: ConfigExport = serde_json::from_str(r#"{"version": 1}"#).unwrap();
+        assert_eq!(
+            export,
+            ConfigExport {
+                version: 1,
+                ..ConfigExport::default()
+            }
+        );
+    }
+
+    #[test]
+    fn backups_roundtrip() {
+        let config = GuildConfig {
+            level_up_message: Some(Interpolation::new("gg {user_mention}").unwrap()),
+            level_up_channel: Some(Id::new(4)),
+            max_xp_per_message: Some(40),
+            reward_strategy: Some(RewardStrategy::Ladder),
+            level_suffix: Some("★".to_string()),
+            rank_bots: Some(true),
+            ..GuildConfig::default()
+        };
+        let rewards = [RoleReward {
+            id: Id::new(8),
+            requirement: 5,
+        }];
+        let file = serde_json::to_vec(&ConfigExport::new(&config, &rewards)).unwrap();
+        let export = ConfigExport::parse(&file).unwrap();
+        assert!(export.notes().is_empty());
+        assert_eq!(export.config.level_up_channel, Some(Id::new(4)));
+        assert_eq!(
+            export.rewards,
+            [ExportedReward {
+                role: Id::new(8),
+                level: 5
+            }]
+        );
+        let update = export.config.into_update().unwrap();
+        assert_eq!(
+            update.level_up_message.as_deref(),
+            Some("gg {user_mention}")
+        );
+        assert_eq!(update.level_up_channel, None);
+        assert_eq!(update.max_xp_per_message, Some(40));
+        assert_eq!(update.reward_strategy, Some(RewardStrategy::Ladder));
+        assert_eq!(update.level_suffix.as_deref(), Some("★"));
+        assert_eq!(update.rank_bots, Some(true));
+    }
+
+    #[test]
+    fn imports_check_versions_and_fields() {
+        assert!(matches!(
+            ConfigExport::parse(b"not json"),
+            Err(Error::InvalidConfigBackup)
+        ));
+        assert!(matches!(
+            ConfigExport::parse(br#"{"config": {}}"#),
+            Err(Error::InvalidConfigBackup)
+        ));
+        assert!(matches!(
+            ConfigExport::parse(br#"{"version": 99}"#),
+            Err(Error::UnsupportedConfigBackupVersion(99))
+        ));
+
+        let export = ConfigExport::parse(
+            br#"{"version": 1, "extra": 1, "config": {"one_at_a_time": true, "colour": "red"}}"#,
+        )
+        .unwrap();
+        let notes = export.notes();
+        assert_eq!(notes.len(), 3, "{notes:?}");
+        assert!(notes[0].contains("`extra`"));
+        assert!(notes[1].contains("`colour`"));
+        assert!(notes[2].contains("obsolete"));
+        let update = export.config.into_update().unwrap();
+        assert_eq!(update.reward_strategy, Some(RewardStrategy::OneAtATime));
+
+        let export =
+            ConfigExport::parse(br#"{"version": 1, "config": {"level_style": "hex"}}"#).unwrap();
+        assert!(matches!(
+            export.config.into_update(),
+            Err(Error::InvalidBackupSetting("level_style", value)) if value == "hex"
+        ));
+        let export =
+            ConfigExport::parse(br#"{"version": 1, "config": {"level_up_message": "{nope}"}}"#)
+                .unwrap();
+        assert!(export.config.into_update().is_err());
+    }
+
+    #[test]
+    fn imports_stay_within_command_bounds() {
+        // Every bounded setting at the edge of its option's range, so a renamed option fails here
+        let config = ExportedConfig {
+            min_xp_per_message: Some(0),
+            max_xp_per_message: Some(32767),
+            message_cooldown: Some(28800),
+            level_up_cooldown: Some(28800),
+            reward_removal_margin: Some(1000),
+            attachment_bonus_xp: Some(100),
+            embed_bonus_xp: Some(100),
+            xp_per_message: Some(32767),
+            reaction_xp: Some(1000),
+            max_reaction_grants: Some(1),
+            percentile_announcements: Some(50),
+            join_grace: Some(10080),
+            min_account_age: Some(365),
+            starting_xp: Some(10_000_000),
+            disabled_commands: Some(vec!["leaderboard".to_string()]),
+            ..ExportedConfig::default()
+        };
+        assert!(config.into_update().is_ok());
+
+        let too_big = [
+            br#"{"version": 1, "config": {"message_cooldown": 28801}}"#.as_slice(),
+            br#"{"version": 1, "config": {"min_account_age": -1}}"#,
+            br#"{"version": 1, "config": {"starting_xp": 10000001}}"#,
+            br#"{"version": 1, "config": {"max_reaction_grants": 0}}"#,
+        ];
+        for file in too_big {
+            let export = ConfigExport::parse(file).unwrap();
+            assert!(matches!(
+                export.config.into_update(),
+                Err(Error::BackupSettingOutOfRange(..))
+            ));
+        }
+
+        for name in ["config", "nope", "LEADERBOARD"] {
+            let config = ExportedConfig {
+                disabled_commands: Some(vec![name.to_string()]),
+                ..ExportedConfig::default()
+            };
+            assert!(matches!(
+                config.into_update(),
+                Err(Error::InvalidBackupSetting("disabled_commands", _))
+            ));
+        }
+    }
+}
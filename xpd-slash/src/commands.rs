@@ -0,0 +1,191 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use sqlx::PgPool;
+use twilight_model::{
+    application::command::CommandType,
+    id::{marker::GuildMarker, Id},
+};
+use xpd_common::GuildConfig;
+use xpd_slash_defs::commands::CommandsCommand;
+
+use crate::{Error, SlashState, XpdSlashResponse};
+
+/// Commands guilds can't turn off. Admin and config commands have to keep working
+/// so a guild can't lock itself out, and members can always get their data with `/gdpr`.
+const ALWAYS_ENABLED_COMMANDS: [&str; 4] = ["admin", "config", "commands", "gdpr"];
+
+pub async fn process_commands(
+    command: CommandsCommand,
+    guild_id: Id<GuildMarker>,
+    state: SlashState,
+) -> Result<XpdSlashResponse, Error> {
+    let message = match command {
+        CommandsCommand::Disable(disable) => {
+            set_disabled(&state, guild_id, &disable.command, true).await?
+        }
+        CommandsCommand::Enable(enable) => {
+            set_disabled(&state, guild_id, &enable.command, false).await?
+        }
+        CommandsCommand::List(_) => {
+            let config = xpd_database::guild_config(&state.db, guild_id)
+                .await?
+                .unwrap_or_default();
+            list_disabled(&config.disabled_commands.unwrap_or_default())
+        }
+    };
+    Ok(XpdSlashResponse::with_embed_text(message).ephemeral(true))
+}
+
+async fn set_disabled(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    input: &str,
+    disable: bool,
+) -> Result<String, Error> {
+    let name = command_name(input)?;
+    let changed = xpd_database::set_command_disabled(&state.db, guild_id, &name, disable).await?;
+    if !changed {
+        return Ok(if disable {
+            format!("`/{name}` is already disabled here.")
+        } else {
+            format!("`/{name}` isn't disabled here.")
+        });
+    }
+    let config = xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .unwrap_or_default();
+    state.update_config(guild_id, config).await;
+    Ok(if disable {
+        format!("Members can no longer use `/{name}` here.")
+    } else {
+        format!("Members can use `/{name}` here again.")
+    })
+}
+
+/// Refuse a slash command the guild disabled, before it does anything else.
+pub async fn ensure_enabled(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    name: &str,
+) -> Result<(), Error> {
+    if ALWAYS_ENABLED_COMMANDS.contains(&name) {
+        return Ok(());
+    }
+    let disabled = state.disabled_commands.get(&state.db, guild_id).await?;
+    if disabled.iter().any(|command| command == name) {
+        return Err(Error::CommandDisabled);
+    }
+    Ok(())
+}
+
+/// Each guild's disabled commands, so checking them doesn't cost every command a database query.
+/// [`SlashState::update_config`] keeps it up to date.
+#[derive(Clone, Debug, Default)]
+pub struct DisabledCommands(Arc<Mutex<GuildDisabledCommands>>);
+
+type GuildDisabledCommands = HashMap<Id<GuildMarker>, Arc<[String]>>;
+
+impl DisabledCommands {
+    async fn get(&self, db: &PgPool, guild_id: Id<GuildMarker>) -> Result<Arc<[String]>, Error> {
+        if let Some(disabled) = self.lock().get(&guild_id) {
+            return Ok(Arc::clone(disabled));
+        }
+        let config = xpd_database::guild_config(db, guild_id)
+            .await?
+            .unwrap_or_default();
+        let disabled = Self::from_config(&config);
+        // An update that landed while this was loading is newer, so it wins
+        Ok(Arc::clone(self.lock().entry(guild_id).or_insert(disabled)))
+    }
+
+    pub(crate) fn update(&self, guild_id: Id<GuildMarker>, config: &GuildConfig) {
+        self.lock().insert(guild_id, Self::from_config(config));
+    }
+
+    fn from_config(config: &GuildConfig) -> Arc<[String]> {
+        config.disabled_commands.clone().unwrap_or_default().into()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, GuildDisabledCommands> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Check that `input` names a slash command that can be disabled, and normalize it
+pub fn command_name(input: &str) -> Result<String, Error> {
+    let name = input.trim().trim_start_matches('/').to_lowercase();
+    if ALWAYS_ENABLED_COMMANDS.contains(&name.as_str()) {
+        return Err(Error::CommandAlwaysEnabled(name));
+    }
+    let exists = xpd_slash_defs::get_commands()
+        .iter()
+        .any(|command| command.kind == CommandType::ChatInput && command.name == name);
+    if !exists {
+        return Err(Error::UnknownCommandName(name));
+    }
+    Ok(name)
+}
+
+fn list_disabled(disabled: &[String]) -> String {
+    if disabled.is_empty() {
+        return "No commands are disabled here.".to_string();
+    }
+    let mut names: Vec<String> = disabled.iter().map(|name| format!("`/{name}`")).collect();
+    names.sort_unstable();
+    format!("Disabled commands: {}", names.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_names() {
+        assert_eq!(command_name("leaderboard").unwrap(), "leaderboard");
+        assert_eq!(command_name(" /Rank ").unwrap(), "rank");
+        assert!(matches!(
+            command_name("config"),
+            Err(Error::CommandAlwaysEnabled(_))
+        ));
+        assert!(matches!(
+            command_name("/admin"),
+            Err(Error::CommandAlwaysEnabled(_))
+        ));
+        assert!(matches!(
+            command_name("nope"),
+            Err(Error::UnknownCommandName(_))
+        ));
+        // Context menu commands aren't slash commands
+        assert!(matches!(
+            command_name("Get level"),
+            Err(Error::UnknownCommandName(_))
+        ));
+    }
+
+    #[test]
+    fn listing_disabled() {
+        assert_eq!(list_disabled(&[]), "No commands are disabled here.");
+        assert_eq!(
+            list_disabled(&["whohas".to_string(), "leaderboard".to_string()]),
+            "Disabled commands: `/leaderboard`, `/whohas`"
+        );
+    }
+
+    #[tokio::test]
+    async fn disabled_commands_are_cached() {
+        // Never connects, because every lookup below is answered from the cache
+        let db = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let cache = DisabledCommands::default();
+        let config = GuildConfig {
+            disabled_commands: Some(vec!["rank".to_string()]),
+            ..GuildConfig::default()
+        };
+        cache.update(Id::new(1), &config);
+        assert_eq!(&*cache.get(&db, Id::new(1)).await.unwrap(), ["rank"]);
+        cache.update(Id::new(1), &GuildConfig::default());
+        assert!(cache.get(&db, Id::new(1)).await.unwrap().is_empty());
+    }
+}
@@ -1,26 +1,58 @@
+use std::fmt::Write;
+
+use rand::Rng;
 use simpleinterpolation::Interpolation;
 use twilight_model::{
-    channel::{message::MessageFlags, ChannelType},
+    application::interaction::modal::ModalInteractionData,
+    channel::{
+        message::{
+            component::{ActionRow, TextInput, TextInputStyle},
+            Component, MessageFlags,
+        },
+        ChannelType,
+    },
+    http::interaction::{InteractionResponse, InteractionResponseType},
     id::{
-        marker::{GuildMarker, RoleMarker},
+        marker::{ChannelMarker, GuildMarker, RoleMarker, UserMarker},
         Id,
     },
 };
+use twilight_util::builder::InteractionResponseDataBuilder;
 use xpd_common::{
-    GuildConfig, DEFAULT_MAX_XP_PER_MESSAGE, DEFAULT_MIN_XP_PER_MESSAGE, TEMPLATE_VARIABLES,
+    GuildConfig, LevelUpStyle, RewardStrategy, DEFAULT_MAX_REACTION_GRANTS,
+    DEFAULT_MAX_XP_PER_MESSAGE, DEFAULT_MIN_XP_PER_MESSAGE, MAX_LEVEL_SUFFIX_LEN,
+    TEMPLATE_VARIABLES,
+};
+use xpd_database::{LevelUpWebhook, UpdateGuildConfig};
+use xpd_slash_defs::config::{
+    ConfigCommand, ConfigCommandAuditChannel, ConfigCommandContentBonus, ConfigCommandCopyFrom,
+    ConfigCommandLevelUpStyle, ConfigCommandLevels, ConfigCommandReactionXp, ConfigCommandRewards,
+    ConfigCommandRoleCooldown, ConfigCommandStartingXp, ConfigCommandWebhook,
+    ConfigCommandXpPerMessage,
 };
-use xpd_database::UpdateGuildConfig;
-use xpd_slash_defs::config::{ConfigCommand, ConfigCommandLevels, ConfigCommandRewards};
 use xpd_util::CanAddRole;
 
-use crate::{Error, SlashState, XpdSlashResponse};
+use crate::{manage_card::CUSTOM_CARD_NULL_SENTINEL, Error, SlashState, XpdSlashResponse};
+
+/// Every component and modal this module creates has a custom ID starting with this
+pub const COMPONENT_PREFIX: &str = "config-";
+const LEVEL_UP_MODAL_ID: &str = "config-levelup-message";
+const LEVEL_UP_INPUT_ID: &str = "config-levelup-message-input";
+pub const MAX_LEVEL_UP_MESSAGE_LEN: usize = 512;
 
 pub async fn process_config(
     command: ConfigCommand,
     guild: Id<GuildMarker>,
+    invoker: Id<UserMarker>,
     state: SlashState,
-) -> Result<XpdSlashResponse, Error> {
-    match command {
+) -> Result<InteractionResponse, Error> {
+    let contents = match command {
+        ConfigCommand::LevelUpMessage(_) => return level_up_message_modal(&state, guild).await,
+        ConfigCommand::Export(_) => {
+            return Ok(crate::backup::export_config(state, guild).await?.into());
+        }
+        ConfigCommand::Import(i) => crate::backup::import_config(state, guild, i.file).await,
+        ConfigCommand::Season(s) => crate::season::process_season_config(state, guild, s).await,
         ConfigCommand::Reset(_) => reset_config(state, guild).await,
         ConfigCommand::Get(_) => xpd_database::guild_config(&state.db, guild)
             .await
@@ -29,8 +61,153 @@ pub async fn process_config(
         ConfigCommand::Rewards(r) => process_rewards_config(state, guild, r).await,
         ConfigCommand::Levels(l) => process_levels_config(state, guild, l).await,
         ConfigCommand::PermsCheckup(_) => process_perm_checkup(state, guild).await,
+        ConfigCommand::StartingXp(s) => process_starting_xp(state, guild, s).await,
+        ConfigCommand::XpPerMessage(x) => process_xp_per_message(state, guild, x).await,
+        ConfigCommand::ReactionXp(r) => process_reaction_xp(state, guild, r).await,
+        ConfigCommand::RoleCooldown(r) => process_role_cooldown(state, guild, r).await,
+        ConfigCommand::ContentBonus(c) => process_content_bonus(state, guild, c).await,
+        ConfigCommand::Webhook(w) => process_webhook(state, guild, w).await,
+        ConfigCommand::CopyFrom(c) => process_copy_from(state, guild, invoker, c).await,
+        ConfigCommand::AuditChannel(a) => process_audit_channel(state, guild, a).await,
+        ConfigCommand::LevelUpStyle(l) => process_level_up_style(state, guild, l).await,
+    }?;
+    Ok(XpdSlashResponse::with_embed_text(contents)
+        .flags(MessageFlags::EPHEMERAL)
+        .into())
+}
+
+async fn level_up_message_modal(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+) -> Result<InteractionResponse, Error> {
+    let current = xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .and_then(|config| config.level_up_message)
+        .map(|message| message.input_value());
+    let input = TextInput {
+        custom_id: LEVEL_UP_INPUT_ID.to_string(),
+        label: "Level-up message".to_string(),
+        max_length: Some(MAX_LEVEL_UP_MESSAGE_LEN.try_into()?),
+        min_length: Some(1),
+        placeholder: Some("{user_mention} has reached level {level}!".to_string()),
+        required: Some(true),
+        style: TextInputStyle::Paragraph,
+        value: current,
+    };
+    Ok(InteractionResponse {
+        kind: InteractionResponseType::Modal,
+        data: Some(
+            InteractionResponseDataBuilder::new()
+                .components([Component::ActionRow(ActionRow {
+                    components: vec![Component::TextInput(input)],
+                })])
+                .custom_id(LEVEL_UP_MODAL_ID)
+                .title("Edit level-up message")
+                .build(),
+        ),
+    })
+}
+
+pub async fn process_modal_submit(
+    data: ModalInteractionData,
+    guild_id: Id<GuildMarker>,
+    state: SlashState,
+) -> Result<XpdSlashResponse, Error> {
+    if data.custom_id != LEVEL_UP_MODAL_ID {
+        return Err(Error::UnknownComponent);
+    }
+    let template = data
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find(|field| field.custom_id == LEVEL_UP_INPUT_ID)
+        .and_then(|field| field.value.clone())
+        .ok_or(Error::NoFormField)?;
+    validate_level_up_message(&template)?;
+    let warning = emoji_warning(&template);
+    let new_cfg = UpdateGuildConfig::new().level_up_message(Some(template));
+    let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+    let msg = with_warning(warning, config.to_string());
+    state.update_config(guild_id, config).await;
+    Ok(XpdSlashResponse::with_embed_text(msg).flags(MessageFlags::EPHEMERAL))
+}
+
+pub fn validate_level_up_message(template: &str) -> Result<(), Error> {
+    if template.len() > MAX_LEVEL_UP_MESSAGE_LEN {
+        return Err(Error::LevelUpMessageTooLong);
+    }
+    let interp = Interpolation::new(template)?;
+    for item in interp.variables_used() {
+        if !TEMPLATE_VARIABLES.contains(&item) {
+            return Err(Error::UnknownInterpolationVariable(item.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Custom emoji in a template which Discord won't show as emoji, because they aren't written
+/// like `<:name:id>` or `<a:name:id>`
+fn malformed_emojis(template: &str) -> Vec<&str> {
+    let mut malformed = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('<') {
+        let candidate = &rest[start..];
+        if !(candidate.starts_with("<:") || candidate.starts_with("<a:")) {
+            rest = &candidate[1..];
+            continue;
+        }
+        // Emoji can't contain spaces, so a space means the `>` is missing
+        let end = candidate
+            .find(|c: char| c == '>' || c.is_whitespace())
+            .map_or(candidate.len(), |end| {
+                end + usize::from(candidate[end..].starts_with('>'))
+            });
+        let emoji = &candidate[..end];
+        if !is_custom_emoji(emoji) {
+            malformed.push(emoji);
+        }
+        rest = &candidate[end..];
+    }
+    malformed
+}
+
+fn is_custom_emoji(emoji: &str) -> bool {
+    let Some(inner) = emoji.strip_suffix('>').and_then(|emoji| {
+        emoji
+            .strip_prefix("<a:")
+            .or_else(|| emoji.strip_prefix("<:"))
+    }) else {
+        return false;
+    };
+    let Some((name, id)) = inner.split_once(':') else {
+        return false;
+    };
+    (2..=32).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && id.bytes().all(|b| b.is_ascii_digit())
+        && id.parse::<u64>().is_ok_and(|id| id != 0)
+}
+
+/// A warning to show with the new config if the level-up message has emoji that won't work
+fn emoji_warning(template: &str) -> Option<String> {
+    let malformed = malformed_emojis(template);
+    if malformed.is_empty() {
+        return None;
+    }
+    let list: Vec<String> = malformed.iter().map(|emoji| format!("`{emoji}`")).collect();
+    Some(format!(
+        "⚠️ Some emoji in the level-up message will show up as text: {}. \
+        Custom emoji look like `<:name:123456789012345678>`, \
+        type `\\:name:` in Discord to get one.",
+        list.join(", ")
+    ))
+}
+
+fn with_warning(warning: Option<String>, msg: String) -> String {
+    match warning {
+        Some(warning) => format!("{warning}\n\n{msg}"),
+        None => msg,
     }
-    .map(|s| XpdSlashResponse::with_embed_text(s).flags(MessageFlags::EPHEMERAL))
 }
 
 async fn process_rewards_config(
@@ -38,7 +215,21 @@ async fn process_rewards_config(
     guild_id: Id<GuildMarker>,
     options: ConfigCommandRewards,
 ) -> Result<String, Error> {
-    let new_cfg = UpdateGuildConfig::new().one_at_a_time(options.one_at_a_time);
+    // The legacy flag still picks a strategy, unless a strategy was given explicitly
+    let reward_strategy = options.reward_strategy.map(Into::into).or_else(|| {
+        options.one_at_a_time.map(|one_at_a_time| {
+            if one_at_a_time {
+                RewardStrategy::OneAtATime
+            } else {
+                RewardStrategy::KeepAll
+            }
+        })
+    });
+    let new_cfg = UpdateGuildConfig::new()
+        .one_at_a_time(options.one_at_a_time)
+        .reward_strategy(reward_strategy)
+        .prune_deleted_rewards(options.prune_deleted_rewards)
+        .reward_removal_margin(safecast_to_i16(options.reward_removal_margin)?);
     let mut update_txn = state.db.begin().await?;
     let config = xpd_database::update_guild_config(&mut update_txn, guild_id, new_cfg).await?;
     validate_config(&config)?;
@@ -47,22 +238,350 @@ async fn process_rewards_config(
     Ok("Updated rewards config!".to_string())
 }
 
-async fn process_levels_config(
+async fn process_starting_xp(
     state: SlashState,
     guild_id: Id<GuildMarker>,
-    options: ConfigCommandLevels,
+    options: ConfigCommandStartingXp,
 ) -> Result<String, Error> {
-    if let Some(interp_template) = options.level_up_message.as_ref() {
-        if interp_template.len() > 512 {
-            return Err(Error::LevelUpMessageTooLong);
+    let new_cfg = UpdateGuildConfig::new().starting_xp(Some(options.xp));
+    let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+    state.update_config(guild_id, config).await;
+    Ok(format!(
+        "New members will now start with {} XP. Existing members keep their XP.",
+        options.xp
+    ))
+}
+
+async fn process_xp_per_message(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: ConfigCommandXpPerMessage,
+) -> Result<String, Error> {
+    let xp: i16 = options.xp.try_into()?;
+    if xp.is_negative() {
+        return Err(Error::NegativeXpPerMessage);
+    }
+    let new_cfg = UpdateGuildConfig::new().xp_per_message(Some(xp));
+    let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+    let (min, max) = config.message_xp_range();
+    state.update_config(guild_id, config).await;
+    Ok(if xp == 0 {
+        format!("Messages will give between {min} and {max} XP again.")
+    } else {
+        format!("Every message will now give {xp} XP.")
+    })
+}
+
+async fn process_reaction_xp(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: ConfigCommandReactionXp,
+) -> Result<String, Error> {
+    let xp: i16 = options.xp.try_into()?;
+    let max_grants: Option<i16> = options.max_per_message.map(TryInto::try_into).transpose()?;
+    let new_cfg = UpdateGuildConfig::new()
+        .reaction_xp(Some(xp))
+        .max_reaction_grants(max_grants);
+    let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+    let max_grants = config
+        .max_reaction_grants
+        .unwrap_or(DEFAULT_MAX_REACTION_GRANTS);
+    state.update_config(guild_id, config).await;
+    Ok(if xp == 0 {
+        "Reactions will no longer give XP.".to_string()
+    } else {
+        format!(
+            "Members will now get {xp} XP for each member who reacts to their messages, \
+            for up to {max_grants} reactions per message."
+        )
+    })
+}
+
+async fn process_role_cooldown(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: ConfigCommandRoleCooldown,
+) -> Result<String, Error> {
+    let Some(role) = options.role else {
+        let cooldowns = xpd_database::guild_role_cooldowns(&state.db, guild_id).await?;
+        if cooldowns.is_empty() {
+            return Ok("No roles have their own cooldown.".to_string());
+        }
+        let mut list =
+            String::from("Members with these roles use their highest role's cooldown:\n");
+        for cooldown in cooldowns {
+            writeln!(list, "<@&{}>: {}s", cooldown.role, cooldown.cooldown)?;
+        }
+        return Ok(list);
+    };
+    let message = if options.remove.is_some_and(|remove| remove) {
+        if xpd_database::delete_role_cooldown(&state.db, guild_id, role.id).await? {
+            format!("<@&{}> uses the server's cooldown again.", role.id)
+        } else {
+            format!("<@&{}> doesn't have its own cooldown.", role.id)
         }
-        let interp = Interpolation::new(interp_template.clone())?;
-        for item in interp.variables_used() {
-            if !TEMPLATE_VARIABLES.contains(&item) {
-                return Err(Error::UnknownInterpolationVariable(item.to_string()));
+    } else if let Some(cooldown) = options.cooldown {
+        let cooldown: i16 = cooldown.try_into()?;
+        xpd_database::set_role_cooldown(&state.db, guild_id, role.id, cooldown).await?;
+        format!(
+            "Members with <@&{}> can now earn XP every {cooldown} seconds.",
+            role.id
+        )
+    } else {
+        return Err(Error::NoRoleCooldown);
+    };
+    state.invalidate_role_cooldowns(guild_id).await;
+    Ok(message)
+}
+
+async fn process_content_bonus(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: ConfigCommandContentBonus,
+) -> Result<String, Error> {
+    let new_cfg = UpdateGuildConfig::new()
+        .attachment_bonus_xp(safecast_to_i16(options.attachment_xp)?)
+        .embed_bonus_xp(safecast_to_i16(options.embed_xp)?);
+    let mut update_txn = state.db.begin().await?;
+    let config = xpd_database::update_guild_config(&mut update_txn, guild_id, new_cfg).await?;
+    validate_config(&config)?;
+    update_txn.commit().await?;
+    let msg = config.to_string();
+    state.update_config(guild_id, config).await;
+    Ok(msg)
+}
+
+async fn process_webhook(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: ConfigCommandWebhook,
+) -> Result<String, Error> {
+    if options.remove.is_some_and(|remove| remove) {
+        let removed = xpd_database::delete_level_up_webhook(&state.db, guild_id).await?;
+        return Ok(if removed {
+            "Level-ups will no longer be sent to your webhook.".to_string()
+        } else {
+            "This server doesn't have a level-up webhook.".to_string()
+        });
+    }
+    let Some(url) = options.url else {
+        let webhook = xpd_database::level_up_webhook(&state.db, guild_id).await?;
+        return Ok(webhook.map_or_else(
+            || "This server doesn't have a level-up webhook.".to_string(),
+            |webhook| format!("Level-ups are sent to <{}>.", webhook.url),
+        ));
+    };
+    if !is_https_url(&url) {
+        return Err(Error::InvalidWebhookUrl);
+    }
+    if !is_public_url(&url).await {
+        return Err(Error::WebhookUrlNotPublic);
+    }
+    let webhook = LevelUpWebhook {
+        url,
+        secret: new_webhook_secret(),
+    };
+    xpd_database::set_level_up_webhook(&state.db, guild_id, &webhook).await?;
+    Ok(format!(
+        "Level-ups will now be sent to <{}>.\n\
+        Requests are signed with this secret, which won't be shown again. \
+        Run this command again to get a new one.\n||`{}`||",
+        webhook.url, webhook.secret
+    ))
+}
+
+async fn process_level_up_style(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: ConfigCommandLevelUpStyle,
+) -> Result<String, Error> {
+    let style = options.style.into();
+    let new_cfg = UpdateGuildConfig::new().level_up_style(Some(style));
+    let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+    state.update_config(guild_id, config).await;
+    Ok(match style {
+        LevelUpStyle::Plain => "Level-up messages will be sent as plain text.".to_string(),
+        LevelUpStyle::Embed => "Level-up messages will be sent as embeds.".to_string(),
+    })
+}
+
+async fn process_audit_channel(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: ConfigCommandAuditChannel,
+) -> Result<String, Error> {
+    if options.remove.is_some_and(|remove| remove) {
+        let removed = xpd_database::delete_audit_channel(&state.db, guild_id).await?;
+        return Ok(if removed {
+            "XP changes will no longer be logged.".to_string()
+        } else {
+            "This server doesn't have an audit channel.".to_string()
+        });
+    }
+    let Some(channel) = options.channel else {
+        let channel = xpd_database::audit_channel(&state.db, guild_id).await?;
+        return Ok(channel.map_or_else(
+            || "This server doesn't have an audit channel.".to_string(),
+            |channel| format!("XP changes are logged in <#{channel}>."),
+        ));
+    };
+    if !xpd_util::can_create_message(&state.cache, state.bot_id, channel.id)? {
+        return Err(Error::CantPostInAuditChannel);
+    }
+    xpd_database::set_audit_channel(&state.db, guild_id, channel.id).await?;
+    Ok(format!(
+        "XP changes will now be logged in <#{}>.",
+        channel.id
+    ))
+}
+
+async fn process_copy_from(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    invoker: Id<UserMarker>,
+    options: ConfigCommandCopyFrom,
+) -> Result<String, Error> {
+    let source = crate::admin::parse_guild_id(&options.source)?;
+    if source == guild_id {
+        return Err(Error::CopyFromSameGuild);
+    }
+    if !owns_guild(&state, source, invoker) || !owns_guild(&state, guild_id, invoker) {
+        return Err(Error::NotOwnerOfBothGuilds);
+    }
+
+    let mut notes = Vec::new();
+    let source_channel = xpd_database::guild_config(&state.db, source)
+        .await?
+        .and_then(|config| config.level_up_channel);
+    let level_up_channel = source_channel.and_then(|channel| {
+        let matched = matching_channel(&state, channel, guild_id);
+        if matched.is_none() {
+            notes.push(format!(
+                "The level-up channel <#{channel}> has no text channel with the same name here, \
+                so level-ups will be sent where members level up."
+            ));
+        }
+        matched
+    });
+
+    let mut txn = state.db.begin().await?;
+    let before = crate::rewards::count_rewards_locked(txn.as_mut(), guild_id).await?;
+    if !xpd_database::copy_guild_config(txn.as_mut(), source, guild_id, level_up_channel).await? {
+        notes.push("That server has no config, so no settings were copied.".to_string());
+    }
+    let mut copied_rewards = 0;
+    if options.rewards.unwrap_or(false) {
+        for reward in xpd_database::guild_rewards(txn.as_mut(), source).await? {
+            if let Some(role) = matching_role(&state, reward.id, guild_id) {
+                xpd_database::add_reward_role(txn.as_mut(), guild_id, reward.requirement, role)
+                    .await?;
+                copied_rewards += 1;
+            } else {
+                notes.push(format!(
+                    "Skipped the level {} reward, because no role here has the same name.",
+                    reward.requirement
+                ));
             }
         }
     }
+    if copied_rewards > 0 {
+        let rewards = xpd_database::guild_rewards(txn.as_mut(), guild_id)
+            .await?
+            .len();
+        crate::rewards::check_reward_limit(&state, before, rewards)?;
+    }
+    let config = xpd_database::guild_config(txn.as_mut(), guild_id)
+        .await?
+        .unwrap_or_default();
+    txn.commit().await?;
+
+    state.update_config(guild_id, config).await;
+    if copied_rewards > 0 {
+        state.invalidate_rewards(guild_id).await;
+    }
+    let mut msg =
+        format!("Copied config from server {source}, including {copied_rewards} role rewards.");
+    for note in notes {
+        let _ = write!(msg, "\n- {note}");
+    }
+    Ok(msg)
+}
+
+fn owns_guild(state: &SlashState, guild_id: Id<GuildMarker>, user_id: Id<UserMarker>) -> bool {
+    state
+        .cache
+        .guild(guild_id)
+        .is_some_and(|guild| guild.owner_id() == user_id)
+}
+
+/// Channels and roles belong to one guild, so these find the one with the same name in `target`.
+fn matching_channel(
+    state: &SlashState,
+    channel: Id<ChannelMarker>,
+    target: Id<GuildMarker>,
+) -> Option<Id<ChannelMarker>> {
+    let name = state.cache.channel(channel)?.name.clone()?;
+    state
+        .cache
+        .guild_channels(target)?
+        .iter()
+        .copied()
+        .find(|id| {
+            state.cache.channel(*id).is_some_and(|candidate| {
+                candidate.kind == ChannelType::GuildText
+                    && candidate.name.as_deref() == Some(name.as_str())
+            })
+        })
+}
+
+fn matching_role(
+    state: &SlashState,
+    role: Id<RoleMarker>,
+    target: Id<GuildMarker>,
+) -> Option<Id<RoleMarker>> {
+    let name = state.cache.role(role)?.name.clone();
+    state
+        .cache
+        .guild_roles(target)?
+        .iter()
+        .copied()
+        .find(|id| *id != target.cast() && state.cache.role(*id).is_some_and(|r| r.name == name))
+}
+
+fn is_https_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| url.scheme() == "https" && url.host().is_some())
+}
+
+/// Whether every address the URL's host resolves to is public, see [`xpd_util::is_internal_ip`]
+async fn is_public_url(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return false;
+    };
+    xpd_util::resolve_public_host(host, port).await.is_ok()
+}
+
+fn new_webhook_secret() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    bytes
+        .iter()
+        .fold(String::with_capacity(64), |mut out, byte| {
+            let _ = write!(out, "{byte:02x}");
+            out
+        })
+}
+
+async fn process_levels_config(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: ConfigCommandLevels,
+) -> Result<String, Error> {
+    if let Some(interp_template) = options.level_up_message.as_ref() {
+        validate_level_up_message(interp_template)?;
+    }
+    let warning = options.level_up_message.as_deref().and_then(emoji_warning);
 
     if options
         .level_up_channel
@@ -75,6 +594,8 @@ async fn process_levels_config(
     let max_xp_per_message = safecast_to_i16(options.max_xp_per_message)?;
     let min_xp_per_message = safecast_to_i16(options.min_xp_per_message)?;
     let message_cooldown = safecast_to_i16(options.message_cooldown)?;
+    let level_up_cooldown = safecast_to_i16(options.level_up_cooldown)?;
+    let level_suffix = process_level_suffix(options.level_suffix)?;
 
     let new_cfg = UpdateGuildConfig {
         level_up_message: options.level_up_message,
@@ -84,17 +605,58 @@ async fn process_levels_config(
         min_xp_per_message,
         message_cooldown,
         one_at_a_time: None,
+        prune_deleted_rewards: None,
+        reward_removal_margin: None,
+        starting_xp: None,
+        count_cooldown_messages: options.count_cooldown_messages,
+        unranked_card: options.unranked_card,
+        ignore_duplicate_messages: options.ignore_duplicate_messages,
+        attachment_bonus_xp: None,
+        embed_bonus_xp: None,
+        level_up_cooldown,
+        silent_level_ups: options.silent_level_ups,
+        reward_strategy: None,
+        level_style: options.level_style.map(Into::into),
+        level_suffix,
+        level_up_style: None,
+        xp_per_message: None,
+        xp_lockdown_until: None,
+        rank_bots: options.rank_bots,
+        reaction_xp: None,
+        max_reaction_grants: None,
+        percentile_announcements: None,
+        disabled_commands: None,
+        role_alerts: None,
+        join_grace: None,
+        min_account_age: None,
     };
     let mut validate_txn = state.db.begin().await?;
     let config = xpd_database::update_guild_config(&mut validate_txn, guild_id, new_cfg).await?;
     validate_config(&config)?;
     validate_txn.commit().await?;
-    let msg = config.to_string();
+    let msg = with_warning(warning, config.to_string());
     state.update_config(guild_id, config).await;
 
     Ok(msg)
 }
 
+/// Blank suffixes or the null sentinel clear the suffix, which is stored as an empty string.
+pub fn process_level_suffix(suffix: Option<String>) -> Result<Option<String>, Error> {
+    let Some(suffix) = suffix else {
+        return Ok(None);
+    };
+    if suffix.trim().is_empty() || suffix.trim() == CUSTOM_CARD_NULL_SENTINEL {
+        return Ok(Some(String::new()));
+    }
+    if suffix.chars().count() > MAX_LEVEL_SUFFIX_LEN {
+        return Err(Error::LevelSuffixTooLong(MAX_LEVEL_SUFFIX_LEN));
+    }
+    if suffix.chars().any(char::is_control) {
+        return Err(Error::LevelSuffixNotAllowed);
+    }
+    Ok(Some(suffix))
+}
+
 fn safecast_to_i16(ou16: Option<i64>) -> Result<Option<i16>, Error> {
     ou16.map(TryInto::try_into).transpose().map_err(Into::into)
 }
@@ -105,7 +667,7 @@ async fn reset_config(state: SlashState, guild_id: Id<GuildMarker>) -> Result<St
     Ok("Reset guild reward config, but NOT rewards themselves!".to_string())
 }
 
-fn validate_config(config: &GuildConfig) -> Result<(), GuildConfigErrorReport> {
+pub fn validate_config(config: &GuildConfig) -> Result<(), GuildConfigErrorReport> {
     let max_xp_per_msg = config
         .max_xp_per_message
         .unwrap_or(DEFAULT_MAX_XP_PER_MESSAGE);
@@ -173,3 +735,61 @@ impl std::fmt::Display for EmojiFormatBool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_urls_must_be_https() {
+        assert!(is_https_url("https://example.com/hooks/xp"));
+        assert!(!is_https_url("http://example.com/hooks/xp"));
+        assert!(!is_https_url("example.com"));
+        assert!(!is_https_url("https://"));
+    }
+
+    #[test]
+    fn webhook_secrets_are_hex() {
+        let secret = new_webhook_secret();
+        assert_eq!(secret.len(), 64);
+        assert!(secret.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(secret, new_webhook_secret());
+    }
+
+    #[test]
+    fn custom_emoji() {
+        assert!(
+            malformed_emojis("{user_mention} <:star:123456789012345678> <a:party:42>").is_empty()
+        );
+        // Timestamps and comparisons aren't emoji
+        assert!(malformed_emojis("<t:1700000000:R> 1 < 2").is_empty());
+        assert_eq!(
+            malformed_emojis("<:star> <:star:abc> <a:party:123 level {level} <:x:1>"),
+            ["<:star>", "<:star:abc>", "<a:party:123", "<:x:1>"]
+        );
+        assert_eq!(malformed_emojis("ends with <:star:"), ["<:star:"]);
+        assert!(emoji_warning("<:star:1234>").is_none());
+        assert!(emoji_warning("<:star:0>").unwrap().contains("`<:star:0>`"));
+    }
+
+    #[test]
+    fn level_suffixes() {
+        assert_eq!(process_level_suffix(None).unwrap(), None);
+        assert_eq!(
+            process_level_suffix(Some(" ★".to_string())).unwrap(),
+            Some(" ★".to_string())
+        );
+        assert_eq!(
+            process_level_suffix(Some("NULL".to_string())).unwrap(),
+            Some(String::new())
+        );
+        assert!(matches!(
+            process_level_suffix(Some("a".repeat(MAX_LEVEL_SUFFIX_LEN + 1))),
+            Err(Error::LevelSuffixTooLong(_))
+        ));
+        assert!(matches!(
+            process_level_suffix(Some("a\nb".to_string())),
+            Err(Error::LevelSuffixNotAllowed)
+        ));
+    }
+}
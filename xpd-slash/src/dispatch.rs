@@ -3,22 +3,33 @@ use twilight_model::{
     application::{
         command::CommandType,
         interaction::{
-            application_command::CommandData, Interaction, InteractionData, InteractionType,
+            application_command::CommandData, Interaction, InteractionData,
+            InteractionDataResolved, InteractionType,
         },
     },
     http::interaction::InteractionResponse,
-    id::{marker::GuildMarker, Id},
+    id::{
+        marker::{GuildMarker, UserMarker},
+        Id,
+    },
+    user::User,
+    util::ImageHash,
 };
 use xpd_common::MemberDisplayInfo;
 use xpd_slash_defs::{
     admin::AdminCommand,
+    announcements::AnnouncementsCommand,
     card::{CardCommand, GuildCardCommand},
+    commands::CommandsCommand,
     config::ConfigCommand,
     experience::XpCommand,
     gdpr::GdprCommand,
-    levels::{LeaderboardCommand, RankCommand},
+    levels::{LeaderboardCommand, RankCommand, WhoHasCommand},
     manage::ManageCommand,
+    newcomers::NewcomersCommand,
     rewards::RewardsCommand,
+    summary::WeeklySummaryCommand,
+    timezone::TimezoneCommand,
 };
 
 use crate::{
@@ -26,6 +37,32 @@ use crate::{
     Error, SlashState, XpdSlashResponse,
 };
 
+/// Which handler owns a message component or modal, decided by its custom ID.
+///
+/// New component features should claim a unique custom ID prefix and get a variant here.
+/// Leaderboard IDs predate this scheme and are unprefixed, so they get everything unclaimed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ComponentRoute {
+    RandomCard,
+    Config,
+    Setup,
+    Leaderboard,
+}
+
+impl ComponentRoute {
+    fn of(custom_id: &str) -> Self {
+        if custom_id.starts_with(crate::random_card::COMPONENT_PREFIX) {
+            Self::RandomCard
+        } else if custom_id.starts_with(crate::config::COMPONENT_PREFIX) {
+            Self::Config
+        } else if custom_id.starts_with(crate::setup::COMPONENT_PREFIX) {
+            Self::Setup
+        } else {
+            Self::Leaderboard
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Respondable {
     token: String,
@@ -69,26 +106,58 @@ pub async fn process(
     .ok_or(Error::NoInvoker)?;
 
     let guild_id = interaction.guild_id;
+    // Numbers are formatted for whoever is reading them, or for the guild if we don't know
+    let locale = interaction.locale.or(interaction.guild_locale);
+    let locale = locale.as_deref();
     match data {
         InteractionData::ApplicationCommand(cmd) => {
-            process_app_cmd(state, *cmd, respondable, invoker, guild_id).await
-        }
-        InteractionData::MessageComponent(mcd) => {
-            let Some(original_msg) = interaction.message else {
-                return Err(Error::NoInteractionMessage);
-            };
-            process_message_component(
-                *mcd,
-                original_msg,
-                guild_id.ok_or(Error::NoGuildId)?,
-                invoker.id,
-                state,
-            )
-            .await
-        }
-        InteractionData::ModalSubmit(mid) => {
-            process_modal_submit(mid, guild_id.ok_or(Error::NoGuildId)?, state).await
+            process_app_cmd(state, *cmd, respondable, invoker, guild_id, locale).await
         }
+        InteractionData::MessageComponent(mcd) => match ComponentRoute::of(&mcd.custom_id) {
+            ComponentRoute::RandomCard => {
+                crate::random_card::process_component(
+                    &mcd.custom_id,
+                    invoker,
+                    guild_id,
+                    locale,
+                    state,
+                )
+                .await
+            }
+            ComponentRoute::Config => Err(Error::UnknownComponent),
+            ComponentRoute::Setup => {
+                crate::setup::process_component(*mcd, guild_id.ok_or(Error::NoGuildId)?, state)
+                    .await
+            }
+            ComponentRoute::Leaderboard => {
+                let Some(original_msg) = interaction.message else {
+                    return Err(Error::NoInteractionMessage);
+                };
+                process_message_component(
+                    *mcd,
+                    original_msg,
+                    guild_id.ok_or(Error::NoGuildId)?,
+                    invoker.id,
+                    locale,
+                    state,
+                )
+                .await
+            }
+        },
+        InteractionData::ModalSubmit(mid) => match ComponentRoute::of(&mid.custom_id) {
+            ComponentRoute::Config => {
+                crate::config::process_modal_submit(mid, guild_id.ok_or(Error::NoGuildId)?, state)
+                    .await
+                    .map(Into::into)
+            }
+            ComponentRoute::Setup => {
+                crate::setup::process_modal_submit(mid, guild_id.ok_or(Error::NoGuildId)?, state)
+                    .await
+            }
+            ComponentRoute::RandomCard | ComponentRoute::Leaderboard => {
+                process_modal_submit(mid, guild_id.ok_or(Error::NoGuildId)?, locale, state).await
+            }
+        },
         _ => Err(Error::NoInteractionData),
     }
 }
@@ -99,34 +168,82 @@ async fn process_app_cmd(
     respondable: Respondable,
     invoker: MemberDisplayInfo,
     guild_id: Option<Id<GuildMarker>>,
+    locale: Option<&str>,
 ) -> Result<InteractionResponse, Error> {
+    if guild_id.is_none() && requires_guild(data.kind, &data.name) {
+        return Err(Error::GuildOnlyCommand);
+    }
     match data.kind {
         CommandType::ChatInput => {
-            process_slash_cmd(data, guild_id, respondable, invoker, state).await
-        }
-        CommandType::User => {
-            process_user_cmd(data, guild_id.ok_or(Error::NoGuildId)?, invoker, state)
-                .await
-                .map(Into::into)
-        }
-        CommandType::Message => {
-            process_msg_cmd(data, guild_id.ok_or(Error::NoGuildId)?, invoker, state)
-                .await
-                .map(Into::into)
+            process_slash_cmd(data, guild_id, respondable, invoker, locale, state).await
         }
+        CommandType::User => process_user_cmd(
+            data,
+            guild_id.ok_or(Error::NoGuildId)?,
+            invoker,
+            locale,
+            state,
+        )
+        .await
+        .map(Into::into),
+        CommandType::Message => process_msg_cmd(
+            data,
+            guild_id.ok_or(Error::NoGuildId)?,
+            invoker,
+            locale,
+            state,
+        )
+        .await
+        .map(Into::into),
         _ => Err(Error::WrongInteractionData),
     }
 }
 
+/// Slash commands that can't do anything outside of a server.
+///
+/// Keep this in sync with the commands registered with `dm_permission = false`,
+/// so anything that slips through in a DM gets a clear error instead of a confusing one.
+const GUILD_ONLY_COMMANDS: [&str; 13] = [
+    "rank",
+    "xp",
+    "config",
+    "admin",
+    "guild-card",
+    "leaderboard",
+    "whohas",
+    "manage",
+    "rewards",
+    "setup",
+    "commands",
+    "newcomers",
+    "announcements",
+];
+
+/// Whether a command needs to be run in a server. Context menu commands always look up
+/// a level, so they always do.
+fn requires_guild(kind: CommandType, name: &str) -> bool {
+    match kind {
+        CommandType::ChatInput => GUILD_ONLY_COMMANDS.contains(&name),
+        CommandType::User | CommandType::Message => true,
+        _ => false,
+    }
+}
+
+#[allow(clippy::too_many_lines)]
 async fn process_slash_cmd(
     data: CommandData,
     guild_id: Option<Id<GuildMarker>>,
     respondable: Respondable,
     invoker: MemberDisplayInfo,
+    locale: Option<&str>,
     state: SlashState,
 ) -> Result<InteractionResponse, Error> {
+    if let Some(guild_id) = guild_id {
+        crate::commands::ensure_enabled(&state, guild_id, &data.name).await?;
+    }
     match data.name.as_str() {
         "help" => Ok(crate::help::help().into()),
+        "about" => Ok(crate::help::about(state.build).into()),
         "rank" => {
             let data = RankCommand::from_interaction(data.into())?;
             let target = data.user.map_or_else(
@@ -143,14 +260,59 @@ async fn process_slash_cmd(
                         avatar: ru.resolved.avatar,
                         local_avatar,
                         bot: ru.resolved.bot,
+                        discriminator: Some(ru.resolved.discriminator),
                     }
                 },
             );
+            if let Some(enabled) = data.notify {
+                return crate::levels::set_notify(invoker.id, enabled, state)
+                    .await
+                    .map(Into::into);
+            }
+            if data.history.is_some_and(|v| v) {
+                return crate::levels::get_history(
+                    guild_id.ok_or(Error::NoGuildId)?,
+                    &target,
+                    invoker.id,
+                    data.showoff,
+                    locale,
+                    state,
+                )
+                .await
+                .map(Into::into);
+            }
+            if let Some(days_ago) = data.days_ago {
+                return crate::levels::get_past_rank(
+                    guild_id.ok_or(Error::NoGuildId)?,
+                    &target,
+                    invoker.id,
+                    days_ago,
+                    data.showoff,
+                    locale,
+                    state,
+                )
+                .await
+                .map(Into::into);
+            }
+            if data.estimate.is_some_and(|v| v) {
+                return crate::levels::get_estimate(
+                    guild_id.ok_or(Error::NoGuildId)?,
+                    &target,
+                    invoker.id,
+                    data.showoff,
+                    locale,
+                    state,
+                )
+                .await
+                .map(Into::into);
+            }
             crate::levels::get_level(
                 guild_id.ok_or(Error::NoGuildId)?,
                 target,
                 invoker.id,
                 data.showoff,
+                data.card_only.unwrap_or(false),
+                locale,
                 state,
             )
             .await
@@ -159,21 +321,26 @@ async fn process_slash_cmd(
         "xp" => crate::experience::process_xp(
             XpCommand::from_interaction(data.into())?,
             guild_id.ok_or(Error::NoGuildId)?,
+            invoker.id,
             state,
         )
         .await
         .map(Into::into),
-        "config" => crate::config::process_config(
-            ConfigCommand::from_interaction(data.into())?,
-            guild_id.ok_or(Error::NoGuildId)?,
-            state,
-        )
-        .await
-        .map(Into::into),
+        "config" => {
+            crate::config::process_config(
+                ConfigCommand::from_interaction(data.into())?,
+                guild_id.ok_or(Error::NoGuildId)?,
+                invoker.id,
+                state,
+            )
+            .await
+        }
         "admin" => crate::admin::process_admin(
             AdminCommand::from_interaction(data.into())?,
             guild_id.ok_or(Error::NoGuildId)?,
+            respondable,
             invoker.id,
+            locale,
             state,
         )
         .await
@@ -183,6 +350,7 @@ async fn process_slash_cmd(
             invoker,
             &state,
             guild_id,
+            locale,
         )
         .await
         .map(Into::into),
@@ -190,6 +358,7 @@ async fn process_slash_cmd(
             GuildCardCommand::from_interaction(data.into())?,
             &state,
             guild_id.ok_or(Error::NoGuildId)?,
+            locale,
         )
         .await?
         .into()),
@@ -203,13 +372,24 @@ async fn process_slash_cmd(
                 state,
                 guild_id.ok_or(Error::NoGuildId)?,
                 LeaderboardCommand::from_interaction(data.into())?,
+                invoker.id,
+                locale,
             )
             .await
         }
+        "whohas" => crate::leaderboard::who_has(
+            state,
+            guild_id.ok_or(Error::NoGuildId)?,
+            WhoHasCommand::from_interaction(data.into())?,
+            locale,
+        )
+        .await
+        .map(Into::into),
         "manage" => crate::manager::process_manage(
             ManageCommand::from_interaction(data.into())?,
             guild_id.ok_or(Error::NoGuildId)?,
             respondable,
+            locale,
             state,
         )
         .await
@@ -217,6 +397,45 @@ async fn process_slash_cmd(
         "rewards" => crate::rewards::process_rewards(
             RewardsCommand::from_interaction(data.into())?,
             guild_id.ok_or(Error::NoGuildId)?,
+            invoker.id,
+            state,
+        )
+        .await
+        .map(Into::into),
+        "timezone" => crate::timezone::process_timezone(
+            TimezoneCommand::from_interaction(data.into())?,
+            invoker.id,
+            state,
+        )
+        .await
+        .map(Into::into),
+        "setup" => crate::setup::process_setup(guild_id.ok_or(Error::NoGuildId)?, state)
+            .await
+            .map(Into::into),
+        "commands" => crate::commands::process_commands(
+            CommandsCommand::from_interaction(data.into())?,
+            guild_id.ok_or(Error::NoGuildId)?,
+            state,
+        )
+        .await
+        .map(Into::into),
+        "newcomers" => crate::newcomers::process_newcomers(
+            NewcomersCommand::from_interaction(data.into())?,
+            guild_id.ok_or(Error::NoGuildId)?,
+            state,
+        )
+        .await
+        .map(Into::into),
+        "announcements" => crate::announcements::process_announcements(
+            AnnouncementsCommand::from_interaction(data.into())?,
+            guild_id.ok_or(Error::NoGuildId)?,
+            state,
+        )
+        .await
+        .map(Into::into),
+        "weekly-summary" => crate::summary::process_weekly_summary(
+            WeeklySummaryCommand::from_interaction(data.into())?,
+            invoker.id,
             state,
         )
         .await
@@ -226,44 +445,177 @@ async fn process_slash_cmd(
 }
 
 const DEFAULT_SHOWOFF: Option<bool> = None;
+/// Context menus have no options, so they always include the details next to the card
+const DEFAULT_CARD_ONLY: bool = false;
 
 async fn process_user_cmd(
     data: CommandData,
     guild_id: Id<GuildMarker>,
     invoker: MemberDisplayInfo,
+    locale: Option<&str>,
     state: SlashState,
 ) -> Result<XpdSlashResponse, Error> {
-    let msg_id = data.target_id.ok_or(Error::NoMessageTargetId)?;
+    // These show the same card as `/rank`, so they're off whenever it is
+    crate::commands::ensure_enabled(&state, guild_id, "rank").await?;
+    let user_id = data.target_id.ok_or(Error::NoMessageTargetId)?.cast();
     let resolved = data.resolved.as_ref().ok_or(Error::NoResolvedData)?;
-    let user = resolved
-        .users
-        .get(&msg_id.cast())
-        .ok_or(Error::NoTarget)?
-        .clone();
-
-    let nick = resolved.members.get(&user.id).and_then(|v| v.nick.clone());
-    let target = MemberDisplayInfo::from(user).with_nick(nick);
+    let user = resolved.users.get(&user_id).cloned();
+    let target = match resolve_target(&state, guild_id, resolved, user_id, user) {
+        Some(target) => target,
+        None => crate::members::member_display_info(&state, guild_id, user_id).await?,
+    };
 
-    crate::levels::get_level(guild_id, target, invoker.id, DEFAULT_SHOWOFF, state).await
+    crate::levels::get_level(
+        guild_id,
+        target,
+        invoker.id,
+        DEFAULT_SHOWOFF,
+        DEFAULT_CARD_ONLY,
+        locale,
+        state,
+    )
+    .await
 }
 
 async fn process_msg_cmd(
     data: CommandData,
     guild_id: Id<GuildMarker>,
     invoker: MemberDisplayInfo,
+    locale: Option<&str>,
     state: SlashState,
 ) -> Result<XpdSlashResponse, Error> {
+    // These show the same card as `/rank`, so they're off whenever it is
+    crate::commands::ensure_enabled(&state, guild_id, "rank").await?;
     let msg_id = data.target_id.ok_or(Error::NoMessageTargetId)?;
-    let resolved = &data.resolved.as_ref().ok_or(Error::NoResolvedData)?;
-    let user = resolved
+    let resolved = data.resolved.as_ref().ok_or(Error::NoResolvedData)?;
+    let author = resolved
         .messages
         .get(&msg_id.cast())
         .ok_or(Error::NoTarget)?
         .author
         .clone();
+    let target = resolve_target(&state, guild_id, resolved, author.id, Some(author))
+        .ok_or(Error::NoTarget)?;
 
-    let nick = resolved.members.get(&user.id).and_then(|v| v.nick.clone());
-    let target = MemberDisplayInfo::from(user).with_nick(nick);
+    crate::levels::get_level(
+        guild_id,
+        target,
+        invoker.id,
+        DEFAULT_SHOWOFF,
+        DEFAULT_CARD_ONLY,
+        locale,
+        state,
+    )
+    .await
+}
 
-    crate::levels::get_level(guild_id, target, invoker.id, DEFAULT_SHOWOFF, state).await
+/// Discord doesn't always send both the user and the member for a context menu target,
+/// so use whichever of them we have, and check the cache for the member before giving up.
+fn resolve_target(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    resolved: &InteractionDataResolved,
+    user_id: Id<UserMarker>,
+    user: Option<User>,
+) -> Option<MemberDisplayInfo> {
+    let member = resolved
+        .members
+        .get(&user_id)
+        .map(|member| (member.nick.clone(), member.avatar))
+        .or_else(|| {
+            state
+                .cache
+                .member(guild_id, user_id)
+                .map(|member| (member.nick().map(ToOwned::to_owned), member.avatar()))
+        });
+    target_display_info(user_id, user, member)
+}
+
+fn target_display_info(
+    user_id: Id<UserMarker>,
+    user: Option<User>,
+    member: Option<(Option<String>, Option<ImageHash>)>,
+) -> Option<MemberDisplayInfo> {
+    match (user, member) {
+        (Some(user), member) => {
+            let (nick, local_avatar) = member.unwrap_or((None, None));
+            Some(MemberDisplayInfo {
+                local_avatar,
+                ..MemberDisplayInfo::from(user).with_nick(nick)
+            })
+        }
+        (None, Some((nick, local_avatar))) => Some(MemberDisplayInfo {
+            id: user_id,
+            name: nick.clone().unwrap_or_else(|| user_id.to_string()),
+            global_name: None,
+            nick,
+            avatar: None,
+            local_avatar,
+            bot: false,
+            discriminator: None,
+        }),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn component_routes() {
+        assert_eq!(
+            ComponentRoute::of("card-random-reroll"),
+            ComponentRoute::RandomCard
+        );
+        assert_eq!(
+            ComponentRoute::of("card-random-save:abc"),
+            ComponentRoute::RandomCard
+        );
+        assert_eq!(
+            ComponentRoute::of("config-levelup-message"),
+            ComponentRoute::Config
+        );
+        assert_eq!(
+            ComponentRoute::of("setup-skip:channel"),
+            ComponentRoute::Setup
+        );
+        assert_eq!(ComponentRoute::of("3:1234"), ComponentRoute::Leaderboard);
+        assert_eq!(
+            ComponentRoute::of("delete_leaderboard"),
+            ComponentRoute::Leaderboard
+        );
+    }
+
+    #[test]
+    fn guild_only_commands() {
+        assert!(requires_guild(CommandType::ChatInput, "rank"));
+        assert!(requires_guild(CommandType::ChatInput, "guild-card"));
+        assert!(requires_guild(CommandType::User, "Get level"));
+        assert!(requires_guild(CommandType::Message, "Get author level"));
+        assert!(!requires_guild(CommandType::ChatInput, "help"));
+        assert!(!requires_guild(CommandType::ChatInput, "card"));
+        assert!(!requires_guild(CommandType::ChatInput, "gdpr"));
+        assert!(!requires_guild(CommandType::ChatInput, "timezone"));
+    }
+
+    #[test]
+    fn member_without_user() {
+        let id = Id::new(1);
+        let info = target_display_info(id, None, Some((Some("nick".to_string()), None))).unwrap();
+        assert_eq!(info.id, id);
+        assert_eq!(info.name, "nick");
+        assert_eq!(info.nick.as_deref(), Some("nick"));
+        // There's no username to tag them with
+        assert_eq!(info.tag(), "nick");
+
+        let info = target_display_info(id, None, Some((None, None))).unwrap();
+        assert_eq!(info.name, "1");
+        assert_eq!(info.tag(), "1");
+    }
+
+    #[test]
+    fn nothing_resolved() {
+        assert!(target_display_info(Id::new(1), None, None).is_none());
+    }
 }
@@ -0,0 +1,175 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use twilight_model::id::{marker::GuildMarker, Id};
+use xpd_common::GuildConfig;
+use xpd_database::LevelCount;
+
+use crate::{Error, SlashState};
+
+/// How long a counted distribution is shown before the guild's levels are counted again
+const DISTRIBUTION_TTL: Duration = Duration::from_mins(1);
+
+/// Members above this level are counted at it, which keeps the threshold list short
+const HIGHEST_COUNTED_LEVEL: u64 = 1000;
+
+/// The most rows the chart has, levels are grouped together to fit
+const CHART_ROWS: i64 = 12;
+
+/// How many characters the longest bar in the chart is
+const CHART_WIDTH: i64 = 20;
+
+/// Recently counted level distributions, because counting every member of a big guild is slow.
+#[derive(Clone, Debug, Default)]
+pub struct LevelDistributionCache(Arc<Mutex<HashMap<Id<GuildMarker>, CountedLevels>>>);
+
+type CountedLevels = (Instant, Vec<LevelCount>);
+
+impl LevelDistributionCache {
+    async fn get(
+        &self,
+        state: &SlashState,
+        guild_id: Id<GuildMarker>,
+    ) -> Result<Vec<LevelCount>, Error> {
+        let cached = self
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&guild_id)
+            .cloned();
+        if let Some((counted, levels)) = cached {
+            if counted.elapsed() < DISTRIBUTION_TTL {
+                return Ok(levels);
+            }
+        }
+        let thresholds: Vec<i64> = (1..=HIGHEST_COUNTED_LEVEL)
+            .map(|level| i64::try_from(mee6::xp_needed_for_level(level)).unwrap_or(i64::MAX))
+            .collect();
+        let levels = xpd_database::level_distribution(&state.db, guild_id, &thresholds).await?;
+        let mut guilds = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        guilds.retain(|_, (counted, _)| counted.elapsed() < DISTRIBUTION_TTL);
+        guilds.insert(guild_id, (Instant::now(), levels.clone()));
+        drop(guilds);
+        Ok(levels)
+    }
+}
+
+/// Chart how many members are at each level in a guild
+pub async fn level_distribution(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    locale: Option<&str>,
+) -> Result<String, Error> {
+    let levels = state.level_distributions.get(state, guild_id).await?;
+    if levels.is_empty() {
+        return Ok("Nobody has any XP in this server yet!".to_string());
+    }
+    let config = xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .unwrap_or_default();
+    let members: i64 = levels.iter().map(|count| count.members).sum();
+    Ok(format!(
+        "Members at each level, out of {} ranked\n{}\n-# Counted within the last minute",
+        xpd_common::format_int(locale, members),
+        distribution_chart(&levels, &config, locale)
+    ))
+}
+
+/// Lay out level counts as a monospace bar chart, grouping levels so it stays short.
+/// `levels` must be sorted by level, and not be empty.
+fn distribution_chart(levels: &[LevelCount], config: &GuildConfig, locale: Option<&str>) -> String {
+    let highest = levels.last().map_or(0, |count| count.level);
+    let group_size = highest / CHART_ROWS + 1;
+    let mut groups = vec![0; usize::try_from(highest / group_size + 1).unwrap_or(0)];
+    for count in levels {
+        if let Some(group) = usize::try_from(count.level / group_size)
+            .ok()
+            .and_then(|group| groups.get_mut(group))
+        {
+            *group += count.members;
+        }
+    }
+    let display = |level: i64| config.display_level(u64::try_from(level).unwrap_or(0));
+    let labels: Vec<String> = (0..)
+        .step_by(usize::try_from(group_size).unwrap_or(1))
+        .take(groups.len())
+        .map(|start| {
+            if group_size == 1 {
+                display(start)
+            } else {
+                format!("{}-{}", display(start), display(start + group_size - 1))
+            }
+        })
+        .collect();
+    let label_width = labels
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let most = groups.iter().copied().max().unwrap_or(0).max(1);
+
+    let mut chart = String::from("```\n");
+    for (label, members) in labels.iter().zip(groups) {
+        // Anyone at all gets a sliver, so small groups don't look empty
+        let bar = if members == 0 {
+            0
+        } else {
+            (members * CHART_WIDTH / most).max(1)
+        };
+        let bar = "█".repeat(usize::try_from(bar).unwrap_or(0));
+        let line = format!(
+            "{label:<label_width$}  {bar:<width$}  {}",
+            xpd_common::format_int(locale, members),
+            width = usize::try_from(CHART_WIDTH).unwrap_or(0)
+        );
+        chart.push_str(line.trim_end());
+        chart.push('\n');
+    }
+    chart.push_str("```");
+    chart
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(level: i64, members: i64) -> LevelCount {
+        LevelCount { level, members }
+    }
+
+    #[test]
+    fn single_level_rows() {
+        let chart = distribution_chart(
+            &[count(0, 10), count(2, 5), count(3, 1)],
+            &GuildConfig::default(),
+            Some("en-US"),
+        );
+        assert_eq!(
+            chart,
+            "```\n\
+            0  ████████████████████  10\n\
+            1                        0\n\
+            2  ██████████            5\n\
+            3  ██                    1\n\
+            ```"
+        );
+    }
+
+    #[test]
+    fn grouped_rows() {
+        let chart = distribution_chart(
+            &[count(0, 3), count(1, 1), count(13, 1_500), count(30, 2)],
+            &GuildConfig::default(),
+            Some("en-US"),
+        );
+        let lines: Vec<&str> = chart.lines().collect();
+        // 3 levels to a row, 0 through 32
+        assert_eq!(lines.len(), 11 + 2);
+        assert_eq!(lines[1], "0-2    █                     4");
+        assert_eq!(lines[5], "12-14  ████████████████████  1,500");
+        assert_eq!(lines[11], "30-32  █                     2");
+    }
+}
@@ -56,8 +56,32 @@ pub enum Error {
     NoInteractionData,
     #[error("Discord did not send a guild ID!")]
     NoGuildId,
+    #[error("This command only works in a server!")]
+    GuildOnlyCommand,
     #[error("CSV encountered an IntoInner error")]
     CsvIntoInner,
+    #[error("Refusing to download an image from a host that is not allowed: {0}")]
+    DisallowedImageHost(String),
+    #[error("Webhook URLs must be valid and start with `https://`.")]
+    InvalidWebhookUrl,
+    #[error("Webhook URLs must point to a public address, not a private network or this machine.")]
+    WebhookUrlNotPublic,
+    #[error(
+        "`{0}` is not a valid server ID! Server IDs are long numbers, like `302094807046684672`."
+    )]
+    InvalidGuildId(String),
+    #[error("You must own both servers to copy config between them!")]
+    NotOwnerOfBothGuilds,
+    #[error("A server can't copy its own config!")]
+    CopyFromSameGuild,
+    #[error("That file isn't a config backup from `/config export`!")]
+    InvalidConfigBackup,
+    #[error("That backup is from a newer version ({0}) of the bot, which I can't read yet!")]
+    UnsupportedConfigBackupVersion(u32),
+    #[error("The backup's `{0}` setting has a value I don't know: `{1}`")]
+    InvalidBackupSetting(&'static str, String),
+    #[error("The backup's `{0}` setting of {1} is outside what its command allows")]
+    BackupSettingOutOfRange(&'static str, i64),
     #[error("Invalid font")]
     InvalidFont,
     #[error("Invalid card")]
@@ -69,10 +93,16 @@ pub enum Error {
     #[error("That file is too big to import automatically. Please email valk@randomairborne.dev or [join our support server](https://discord.com/invite/KWkPYxqNKe) to set up imports for your server."
     )]
     ImportFileTooBig,
-    #[error("This page does not exist!")]
-    NoUsersForPage,
-    #[error("This page does not exist!")]
-    PageDoesNotExist,
+    #[error("This server is already importing level data! Wait for that import to finish first.")]
+    ImportAlreadyRunning,
+    #[error(
+        "Too many servers are importing level data right now. Please try again in a few minutes."
+    )]
+    TooManyImports,
+    #[error("Level imports are paused for maintenance right now. Try again later!")]
+    ImportsPaused,
+    #[error("CSV imports must start with a `user_id,xp` header row!")]
+    InvalidCsvImportHeader,
     #[error("This modal did not contain any action rows!")]
     NoModalActionRow,
     #[error("This modal did not contain the required form field!")]
@@ -81,8 +111,8 @@ pub enum Error {
     NoDestinationInComponent,
     #[error("HTTP body error!")]
     RawHttpBody,
-    #[error("That would make this user's XP negative!")]
-    XpWouldBeNegative,
+    #[error("I don't have permission to take away this member's reward roles!")]
+    CantRevokeRewards,
     #[error("Unknown variable `{0}` used in level-up message!")]
     UnknownInterpolationVariable(String),
     #[error("Level up message must be less than 512 characters!")]
@@ -93,20 +123,80 @@ pub enum Error {
     UnknownCard,
     #[error("That toy does not exist!")]
     UnknownToy,
+    #[error("That background does not exist!")]
+    UnknownBackground,
     #[error("That font does not exist!")]
     UnknownFont,
+    #[error("Card subtitles must be at most 32 characters!")]
+    SubtitleTooLong,
+    #[error("Card subtitles can't contain links or line breaks!")]
+    SubtitleNotAllowed,
+    #[error("XP per message can't be negative!")]
+    NegativeXpPerMessage,
+    #[error("Level suffixes can't be longer than {0} characters!")]
+    LevelSuffixTooLong(usize),
+    #[error("Level suffixes can't contain line breaks!")]
+    LevelSuffixNotAllowed,
+    #[error("Your card was not changed, because some text would be hard to read:\n{0}")]
+    LowContrast(String),
+    #[error("`{0}` is not a known timezone! Try picking one from the suggestions.")]
+    UnknownTimezone(String),
+    #[error("This command is disabled here!")]
+    CommandDisabled,
+    #[error("`/{0}` can't be disabled!")]
+    CommandAlwaysEnabled(String),
+    #[error("There's no `/{0}` command!")]
+    UnknownCommandName(String),
     #[error("There is no autocomplete for that command.")]
     NoAutocompleteForCommand,
     #[error("Discord didn't send an interaction message for that message component")]
     NoInteractionMessage,
     #[error("Discord sent an interaction response message without interaction invocation data")]
     NoInteractionInvocationOnInteractionMessage,
+    #[error("Discord sent a message component this bot doesn't know about!")]
+    UnknownComponent,
     #[error("You didn't create this leaderboard.")]
     NotYourLeaderboard,
-    #[error("Bots do not have leveling data. If one does somehow, you can still use /xp experience reset on it.")]
+    #[error("Bots do not have leveling data, unless `rank_bots` is turned on in `/config levels`. If one does somehow, you can still use /xp experience reset on it.")]
     BotsDontLevel,
+    #[error("That user hasn't earned any XP yet, so they aren't on the leaderboard.")]
+    NotOnLeaderboard,
     #[error("Nobody in this server is ranked yet.")]
     NoRanksYet,
+    #[error("Nobody with that role is ranked yet.")]
+    NoRanksWithRole,
+    #[error("Nobody in this server has gained XP today yet.")]
+    NoGainsToday,
     #[error("This user does not have a most recent message.")]
     NoLastMessage,
+    #[error("Pick a cooldown for the role, or use `remove:True` to take its cooldown away!")]
+    NoRoleCooldown,
+    #[error("I can't send messages in that channel, so it can't be the audit channel!")]
+    CantPostInAuditChannel,
+    #[error("I can't send messages in that channel, so it can't be the level-up channel!")]
+    CantPostInLevelUpChannel,
+    #[error("The reward level must be a whole number of at least 1!")]
+    InvalidRewardLevel,
+    #[error("Give at least one role and the level to grant it at!")]
+    NoBulkRewards,
+    #[error(
+        "Servers can have at most {0} role rewards! Remove some with `/rewards remove` first."
+    )]
+    TooManyRewards(usize),
+    #[error(
+        "Servers can have at most {0} one-time rewards! Remove some with `/rewards once-remove` first."
+    )]
+    TooManyOneTimeRewards(usize),
+    #[error("One-time rewards need a name!")]
+    EmptyRewardName,
+    #[error("Every role needs a level to grant it at, and every level needs a role!")]
+    UnpairedBulkReward,
+    #[error("<@&{0}> was given more than one level, but a role can only be a reward once!")]
+    DuplicateBulkReward(twilight_model::id::Id<twilight_model::id::marker::RoleMarker>),
+    #[error("The minimum XP must not be above the maximum XP!")]
+    InvalidXpRange,
+    #[error("Only the first {0} results can be listed, narrow the XP range instead.")]
+    XpRangeTooDeep(i64),
+    #[error("I couldn't look up that member, please try again!")]
+    MemberLookupFailed,
 }
@@ -1,23 +1,36 @@
+use std::{
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use twilight_model::{
     channel::message::AllowedMentions,
     id::{
         marker::{GuildMarker, UserMarker},
         Id,
     },
+    util::Timestamp,
 };
 use twilight_util::builder::embed::EmbedBuilder;
-use xpd_slash_defs::experience::XpCommand;
+use xpd_common::{RewardRoleChanges, XP_LOCKDOWN_INDEFINITE};
+use xpd_database::UpdateGuildConfig;
+use xpd_slash_defs::experience::{XpCommand, XpCommandLockdown};
+use xpd_util::LogError;
 
-use crate::{Error, SlashState, XpdSlashResponse};
+use crate::{http::DiscordHttp, Error, SlashState, XpdSlashResponse};
 
 pub async fn process_xp(
     data: XpCommand,
     guild_id: Id<GuildMarker>,
+    invoker: Id<UserMarker>,
     state: SlashState,
 ) -> Result<XpdSlashResponse, Error> {
-    let contents = process_experience(data, guild_id, state).await?;
+    let (contents, audit) = process_experience(data, guild_id, invoker, state.clone()).await?;
+    log_xp_change(&state, guild_id, &audit)
+        .await
+        .log_error("Failed to log XP change to audit channel");
     Ok(XpdSlashResponse::new()
-        .allowed_mentions_o(Some(AllowedMentions::default()))
+        .no_pings()
         .ephemeral(true)
         .embeds([EmbedBuilder::new().description(contents).build()]))
 }
@@ -25,69 +38,309 @@ pub async fn process_xp(
 async fn process_experience(
     data: XpCommand,
     guild_id: Id<GuildMarker>,
+    invoker: Id<UserMarker>,
     state: SlashState,
-) -> Result<String, Error> {
-    if !allowed_command_for_target(&data) {
+) -> Result<(String, XpAudit), Error> {
+    if !allowed_command_for_target(&data) && !crate::levels::rank_bots(&state, guild_id).await? {
         return Err(Error::BotsDontLevel);
     }
     match data {
         XpCommand::Add(add) => {
-            modify_user_xp(state, guild_id, add.user.resolved.id, add.amount).await
+            modify_user_xp(state, guild_id, invoker, add.user.resolved.id, add.amount).await
         }
         XpCommand::Remove(rm) => {
-            modify_user_xp(state, guild_id, rm.user.resolved.id, -rm.amount).await
+            modify_user_xp(state, guild_id, invoker, rm.user.resolved.id, -rm.amount).await
+        }
+        XpCommand::Reset(reset) => {
+            reset_user_xp(state, guild_id, invoker, reset.user.resolved.id).await
+        }
+        XpCommand::Set(set) => {
+            set_user_xp(state, guild_id, invoker, set.user.resolved.id, set.xp).await
+        }
+        XpCommand::Lockdown(lockdown) => set_lockdown(state, guild_id, invoker, lockdown).await,
+    }
+}
+
+/// What an `/xp` command tells the guild's audit channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum XpAudit {
+    Change(XpChange),
+    /// XP gain was locked down until `until` (Unix seconds), or lifted again if that's None
+    Lockdown {
+        invoker: Id<UserMarker>,
+        until: Option<i64>,
+    },
+}
+
+impl XpAudit {
+    const fn title(&self) -> &'static str {
+        match self {
+            Self::Change(_) => "XP changed",
+            Self::Lockdown { .. } => "XP lockdown",
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            Self::Change(change) => change.description(),
+            Self::Lockdown {
+                invoker,
+                until: Some(XP_LOCKDOWN_INDEFINITE),
+            } => format!("<@{invoker}> locked down XP gain until it's turned off"),
+            Self::Lockdown {
+                invoker,
+                until: Some(until),
+            } => format!("<@{invoker}> locked down XP gain until <t:{until}:f>"),
+            Self::Lockdown {
+                invoker,
+                until: None,
+            } => format!("<@{invoker}> lifted the XP lockdown"),
         }
-        XpCommand::Reset(reset) => reset_user_xp(state, guild_id, reset.user.resolved.id).await,
-        XpCommand::Set(set) => set_user_xp(state, guild_id, set.user.resolved.id, set.xp).await,
     }
 }
 
+/// A manual change to a member's XP, which is logged to the guild's audit channel if it has one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct XpChange {
+    action: XpChangeAction,
+    invoker: Id<UserMarker>,
+    user: Id<UserMarker>,
+    before: i64,
+    after: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum XpChangeAction {
+    Add,
+    Remove,
+    Reset,
+    Set,
+}
+
+impl XpChange {
+    fn description(&self) -> String {
+        let Self {
+            invoker,
+            user,
+            before,
+            after,
+            ..
+        } = self;
+        let action = match self.action {
+            XpChangeAction::Add => format!("added {} XP to", after - before),
+            XpChangeAction::Remove => format!("removed {} XP from", before - after),
+            XpChangeAction::Reset => "reset".to_string(),
+            XpChangeAction::Set => "set the XP of".to_string(),
+        };
+        format!("<@{invoker}> {action} <@{user}>\n**XP:** {before} → {after}")
+    }
+}
+
+/// Posts an XP change to the guild's audit channel. Guilds without one don't log anything.
+async fn log_xp_change(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    audit: &XpAudit,
+) -> Result<(), Error> {
+    let Some(channel) = xpd_database::audit_channel(&state.db, guild_id).await? else {
+        return Ok(());
+    };
+    if !xpd_util::can_create_message(&state.cache, state.bot_id, channel)? {
+        warn!(?channel, guild = ?guild_id, "Could not post to audit channel");
+        return Ok(());
+    }
+    let now = unix_now();
+    let mut embed = EmbedBuilder::new()
+        .title(audit.title())
+        .description(audit.description());
+    if let Ok(timestamp) = Timestamp::from_secs(now) {
+        embed = embed.timestamp(timestamp);
+    }
+    let embed = embed.build();
+    state
+        .client
+        .create_message(channel)
+        .allowed_mentions(Some(&AllowedMentions::default()))
+        .embeds(&[embed])
+        .await?;
+    Ok(())
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| {
+            i64::try_from(since.as_secs()).unwrap_or(i64::MAX)
+        })
+}
+
+async fn set_lockdown(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    invoker: Id<UserMarker>,
+    options: XpCommandLockdown,
+) -> Result<(String, XpAudit), Error> {
+    let until = options.enabled.then(|| {
+        options.minutes.map_or(XP_LOCKDOWN_INDEFINITE, |minutes| {
+            unix_now().saturating_add(minutes.saturating_mul(60))
+        })
+    });
+    let new_cfg = UpdateGuildConfig::new().xp_lockdown_until(Some(until.unwrap_or(0)));
+    let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+    state.update_config(guild_id, config).await;
+    let message = match until {
+        Some(XP_LOCKDOWN_INDEFINITE) => {
+            "Nobody can earn XP from messages until you run `/xp lockdown enabled:False`."
+                .to_string()
+        }
+        Some(until) => format!("Nobody can earn XP from messages until <t:{until}:f>."),
+        None => "Members can earn XP from messages again.".to_string(),
+    };
+    Ok((message, XpAudit::Lockdown { invoker, until }))
+}
+
 async fn modify_user_xp(
     state: SlashState,
     guild_id: Id<GuildMarker>,
+    invoker: Id<UserMarker>,
     user_id: Id<UserMarker>,
     amount: i64,
-) -> Result<String, Error> {
+) -> Result<(String, XpAudit), Error> {
     let mut txn = state.db.begin().await?;
+    let before = xpd_database::user_xp(txn.as_mut(), guild_id, user_id)
+        .await?
+        .unwrap_or(0);
     let xp = xpd_database::add_xp(txn.as_mut(), user_id, guild_id, amount).await?;
-    if xp.is_negative() {
-        txn.rollback().await?;
-        return Err(Error::XpWouldBeNegative);
-    }
     txn.commit().await?;
     let current_level = mee6::LevelInfo::new(xp.try_into().unwrap_or(0)).level();
-    let (action, targeter) = if amount.is_positive() {
-        ("Added", "to")
+    let change = XpChange {
+        action: if amount.is_positive() {
+            XpChangeAction::Add
+        } else {
+            XpChangeAction::Remove
+        },
+        invoker,
+        user: user_id,
+        before,
+        after: xp,
+    };
+    let mut message = if amount.is_positive() {
+        format!(
+            "Added {amount} XP to <@{user_id}>, leaving them with {xp} XP at level {current_level}"
+        )
     } else {
-        ("Removed", "from")
+        // Penalties stop at zero, so this can be less than was asked for
+        let removed = before - xp;
+        format!("Removed {removed} XP from <@{user_id}>, leaving them with {xp} XP at level {current_level}")
+    };
+    if amount.is_negative() {
+        match revoke_lapsed_rewards(&state, guild_id, user_id, xp).await {
+            Ok(0) => {}
+            Ok(revoked) => write!(message, ", and took away {revoked} reward role(s)")?,
+            Err(source) => {
+                warn!(?source, guild = ?guild_id, user = ?user_id, "Failed to take away lapsed rewards");
+                message.push_str(", but I couldn't take away their reward roles");
+            }
+        }
+    }
+    Ok((message, XpAudit::Change(change)))
+}
+
+/// Bring the member's reward roles in line with their level after losing XP, if the guild opted
+/// into taking rewards away. Returns how many roles were taken away.
+async fn revoke_lapsed_rewards(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+    xp: i64,
+) -> Result<usize, Error> {
+    let Some(config) = xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .filter(|config| config.reward_removal_margin.is_some())
+    else {
+        return Ok(0);
+    };
+    let mut rewards = xpd_database::guild_rewards(&state.db, guild_id).await?;
+    if rewards.is_empty() {
+        return Ok(0);
+    }
+    rewards.sort_by(xpd_common::compare_rewards_requirement);
+    let level = mee6::LevelInfo::new(u64::try_from(xp).unwrap_or(0)).level();
+    let level = i64::try_from(level).unwrap_or(i64::MAX);
+
+    let Some(current) = state.client.member_roles(guild_id, user_id).await? else {
+        return Ok(0);
     };
-    let amount_abs = amount.abs();
-    Ok(format!("{action} {amount_abs} XP {targeter} <@{user_id}>, leaving them with {xp} XP at level {current_level}"))
+    let roles = RewardRoleChanges::new(&config, &current, &rewards, level);
+    if roles.changed_roles.is_empty() {
+        return Ok(0);
+    }
+    if !xpd_util::can_manage_roles(&state.cache, state.bot_id, guild_id, &roles.changed_roles)?
+        .can_update_roles()
+    {
+        return Err(Error::CantRevokeRewards);
+    }
+    state
+        .client
+        .set_member_roles(guild_id, user_id, &roles.total_roles)
+        .await?;
+    let revoked = roles
+        .changed_roles
+        .iter()
+        .filter(|role| current.contains(role))
+        .count();
+    Ok(revoked)
 }
 
 async fn reset_user_xp(
     state: SlashState,
     guild_id: Id<GuildMarker>,
+    invoker: Id<UserMarker>,
     user_id: Id<UserMarker>,
-) -> Result<String, Error> {
-    xpd_database::delete_levels_user_guild(&state.db, user_id, guild_id).await?;
-    Ok(format!(
-        "Deleted <@{user_id}> from my database in this server!"
+) -> Result<(String, XpAudit), Error> {
+    let mut txn = state.db.begin().await?;
+    let before = xpd_database::user_xp(txn.as_mut(), guild_id, user_id).await?;
+    xpd_database::delete_levels_user_guild(txn.as_mut(), user_id, guild_id).await?;
+    txn.commit().await?;
+    let change = XpChange {
+        action: XpChangeAction::Reset,
+        invoker,
+        user: user_id,
+        before: before.unwrap_or(0),
+        after: 0,
+    };
+    Ok((
+        format!("Deleted <@{user_id}> from my database in this server!"),
+        XpAudit::Change(change),
     ))
 }
 
 async fn set_user_xp(
     state: SlashState,
     guild_id: Id<GuildMarker>,
+    invoker: Id<UserMarker>,
     user_id: Id<UserMarker>,
     setpoint: i64,
-) -> Result<String, Error> {
-    xpd_database::set_xp(&state.db, user_id, guild_id, setpoint).await?;
+) -> Result<(String, XpAudit), Error> {
+    let mut txn = state.db.begin().await?;
+    let before = xpd_database::user_xp(txn.as_mut(), guild_id, user_id).await?;
+    xpd_database::set_xp(txn.as_mut(), user_id, guild_id, setpoint).await?;
+    txn.commit().await?;
     let level = mee6::LevelInfo::new(setpoint.try_into().unwrap_or(0));
-    Ok(format!(
-        "Set <@{user_id}>'s XP to {}, leaving them at level {}",
-        level.xp(),
-        level.level()
+    let change = XpChange {
+        action: XpChangeAction::Set,
+        invoker,
+        user: user_id,
+        before: before.unwrap_or(0),
+        after: setpoint.max(0),
+    };
+    Ok((
+        format!(
+            "Set <@{user_id}>'s XP to {}, leaving them at level {}",
+            level.xp(),
+            level.level()
+        ),
+        XpAudit::Change(change),
     ))
 }
 
@@ -97,6 +350,50 @@ const fn allowed_command_for_target(data: &XpCommand) -> bool {
         XpCommand::Add(add) => !add.user.resolved.bot,
         XpCommand::Remove(rm) => !rm.user.resolved.bot,
         XpCommand::Set(set) => !set.user.resolved.bot,
-        XpCommand::Reset(_) => true,
+        XpCommand::Reset(_) | XpCommand::Lockdown(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn xp_change_descriptions() {
+        let change = XpChange {
+            action: XpChangeAction::Remove,
+            invoker: Id::new(1),
+            user: Id::new(2),
+            before: 150,
+            after: 100,
+        };
+        assert_eq!(
+            change.description(),
+            "<@1> removed 50 XP from <@2>\n**XP:** 150 → 100"
+        );
+        let change = XpChange {
+            action: XpChangeAction::Reset,
+            after: 0,
+            ..change
+        };
+        assert_eq!(change.description(), "<@1> reset <@2>\n**XP:** 150 → 0");
+    }
+
+    #[test]
+    fn lockdown_descriptions() {
+        let lockdown = |until| XpAudit::Lockdown {
+            invoker: Id::new(1),
+            until,
+        };
+        assert_eq!(
+            lockdown(Some(1_800_000_000)).description(),
+            "<@1> locked down XP gain until <t:1800000000:f>"
+        );
+        assert_eq!(
+            lockdown(Some(XP_LOCKDOWN_INDEFINITE)).description(),
+            "<@1> locked down XP gain until it's turned off"
+        );
+        assert_eq!(lockdown(None).description(), "<@1> lifted the XP lockdown");
+        assert_eq!(lockdown(None).title(), "XP lockdown");
     }
 }
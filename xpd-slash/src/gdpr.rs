@@ -31,6 +31,8 @@ async fn delete(
         let mut txn = state.db.begin().await?;
         xpd_database::delete_levels_user(&mut txn, invoker.id).await?;
         xpd_database::delete_card_customizations(&mut txn, invoker.id.cast()).await?;
+        xpd_database::delete_user_preferences(&mut txn, invoker.id).await?;
+        xpd_database::delete_claimed_rewards_user(&mut txn, invoker.id).await?;
         txn.commit().await?;
         Ok(
             XpdSlashResponse::with_embed_text("All data wiped. Thank you for using experienced.")
@@ -50,6 +52,7 @@ async fn download(
 ) -> Result<XpdSlashResponse, Error> {
     let invoker = Arc::new(invoker);
     let levels = xpd_database::get_all_levels(&state.db, invoker.id).await?;
+    let deleted_levels = xpd_database::get_all_deleted_levels(&state.db, invoker.id).await?;
 
     let invoker_id = &[invoker.id.cast()];
     let custom_card = get_customizations(&state, invoker_id).await?;
@@ -58,17 +61,36 @@ async fn download(
         .into_iter()
         .map(|v| UserXpArchiveEntry::from_record(v.guild, v.xp))
         .collect();
+    let deleted_levels: Vec<UserXpArchiveEntry> = deleted_levels
+        .into_iter()
+        .map(|v| UserXpArchiveEntry::from_record(v.guild, v.xp))
+        .collect();
+
+    let preferences = UserPreferencesArchiveEntry {
+        timezone: xpd_database::user_timezone(&state.db, invoker.id).await?,
+        level_up_dms: xpd_database::level_up_dms(&state.db, invoker.id).await?,
+    };
 
     let levels = multicsv(&levels)?;
+    let deleted_levels = multicsv(&deleted_levels)?;
     let custom_card = multicsv(&[custom_card])?;
+    let preferences = multicsv(&[preferences])?;
 
     let level_file = Attachment::from_bytes(format!("leveling-{}.csv", invoker.id), levels, 1);
     let card_file = Attachment::from_bytes(format!("card-{}.csv", invoker.id), custom_card, 2);
+    let preferences_file =
+        Attachment::from_bytes(format!("preferences-{}.csv", invoker.id), preferences, 3);
+    let deleted_level_file = Attachment::from_bytes(
+        format!("leveling-reset-{}.csv", invoker.id),
+        deleted_levels,
+        4,
+    );
 
-    let attachments: Vec<Attachment> = [level_file, card_file]
-        .into_iter()
-        .filter(|v| !v.file.is_empty())
-        .collect();
+    let attachments: Vec<Attachment> =
+        [level_file, card_file, preferences_file, deleted_level_file]
+            .into_iter()
+            .filter(|v| !v.file.is_empty())
+            .collect();
 
     Ok(XpdSlashResponse::new()
         .content("Here you go!".to_string())
@@ -88,6 +110,12 @@ impl UserXpArchiveEntry {
     }
 }
 
+#[derive(Serialize)]
+struct UserPreferencesArchiveEntry {
+    timezone: Option<String>,
+    level_up_dms: bool,
+}
+
 fn multicsv<T: Serialize>(data: &[T]) -> Result<Vec<u8>, Error> {
     let mut data_wtr = CsvWriter::from_writer(Vec::new());
     for datum in data {
@@ -1,6 +1,24 @@
+use twilight_util::builder::embed::{EmbedBuilder, EmbedFooterBuilder};
+use xpd_common::{BuildInfo, THEME_COLOR};
+
 use crate::XpdSlashResponse;
 
 pub fn help() -> XpdSlashResponse {
     const HELP_MESSAGE: &str = "Visit [the docs](<https://xp.valk.sh/docs/>) or [join the discord](<https://valk.sh/discord>)";
     XpdSlashResponse::with_embed_text(HELP_MESSAGE).ephemeral(true)
 }
+
+pub fn about(build: BuildInfo) -> XpdSlashResponse {
+    const ABOUT_MESSAGE: &str = "Experienced is a free and open-source leveling bot. \
+        Members earn XP by chatting, level up, and can earn reward roles along the way.\n\n\
+        [Docs](<https://xp.valk.sh/docs/>) • [Support server](<https://valk.sh/discord>) • \
+        [Source code](<https://github.com/randomairborne/experienced>) • \
+        [Privacy policy](<https://xp.valk.sh/privacy/>)";
+    let embed = EmbedBuilder::new()
+        .title("About experienced")
+        .description(ABOUT_MESSAGE)
+        .color(THEME_COLOR)
+        .footer(EmbedFooterBuilder::new(format!("Version {build}")))
+        .build();
+    XpdSlashResponse::new().embeds([embed]).ephemeral(true)
+}
@@ -0,0 +1,320 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use twilight_model::id::{
+    marker::{GuildMarker, RoleMarker, UserMarker},
+    Id,
+};
+
+use crate::Error;
+
+/// The Discord API calls commands make on their own, outside of responding to the interaction.
+/// [`twilight_http::Client`] is the real implementation, tests can swap in a fake one.
+pub trait DiscordHttp: Send + Sync {
+    /// Make the bot leave a guild.
+    fn leave_guild(
+        &self,
+        guild_id: Id<GuildMarker>,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Set the bot's nickname in a guild, or reset it with `None`.
+    fn set_own_nick(
+        &self,
+        guild_id: Id<GuildMarker>,
+        nick: Option<&str>,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// The roles a member has, or None if they aren't in the guild.
+    fn member_roles(
+        &self,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+    ) -> impl Future<Output = Result<Option<Vec<Id<RoleMarker>>>, Error>> + Send;
+
+    /// Replace every role a member has.
+    fn set_member_roles(
+        &self,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+        roles: &[Id<RoleMarker>],
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// A guild's name and approximate member counts.
+    fn guild_counts(
+        &self,
+        guild_id: Id<GuildMarker>,
+    ) -> impl Future<Output = Result<GuildCounts, Error>> + Send;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuildCounts {
+    pub name: String,
+    pub large: bool,
+    pub online: Option<u64>,
+    pub members: Option<u64>,
+}
+
+/// How long fetched guild counts are reused before asking Discord again
+const GUILD_COUNTS_TTL: Duration = Duration::from_mins(1);
+
+/// Recently fetched [`GuildCounts`], so repeated stats lookups don't each cost an API request.
+#[derive(Clone, Debug, Default)]
+pub struct GuildCountsCache(Arc<Mutex<HashMap<Id<GuildMarker>, FetchedCounts>>>);
+
+type FetchedCounts = (Instant, GuildCounts);
+
+impl GuildCountsCache {
+    /// A guild's counts, from the cache if they're fresh. If Discord can't be reached,
+    /// counts which are out of date are better than none.
+    /// # Errors
+    /// If Discord can't be reached, and the guild's counts were never fetched before.
+    pub async fn get(
+        &self,
+        http: &impl DiscordHttp,
+        guild_id: Id<GuildMarker>,
+    ) -> Result<GuildCounts, Error> {
+        self.get_with_ttl(http, guild_id, GUILD_COUNTS_TTL).await
+    }
+
+    async fn get_with_ttl(
+        &self,
+        http: &impl DiscordHttp,
+        guild_id: Id<GuildMarker>,
+        ttl: Duration,
+    ) -> Result<GuildCounts, Error> {
+        let cached = self.cached(guild_id);
+        if let Some((fetched, counts)) = &cached {
+            if fetched.elapsed() < ttl {
+                return Ok(counts.clone());
+            }
+        }
+        match http.guild_counts(guild_id).await {
+            Ok(counts) => {
+                let mut guilds = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+                guilds.retain(|_, (fetched, _)| fetched.elapsed() < ttl);
+                guilds.insert(guild_id, (Instant::now(), counts.clone()));
+                drop(guilds);
+                Ok(counts)
+            }
+            Err(source) => {
+                let Some((_, counts)) = cached else {
+                    return Err(source);
+                };
+                warn!(
+                    ?guild_id,
+                    ?source,
+                    "Failed to refresh guild counts, using stale ones"
+                );
+                Ok(counts)
+            }
+        }
+    }
+
+    fn cached(&self, guild_id: Id<GuildMarker>) -> Option<FetchedCounts> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&guild_id)
+            .cloned()
+    }
+}
+
+impl DiscordHttp for twilight_http::Client {
+    async fn leave_guild(&self, guild_id: Id<GuildMarker>) -> Result<(), Error> {
+        self.leave_guild(guild_id).await?;
+        Ok(())
+    }
+
+    async fn set_own_nick(
+        &self,
+        guild_id: Id<GuildMarker>,
+        nick: Option<&str>,
+    ) -> Result<(), Error> {
+        self.update_current_member(guild_id).nick(nick).await?;
+        Ok(())
+    }
+
+    async fn member_roles(
+        &self,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+    ) -> Result<Option<Vec<Id<RoleMarker>>>, Error> {
+        match self.guild_member(guild_id, user_id).await {
+            Ok(member) => Ok(Some(member.model().await?.roles)),
+            Err(source)
+                if xpd_util::discord_error_code(&source) == Some(xpd_util::UNKNOWN_MEMBER) =>
+            {
+                Ok(None)
+            }
+            Err(source) => Err(source.into()),
+        }
+    }
+
+    async fn set_member_roles(
+        &self,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+        roles: &[Id<RoleMarker>],
+    ) -> Result<(), Error> {
+        self.update_guild_member(guild_id, user_id)
+            .roles(roles)
+            .await?;
+        Ok(())
+    }
+
+    async fn guild_counts(&self, guild_id: Id<GuildMarker>) -> Result<GuildCounts, Error> {
+        let guild = self
+            .guild(guild_id)
+            .with_counts(true)
+            .await?
+            .model()
+            .await?;
+        Ok(GuildCounts {
+            name: guild.name,
+            large: guild.large,
+            online: guild.approximate_presence_count,
+            members: guild.approximate_member_count,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::{
+        collections::HashMap,
+        sync::{Mutex, PoisonError},
+    };
+
+    use super::*;
+
+    type MemberRoles = HashMap<(Id<GuildMarker>, Id<UserMarker>), Vec<Id<RoleMarker>>>;
+
+    /// An in-memory Discord which remembers what commands did to it.
+    #[derive(Debug, Default)]
+    pub struct MockHttp {
+        pub guilds: HashMap<Id<GuildMarker>, GuildCounts>,
+        pub members: Mutex<MemberRoles>,
+        pub left: Mutex<Vec<Id<GuildMarker>>>,
+        pub nicks: Mutex<Vec<(Id<GuildMarker>, Option<String>)>>,
+    }
+
+    impl MockHttp {
+        pub fn with_member(
+            self,
+            guild_id: Id<GuildMarker>,
+            user_id: Id<UserMarker>,
+            roles: &[Id<RoleMarker>],
+        ) -> Self {
+            self.members
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert((guild_id, user_id), roles.to_vec());
+            self
+        }
+
+        pub fn roles(
+            &self,
+            guild_id: Id<GuildMarker>,
+            user_id: Id<UserMarker>,
+        ) -> Option<Vec<Id<RoleMarker>>> {
+            self.members
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(&(guild_id, user_id))
+                .cloned()
+        }
+
+        fn update_roles(
+            &self,
+            guild_id: Id<GuildMarker>,
+            user_id: Id<UserMarker>,
+            update: impl FnOnce(&mut Vec<Id<RoleMarker>>),
+        ) -> Result<(), Error> {
+            let mut members = self.members.lock().unwrap_or_else(PoisonError::into_inner);
+            let roles = members
+                .get_mut(&(guild_id, user_id))
+                .ok_or(Error::NoTarget)?;
+            update(roles);
+            drop(members);
+            Ok(())
+        }
+    }
+
+    impl DiscordHttp for MockHttp {
+        async fn leave_guild(&self, guild_id: Id<GuildMarker>) -> Result<(), Error> {
+            self.left
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(guild_id);
+            Ok(())
+        }
+
+        async fn set_own_nick(
+            &self,
+            guild_id: Id<GuildMarker>,
+            nick: Option<&str>,
+        ) -> Result<(), Error> {
+            self.nicks
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push((guild_id, nick.map(ToOwned::to_owned)));
+            Ok(())
+        }
+
+        async fn member_roles(
+            &self,
+            guild_id: Id<GuildMarker>,
+            user_id: Id<UserMarker>,
+        ) -> Result<Option<Vec<Id<RoleMarker>>>, Error> {
+            Ok(self.roles(guild_id, user_id))
+        }
+
+        async fn set_member_roles(
+            &self,
+            guild_id: Id<GuildMarker>,
+            user_id: Id<UserMarker>,
+            roles: &[Id<RoleMarker>],
+        ) -> Result<(), Error> {
+            self.update_roles(guild_id, user_id, |current| *current = roles.to_vec())
+        }
+
+        async fn guild_counts(&self, guild_id: Id<GuildMarker>) -> Result<GuildCounts, Error> {
+            self.guilds.get(&guild_id).cloned().ok_or(Error::NoGuildId)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mock::MockHttp, *};
+
+    #[tokio::test]
+    async fn guild_counts_are_cached() {
+        let counts = GuildCounts {
+            name: "Levels".to_string(),
+            large: false,
+            online: Some(3),
+            members: Some(10),
+        };
+        let mut http = MockHttp::default();
+        http.guilds.insert(Id::new(5), counts.clone());
+        let cache = GuildCountsCache::default();
+        assert_eq!(cache.get(&http, Id::new(5)).await.unwrap(), counts);
+
+        // Fresh counts don't need Discord
+        http.guilds.clear();
+        assert_eq!(cache.get(&http, Id::new(5)).await.unwrap(), counts);
+        // Stale counts are still used if Discord can't give new ones
+        let stale = cache
+            .get_with_ttl(&http, Id::new(5), Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(stale, counts);
+        // But there's nothing to fall back on for a guild that was never fetched
+        assert!(cache.get(&http, Id::new(6)).await.is_err());
+    }
+}
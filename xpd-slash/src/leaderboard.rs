@@ -13,81 +13,295 @@ use twilight_model::{
     },
     http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
     id::{
-        marker::{GuildMarker, UserMarker},
+        marker::{GuildMarker, RoleMarker, UserMarker},
         Id,
     },
 };
-use twilight_util::builder::InteractionResponseDataBuilder;
-use xpd_slash_defs::levels::LeaderboardCommand;
+use twilight_util::builder::{embed::EmbedBuilder, InteractionResponseDataBuilder};
+use xpd_common::UserStatus;
+use xpd_slash_defs::levels::{LeaderboardCommand, WhoHasCommand};
 
-use crate::{Error, SlashState};
+use crate::{Error, SlashState, XpdSlashResponse};
 
 pub async fn leaderboard(
     state: SlashState,
     guild_id: Id<GuildMarker>,
     guild_command: LeaderboardCommand,
+    invoker_id: Id<UserMarker>,
+    locale: Option<&str>,
 ) -> Result<InteractionResponse, Error> {
+    // @everyone has the same ID as the guild, and everyone has it
+    let role = guild_command
+        .role
+        .map(|role| role.id)
+        .filter(|role| role.cast() != guild_id);
+    let board = if guild_command.today.is_some_and(|today| today) {
+        if role.is_some() {
+            return Err(Error::WrongArgumentCount(
+                "`/leaderboard` can't rank by today's XP and filter by role at the same time!",
+            ));
+        }
+        Board::Today
+    } else {
+        role.map_or(Board::Everyone, Board::Role)
+    };
     // "zpage" means "zero-indexed page", which is how this is represented internally.
     // We add one whenever we show it to the user, and subtract one every time we get it from the user.
+    let target = if guild_command.me.is_some_and(|me| me) {
+        Some(invoker_id)
+    } else {
+        guild_command.user.map(|user| user.resolved.id)
+    };
     let zpage = if let Some(pick) = guild_command.page {
         pick - 1
-    } else if let Some(pick) = guild_command.user {
-        state.get_user_stats(pick.resolved.id, guild_id).await?.rank / 10
+    } else if let Some(target) = target {
+        let higher = if board == Board::Today {
+            xpd_database::todays_gainer_position(&state.db, guild_id, target)
+                .await?
+                .ok_or(Error::NotOnLeaderboard)?
+                - 1
+        } else {
+            let xp = state
+                .xp_store()
+                .get_xp(target, guild_id)
+                .await?
+                .ok_or(Error::NotOnLeaderboard)?;
+            if let Board::Role(role) = board {
+                let members = role_members(&state, guild_id, role);
+                xpd_database::count_with_higher_xp_among(&state.db, guild_id, &members, xp).await?
+            } else {
+                xpd_database::count_with_higher_xp(&state.db, guild_id, xp).await?
+            }
+            .unwrap_or(0)
+        };
+        higher / USERS_PER_PAGE
     } else {
         0
     };
     Ok(InteractionResponse {
-        data: Some(gen_leaderboard(&state, guild_id, zpage, guild_command.show_off).await?),
+        data: Some(
+            gen_leaderboard(
+                &state,
+                guild_id,
+                zpage,
+                board,
+                guild_command.show_off,
+                target,
+                locale,
+            )
+            .await?,
+        ),
         kind: InteractionResponseType::ChannelMessageWithSource,
     })
 }
 
-const USERS_PER_PAGE_USIZE: usize = 10;
-#[allow(clippy::cast_possible_wrap)]
-const USERS_PER_PAGE: i64 = USERS_PER_PAGE_USIZE as i64;
+const WHO_HAS_PER_PAGE: i64 = 20;
+
+/// List everyone who is at exactly `level`, most XP first
+pub async fn who_has(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    command: WhoHasCommand,
+    locale: Option<&str>,
+) -> Result<XpdSlashResponse, Error> {
+    let (min, max) = level_xp_range(command.level.try_into().unwrap_or(0));
+    let page = command.page.unwrap_or(1).max(1);
+    let (users, total) = xpd_database::users_in_xp_range(
+        &state.db,
+        guild_id,
+        min,
+        max,
+        WHO_HAS_PER_PAGE,
+        (page - 1) * WHO_HAS_PER_PAGE,
+    )
+    .await?;
+    let config = xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .unwrap_or_default();
+    let level = config.display_level(command.level.try_into().unwrap_or(0));
+    let mut description = format!("### Members at level {level}\n");
+    if total == 0 {
+        writeln!(description, "Nobody is at level {level} right now.")?;
+    } else if users.is_empty() {
+        let pages = (total + WHO_HAS_PER_PAGE - 1) / WHO_HAS_PER_PAGE;
+        writeln!(
+            description,
+            "There's no page {}, the last one is page {}.",
+            xpd_common::format_int(locale, page),
+            xpd_common::format_int(locale, pages)
+        )?;
+    } else {
+        for user in &users {
+            writeln!(
+                description,
+                "**{}** (<@{}>) - {} XP",
+                crate::members::member_tag(&state, guild_id, user.id).await,
+                user.id,
+                xpd_common::format_int(locale, user.xp)
+            )?;
+        }
+        let pages = (total + WHO_HAS_PER_PAGE - 1) / WHO_HAS_PER_PAGE;
+        write!(
+            description,
+            "-# Page {} of {}, {} members at this level",
+            xpd_common::format_int(locale, page),
+            xpd_common::format_int(locale, pages),
+            xpd_common::format_int(locale, total)
+        )?;
+    }
+    let embed = EmbedBuilder::new().description(description).build();
+    Ok(XpdSlashResponse::new()
+        .embeds([embed])
+        .ephemeral(!command.show_off.is_some_and(|v| v)))
+}
+
+/// The lowest and highest XP, inclusive, which are at `level`
+fn level_xp_range(level: u64) -> (i64, i64) {
+    let min = mee6::xp_needed_for_level(level);
+    let max = mee6::xp_needed_for_level(level + 1) - 1;
+    (
+        min.try_into().unwrap_or(i64::MAX),
+        max.try_into().unwrap_or(i64::MAX),
+    )
+}
+
+/// Members of `role` the bot knows about. Without the privileged members intent we can't list
+/// a role's members, so this is everyone with the role that has shown up in the cache.
+fn role_members(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    role: Id<RoleMarker>,
+) -> Vec<Id<UserMarker>> {
+    let Some(members) = state.cache.guild_members(guild_id) else {
+        return Vec::new();
+    };
+    members
+        .iter()
+        .copied()
+        .filter(|user| {
+            state
+                .cache
+                .member(guild_id, *user)
+                .is_some_and(|member| member.roles().contains(&role))
+        })
+        .collect()
+}
+
+/// Which users a leaderboard ranks, and by what
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Board {
+    /// Everyone, by total XP
+    Everyone,
+    /// Members with this role, by total XP
+    Role(Id<RoleMarker>),
+    /// Everyone who gained XP since the start of the UTC day, by how much they gained
+    Today,
+}
+
+const TODAY_BOARD_ID: &str = "today";
+
+/// Component and modal IDs carry the board they belong to, so that changing pages keeps
+/// the role filter or today's mode without storing anything.
+fn with_board(id: impl std::fmt::Display, board: Board) -> String {
+    match board {
+        Board::Everyone => id.to_string(),
+        Board::Role(role) => format!("{id}:{role}"),
+        Board::Today => format!("{id}:{TODAY_BOARD_ID}"),
+    }
+}
+
+fn split_board(id: &str) -> Result<(&str, Board), Error> {
+    match id.split_once(':') {
+        Some((id, TODAY_BOARD_ID)) => Ok((id, Board::Today)),
+        Some((id, role)) => Ok((id, Board::Role(role.parse()?))),
+        None => Ok((id, Board::Everyone)),
+    }
+}
+
+const USERS_PER_PAGE: i64 = 10;
 
 async fn gen_leaderboard(
     state: &SlashState,
     guild_id: Id<GuildMarker>,
     zpage: i64,
+    board: Board,
     show_off: Option<bool>,
+    highlight: Option<Id<UserMarker>>,
+    locale: Option<&str>,
 ) -> Result<InteractionResponseData, Error> {
-    if zpage.is_negative() {
-        return Err(Error::PageDoesNotExist);
-    }
     let is_ephemeral = !show_off.is_some_and(|v| v);
-    let users = xpd_database::get_leaderboard_page(
-        &state.db,
-        guild_id,
-        USERS_PER_PAGE + 1,
-        zpage * USERS_PER_PAGE,
-    )
-    .await?;
-
-    if users.is_empty() {
-        return Err(if zpage == 0 {
-            Error::NoUsersForPage
-        } else {
-            Error::NoRanksYet
+    let members = match board {
+        Board::Role(role) => Some(role_members(state, guild_id, role)),
+        Board::Everyone | Board::Today => None,
+    };
+    let total = if let Some(members) = &members {
+        xpd_database::levels_in_guild_among(&state.db, guild_id, members).await?
+    } else if board == Board::Today {
+        xpd_database::todays_gainers(&state.db, guild_id).await?
+    } else {
+        state.xp_store().ranked_users(guild_id).await?
+    };
+    if total == 0 {
+        return Err(match board {
+            Board::Everyone => Error::NoRanksYet,
+            Board::Role(_) => Error::NoRanksWithRole,
+            Board::Today => Error::NoGainsToday,
         });
     }
+    let pages = (total + USERS_PER_PAGE - 1) / USERS_PER_PAGE;
+    let requested = zpage;
+    let zpage = clamp_zpage(zpage, pages);
 
-    let one_more_page_bro = users.len() >= (USERS_PER_PAGE_USIZE + 1);
-    let last_user_idx = users.len().clamp(0, USERS_PER_PAGE_USIZE);
-    let users = &users[0..last_user_idx];
+    let users = board_page(state, guild_id, board, members.as_deref(), zpage).await?;
+
+    let one_more_page_bro = zpage + 1 < pages;
     // this is kinda the only way to do this
     // It's designed to only allocate once, at the start here
     let mut description = String::with_capacity(256 + users.len() * 128);
-    writeln!(description, "### Leaderboard")?;
+    match board {
+        Board::Everyone => writeln!(description, "### Leaderboard")?,
+        Board::Role(role) => writeln!(description, "### Leaderboard for <@&{role}>")?,
+        Board::Today => writeln!(description, "### Top gainers today")?,
+    }
     for (i, user) in users.iter().enumerate() {
-        let level = mee6::LevelInfo::new(user.xp.try_into().unwrap_or(0)).level();
         let rank: i64 = i
             .try_into()
             .map_or(-1, |v: i64| v + (zpage * USERS_PER_PAGE) + 1);
-        writeln!(description, "**#{rank}.** <@{}> - Level {level}", user.id)?;
+        write!(
+            description,
+            "**#{}.** <@{}> - ",
+            xpd_common::format_int(locale, rank),
+            user.id,
+        )?;
+        if board == Board::Today {
+            write!(
+                description,
+                "{} XP",
+                xpd_common::format_int(locale, user.xp)
+            )?;
+        } else {
+            let level = mee6::LevelInfo::new(user.xp.try_into().unwrap_or(0)).level();
+            write!(
+                description,
+                "Level {}",
+                xpd_common::format_int(locale, level)
+            )?;
+        }
+        if highlight == Some(user.id) {
+            description.push_str(" ◀");
+        }
+        description.push('\n');
+    }
+    if requested != zpage {
+        writeln!(
+            description,
+            "*There's no page {}, so this is page {}.*",
+            xpd_common::format_int(locale, requested.saturating_add(1)),
+            xpd_common::format_int(locale, zpage + 1)
+        )?;
     }
 
-    let control_options = control_options(zpage, one_more_page_bro);
+    let control_options = control_options(zpage, pages, board, one_more_page_bro);
 
     let (components, flags) = if is_ephemeral {
         let second_last_idx = control_options.len() - 2;
@@ -111,18 +325,52 @@ async fn gen_leaderboard(
         .build())
 }
 
-fn control_options(zpage: i64, next_page_exists: bool) -> [Component; 5] {
+/// The users on a zero-indexed page of a board. `members` must be set for role boards.
+async fn board_page(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    board: Board,
+    members: Option<&[Id<UserMarker>]>,
+    zpage: i64,
+) -> Result<Vec<UserStatus>, Error> {
+    let offset = zpage * USERS_PER_PAGE;
+    let users = if let Some(members) = members {
+        xpd_database::get_leaderboard_page_among(
+            &state.db,
+            guild_id,
+            members,
+            USERS_PER_PAGE,
+            offset,
+        )
+        .await?
+    } else if board == Board::Today {
+        xpd_database::todays_top_gainers(&state.db, guild_id, USERS_PER_PAGE, offset).await?
+    } else {
+        state
+            .xp_store()
+            .top_n(guild_id, USERS_PER_PAGE, offset)
+            .await?
+    };
+    Ok(users)
+}
+
+/// Keeps a zero-indexed page within the `pages` a leaderboard has, which must be at least one.
+fn clamp_zpage(zpage: i64, pages: i64) -> i64 {
+    zpage.clamp(0, pages - 1)
+}
+
+fn control_options(zpage: i64, pages: i64, board: Board, next_page_exists: bool) -> [Component; 5] {
     [
         Button {
             custom_id: Some("page_indicator".to_string()),
             disabled: true,
             emoji: None,
-            label: Some(format!("Page {}", zpage + 1)),
+            label: Some(format!("Page {} of {pages}", zpage + 1)),
             style: ButtonStyle::Secondary,
             url: None,
         },
         Button {
-            custom_id: Some((zpage - 1).to_string()),
+            custom_id: Some(with_board(zpage - 1, board)),
             disabled: zpage == 0,
             emoji: Some(ReactionType::Unicode {
                 name: "⬅".to_string(),
@@ -132,15 +380,15 @@ fn control_options(zpage: i64, next_page_exists: bool) -> [Component; 5] {
             url: None,
         },
         Button {
-            custom_id: Some("jump_modal".to_string()),
-            disabled: !next_page_exists && zpage == 0,
+            custom_id: Some(with_board("jump_modal", board)),
+            disabled: pages <= 1,
             emoji: None,
             label: Some("Go to page".to_string()),
             style: ButtonStyle::Primary,
             url: None,
         },
         Button {
-            custom_id: Some((zpage + 1).to_string()),
+            custom_id: Some(with_board(zpage + 1, board)),
             disabled: !next_page_exists,
             emoji: Some(ReactionType::Unicode {
                 name: "➡️".to_string(),
@@ -166,6 +414,7 @@ fn control_options(zpage: i64, next_page_exists: bool) -> [Component; 5] {
 pub async fn process_modal_submit(
     data: ModalInteractionData,
     guild_id: Id<GuildMarker>,
+    locale: Option<&str>,
     state: SlashState,
 ) -> Result<InteractionResponse, Error> {
     // You can't get this modal unless you are the triggering user
@@ -177,9 +426,12 @@ pub async fn process_modal_submit(
         .ok_or(Error::NoDestinationInComponent)?
         .parse()?;
     let zpage = choice - 1;
+    let (_, board) = split_board(&data.custom_id)?;
     Ok(InteractionResponse {
         kind: InteractionResponseType::UpdateMessage,
-        data: Some(gen_leaderboard(&state, guild_id, zpage, Some(true)).await?),
+        data: Some(
+            gen_leaderboard(&state, guild_id, zpage, board, Some(true), None, locale).await?,
+        ),
     })
 }
 
@@ -188,6 +440,7 @@ pub async fn process_message_component(
     original_message: Message,
     guild_id: Id<GuildMarker>,
     invoker_id: Id<UserMarker>,
+    locale: Option<&str>,
     state: SlashState,
 ) -> Result<InteractionResponse, Error> {
     if original_message
@@ -199,7 +452,8 @@ pub async fn process_message_component(
     {
         return Err(Error::NotYourLeaderboard);
     }
-    match data.custom_id.as_str() {
+    let (custom_id, board) = split_board(&data.custom_id)?;
+    match custom_id {
         "jump_modal" => {
             let input = TextInput {
                 custom_id: "jump_modal_input".to_string(),
@@ -218,7 +472,7 @@ pub async fn process_message_component(
                         .components([Component::ActionRow(ActionRow {
                             components: vec![Component::TextInput(input)],
                         })])
-                        .custom_id("jump_modal")
+                        .custom_id(with_board("jump_modal", board))
                         .title("Go to page..")
                         .build(),
                 ),
@@ -244,8 +498,66 @@ pub async fn process_message_component(
                 .is_none_or(|f| !f.contains(MessageFlags::EPHEMERAL));
             Ok(InteractionResponse {
                 kind: InteractionResponseType::UpdateMessage,
-                data: Some(gen_leaderboard(&state, guild_id, offset, Some(show_delete_btn)).await?),
+                data: Some(
+                    gen_leaderboard(
+                        &state,
+                        guild_id,
+                        offset,
+                        board,
+                        Some(show_delete_btn),
+                        None,
+                        locale,
+                    )
+                    .await?,
+                ),
             })
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn board_survives_component_ids() {
+        let role = Id::new(1234);
+        assert_eq!(
+            split_board(&with_board(3, Board::Role(role))).unwrap(),
+            ("3", Board::Role(role))
+        );
+        assert_eq!(
+            split_board(&with_board("jump_modal", Board::Everyone)).unwrap(),
+            ("jump_modal", Board::Everyone)
+        );
+        assert_eq!(
+            split_board(&with_board(-1, Board::Today)).unwrap(),
+            ("-1", Board::Today)
+        );
+        assert!(split_board("3:notarole").is_err());
+    }
+
+    #[test]
+    fn levels_cover_xp_without_gaps() {
+        assert_eq!(level_xp_range(0), (0, 99));
+        assert_eq!(level_xp_range(1), (100, 254));
+        assert_eq!(level_xp_range(2), (255, 474));
+        for level in 0..50 {
+            let (min, max) = level_xp_range(level);
+            assert_eq!(level_xp_range(level + 1).0, max + 1);
+            for xp in [min, max] {
+                let info = mee6::LevelInfo::new(xp.try_into().unwrap());
+                assert_eq!(info.level(), level);
+            }
+        }
+    }
+
+    #[test]
+    fn pages_are_clamped() {
+        assert_eq!(clamp_zpage(0, 3), 0);
+        assert_eq!(clamp_zpage(2, 3), 2);
+        assert_eq!(clamp_zpage(999_998, 3), 2);
+        assert_eq!(clamp_zpage(-5, 3), 0);
+        assert_eq!(clamp_zpage(4, 1), 0);
+    }
+}
@@ -1,3 +1,8 @@
+use std::{
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use base64::Engine;
 use tokio::try_join;
 use twilight_model::{
@@ -10,16 +15,19 @@ use twilight_model::{
     util::ImageHash,
 };
 use twilight_util::builder::embed::EmbedBuilder;
-use xpd_common::{DisplayName, MemberDisplayInfo};
+use xpd_common::{DisplayName, GuildConfig, MemberDisplayInfo, DEFAULT_MESSAGE_COOLDOWN};
+use xpd_database::{RawCustomizations, SnapshotRank, XpHistoryEntry};
 use xpd_rank_card::customizations::{Color, Customizations};
 
-use crate::{Error, SlashState, XpdSlashResponse};
+use crate::{Error, SlashState, UserStats, XpdSlashResponse};
 
 pub async fn get_level(
     guild_id: Id<GuildMarker>,
     target: MemberDisplayInfo,
     invoker: Id<UserMarker>,
     showoff: Option<bool>,
+    card_only: bool,
+    locale: Option<&str>,
     state: SlashState,
 ) -> Result<XpdSlashResponse, Error> {
     let rank_stats = state.get_user_stats(target.id, guild_id).await?;
@@ -29,36 +37,30 @@ pub async fn get_level(
         MessageFlags::EPHEMERAL
     };
 
-    let level_info = mee6::LevelInfo::new(u64::try_from(rank_stats.xp).unwrap_or(0));
-    let content = if target.bot {
+    let content = if target.bot && !rank_bots(&state, guild_id).await? {
         "Bots aren't ranked, that would be silly!".to_string()
+    } else if rank_stats.xp == 0 && unranked_card(&state, guild_id).await? {
+        return generate_level_response(
+            &state, target, guild_id, invoker, rank_stats, locale, flags, card_only,
+        )
+        .await;
     } else if invoker == target.id {
         if rank_stats.xp == 0 {
             "You aren't ranked yet, because you haven't sent any messages!".to_string()
         } else {
             return generate_level_response(
-                &state,
-                target,
-                guild_id,
-                level_info,
-                rank_stats.rank,
-                flags,
+                &state, target, guild_id, invoker, rank_stats, locale, flags, card_only,
             )
             .await;
         }
     } else if rank_stats.xp == 0 {
         format!(
             "{} isn't ranked yet, because they haven't sent any messages!",
-            target.display_name()
+            target.tag()
         )
     } else {
         return generate_level_response(
-            &state,
-            target,
-            guild_id,
-            level_info,
-            rank_stats.rank,
-            flags,
+            &state, target, guild_id, invoker, rank_stats, locale, flags, card_only,
         )
         .await;
     };
@@ -66,19 +68,383 @@ pub async fn get_level(
     Ok(XpdSlashResponse::new().embeds([embed]).flags(flags))
 }
 
+/// Choose whether the invoker's own level-ups are sent to their DMs, in every server
+pub async fn set_notify(
+    invoker: Id<UserMarker>,
+    enabled: bool,
+    state: SlashState,
+) -> Result<XpdSlashResponse, Error> {
+    xpd_database::set_level_up_dms(&state.db, invoker, enabled).await?;
+    let message = if enabled {
+        "Your level-ups will be sent to your DMs instead of announced in the server. \
+        Make sure you allow DMs from servers you share with me!"
+    } else {
+        "Your level-ups will be announced in the server again."
+    };
+    Ok(XpdSlashResponse::with_embed_text(message).ephemeral(true))
+}
+
+/// Show how someone's XP has changed over the last few days they earned some
+pub async fn get_history(
+    guild_id: Id<GuildMarker>,
+    target: &MemberDisplayInfo,
+    invoker: Id<UserMarker>,
+    showoff: Option<bool>,
+    locale: Option<&str>,
+    state: SlashState,
+) -> Result<XpdSlashResponse, Error> {
+    let flags = if showoff.is_some_and(|v| v) {
+        MessageFlags::empty()
+    } else {
+        MessageFlags::EPHEMERAL
+    };
+    let bot_unranked = target.bot && !rank_bots(&state, guild_id).await?;
+    let history = if bot_unranked {
+        Vec::new()
+    } else {
+        xpd_database::xp_history(&state.db, target.id, guild_id, HISTORY_DAYS_SHOWN).await?
+    };
+    let whose = if invoker == target.id {
+        "your".to_string()
+    } else {
+        format!("{}'s", target.display_name())
+    };
+    let content = if bot_unranked {
+        "Bots aren't ranked, so they don't have a history either!".to_string()
+    } else if history.is_empty() {
+        format!("There's no XP history for {whose} account yet. It's recorded every day XP is earned by chatting.")
+    } else {
+        let mut content = format!("XP history for {whose} account\n");
+        content.push_str(&history_table(&history, locale));
+        if history.len() == 1 {
+            content.push_str(
+                "\n-# Only one day so far, check back after a few more days of chatting!",
+            );
+        }
+        let last_week = xpd_database::rank_at_snapshot(
+            &state.db,
+            guild_id,
+            target.id,
+            days_ago_time(HISTORY_RANK_DAYS_AGO),
+        )
+        .await?;
+        if let Some(last_week) = last_week {
+            content.push_str("\n-# ");
+            content.push_str(&past_rank_text(
+                Some(&last_week),
+                HISTORY_RANK_DAYS_AGO,
+                &whose,
+                locale,
+            ));
+        }
+        content
+    };
+    let embed = EmbedBuilder::new().description(content).build();
+    Ok(XpdSlashResponse::new().embeds([embed]).flags(flags))
+}
+
+/// Show where someone stood in the server some days ago, going by the XP history
+pub async fn get_past_rank(
+    guild_id: Id<GuildMarker>,
+    target: &MemberDisplayInfo,
+    invoker: Id<UserMarker>,
+    days_ago: i64,
+    showoff: Option<bool>,
+    locale: Option<&str>,
+    state: SlashState,
+) -> Result<XpdSlashResponse, Error> {
+    let flags = if showoff.is_some_and(|v| v) {
+        MessageFlags::empty()
+    } else {
+        MessageFlags::EPHEMERAL
+    };
+    let content = if target.bot && !rank_bots(&state, guild_id).await? {
+        "Bots aren't ranked, they never were!".to_string()
+    } else {
+        let whose = if invoker == target.id {
+            "your".to_string()
+        } else {
+            format!("{}'s", target.display_name())
+        };
+        let past =
+            xpd_database::rank_at_snapshot(&state.db, guild_id, target.id, days_ago_time(days_ago))
+                .await?;
+        past_rank_text(past.as_ref(), days_ago, &whose, locale)
+    };
+    let embed = EmbedBuilder::new().description(content).build();
+    Ok(XpdSlashResponse::new().embeds([embed]).flags(flags))
+}
+
+/// Seconds since the Unix epoch, `days` days ago
+fn days_ago_time(days: i64) -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| {
+            i64::try_from(since.as_secs()).unwrap_or(i64::MAX)
+        });
+    now.saturating_sub(days.saturating_mul(86_400))
+}
+
+/// Describe a past snapshot rank, or that the member wasn't in the snapshot
+fn past_rank_text(
+    past: Option<&SnapshotRank>,
+    days_ago: i64,
+    whose: &str,
+    locale: Option<&str>,
+) -> String {
+    let plural = if days_ago == 1 { "" } else { "s" };
+    past.map_or_else(
+        || {
+            format!(
+                "There's no record of {whose} XP from {days_ago} day{plural} ago. \
+                XP history only goes back {} days, and only has members who had chatted by then.",
+                xpd_common::XP_HISTORY_DAYS
+            )
+        },
+        |past| {
+            format!(
+                "{days_ago} day{plural} ago ({} UTC), {whose} rank was #{} of {}, with {} XP.",
+                past.day,
+                xpd_common::format_int(locale, past.rank),
+                xpd_common::format_int(locale, past.members),
+                xpd_common::format_int(locale, past.xp)
+            )
+        },
+    )
+}
+
+pub async fn get_estimate(
+    guild_id: Id<GuildMarker>,
+    target: &MemberDisplayInfo,
+    invoker: Id<UserMarker>,
+    showoff: Option<bool>,
+    locale: Option<&str>,
+    state: SlashState,
+) -> Result<XpdSlashResponse, Error> {
+    let flags = if showoff.is_some_and(|v| v) {
+        MessageFlags::empty()
+    } else {
+        MessageFlags::EPHEMERAL
+    };
+    let config = xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .unwrap_or_default();
+    let content = if target.bot && !config.rank_bots.unwrap_or(false) {
+        "Bots aren't ranked, so they don't have a next level either!".to_string()
+    } else {
+        // Members without XP get the starting XP with their first message
+        let xp = xpd_database::user_xp(&state.db, guild_id, target.id)
+            .await?
+            .unwrap_or_else(|| config.starting_xp.unwrap_or(0));
+        let whose = if invoker == target.id {
+            "your".to_string()
+        } else {
+            format!("{}'s", target.display_name())
+        };
+        describe_estimate(&config, u64::try_from(xp).unwrap_or(0), &whose, locale)
+    };
+    let embed = EmbedBuilder::new().description(content).build();
+    Ok(XpdSlashResponse::new().embeds([embed]).flags(flags))
+}
+
+/// How far someone is from their next level, in messages and the time they'd take
+#[derive(Debug, PartialEq, Eq)]
+struct NextLevelEstimate {
+    next_level: u64,
+    xp_needed: u64,
+    messages: u64,
+    /// The least time those messages can take, because of the cooldown between messages that earn XP
+    seconds: u64,
+}
+
+/// Estimate the next level from the average of the XP range, or None if messages don't earn any XP
+fn estimate_next_level(
+    xp: u64,
+    (min, max): (i16, i16),
+    cooldown: i16,
+) -> Option<NextLevelEstimate> {
+    let xp_range_sum = u64::try_from(i32::from(min) + i32::from(max)).ok()?;
+    if xp_range_sum == 0 {
+        return None;
+    }
+    let next_level = mee6::LevelInfo::new(xp).level() + 1;
+    let xp_needed = mee6::xp_needed_for_level(next_level).saturating_sub(xp);
+    // The average XP per message is half the sum of the range, so this is xp_needed / average, rounded up
+    let messages = (xp_needed * 2).div_ceil(xp_range_sum);
+    let cooldown = u64::try_from(cooldown).unwrap_or(0);
+    Some(NextLevelEstimate {
+        next_level,
+        xp_needed,
+        messages,
+        // The first message can be sent right away
+        seconds: messages.saturating_sub(1) * cooldown,
+    })
+}
+
+fn describe_estimate(config: &GuildConfig, xp: u64, whose: &str, locale: Option<&str>) -> String {
+    let (min, max) = config.message_xp_range();
+    let cooldown = config.cooldown.unwrap_or(DEFAULT_MESSAGE_COOLDOWN);
+    let Some(estimate) = estimate_next_level(xp, (min, max), cooldown) else {
+        return "Messages don't earn any XP in this server, so there's no way to tell!".to_string();
+    };
+    let mut content = format!(
+        "Level {} is **about {} message(s)** away for {whose} account ({} XP to go).",
+        config.display_level(estimate.next_level),
+        xpd_common::format_int(locale, estimate.messages),
+        xpd_common::format_int(locale, estimate.xp_needed),
+    );
+    if estimate.seconds > 0 {
+        let _ = write!(
+            content,
+            "\nOnly one message every {cooldown} seconds earns XP, so that takes at least {}.",
+            approximate_duration(estimate.seconds)
+        );
+    }
+    let earned = if min == max {
+        format!("{min} XP")
+    } else {
+        format!("{min} to {max} XP at random")
+    };
+    let _ = write!(
+        content,
+        "\n-# This is an estimate. Messages earn {earned}, \
+        and role cooldowns, content bonuses and reaction XP aren't counted."
+    );
+    content
+}
+
+/// A rough, human-friendly length of time, like "about 3 hours"
+fn approximate_duration(seconds: u64) -> String {
+    let (amount, unit) = match seconds {
+        0..60 => return "less than a minute".to_string(),
+        60..3600 => (seconds.div_ceil(60), "minute"),
+        3600..86400 => (seconds.div_ceil(3600), "hour"),
+        _ => (seconds.div_ceil(86400), "day"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    format!("about {amount} {unit}{plural}")
+}
+
+/// The most days of history `/rank history` shows
+const HISTORY_DAYS_SHOWN: i64 = 10;
+
+/// `/rank history` also shows where the member stood this many days ago
+const HISTORY_RANK_DAYS_AGO: i64 = 7;
+
+/// Lay out XP history as a monospace table, with the XP gained since the previous recorded day.
+fn history_table(history: &[XpHistoryEntry], locale: Option<&str>) -> String {
+    let rows: Vec<[String; 4]> = history
+        .iter()
+        .enumerate()
+        .map(|(idx, entry)| {
+            let gain = idx
+                .checked_sub(1)
+                .map(|previous| entry.xp - history[previous].xp)
+                .map_or_else(String::new, |gain| {
+                    let sign = if gain < 0 { "" } else { "+" };
+                    format!("{sign}{}", xpd_common::format_int(locale, gain))
+                });
+            let level = mee6::LevelInfo::new(u64::try_from(entry.xp).unwrap_or(0)).level();
+            [
+                entry.day.clone(),
+                xpd_common::format_int(locale, entry.xp),
+                level.to_string(),
+                gain,
+            ]
+        })
+        .collect();
+    let header = ["Day", "XP", "Level", "Gained"].map(ToString::to_string);
+    let mut widths = [0; 4];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::from("```\n");
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<String>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table.push_str("```");
+    table
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn generate_level_response(
     state: &SlashState,
     user: MemberDisplayInfo,
     guild_id: Id<GuildMarker>,
-    level_info: mee6::LevelInfo,
-    rank: i64,
+    invoker: Id<UserMarker>,
+    user_stats: UserStats,
+    locale: Option<&str>,
     flags: MessageFlags,
+    card_only: bool,
 ) -> Result<XpdSlashResponse, Error> {
-    let card = gen_card(state.clone(), user, Some(guild_id), level_info, rank).await?;
-    Ok(XpdSlashResponse::new().attachments([card]).flags(flags))
+    let user_id = user.id;
+    let card = gen_card(state.clone(), user, Some(guild_id), user_stats, locale);
+    // The details aren't shown next to a lone card, so don't look them up
+    if card_only {
+        return Ok(XpdSlashResponse::new()
+            .attachments([card.await?])
+            .flags(flags));
+    }
+    let last_active = last_active(state, user_id, guild_id, invoker);
+    let (last_active, card) = try_join!(last_active, card)?;
+    let mut details = Vec::with_capacity(2);
+    if let Some(time) = last_active {
+        details.push(format!("Last active: {time}"));
+    }
+    if let Some(messages) = user_stats.messages.filter(|messages| *messages > 0) {
+        details.push(format!(
+            "Messages: {}",
+            xpd_common::format_int(locale, messages)
+        ));
+    }
+    let content = (!details.is_empty()).then(|| details.join("\n"));
+    Ok(XpdSlashResponse::new()
+        .attachments([card])
+        .content_o(content)
+        .flags(flags))
+}
+
+/// Whether this guild would rather see a level 0 card than a message for members without XP
+async fn unranked_card(state: &SlashState, guild_id: Id<GuildMarker>) -> Result<bool, Error> {
+    Ok(xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .and_then(|config| config.unranked_card)
+        .unwrap_or(false))
 }
 
-async fn get_customizations_fields(
+/// Whether this guild treats bots like any other member, instead of refusing to rank them
+pub async fn rank_bots(state: &SlashState, guild_id: Id<GuildMarker>) -> Result<bool, Error> {
+    Ok(xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .and_then(|config| config.rank_bots)
+        .unwrap_or(false))
+}
+
+/// When the user last sent a message in this guild, shown in the invoker's timezone.
+async fn last_active(
+    state: &SlashState,
+    user: Id<UserMarker>,
+    guild_id: Id<GuildMarker>,
+    invoker: Id<UserMarker>,
+) -> Result<Option<String>, Error> {
+    let Some(last_message) = xpd_database::last_active(&state.db, user, guild_id).await? else {
+        return Ok(None);
+    };
+    let timezone = crate::timezone::user_timezone(state, invoker).await?;
+    Ok(crate::timezone::format_timestamp(last_message, timezone))
+}
+
+pub async fn get_customizations_fields(
     state: SlashState,
     user_id: Id<UserMarker>,
     guild_id: Option<Id<GuildMarker>>,
@@ -94,38 +460,114 @@ pub async fn gen_card(
     state: SlashState,
     user: MemberDisplayInfo,
     guild_id: Option<Id<GuildMarker>>,
-    level_info: mee6::LevelInfo,
-    rank: i64,
+    user_stats: UserStats,
+    locale: Option<&str>,
 ) -> Result<Attachment, Error> {
     let customizations_future = get_customizations_fields(state.clone(), user.id, guild_id);
     let avatar_future = get_avatar(state.clone(), user.id, user.avatar);
-    let (customizations, avatar) = try_join!(customizations_future, avatar_future)?;
+    let config_future = card_guild_config(&state, guild_id);
+    let (customizations, avatar, config) =
+        try_join!(customizations_future, avatar_future, config_future)?;
+    render_card(
+        &state,
+        &user,
+        &config,
+        customizations,
+        avatar,
+        user_stats,
+        locale,
+    )
+    .await
+}
+
+/// Like [`gen_card`], but with the card's customizations already picked out.
+pub async fn gen_card_with(
+    state: SlashState,
+    user: MemberDisplayInfo,
+    guild_id: Option<Id<GuildMarker>>,
+    customizations: Customizations,
+    user_stats: UserStats,
+    locale: Option<&str>,
+) -> Result<Attachment, Error> {
+    let avatar_future = get_avatar(state.clone(), user.id, user.avatar);
+    let config_future = card_guild_config(&state, guild_id);
+    let (avatar, config) = try_join!(avatar_future, config_future)?;
+    render_card(
+        &state,
+        &user,
+        &config,
+        customizations,
+        avatar,
+        user_stats,
+        locale,
+    )
+    .await
+}
+
+/// The config of the guild a card is shown in, which decides how levels are written on it.
+async fn card_guild_config(
+    state: &SlashState,
+    guild_id: Option<Id<GuildMarker>>,
+) -> Result<GuildConfig, Error> {
+    let Some(guild_id) = guild_id else {
+        return Ok(GuildConfig::default());
+    };
+    Ok(xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .unwrap_or_default())
+}
+
+async fn render_card(
+    state: &SlashState,
+    user: &MemberDisplayInfo,
+    config: &GuildConfig,
+    customizations: Customizations,
+    avatar: String,
+    user_stats: UserStats,
+    locale: Option<&str>,
+) -> Result<Attachment, Error> {
+    let level_info = mee6::LevelInfo::new(u64::try_from(user_stats.xp).unwrap_or(0));
+    let rank = user_stats.rank;
     #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
     let percentage = (level_info.percentage() * 100.0).round() as u64;
-    let png = state
+    let image = state
         .svg
-        .render(xpd_rank_card::Context {
-            level: level_info.level(),
-            rank,
-            name: user.display_name().to_string(),
-            percentage,
-            current: level_info.xp(),
-            needed: mee6::xp_needed_for_level(level_info.level() + 1),
-            customizations,
-            avatar,
-        })
+        .render_as(
+            xpd_rank_card::Context {
+                level: config.display_level(level_info.level()),
+                rank,
+                name: user.display_name().to_string(),
+                percentage,
+                current: level_info.xp(),
+                needed: mee6::xp_needed_for_level(level_info.level() + 1),
+                messages: user_stats
+                    .messages
+                    .and_then(|messages| u64::try_from(messages).ok()),
+                // Cards read like English unless we know who's looking
+                separator: locale.map_or(xpd_rank_card::DEFAULT_SEPARATOR, |locale| {
+                    xpd_common::thousands_separator(Some(locale))
+                }),
+                labels: card_labels(locale),
+                customizations,
+                avatar,
+            },
+            state.card_format,
+        )
         .await?;
     Ok(Attachment {
         description: Some(format!(
-            "{} is level {} (rank #{}), and is {}% of the way to level {}.",
+            "{} is level {} (rank #{}), and is {} of the way to level {}.",
             user.display_name(),
-            level_info.level(),
+            config.display_level(level_info.level()),
             rank,
-            (level_info.percentage() * 100.0).round(),
-            level_info.level() + 1
+            xpd_common::format_percentage(
+                level_info.percentage(),
+                xpd_common::DEFAULT_PERCENTAGE_PRECISION
+            ),
+            config.display_level(level_info.level() + 1)
         )),
-        file: png,
-        filename: "card.png".to_string(),
+        file: image,
+        filename: state.card_filename(),
         id: 0,
     })
 }
@@ -134,7 +576,16 @@ pub async fn get_customizations(
     state: &SlashState,
     ids: &[Id<GenericMarker>],
 ) -> Result<Customizations, Error> {
-    let Some(customizations) = xpd_database::card_customizations(&state.db, ids).await? else {
+    let customizations = xpd_database::card_customizations(&state.db, ids).await?;
+    resolve_customizations(state, customizations)
+}
+
+/// Fill in the defaults for anything the database doesn't set
+pub fn resolve_customizations(
+    state: &SlashState,
+    customizations: Option<RawCustomizations>,
+) -> Result<Customizations, Error> {
+    let Some(customizations) = customizations else {
         return Ok(state.svg.default_customizations().clone());
     };
     let defaults = state
@@ -152,6 +603,12 @@ pub async fn get_customizations(
             customizations.progress_foreground.as_deref(),
             defaults.progress_foreground,
         )?,
+        // an empty string means the gradient was removed, even if the layout has one
+        progress_foreground_2: match customizations.progress_foreground_2.as_deref() {
+            Some("") => None,
+            Some(color) => Some(Color::from_hex(&color)?),
+            None => defaults.progress_foreground_2,
+        },
         progress_background: color_or_default(
             customizations.progress_background.as_deref(),
             defaults.progress_background,
@@ -167,9 +624,39 @@ pub async fn get_customizations(
         font: customizations.font.unwrap_or_else(|| defaults.font.clone()),
         toy: customizations.toy_image,
         internal_name: customizations.card_layout,
+        subtitle: customizations
+            .subtitle
+            .filter(|subtitle| !subtitle.is_empty()),
+        // Anything unrecognized is ignored rather than breaking the card
+        progress_style: customizations
+            .progress_style
+            .and_then(|style| style.parse().ok())
+            .unwrap_or(defaults.progress_style),
+        size: customizations
+            .card_size
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(defaults.size),
+        // Presets which were removed from the gallery fall back to the background color
+        background_preset: customizations.background_preset.filter(|preset| {
+            state
+                .svg
+                .config()
+                .backgrounds
+                .iter()
+                .any(|background| background.internal_name == *preset)
+        }),
     })
 }
 
+/// Stat names for the card in the viewer's language, English if we don't know who's looking
+fn card_labels(locale: Option<&str>) -> xpd_rank_card::Labels {
+    let (rank, level) = xpd_common::card_labels(locale);
+    xpd_rank_card::Labels {
+        rank: rank.to_string(),
+        level: level.to_string(),
+    }
+}
+
 fn color_or_default(color: Option<&str>, default: Color) -> Result<Color, Error> {
     if let Some(color) = &color {
         Ok(Color::from_hex(color)?)
@@ -183,24 +670,194 @@ async fn get_avatar(
     user_id: Id<UserMarker>,
     avatar_hash: Option<ImageHash>,
 ) -> Result<String, Error> {
-    let url = avatar_hash.map_or_else(
-        || {
-            format!(
-                "https://cdn.discordapp.com/embed/avatars/{}.png",
-                (user_id.get() >> 22) % 6
-            )
-        },
-        |hash| format!("https://cdn.discordapp.com/avatars/{user_id}/{hash}.png",),
-    );
+    if let Some(avatar) =
+        fetch_avatar(&state, xpd_common::user_avatar_url(user_id, avatar_hash)).await?
+    {
+        return Ok(avatar);
+    }
+    if avatar_hash.is_some() {
+        if let Some(avatar) =
+            fetch_avatar(&state, xpd_common::user_avatar_url(user_id, None)).await?
+        {
+            return Ok(avatar);
+        }
+    }
+    // The card leaves the avatar's circle empty, which beats failing the whole command
+    Ok(String::new())
+}
+
+/// Download an avatar as a data URL, or None if what came back isn't an image the card renderer can read.
+async fn fetch_avatar(state: &SlashState, url: String) -> Result<Option<String>, Error> {
+    if !image_host_allowed(&url, &state.avatar_hosts) {
+        return Err(Error::DisallowedImageHost(url));
+    }
     debug!(url, "Downloading avatar");
-    let png = state.http.get(url).send().await?.bytes().await?;
+    let response = state.http.get(&url).send().await?;
+    // Redirects could otherwise take us somewhere we were never allowed to go
+    if !image_host_allowed(response.url().as_str(), &state.avatar_hosts) {
+        return Err(Error::DisallowedImageHost(response.url().to_string()));
+    }
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(ToOwned::to_owned);
+    let image = response.bytes().await?;
+    let Some(mime) = image_mime(&image).filter(|_| status.is_success()) else {
+        warn!(url, %status, ?content_type, len = image.len(), "Avatar download wasn't an image");
+        return Ok(None);
+    };
     debug!("Encoding avatar");
-    let data = "data:image/png;base64,".to_string() + &BASE64_ENGINE.encode(png);
+    let data = format!("data:{mime};base64,{}", BASE64_ENGINE.encode(image));
     debug!("Encoded avatar");
-    Ok(data)
+    Ok(Some(data))
+}
+
+/// The MIME type of an image the card renderer supports, going by its magic bytes
+fn image_mime(image: &[u8]) -> Option<&'static str> {
+    if image.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if image.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else {
+        None
+    }
+}
+
+/// Check that `url` is an HTTPS URL pointing at exactly one of `hosts`
+fn image_host_allowed(url: &str, hosts: &[String]) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    url.scheme() == "https"
+        && url.host_str().is_some_and(|host| {
+            hosts
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(host))
+        })
 }
 
 const BASE64_ENGINE: base64::engine::GeneralPurpose = base64::engine::GeneralPurpose::new(
     &base64::alphabet::STANDARD,
     base64::engine::general_purpose::NO_PAD,
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_level_estimates() {
+        // Level 1 needs 100 XP, and the default range averages 20 XP
+        assert_eq!(
+            estimate_next_level(0, (15, 25), 60),
+            Some(NextLevelEstimate {
+                next_level: 1,
+                xp_needed: 100,
+                messages: 5,
+                seconds: 240,
+            })
+        );
+        let partway = estimate_next_level(90, (15, 25), 0).unwrap();
+        assert_eq!(partway.messages, 1);
+        assert_eq!(partway.seconds, 0);
+        // Rounds up, a little short is still another message
+        assert_eq!(estimate_next_level(0, (30, 30), 60).unwrap().messages, 4);
+        assert_eq!(estimate_next_level(0, (0, 0), 60), None);
+    }
+
+    #[test]
+    fn approximate_durations() {
+        assert_eq!(approximate_duration(59), "less than a minute");
+        assert_eq!(approximate_duration(60), "about 1 minute");
+        assert_eq!(approximate_duration(61), "about 2 minutes");
+        assert_eq!(approximate_duration(7200), "about 2 hours");
+        assert_eq!(approximate_duration(86400 * 3), "about 3 days");
+    }
+
+    fn entry(day: &str, xp: i64) -> XpHistoryEntry {
+        XpHistoryEntry {
+            day: day.to_string(),
+            xp,
+        }
+    }
+
+    #[test]
+    fn past_ranks() {
+        let past = SnapshotRank {
+            day: "2026-10-01".to_string(),
+            xp: 12_345,
+            rank: 3,
+            members: 1_200,
+        };
+        assert_eq!(
+            past_rank_text(Some(&past), 7, "your", Some("en-US")),
+            "7 days ago (2026-10-01 UTC), your rank was #3 of 1,200, with 12,345 XP."
+        );
+        assert_eq!(
+            past_rank_text(None, 1, "Alex's", Some("en-US")),
+            "There's no record of Alex's XP from 1 day ago. \
+            XP history only goes back 90 days, and only has members who had chatted by then."
+        );
+    }
+
+    #[test]
+    fn history_tables() {
+        let table = history_table(
+            &[
+                entry("2026-10-01", 90),
+                entry("2026-10-02", 1_200),
+                entry("2026-10-05", 1_150),
+            ],
+            Some("en-US"),
+        );
+        assert_eq!(
+            table,
+            "```\n\
+            Day         XP     Level  Gained\n\
+            2026-10-01  90     0\n\
+            2026-10-02  1,200  5      +1,110\n\
+            2026-10-05  1,150  5      -50\n\
+            ```"
+        );
+        let single = history_table(&[entry("2026-10-01", 100)], Some("en-US"));
+        assert_eq!(single.lines().count(), 4);
+    }
+
+    #[test]
+    fn image_mimes() {
+        assert_eq!(
+            image_mime(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(image_mime(&[0xFF, 0xD8, 0xFF, 0xE0]), Some("image/jpeg"));
+        assert_eq!(image_mime(b"<!DOCTYPE html><html>"), None);
+        assert_eq!(image_mime(b"RIFF\0\0\0\0WEBP"), None);
+        assert_eq!(image_mime(b""), None);
+    }
+
+    #[test]
+    fn image_hosts() {
+        let hosts = ["cdn.discordapp.com".to_string()];
+        assert!(image_host_allowed(
+            "https://cdn.discordapp.com/avatars/1/abc.png",
+            &hosts
+        ));
+        assert!(image_host_allowed(
+            "https://CDN.discordapp.com/a.png",
+            &hosts
+        ));
+        assert!(!image_host_allowed(
+            "http://cdn.discordapp.com/a.png",
+            &hosts
+        ));
+        assert!(!image_host_allowed("https://evil.example/a.png", &hosts));
+        assert!(!image_host_allowed(
+            "https://cdn.discordapp.com.evil.example/a.png",
+            &hosts
+        ));
+        assert!(!image_host_allowed("https://169.254.169.254/", &hosts));
+        assert!(!image_host_allowed("not a url", &hosts));
+    }
+}
@@ -1,28 +1,49 @@
 #![deny(clippy::all, clippy::pedantic, clippy::nursery)]
-#![allow(clippy::module_name_repetitions)]
+#![allow(clippy::module_name_repetitions, clippy::result_large_err)]
 
 mod admin;
+mod announcements;
 mod autocomplete;
+mod backup;
+mod commands;
 mod config;
 mod dispatch;
+mod distribution;
 mod error;
 mod experience;
 mod gdpr;
 mod help;
+mod http;
 mod leaderboard;
 mod levels;
 mod manage_card;
 mod manager;
+mod members;
+mod newcomers;
+mod random_card;
 mod response;
 mod rewards;
+mod season;
+mod setup;
+mod summary;
+mod timezone;
 
-use std::{future::Future, sync::Arc, time::Instant};
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+pub use commands::DisabledCommands;
+pub use distribution::LevelDistributionCache;
 pub use error::Error;
+pub use http::GuildCountsCache;
+pub use manager::{ImportPause, RunningImports};
+pub use members::MemberFetches;
 pub use response::XpdSlashResponse;
 use sqlx::PgPool;
 use tokio::{runtime::Handle, sync::mpsc::Sender, task::JoinHandle};
-use tokio_util::task::TaskTracker;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use twilight_cache_inmemory::{InMemoryCache, ResourceType};
 use twilight_gateway::EventTypeFlags;
 use twilight_model::{
@@ -36,7 +57,9 @@ use twilight_model::{
     },
 };
 use twilight_util::builder::InteractionResponseDataBuilder;
-use xpd_common::{EventBusMessage, GuildConfig, RequiredDiscordResources};
+use xpd_common::{BuildInfo, EventBusMessage, GuildConfig, RequiredDiscordResources};
+use xpd_database::{RetryPolicy, RetryingStore, XpStore};
+pub use xpd_rank_card::CardFormat;
 use xpd_rank_card::SvgState;
 use xpd_util::LogError;
 
@@ -68,10 +91,12 @@ impl XpdSlash {
         control_guild: Id<GuildMarker>,
         owners: Vec<Id<UserMarker>>,
         event_bus: EventBus,
+        config: SlashConfig,
     ) -> Self {
         let svg = SvgState::new("xpd-card-resources").expect("Failed to initialize card renderer");
         let rt = Handle::current();
         let state = SlashState {
+            xp_store: RetryingStore::new(db.clone(), config.db_retry),
             db,
             client,
             app_id,
@@ -84,6 +109,18 @@ impl XpdSlash {
             control_guild,
             owners: owners.into(),
             event_bus,
+            import_sync: config.import_sync,
+            running_imports: RunningImports::default(),
+            import_pause: ImportPause::default(),
+            member_fetches: MemberFetches::default(),
+            guild_counts: GuildCountsCache::default(),
+            level_distributions: LevelDistributionCache::default(),
+            disabled_commands: DisabledCommands::default(),
+            reset_undo_days: config.reset_undo_days,
+            max_rewards: config.max_rewards,
+            avatar_hosts: config.avatar_hosts.into(),
+            card_format: config.card_format,
+            build: config.build,
         };
         Self { state }
     }
@@ -119,6 +156,18 @@ impl XpdSlash {
             })
     }
 
+    /// Start sending weekly summary DMs to users who asked for them, until `shutdown` is cancelled.
+    pub fn start_weekly_summaries(&self, shutdown: CancellationToken) {
+        self.state
+            .spawn(summary::summary_worker(self.state.clone(), shutdown));
+    }
+
+    /// Start ending seasons and announcing new ones as they come due, until `shutdown` is cancelled.
+    pub fn start_seasons(&self, shutdown: CancellationToken) {
+        self.state
+            .spawn(season::season_worker(self.state.clone(), shutdown));
+    }
+
     #[must_use]
     pub fn client(&self) -> Arc<twilight_http::Client> {
         self.state.client.clone()
@@ -157,28 +206,131 @@ pub struct SlashState {
     pub owners: Arc<[Id<UserMarker>]>,
     pub control_guild: Id<GuildMarker>,
     pub event_bus: EventBus,
+    pub import_sync: ImportSyncConfig,
+    pub running_imports: RunningImports,
+    /// Whether the bot owners have paused imports
+    pub import_pause: ImportPause,
+    /// Members being fetched from Discord because they weren't cached
+    pub member_fetches: MemberFetches,
+    /// Guild member counts recently fetched for admin stats
+    pub guild_counts: GuildCountsCache,
+    /// Guild level distributions recently counted for `/manage distribution`
+    pub level_distributions: LevelDistributionCache,
+    /// Commands each guild turned off with `/commands disable`
+    pub disabled_commands: DisabledCommands,
+    /// How many days a guild level reset can still be undone for
+    pub reset_undo_days: f64,
+    /// The most reward roles a guild can have
+    pub max_rewards: usize,
+    /// Hosts the card renderer is allowed to download images from
+    pub avatar_hosts: Arc<[String]>,
+    /// Image format rank cards are sent in
+    pub card_format: CardFormat,
+    /// Leveling data, with reads retried after brief database hiccups
+    pub xp_store: RetryingStore,
+    /// Which build of the bot is running
+    pub build: BuildInfo,
+}
+
+/// Image hosts card rendering may download from when nothing else is configured
+pub const DEFAULT_AVATAR_HOSTS: [&str; 1] = ["cdn.discordapp.com"];
+
+/// Settings picked by whoever runs the bot, rather than by each guild
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlashConfig {
+    /// How reward roles are handed out after a level import
+    pub import_sync: ImportSyncConfig,
+    /// How many days a guild level reset can still be undone for
+    pub reset_undo_days: f64,
+    /// The most reward roles a guild can have
+    pub max_rewards: usize,
+    /// Hosts the card renderer is allowed to download images from
+    pub avatar_hosts: Vec<String>,
+    /// Image format rank cards are sent in
+    pub card_format: CardFormat,
+    /// How reads of leveling data are retried after brief database hiccups
+    pub db_retry: RetryPolicy,
+    /// Which build of the bot is running, from the binary so it reports its own version
+    pub build: BuildInfo,
+}
+
+impl SlashConfig {
+    /// The default settings, for the bot build `build`
+    #[must_use]
+    pub fn new(build: BuildInfo) -> Self {
+        Self {
+            import_sync: ImportSyncConfig::default(),
+            reset_undo_days: xpd_common::DEFAULT_RESET_UNDO_DAYS,
+            max_rewards: xpd_common::DEFAULT_MAX_REWARDS,
+            avatar_hosts: DEFAULT_AVATAR_HOSTS.map(ToOwned::to_owned).to_vec(),
+            card_format: CardFormat::default(),
+            db_retry: RetryPolicy::default(),
+            build,
+        }
+    }
+}
+
+/// How quickly reward roles are handed out to members after a level import.
+/// Every member costs a couple of Discord API requests, so this trades import speed
+/// against rate limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImportSyncConfig {
+    /// How many members are processed before pausing
+    pub batch_size: usize,
+    /// How long to pause between batches
+    pub interval: Duration,
+    /// How many members within a batch are processed at the same time
+    pub concurrency: usize,
+    /// How many guilds can import level data at the same time
+    pub max_running_imports: usize,
+}
+
+impl Default for ImportSyncConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 100,
+            interval: Duration::from_secs(1),
+            concurrency: 1,
+            max_running_imports: 4,
+        }
+    }
 }
 
 impl SlashState {
     pub async fn update_config(&self, guild: Id<GuildMarker>, config: GuildConfig) {
+        self.disabled_commands.update(guild, &config);
         let _ = self
             .event_bus
             .send(EventBusMessage::UpdateConfig(guild, config))
             .await;
     }
 
+    /// What rank card attachments are called, which embeds need to show them
+    #[must_use]
+    pub fn card_filename(&self) -> String {
+        format!("card.{}", self.card_format.extension())
+    }
+
     pub async fn invalidate_rewards(&self, guild: Id<GuildMarker>) {
         let _ = self
             .event_bus
             .send(EventBusMessage::InvalidateRewards(guild))
             .await;
     }
+
+    pub async fn invalidate_role_cooldowns(&self, guild: Id<GuildMarker>) {
+        let _ = self
+            .event_bus
+            .send(EventBusMessage::InvalidateRoleCooldowns(guild))
+            .await;
+    }
 }
 
 #[derive(Copy, Clone)]
 pub struct UserStats {
     xp: i64,
     rank: i64,
+    messages: Option<i64>,
 }
 
 impl SlashState {
@@ -192,14 +344,19 @@ impl SlashState {
         id: Id<UserMarker>,
         guild_id: Id<GuildMarker>,
     ) -> Result<UserStats, Error> {
-        let xp = xpd_database::user_xp(&self.db, guild_id, id)
-            .await?
-            .unwrap_or(0);
-        let rank = xpd_database::count_with_higher_xp(&self.db, guild_id, xp)
-            .await?
-            .unwrap_or(0)
-            + 1;
-        Ok(UserStats { xp, rank })
+        let (xp, rank) = self.xp_store().xp_and_rank(id, guild_id).await?;
+        let messages = self
+            .xp_store
+            .policy()
+            .run(|| xpd_database::user_messages(&self.db, id, guild_id))
+            .await?;
+        Ok(UserStats { xp, rank, messages })
+    }
+
+    /// Leveling data storage, for code that doesn't need anything Postgres-specific.
+    #[must_use]
+    pub fn xp_store(&self) -> &dyn XpStore {
+        &self.xp_store
     }
 
     /// # Errors
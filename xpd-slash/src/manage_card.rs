@@ -1,50 +1,65 @@
-use mee6::LevelInfo;
+use std::borrow::Cow;
+
 use twilight_model::id::{
-    marker::{GenericMarker, GuildMarker},
+    marker::{GenericMarker, GuildMarker, UserMarker},
     Id,
 };
-use twilight_util::builder::embed::{EmbedBuilder, ImageSource};
+use twilight_util::builder::embed::{EmbedBuilder, EmbedFieldBuilder, ImageSource};
+use twilight_validate::embed::{EMBED_TOTAL_LENGTH, FIELD_COUNT};
 use xpd_common::MemberDisplayInfo;
 use xpd_database::CardUpdate;
-use xpd_rank_card::NameableItem;
-use xpd_slash_defs::card::{CardCommand, CardCommandEdit, ColorOption, GuildCardCommand};
+use xpd_rank_card::{
+    customizations::{
+        CardSetting, CardSize, Color, Customizations, LowContrast, ProgressStyle,
+        MIN_CARD_TEXT_CONTRAST,
+    },
+    ConfigItem, NameableItem,
+};
+use xpd_slash_defs::card::{
+    CardCommand, CardCommandEdit, CardPreset, ColorOption, GuildCardCommand,
+};
 
-use crate::{Error, SlashState, UserStats, XpdSlashResponse};
+use crate::{random_card::CardColors, Error, SlashState, UserStats, XpdSlashResponse};
 
 pub async fn user_card_update(
     command: CardCommand,
     invoker: MemberDisplayInfo,
     state: &SlashState,
     guild_id: Option<Id<GuildMarker>>,
+    locale: Option<&str>,
 ) -> Result<XpdSlashResponse, Error> {
     let (contents, target) = match command {
-        CardCommand::Reset(_reset) => (process_reset(state, invoker.id.cast()).await?, invoker),
+        CardCommand::Reset(_reset) => (
+            text_embed(process_reset(state, invoker.id.cast()).await?),
+            invoker,
+        ),
         CardCommand::Fetch(fetch) => {
             let target = fetch
                 .user
                 .map_or(invoker, |v| MemberDisplayInfo::from(v.resolved));
-            let contents = if let Some(guild_id) = guild_id {
+            let settings = if let Some(guild_id) = guild_id {
                 process_fetch(state, &[target.id.cast(), guild_id.cast()]).await
             } else {
                 process_fetch(state, &[target.id.cast()]).await
             }?;
-            (contents, target)
+            (settings_embed(&settings), target)
+        }
+        CardCommand::Edit(edit) => (
+            text_embed(process_edit(edit, state, invoker.id.cast()).await?),
+            invoker,
+        ),
+        CardCommand::Preset(preset) => (
+            text_embed(process_preset(preset.preset, state, invoker.id.cast()).await?),
+            invoker,
+        ),
+        CardCommand::Random(_random) => {
+            return crate::random_card::process_random(invoker, guild_id, state, locale).await;
         }
-        CardCommand::Edit(edit) => (process_edit(edit, state, invoker.id.cast()).await?, invoker),
-    };
-    let user_stats = if let Some(id) = guild_id {
-        state.get_user_stats(target.id, id).await?
-    } else {
-        // I am so mature.
-        UserStats { xp: 420, rank: 69 }
     };
-    let level_info = LevelInfo::new(u64::try_from(user_stats.xp).unwrap_or(0));
-    let card =
-        crate::levels::gen_card(state.clone(), target, guild_id, level_info, user_stats.rank)
-            .await?;
-    let embed = EmbedBuilder::new()
-        .description(contents)
-        .image(ImageSource::attachment("card.png")?)
+    let user_stats = card_stats(state, target.id, guild_id).await?;
+    let card = crate::levels::gen_card(state.clone(), target, guild_id, user_stats, locale).await?;
+    let embed = contents
+        .image(ImageSource::attachment(state.card_filename())?)
         .build();
     Ok(XpdSlashResponse::new()
         .attachments([card])
@@ -52,29 +67,55 @@ pub async fn user_card_update(
         .embeds([embed]))
 }
 
+/// Stats to show on a card preview. Outside of guilds, there are no real stats to show.
+pub async fn card_stats(
+    state: &SlashState,
+    user_id: Id<UserMarker>,
+    guild_id: Option<Id<GuildMarker>>,
+) -> Result<UserStats, Error> {
+    if let Some(id) = guild_id {
+        state.get_user_stats(user_id, id).await
+    } else {
+        // I am so mature.
+        Ok(UserStats {
+            xp: 420,
+            rank: 69,
+            messages: None,
+        })
+    }
+}
+
 pub async fn guild_card_update(
     command: GuildCardCommand,
     state: &SlashState,
     guild_id: Id<GuildMarker>,
+    locale: Option<&str>,
 ) -> Result<XpdSlashResponse, Error> {
     let contents = match command {
-        GuildCardCommand::Reset(_reset) => process_reset(state, guild_id.cast()).await?,
-        GuildCardCommand::Fetch(_fetch) => process_fetch(state, &[guild_id.cast()]).await?,
-        GuildCardCommand::Edit(edit) => process_edit(edit, state, guild_id.cast()).await?,
+        GuildCardCommand::Reset(_reset) => text_embed(process_reset(state, guild_id.cast()).await?),
+        GuildCardCommand::Fetch(_fetch) => {
+            settings_embed(&process_fetch(state, &[guild_id.cast()]).await?)
+        }
+        GuildCardCommand::Edit(edit) => {
+            text_embed(process_edit(edit, state, guild_id.cast()).await?)
+        }
     };
     let referenced_user = fake_user(guild_id.cast());
-    let level_info = LevelInfo::new(40);
+    let user_stats = UserStats {
+        xp: 40,
+        rank: 127,
+        messages: None,
+    };
     let card = crate::levels::gen_card(
         state.clone(),
         referenced_user,
         Some(guild_id),
-        level_info,
-        127,
+        user_stats,
+        locale,
     )
     .await?;
-    let embed = EmbedBuilder::new()
-        .description(contents)
-        .image(ImageSource::attachment("card.png")?)
+    let embed = contents
+        .image(ImageSource::attachment(state.card_filename())?)
         .build();
     Ok(XpdSlashResponse::new()
         .ephemeral(true)
@@ -103,15 +144,73 @@ fn process_edit_helper<I: NameableItem>(
 
 pub const CUSTOM_CARD_NULL_SENTINEL: &str = "NULL";
 
+pub const MAX_CARD_SUBTITLE_LEN: usize = 32;
+
+/// Blank subtitles or the null sentinel clear the subtitle, which is stored as an empty string.
+fn process_subtitle(subtitle: Option<String>) -> Result<Option<String>, Error> {
+    let Some(subtitle) = subtitle else {
+        return Ok(None);
+    };
+    let subtitle = subtitle.trim();
+    if subtitle == CUSTOM_CARD_NULL_SENTINEL {
+        return Ok(Some(String::new()));
+    }
+    if subtitle.chars().count() > MAX_CARD_SUBTITLE_LEN {
+        return Err(Error::SubtitleTooLong);
+    }
+    if !subtitle_allowed(subtitle) {
+        return Err(Error::SubtitleNotAllowed);
+    }
+    Ok(Some(subtitle.to_string()))
+}
+
+/// The null sentinel clears the gradient, which is stored as an empty string.
+fn process_gradient(color: Option<&str>) -> Result<Option<String>, Error> {
+    match color.map(str::trim) {
+        None => Ok(None),
+        Some(CUSTOM_CARD_NULL_SENTINEL) => Ok(Some(String::new())),
+        Some(color) => Ok(Some(Color::from_hex(&color)?.to_string())),
+    }
+}
+
+/// The null sentinel clears the background image, which is stored as an empty string.
+fn process_background_preset(
+    backgrounds: &[ConfigItem],
+    background: Option<&str>,
+) -> Result<Option<String>, Error> {
+    match background {
+        None => Ok(None),
+        Some(CUSTOM_CARD_NULL_SENTINEL) => Ok(Some(String::new())),
+        Some(chosen) => backgrounds
+            .iter()
+            .find_map(|ci| matches_config_item(ci, chosen))
+            .map(Some)
+            .ok_or(Error::UnknownBackground),
+    }
+}
+
+/// Cards get shown off in public, so keep links and invites off of them.
+fn subtitle_allowed(subtitle: &str) -> bool {
+    let lower = subtitle.to_lowercase();
+    !subtitle.chars().any(char::is_control)
+        && !["://", "discord.gg/", "discord.com/invite", "www."]
+            .iter()
+            .any(|link| lower.contains(link))
+}
+
 async fn process_edit(
     edit: CardCommandEdit,
     state: &SlashState,
     id: Id<GenericMarker>,
 ) -> Result<String, Error> {
+    let reject_low_contrast = edit.reject_low_contrast.unwrap_or(false);
     let items = state.svg.config();
     let toy_image = process_edit_helper(&items.toys, edit.toy_image, Error::UnknownToy)?;
     let card_layout = process_edit_helper(&items.cards, edit.card_layout, Error::UnknownCard)?;
     let font = process_edit_helper(&items.fonts, edit.font, Error::UnknownFont)?;
+    let subtitle = process_subtitle(edit.subtitle)?;
+    let background_preset =
+        process_background_preset(&items.backgrounds, edit.background_preset.as_deref())?;
 
     let update = CardUpdate {
         username: edit.username.map(ColorOption::string),
@@ -121,17 +220,92 @@ async fn process_edit(
         background: edit.background.map(ColorOption::string),
         progress_background: edit.progress_background.map(ColorOption::string),
         progress_foreground: edit.progress_foreground.map(ColorOption::string),
+        progress_foreground_2: process_gradient(edit.progress_foreground_2.as_deref())?,
         foreground_xp_count: edit.foreground_xp_count.map(ColorOption::string),
         background_xp_count: edit.background_xp_count.map(ColorOption::string),
         font,
         toy_image,
         card_layout,
         card_layout_default: "classic.svg".to_string(),
+        subtitle,
+        progress_style: edit
+            .progress_style
+            .map(|style| ProgressStyle::from(style).as_str().to_string()),
+        card_size: edit
+            .card_size
+            .map(|size| CardSize::from(size).as_str().to_string()),
+        background_preset,
     };
 
-    xpd_database::update_card(&state.db, id, &update).await?;
+    let before = crate::levels::get_customizations(state, &[id]).await?;
+    let mut txn = state.db.begin().await?;
+    xpd_database::update_card(txn.as_mut(), id, &update).await?;
+    let after = xpd_database::card_customizations(txn.as_mut(), &[id]).await?;
+    let after = crate::levels::resolve_customizations(state, after)?;
+
+    let warnings = new_low_contrast(&before, &after, &items.backgrounds);
+    if reject_low_contrast && !warnings.is_empty() {
+        // dropping the transaction rolls the edit back
+        return Err(Error::LowContrast(join_lines(&warnings)));
+    }
+    txn.commit().await?;
 
-    Ok("Updated card!".to_string())
+    if warnings.is_empty() {
+        Ok("Updated card!".to_string())
+    } else {
+        Ok(format!(
+            "Updated card! Some text may be hard to read now:\n{}",
+            join_lines(&warnings)
+        ))
+    }
+}
+
+/// Only complain about text the edit made harder to read, not whatever was already there
+fn new_low_contrast(
+    before: &Customizations,
+    after: &Customizations,
+    backgrounds: &[ConfigItem],
+) -> Vec<LowContrast> {
+    let old = low_contrast(before, backgrounds);
+    low_contrast(after, backgrounds)
+        .into_iter()
+        .filter(|new| {
+            !old.iter()
+                .any(|old| old.text == new.text && old.ratio <= new.ratio)
+        })
+        .collect()
+}
+
+/// Text on a background image is checked against the image's average color, since the solid
+/// background color is hidden behind it.
+fn low_contrast(customizations: &Customizations, backgrounds: &[ConfigItem]) -> Vec<LowContrast> {
+    let image_color = customizations
+        .background_preset
+        .as_deref()
+        .and_then(|preset| {
+            backgrounds
+                .iter()
+                .find(|background| background.internal_name == preset)
+                .and_then(|background| background.color)
+        });
+    image_color.map_or_else(
+        || customizations.low_contrast(MIN_CARD_TEXT_CONTRAST),
+        |background| {
+            Customizations {
+                background,
+                ..customizations.clone()
+            }
+            .low_contrast(MIN_CARD_TEXT_CONTRAST)
+        },
+    )
+}
+
+fn join_lines(warnings: &[LowContrast]) -> String {
+    warnings
+        .iter()
+        .map(|warning| format!("- {warning}"))
+        .collect::<Vec<String>>()
+        .join("\n")
 }
 
 fn matches_config_item<I: NameableItem>(ci: &I, choice: &str) -> Option<String> {
@@ -142,18 +316,136 @@ fn matches_config_item<I: NameableItem>(ci: &I, choice: &str) -> Option<String>
     }
 }
 
+async fn process_preset(
+    preset: CardPreset,
+    state: &SlashState,
+    id: Id<GenericMarker>,
+) -> Result<String, Error> {
+    let colors = preset_colors(preset);
+    xpd_database::update_card(&state.db, id, &colors.card_update()).await?;
+    Ok("Applied the theme! You can still change single colors with `/card edit`.".to_string())
+}
+
+const fn preset_colors(preset: CardPreset) -> CardColors {
+    match preset {
+        CardPreset::Dark => DARK_PRESET,
+        CardPreset::Light => LIGHT_PRESET,
+        CardPreset::Ocean => OCEAN_PRESET,
+        CardPreset::Forest => FOREST_PRESET,
+    }
+}
+
+const DARK_PRESET: CardColors = CardColors {
+    background: Color::new(0x1e, 0x1f, 0x22),
+    border: Color::new(0x2b, 0x2d, 0x31),
+    username: Color::new(0xf2, 0xf3, 0xf5),
+    rank: Color::new(0xb5, 0xba, 0xc1),
+    level: Color::new(0x94, 0x9c, 0xf7),
+    progress_foreground: Color::new(0x58, 0x65, 0xf2),
+    progress_background: Color::new(0x38, 0x3a, 0x40),
+    foreground_xp_count: Color::new(0xff, 0xff, 0xff),
+    background_xp_count: Color::new(0xf2, 0xf3, 0xf5),
+};
+
+const LIGHT_PRESET: CardColors = CardColors {
+    background: Color::new(0xf6, 0xf8, 0xfa),
+    border: Color::new(0xd0, 0xd7, 0xde),
+    username: Color::new(0x1f, 0x23, 0x28),
+    rank: Color::new(0x57, 0x60, 0x6a),
+    level: Color::new(0x09, 0x69, 0xda),
+    progress_foreground: Color::new(0x09, 0x69, 0xda),
+    progress_background: Color::new(0xd8, 0xde, 0xe4),
+    foreground_xp_count: Color::new(0xff, 0xff, 0xff),
+    background_xp_count: Color::new(0x1f, 0x23, 0x28),
+};
+
+const OCEAN_PRESET: CardColors = CardColors {
+    background: Color::new(0x0b, 0x1f, 0x33),
+    border: Color::new(0x12, 0x3a, 0x5c),
+    username: Color::new(0xe6, 0xf4, 0xff),
+    rank: Color::new(0x7c, 0xc4, 0xf2),
+    level: Color::new(0x38, 0xd9, 0xc7),
+    progress_foreground: Color::new(0x17, 0x6f, 0xa3),
+    progress_background: Color::new(0x16, 0x34, 0x4f),
+    foreground_xp_count: Color::new(0xff, 0xff, 0xff),
+    background_xp_count: Color::new(0xcf, 0xe9, 0xff),
+};
+
+const FOREST_PRESET: CardColors = CardColors {
+    background: Color::new(0x14, 0x22, 0x1a),
+    border: Color::new(0x27, 0x42, 0x2f),
+    username: Color::new(0xea, 0xf5, 0xe4),
+    rank: Color::new(0xa9, 0xd1, 0x8e),
+    level: Color::new(0xe3, 0xc5, 0x67),
+    progress_foreground: Color::new(0x3f, 0x6e, 0x30),
+    progress_background: Color::new(0x24, 0x38, 0x2a),
+    foreground_xp_count: Color::new(0xff, 0xff, 0xff),
+    background_xp_count: Color::new(0xd6, 0xea, 0xd0),
+};
+
 async fn process_reset(state: &SlashState, id: Id<GenericMarker>) -> Result<String, Error> {
     xpd_database::delete_card_customizations(&state.db, id).await?;
     Ok("Card settings cleared!".to_string())
 }
 
-async fn process_fetch(state: &SlashState, ids: &[Id<GenericMarker>]) -> Result<String, Error> {
+async fn process_fetch(
+    state: &SlashState,
+    ids: &[Id<GenericMarker>],
+) -> Result<Vec<CardSetting>, Error> {
     let card = crate::levels::get_customizations(state, ids).await?;
     let defaults = state
         .svg
         .customizations_for(&card.internal_name)
         .ok_or(Error::UnknownCard)?;
-    Ok(card.display(defaults)?)
+    Ok(card.settings(defaults))
+}
+
+fn text_embed(contents: String) -> EmbedBuilder {
+    EmbedBuilder::new().description(contents)
+}
+
+/// The most characters of a setting's value shown, the rest is cut off
+const SETTING_VALUE_LENGTH: usize = 256;
+
+/// Room kept in the embed for the field saying how many settings didn't fit
+const MORE_SETTINGS_LENGTH: usize = 64;
+
+/// Lay out card settings as one embed field each. Long values are cut short, and settings which
+/// would push the embed past Discord's limits are left out and counted in a last field instead.
+fn settings_embed(settings: &[CardSetting]) -> EmbedBuilder {
+    let mut embed = EmbedBuilder::new();
+    let mut length = 0;
+    for (idx, setting) in settings.iter().enumerate() {
+        let default = if setting.default { " (default)" } else { "" };
+        let value = format!(
+            "`{}`{default}",
+            truncate_chars(&setting.value, SETTING_VALUE_LENGTH)
+        );
+        let field_length = setting.name.chars().count() + value.chars().count();
+        let remaining = settings.len() - idx;
+        let fits_count = remaining == 1 || idx + 1 < FIELD_COUNT;
+        let fits_length = length + field_length + MORE_SETTINGS_LENGTH <= EMBED_TOTAL_LENGTH;
+        if !(fits_count && fits_length) {
+            let plural = if remaining == 1 { "" } else { "s" };
+            return embed.field(EmbedFieldBuilder::new(
+                "More",
+                format!("...and {remaining} more setting{plural}"),
+            ));
+        }
+        length += field_length;
+        embed = embed.field(EmbedFieldBuilder::new(setting.name, value).inline());
+    }
+    embed
+}
+
+/// Cut `text` down to at most `max` characters, marking that it was cut
+fn truncate_chars(text: &str, max: usize) -> Cow<'_, str> {
+    if text.chars().count() <= max {
+        return Cow::Borrowed(text);
+    }
+    let mut short: String = text.chars().take(max.saturating_sub(1)).collect();
+    short.push('…');
+    Cow::Owned(short)
 }
 
 fn fake_user(id: Id<GenericMarker>) -> MemberDisplayInfo {
@@ -165,5 +457,232 @@ fn fake_user(id: Id<GenericMarker>) -> MemberDisplayInfo {
         avatar: None,
         local_avatar: None,
         bot: false,
+        discriminator: Some(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn background_presets() {
+        let backgrounds = [ConfigItem {
+            file: "./backgrounds/ocean.png".into(),
+            internal_name: "ocean.png".to_string(),
+            display_name: "Ocean".to_string(),
+            color: Some(Color::new(14, 74, 114)),
+        }];
+        assert_eq!(process_background_preset(&backgrounds, None).unwrap(), None);
+        assert_eq!(
+            process_background_preset(&backgrounds, Some("ocean.png")).unwrap(),
+            Some("ocean.png".to_string())
+        );
+        assert_eq!(
+            process_background_preset(&backgrounds, Some("NULL")).unwrap(),
+            Some(String::new())
+        );
+        for bad in ["Ocean", "https://example.com/a.png", "bee.png"] {
+            assert!(matches!(
+                process_background_preset(&backgrounds, Some(bad)),
+                Err(Error::UnknownBackground)
+            ));
+        }
+    }
+
+    fn setting(name: &'static str, value: String) -> CardSetting {
+        CardSetting {
+            name,
+            value,
+            default: false,
+        }
+    }
+
+    #[test]
+    fn settings_fit_in_an_embed() {
+        let color = Color::new(1, 2, 3);
+        let long = "🦀".repeat(2000);
+        let card = Customizations {
+            username: color,
+            rank: color,
+            level: color,
+            border: color,
+            background: color,
+            progress_foreground: color,
+            progress_foreground_2: Some(color),
+            progress_background: color,
+            background_xp_count: color,
+            foreground_xp_count: color,
+            font: long.clone(),
+            toy: Some(long.clone()),
+            internal_name: long.clone(),
+            subtitle: Some(long.clone()),
+            progress_style: ProgressStyle::Square,
+            size: CardSize::Large,
+            background_preset: Some(long),
+        };
+        let defaults = Customizations {
+            font: "Roboto".to_string(),
+            internal_name: "classic.svg".to_string(),
+            toy: None,
+            subtitle: None,
+            background_preset: None,
+            ..card
+        };
+        let embed = settings_embed(&card.settings(&defaults)).build();
+        twilight_validate::embed::embed(&embed).unwrap();
+        assert_eq!(embed.fields.len(), card.settings(&defaults).len());
+        assert_eq!(embed.fields[0].value, "`#010203` (default)");
+        let font = embed
+            .fields
+            .iter()
+            .find(|field| field.name == "Font")
+            .unwrap();
+        assert_eq!(font.value.chars().count(), SETTING_VALUE_LENGTH + 2);
+        assert!(font.value.ends_with("…`"));
+
+        // However many settings cards grow, the embed stays valid
+        let many: Vec<CardSetting> = (0..40)
+            .map(|_| setting("Setting", "a".repeat(300)))
+            .collect();
+        let embed = settings_embed(&many).build();
+        twilight_validate::embed::embed(&embed).unwrap();
+        let last = embed.fields.last().unwrap();
+        assert_eq!(last.name, "More");
+        assert!(embed.fields.len() <= FIELD_COUNT);
+
+        let exact: Vec<CardSetting> = (0..FIELD_COUNT)
+            .map(|_| setting("Setting", "a".to_string()))
+            .collect();
+        assert_eq!(settings_embed(&exact).build().fields.len(), FIELD_COUNT);
+    }
+
+    #[test]
+    fn subtitles() {
+        assert_eq!(process_subtitle(None).unwrap(), None);
+        assert_eq!(
+            process_subtitle(Some(" she/her ".to_string())).unwrap(),
+            Some("she/her".to_string())
+        );
+        assert_eq!(
+            process_subtitle(Some("NULL".to_string())).unwrap(),
+            Some(String::new())
+        );
+        assert_eq!(
+            process_subtitle(Some("  ".to_string())).unwrap(),
+            Some(String::new())
+        );
+        assert!(matches!(
+            process_subtitle(Some("a".repeat(MAX_CARD_SUBTITLE_LEN + 1))),
+            Err(Error::SubtitleTooLong)
+        ));
+        assert!(process_subtitle(Some("🦀".repeat(MAX_CARD_SUBTITLE_LEN))).is_ok());
+        for bad in ["join discord.gg/abc", "https://example.com", "two\nlines"] {
+            assert!(matches!(
+                process_subtitle(Some(bad.to_string())),
+                Err(Error::SubtitleNotAllowed)
+            ));
+        }
+    }
+
+    #[test]
+    fn background_images_are_checked() {
+        let white = Color::new(255, 255, 255);
+        let black = Color::new(0, 0, 0);
+        let backgrounds = [ConfigItem {
+            file: "./backgrounds/night_sky.png".into(),
+            internal_name: "night_sky.png".to_string(),
+            display_name: "Night Sky".to_string(),
+            color: Some(Color::new(19, 23, 55)),
+        }];
+        let plain = Customizations {
+            username: black,
+            rank: black,
+            level: black,
+            border: white,
+            background: white,
+            progress_foreground: white,
+            progress_foreground_2: None,
+            progress_background: white,
+            background_xp_count: black,
+            foreground_xp_count: black,
+            font: String::new(),
+            toy: None,
+            internal_name: String::new(),
+            subtitle: None,
+            progress_style: ProgressStyle::Rounded,
+            size: CardSize::Normal,
+            background_preset: None,
+        };
+        assert!(new_low_contrast(&plain, &plain, &backgrounds).is_empty());
+
+        // Black text is fine on white, but not on the dark image covering it
+        let night = Customizations {
+            background_preset: Some("night_sky.png".to_string()),
+            ..plain.clone()
+        };
+        let warnings = new_low_contrast(&plain, &night, &backgrounds);
+        let texts: Vec<&str> = warnings.iter().map(|warning| warning.text).collect();
+        assert_eq!(texts, ["Important text", "Rank", "Level"]);
+    }
+
+    #[test]
+    fn gradients() {
+        assert_eq!(process_gradient(None).unwrap(), None);
+        assert_eq!(
+            process_gradient(Some("#ff8000")).unwrap(),
+            Some("#FF8000".to_string())
+        );
+        assert_eq!(process_gradient(Some("NULL")).unwrap(), Some(String::new()));
+        assert!(process_gradient(Some("orange")).is_err());
+    }
+
+    #[test]
+    fn presets_clear_the_gradient() {
+        for preset in [
+            CardPreset::Dark,
+            CardPreset::Light,
+            CardPreset::Ocean,
+            CardPreset::Forest,
+        ] {
+            let update = preset_colors(preset).card_update();
+            assert_eq!(
+                update.progress_foreground_2,
+                Some(String::new()),
+                "{preset:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn presets_are_readable() {
+        for preset in [
+            CardPreset::Dark,
+            CardPreset::Light,
+            CardPreset::Ocean,
+            CardPreset::Forest,
+        ] {
+            let colors = preset_colors(preset);
+            for text in [colors.username, colors.rank, colors.level] {
+                assert!(
+                    text.contrast_ratio(colors.background) >= crate::random_card::MIN_TEXT_CONTRAST,
+                    "{preset:?} {text}"
+                );
+            }
+            assert!(
+                colors
+                    .foreground_xp_count
+                    .contrast_ratio(colors.progress_foreground)
+                    >= crate::random_card::MIN_TEXT_CONTRAST,
+                "{preset:?}"
+            );
+            assert!(
+                colors
+                    .background_xp_count
+                    .contrast_ratio(colors.progress_background)
+                    >= crate::random_card::MIN_TEXT_CONTRAST,
+                "{preset:?}"
+            );
+        }
     }
 }
@@ -1,23 +1,30 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, PoisonError},
+};
+
 use http_body_util::{BodyExt, Limited};
 use serde::{Deserialize, Serialize};
-use tokio::time::Instant;
+use tokio::{sync::watch, task::JoinSet, time::Instant};
 use twilight_model::{
-    channel::{message::AllowedMentions, Attachment},
+    channel::Attachment,
     http::attachment::Attachment as HttpAttachment,
     id::{
-        marker::{GuildMarker, UserMarker},
+        marker::{GuildMarker, RoleMarker, UserMarker},
         Id,
     },
 };
 use twilight_util::builder::embed::EmbedBuilder;
+use xpd_common::{RewardStrategy, RoleReward};
 use xpd_slash_defs::manage::{ManageCommand, CONFIRMATION_STRING};
 
-use crate::{dispatch::Respondable, Error, SlashState, XpdSlashResponse};
+use crate::{dispatch::Respondable, http::DiscordHttp, Error, SlashState, XpdSlashResponse};
 
 pub async fn process_manage(
     data: ManageCommand,
     guild_id: Id<GuildMarker>,
     respondable: Respondable,
+    locale: Option<&str>,
     state: SlashState,
 ) -> Result<XpdSlashResponse, Error> {
     let contents = match data {
@@ -32,17 +39,22 @@ pub async fn process_manage(
             import.overwrite.unwrap_or(false),
         )?,
         ManageCommand::Export(_) => export_level_data(state, respondable, guild_id)?,
+        ManageCommand::Distribution(_) => {
+            crate::distribution::level_distribution(&state, guild_id, locale).await?
+        }
     };
     Ok(XpdSlashResponse::new()
-        .allowed_mentions(AllowedMentions::default())
+        .no_pings()
         .ephemeral(true)
         .embeds([EmbedBuilder::new().description(contents).build()]))
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
 pub struct ImportUser {
     id: Id<UserMarker>,
     xp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    messages: Option<i64>,
 }
 
 #[allow(clippy::unnecessary_wraps)]
@@ -74,6 +86,7 @@ async fn background_data_export(
         .map(|us| ImportUser {
             id: us.id,
             xp: us.xp,
+            messages: Some(us.messages),
         })
         .collect();
     let file = serde_json::to_vec_pretty(&levels)?;
@@ -83,7 +96,6 @@ async fn background_data_export(
         .attachments([attachment]))
 }
 
-#[allow(clippy::unnecessary_wraps)]
 fn import_level_data(
     state: SlashState,
     respondable: Respondable,
@@ -91,17 +103,115 @@ fn import_level_data(
     attachment: Attachment,
     overwrite: bool,
 ) -> Result<String, Error> {
-    state.clone().spawn(background_data_operation_wrapper(
-        state,
-        respondable,
-        guild_id,
-        Some(attachment),
-        overwrite,
-    ));
+    if usize::try_from(attachment.size).map_or(true, |size| size > MAX_IMPORT_SIZE) {
+        return Err(Error::ImportFileTooBig);
+    }
+    if state.import_pause.is_paused() {
+        return Err(Error::ImportsPaused);
+    }
+    let slot = state
+        .running_imports
+        .start(guild_id, state.import_sync.max_running_imports)?;
+    state.clone().spawn(async move {
+        background_data_operation_wrapper(
+            state,
+            respondable,
+            guild_id,
+            Some(attachment),
+            overwrite,
+        )
+        .await;
+        drop(slot);
+    });
     Ok("Importing level data, check back soon!".to_string())
 }
 
+/// Guilds which are importing level data right now
+#[derive(Clone, Debug, Default)]
+pub struct RunningImports(Arc<Mutex<HashSet<Id<GuildMarker>>>>);
+
+impl RunningImports {
+    /// Claim a spot for this guild's import, which is given back when the returned slot is dropped.
+    /// # Errors
+    /// If this guild is already importing, or `max` guilds already are.
+    pub fn start(&self, guild_id: Id<GuildMarker>, max: usize) -> Result<ImportSlot, Error> {
+        let mut running = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if running.contains(&guild_id) {
+            return Err(Error::ImportAlreadyRunning);
+        }
+        if running.len() >= max {
+            return Err(Error::TooManyImports);
+        }
+        running.insert(guild_id);
+        drop(running);
+        Ok(ImportSlot {
+            imports: self.clone(),
+            guild_id,
+        })
+    }
+
+    /// How many guilds are importing level data right now
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+}
+
+/// Lets the bot owners hold off imports without restarting, like during a Discord outage.
+/// New imports are refused while paused, and reward syncs stop between batches until resumed.
+#[derive(Clone, Debug)]
+pub struct ImportPause(Arc<watch::Sender<bool>>);
+
+impl Default for ImportPause {
+    fn default() -> Self {
+        Self(Arc::new(watch::Sender::new(false)))
+    }
+}
+
+impl ImportPause {
+    /// Pause or resume imports. Returns whether they were paused before.
+    #[must_use = "this returns whether imports were already paused"]
+    pub fn set(&self, paused: bool) -> bool {
+        self.0.send_replace(paused)
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Wait until imports aren't paused, returning straight away if they aren't.
+    pub async fn wait(&self) {
+        // The sender lives as long as self does, so this can't fail
+        let _ = self.0.subscribe().wait_for(|paused| !paused).await;
+    }
+}
+
+#[derive(Debug)]
+pub struct ImportSlot {
+    imports: RunningImports,
+    guild_id: Id<GuildMarker>,
+}
+
+impl Drop for ImportSlot {
+    fn drop(&mut self) {
+        self.imports
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.guild_id);
+    }
+}
+
 const MAX_IMPORT_SIZE: usize = 1024 * 1024 * 10;
+/// The last column is optional
+const CSV_IMPORT_HEADER: [&str; 3] = ["user_id", "xp", "messages"];
+
+#[derive(Debug, Default)]
+struct ParsedImport {
+    users: Vec<ImportUser>,
+    skipped: usize,
+}
 
 async fn background_data_import(
     state: &SlashState,
@@ -111,6 +221,7 @@ async fn background_data_import(
 ) -> Result<XpdSlashResponse, Error> {
     let start = Instant::now();
 
+    let is_csv = attachment.filename.to_ascii_lowercase().ends_with(".csv");
     let request = state.http.get(attachment.url).send().await?;
     request.error_for_status_ref()?;
 
@@ -121,25 +232,221 @@ async fn background_data_import(
         .map_err(|_| Error::RawHttpBody)?
         .to_bytes();
 
-    let data: Vec<ImportUser> = serde_json::from_slice(&body)?;
-    let user_count = data.len();
+    let parsed = if is_csv {
+        parse_csv_import(&body)?
+    } else {
+        parse_json_import(&body)?
+    };
+
+    let mut imported = 0;
+    let mut updated = 0;
+    let mut new_xp = HashMap::with_capacity(parsed.users.len());
     let mut txn = state.db.begin().await?;
-    for user in data {
-        if overwrite {
-            xpd_database::set_xp(txn.as_mut(), user.id, guild_id, user.xp).await?;
+    for user in parsed.users {
+        let result = xpd_database::import_xp(
+            txn.as_mut(),
+            user.id,
+            guild_id,
+            user.xp,
+            user.messages,
+            overwrite,
+        )
+        .await?;
+        if result.inserted {
+            imported += 1;
         } else {
-            xpd_database::add_xp(txn.as_mut(), user.id, guild_id, user.xp).await?;
+            updated += 1;
         }
+        new_xp.insert(user.id, result.xp);
     }
 
     txn.commit().await?;
 
+    state.spawn(sync_imported_rewards(state.clone(), guild_id, new_xp));
+
     let seconds = start.elapsed().as_secs_f64();
+    let skipped = parsed.skipped;
     Ok(XpdSlashResponse::with_embed_text(format!(
-        "Imported XP data for {user_count} users in {seconds:.2} seconds!"
+        "Imported XP data in {seconds:.2} seconds!\n\
+        New users: {imported}\nUpdated users: {updated}\nSkipped rows: {skipped}\n\
+        Reward roles will be updated in the background."
     )))
 }
 
+fn parse_json_import(data: &[u8]) -> Result<ParsedImport, Error> {
+    let users: Vec<ImportUser> = serde_json::from_slice(data)?;
+    let total = users.len();
+    let users: Vec<ImportUser> = users
+        .into_iter()
+        .filter(|user| user.xp >= 0 && user.messages.is_none_or(|messages| messages >= 0))
+        .collect();
+    Ok(ParsedImport {
+        skipped: total - users.len(),
+        users,
+    })
+}
+
+fn parse_csv_import(data: &[u8]) -> Result<ParsedImport, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let header = reader.headers()?;
+    if !(CSV_IMPORT_HEADER.len() - 1..=CSV_IMPORT_HEADER.len()).contains(&header.len())
+        || !header
+            .iter()
+            .zip(CSV_IMPORT_HEADER)
+            .all(|(got, expected)| got.eq_ignore_ascii_case(expected))
+    {
+        return Err(Error::InvalidCsvImportHeader);
+    }
+
+    let mut parsed = ParsedImport::default();
+    for record in reader.records() {
+        match record.ok().as_ref().and_then(parse_csv_user) {
+            Some(user) => parsed.users.push(user),
+            None => parsed.skipped += 1,
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse_csv_user(record: &csv::StringRecord) -> Option<ImportUser> {
+    let id = record.get(0)?.parse().ok().and_then(Id::new_checked)?;
+    let xp = record.get(1)?.parse().ok().filter(|xp: &i64| *xp >= 0)?;
+    let messages = match record.get(2).filter(|messages| !messages.is_empty()) {
+        Some(messages) => Some(
+            messages
+                .parse()
+                .ok()
+                .filter(|messages: &i64| *messages >= 0)?,
+        ),
+        None => None,
+    };
+    Some(ImportUser { id, xp, messages })
+}
+
+async fn sync_imported_rewards(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    users: HashMap<Id<UserMarker>, i64>,
+) {
+    let users = users.into_iter().collect();
+    if let Err(source) = sync_guild_rewards(&state, guild_id, users).await {
+        error!(?source, guild = ?guild_id, "Failed to update reward roles after import");
+    }
+}
+
+/// Give every listed member the reward roles their XP earns them, a batch at a time.
+/// Roles are only ever added, so this is safe to run again if it fails partway.
+/// Returns how many members had roles added.
+pub async fn sync_guild_rewards(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    users: Vec<(Id<UserMarker>, i64)>,
+) -> Result<usize, Error> {
+    let mut rewards = xpd_database::guild_rewards(&state.db, guild_id).await?;
+    if rewards.is_empty() {
+        return Ok(0);
+    }
+    rewards.sort_by(xpd_common::compare_rewards_requirement);
+    let strategy = xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .unwrap_or_default()
+        .reward_strategy();
+
+    let reward_roles: Vec<Id<RoleMarker>> = rewards.iter().map(|reward| reward.id).collect();
+    if !xpd_util::can_manage_roles(&state.cache, state.bot_id, guild_id, &reward_roles)?
+        .can_update_roles()
+    {
+        warn!(guild = ?guild_id, "Could not update reward roles");
+        return Ok(0);
+    }
+
+    let sync = state.import_sync;
+    let mut done = 0;
+    let mut updated = 0;
+    for chunk in users.chunks(sync.batch_size.max(1)) {
+        if state.import_pause.is_paused() {
+            info!(guild = ?guild_id, done, total = users.len(), "Reward sync paused");
+            state.import_pause.wait().await;
+        }
+        let mut tasks = JoinSet::new();
+        for (user_id, xp) in chunk {
+            let level = mee6::LevelInfo::new(u64::try_from(*xp).unwrap_or(0)).level();
+            let level = i64::try_from(level).unwrap_or(i64::MAX);
+            let earned = earned_rewards(&rewards, level, strategy);
+            if earned.is_empty() {
+                continue;
+            }
+            if tasks.len() >= sync.concurrency.max(1) {
+                if let Some(result) = tasks.join_next().await {
+                    updated += usize::from(result??);
+                }
+            }
+            let http = state.client.clone();
+            let user_id = *user_id;
+            tasks.spawn(async move {
+                sync_member_rewards(http.as_ref(), guild_id, user_id, earned).await
+            });
+        }
+        while let Some(result) = tasks.join_next().await {
+            updated += usize::from(result??);
+        }
+        done += chunk.len();
+        info!(guild = ?guild_id, done, total = users.len(), updated, "Synced a batch of reward roles");
+        tokio::time::sleep(sync.interval).await;
+    }
+    Ok(updated)
+}
+
+async fn sync_member_rewards(
+    http: &impl DiscordHttp,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+    earned: Vec<RoleReward>,
+) -> Result<bool, Error> {
+    let Some(current) = http.member_roles(guild_id, user_id).await? else {
+        return Ok(false);
+    };
+    let Some(roles) = roles_with_rewards(&current, &earned) else {
+        return Ok(false);
+    };
+    http.set_member_roles(guild_id, user_id, &roles).await?;
+    Ok(true)
+}
+
+/// Get the rewards a user at this level should have, assuming `rewards` is sorted
+fn earned_rewards(rewards: &[RoleReward], level: i64, strategy: RewardStrategy) -> Vec<RoleReward> {
+    let earned = rewards.partition_point(|reward| reward.requirement <= level);
+    let mut held = rewards[..earned].to_vec();
+    match strategy {
+        RewardStrategy::KeepAll => {}
+        RewardStrategy::OneAtATime => {
+            held.drain(..earned.saturating_sub(1));
+        }
+        RewardStrategy::Ladder => {
+            if let Some(outgrown) = earned.checked_sub(2) {
+                held.remove(outgrown);
+            }
+        }
+    }
+    held
+}
+
+/// Returns the member's new role list, or None if they already have every earned role.
+/// Roles are never removed here, the listener cleans those up on the member's next message.
+fn roles_with_rewards(
+    current: &[Id<RoleMarker>],
+    earned: &[RoleReward],
+) -> Option<Vec<Id<RoleMarker>>> {
+    let missing = earned
+        .iter()
+        .map(|reward| reward.id)
+        .filter(|role| !current.contains(role));
+    let roles: Vec<Id<RoleMarker>> = current.iter().copied().chain(missing).collect();
+    (roles.len() != current.len()).then_some(roles)
+}
+
 async fn background_data_operation_wrapper(
     state: SlashState,
     respondable: Respondable,
@@ -177,3 +484,175 @@ async fn reset_guild_xp(
     xpd_database::delete_levels_guild(&state.db, guild_id).await?;
     Ok("Done. Thank you for using Experienced.".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::http::mock::MockHttp;
+
+    const REWARDS: [RoleReward; 2] = [
+        RoleReward {
+            id: Id::new(1),
+            requirement: 5,
+        },
+        RoleReward {
+            id: Id::new(2),
+            requirement: 10,
+        },
+    ];
+
+    #[test]
+    fn csv_import_skips_invalid_rows() {
+        let data = b"user_id,xp\n1,100\n2,-5\n0,10\nnot-an-id,10\n3,12.5\n4\n 5 , 20 \n";
+        let parsed = parse_csv_import(data).unwrap();
+        assert_eq!(
+            parsed.users,
+            [
+                ImportUser {
+                    id: Id::new(1),
+                    xp: 100,
+                    messages: None
+                },
+                ImportUser {
+                    id: Id::new(5),
+                    xp: 20,
+                    messages: None
+                }
+            ]
+        );
+        assert_eq!(parsed.skipped, 5);
+    }
+
+    #[test]
+    fn csv_import_reads_messages() {
+        let data = b"user_id,xp,messages\n1,100,40\n2,50,\n3,10,-1\n";
+        let parsed = parse_csv_import(data).unwrap();
+        assert_eq!(
+            parsed.users,
+            [
+                ImportUser {
+                    id: Id::new(1),
+                    xp: 100,
+                    messages: Some(40)
+                },
+                ImportUser {
+                    id: Id::new(2),
+                    xp: 50,
+                    messages: None
+                }
+            ]
+        );
+        assert_eq!(parsed.skipped, 1);
+    }
+
+    #[test]
+    fn csv_import_requires_header() {
+        assert!(matches!(
+            parse_csv_import(b"1,100\n"),
+            Err(Error::InvalidCsvImportHeader)
+        ));
+        assert!(matches!(
+            parse_csv_import(b"user_id,xp,level\n1,100,1\n"),
+            Err(Error::InvalidCsvImportHeader)
+        ));
+    }
+
+    #[test]
+    fn earned_rewards_respects_strategy() {
+        let ids = |strategy| -> Vec<Id<RoleMarker>> {
+            earned_rewards(&REWARDS, 10, strategy)
+                .iter()
+                .map(|reward| reward.id)
+                .collect()
+        };
+        assert!(earned_rewards(&REWARDS, 4, RewardStrategy::KeepAll).is_empty());
+        assert_eq!(ids(RewardStrategy::KeepAll), [Id::new(1), Id::new(2)]);
+        assert_eq!(ids(RewardStrategy::OneAtATime), [Id::new(2)]);
+        assert_eq!(ids(RewardStrategy::Ladder), [Id::new(2)]);
+
+        let mut three = REWARDS.to_vec();
+        three.push(RoleReward {
+            id: Id::new(3),
+            requirement: 15,
+        });
+        let ladder: Vec<Id<RoleMarker>> = earned_rewards(&three, 20, RewardStrategy::Ladder)
+            .iter()
+            .map(|reward| reward.id)
+            .collect();
+        assert_eq!(ladder, [Id::new(1), Id::new(3)]);
+    }
+
+    #[test]
+    fn roles_only_updated_when_missing() {
+        assert_eq!(
+            roles_with_rewards(&[Id::new(1), Id::new(2)], &REWARDS),
+            None
+        );
+        assert_eq!(
+            roles_with_rewards(&[Id::new(7)], &REWARDS),
+            Some(vec![Id::new(7), Id::new(1), Id::new(2)])
+        );
+    }
+
+    #[test]
+    fn import_slots() {
+        let imports = RunningImports::default();
+        let first = imports.start(Id::new(1), 2).unwrap();
+        assert!(matches!(
+            imports.start(Id::new(1), 2),
+            Err(Error::ImportAlreadyRunning)
+        ));
+        let second = imports.start(Id::new(2), 2).unwrap();
+        assert!(matches!(
+            imports.start(Id::new(3), 2),
+            Err(Error::TooManyImports)
+        ));
+        drop(first);
+        let _third = imports.start(Id::new(3), 2).unwrap();
+        drop(second);
+        let _again = imports.start(Id::new(2), 2).unwrap();
+    }
+
+    #[tokio::test]
+    async fn pausing_imports() {
+        let pause = ImportPause::default();
+        assert!(!pause.is_paused());
+        pause.wait().await;
+        assert!(!pause.set(true));
+        assert!(pause.is_paused());
+        let waiting = tokio::spawn({
+            let pause = pause.clone();
+            async move { pause.wait().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        assert!(pause.set(false));
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn syncing_rewards_only_touches_members_missing_roles() {
+        let guild = Id::new(1);
+        let other_role = Id::new(7);
+        let http = MockHttp::default()
+            .with_member(guild, Id::new(10), &[other_role])
+            .with_member(guild, Id::new(11), &[Id::new(1), Id::new(2)]);
+
+        let synced = sync_member_rewards(&http, guild, Id::new(10), REWARDS.to_vec());
+        assert!(synced.await.unwrap());
+        let mut roles = http.roles(guild, Id::new(10)).unwrap();
+        roles.sort_unstable();
+        assert_eq!(roles, [Id::new(1), Id::new(2), other_role]);
+
+        let synced = sync_member_rewards(&http, guild, Id::new(11), REWARDS.to_vec());
+        assert!(!synced.await.unwrap());
+        let synced = sync_member_rewards(&http, guild, Id::new(12), REWARDS.to_vec());
+        assert!(!synced.await.unwrap());
+        assert_eq!(http.roles(guild, Id::new(12)), None);
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use tokio::sync::OnceCell;
+use twilight_model::{
+    gateway::payload::incoming::MemberChunk,
+    id::{
+        marker::{GuildMarker, UserMarker},
+        Id,
+    },
+};
+use xpd_common::MemberDisplayInfo;
+
+use crate::{Error, SlashState};
+
+type MemberKey = (Id<GuildMarker>, Id<UserMarker>);
+
+/// One fetch of a member, whose answer everyone looking them up meanwhile shares.
+/// `None` if the fetch failed.
+type Flight = Arc<OnceCell<Option<MemberDisplayInfo>>>;
+
+/// Member fetches which are running right now, so that concurrent lookups of the same member
+/// wait for the first one instead of all asking Discord.
+#[derive(Clone, Debug, Default)]
+pub struct MemberFetches(Arc<Mutex<HashMap<MemberKey, Flight>>>);
+
+impl MemberFetches {
+    /// The fetch every lookup of this member shares until [`MemberFetches::land`] is called.
+    fn flight(&self, key: MemberKey) -> Flight {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(key)
+            .or_default()
+            .clone()
+    }
+
+    /// Forget a finished fetch, so later lookups use the cache or ask Discord again.
+    /// Lookups still holding `flight` keep its answer, and a newer fetch of the member is left alone.
+    fn land(&self, key: MemberKey, flight: &Flight) {
+        let mut flights = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if flights
+            .get(&key)
+            .is_some_and(|current| Arc::ptr_eq(current, flight))
+        {
+            flights.remove(&key);
+        }
+    }
+}
+
+/// Display info for a member, asking Discord and caching the answer if the gateway hasn't
+/// sent us the member. Users who aren't in the guild anymore are looked up without a nickname.
+/// # Errors
+/// If Discord can't be reached, or the user doesn't exist.
+pub async fn member_display_info(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+) -> Result<MemberDisplayInfo, Error> {
+    if let Some(info) = cached_member(state, guild_id, user_id) {
+        return Ok(info);
+    }
+    let key = (guild_id, user_id);
+    let flight = state.member_fetches.flight(key);
+    let info = flight
+        .get_or_init(|| async {
+            // A fetch which landed since we checked has cached the member already
+            if let Some(info) = cached_member(state, guild_id, user_id) {
+                return Some(info);
+            }
+            fetch_member(state, guild_id, user_id)
+                .await
+                .inspect_err(|source| {
+                    warn!(?source, guild = ?guild_id, user = ?user_id, "Could not fetch member");
+                })
+                .ok()
+        })
+        .await
+        .clone();
+    state.member_fetches.land(key, &flight);
+    info.ok_or(Error::MemberLookupFailed)
+}
+
+/// The member's tag to list them under, see [`MemberDisplayInfo::tag`],
+/// or "unknown user" if they can't be looked up
+pub async fn member_tag(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+) -> String {
+    member_display_info(state, guild_id, user_id)
+        .await
+        .map_or_else(|_| "unknown user".to_string(), |info| info.tag())
+}
+
+fn cached_member(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+) -> Option<MemberDisplayInfo> {
+    let member = state.cache.member(guild_id, user_id)?;
+    let user = state.cache.user(user_id)?;
+    Some(MemberDisplayInfo {
+        local_avatar: member.avatar(),
+        ..MemberDisplayInfo::from(user.clone()).with_nick(member.nick().map(ToOwned::to_owned))
+    })
+}
+
+async fn fetch_member(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+) -> Result<MemberDisplayInfo, Error> {
+    let member = match state.client.guild_member(guild_id, user_id).await {
+        Ok(member) => member.model().await?,
+        Err(source) if xpd_util::discord_error_code(&source) == Some(xpd_util::UNKNOWN_MEMBER) => {
+            let user = state.client.user(user_id).await?.model().await?;
+            return Ok(MemberDisplayInfo::from(user));
+        }
+        Err(source) => return Err(source.into()),
+    };
+    debug!(guild = ?guild_id, user = ?user_id, "Caching fetched member");
+    // A chunk of one member caches it without touching the guild's member count
+    state.cache.update(&MemberChunk {
+        chunk_count: 1,
+        chunk_index: 0,
+        guild_id,
+        members: vec![member.clone()],
+        nonce: None,
+        not_found: Vec::new(),
+        presences: Vec::new(),
+    });
+    Ok(MemberDisplayInfo::from(member))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fetches_share_a_flight() {
+        let fetches = MemberFetches::default();
+        let key = (Id::new(1), Id::new(2));
+        let first = fetches.flight(key);
+        assert!(Arc::ptr_eq(&first, &fetches.flight(key)));
+        assert!(!Arc::ptr_eq(
+            &first,
+            &fetches.flight((Id::new(1), Id::new(3)))
+        ));
+
+        fetches.land(key, &first);
+        let second = fetches.flight(key);
+        assert!(!Arc::ptr_eq(&first, &second));
+
+        // Landing an old fetch late doesn't forget the one that replaced it
+        fetches.land(key, &first);
+        assert!(Arc::ptr_eq(&second, &fetches.flight(key)));
+    }
+
+    #[tokio::test]
+    async fn lookups_share_the_answer() {
+        let fetches = MemberFetches::default();
+        let key = (Id::new(1), Id::new(2));
+        let first = fetches.flight(key);
+        let waiter = fetches.flight(key);
+        first.get_or_init(|| async { None }).await;
+        fetches.land(key, &first);
+        // The waiter gets the failure too, rather than fetching again
+        let answer = waiter.get_or_init(|| async { unreachable!() }).await;
+        assert_eq!(answer, &None);
+    }
+}
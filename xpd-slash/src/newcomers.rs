@@ -0,0 +1,53 @@
+use twilight_model::id::{marker::GuildMarker, Id};
+use xpd_database::UpdateGuildConfig;
+use xpd_slash_defs::newcomers::{
+    NewcomersCommand, NewcomersCommandJoinGrace, NewcomersCommandMinAccountAge,
+};
+
+use crate::{Error, SlashState, XpdSlashResponse};
+
+pub async fn process_newcomers(
+    command: NewcomersCommand,
+    guild_id: Id<GuildMarker>,
+    state: SlashState,
+) -> Result<XpdSlashResponse, Error> {
+    let message = match command {
+        NewcomersCommand::JoinGrace(j) => process_join_grace(state, guild_id, j).await?,
+        NewcomersCommand::MinAccountAge(m) => process_min_account_age(state, guild_id, m).await?,
+    };
+    Ok(XpdSlashResponse::with_embed_text(message).ephemeral(true))
+}
+
+async fn process_join_grace(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: NewcomersCommandJoinGrace,
+) -> Result<String, Error> {
+    let minutes: i16 = options.minutes.try_into()?;
+    let new_cfg = UpdateGuildConfig::new().join_grace(Some(minutes));
+    let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+    state.update_config(guild_id, config).await;
+    if minutes == 0 {
+        return Ok("New members will now earn XP as soon as they join.".to_string());
+    }
+    Ok(format!(
+        "New members will now have to be in the server for {minutes} minute(s) before they earn XP."
+    ))
+}
+
+async fn process_min_account_age(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: NewcomersCommandMinAccountAge,
+) -> Result<String, Error> {
+    let days: i16 = options.days.try_into()?;
+    let new_cfg = UpdateGuildConfig::new().min_account_age(Some(days));
+    let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+    state.update_config(guild_id, config).await;
+    if days == 0 {
+        return Ok("Accounts of any age will now earn XP.".to_string());
+    }
+    Ok(format!(
+        "Discord accounts will now have to be at least {days} day(s) old before they earn XP."
+    ))
+}
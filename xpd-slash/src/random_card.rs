@@ -0,0 +1,312 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use twilight_model::{
+    channel::message::{
+        component::{ActionRow, Button, ButtonStyle},
+        Component,
+    },
+    http::interaction::{InteractionResponse, InteractionResponseType},
+    id::{marker::GuildMarker, Id},
+};
+use twilight_util::builder::embed::{EmbedBuilder, ImageSource};
+use xpd_common::MemberDisplayInfo;
+use xpd_database::CardUpdate;
+use xpd_rank_card::customizations::{Color, Customizations};
+
+use crate::{Error, SlashState, XpdSlashResponse};
+
+/// Every component this module creates has a custom ID starting with this
+pub const COMPONENT_PREFIX: &str = "card-random";
+const REROLL_ID: &str = "card-random-reroll";
+const SAVE_ID_PREFIX: &str = "card-random-save:";
+/// Minimum WCAG contrast ratio between text and what it's drawn on (WCAG AA for normal text)
+pub const MIN_TEXT_CONTRAST: f64 = 4.5;
+
+pub async fn process_random(
+    invoker: MemberDisplayInfo,
+    guild_id: Option<Id<GuildMarker>>,
+    state: &SlashState,
+    locale: Option<&str>,
+) -> Result<XpdSlashResponse, Error> {
+    let colors = CardColors::generate(&mut StdRng::from_entropy());
+    preview(state, invoker, guild_id, colors, locale).await
+}
+
+pub async fn process_component(
+    custom_id: &str,
+    invoker: MemberDisplayInfo,
+    guild_id: Option<Id<GuildMarker>>,
+    locale: Option<&str>,
+    state: SlashState,
+) -> Result<InteractionResponse, Error> {
+    let response = if custom_id == REROLL_ID {
+        process_random(invoker, guild_id, &state, locale).await?
+    } else if let Some(encoded) = custom_id.strip_prefix(SAVE_ID_PREFIX) {
+        let colors = CardColors::decode(encoded).ok_or(Error::UnknownComponent)?;
+        xpd_database::update_card(&state.db, invoker.id.cast(), &colors.card_update()).await?;
+        let embed = EmbedBuilder::new()
+            .description("Saved your new card colors!")
+            .image(ImageSource::attachment(state.card_filename())?)
+            .build();
+        XpdSlashResponse::new()
+            .embeds([embed])
+            .components(Vec::new())
+    } else {
+        return Err(Error::UnknownComponent);
+    };
+    Ok(InteractionResponse {
+        kind: InteractionResponseType::UpdateMessage,
+        data: Some(response.into()),
+    })
+}
+
+async fn preview(
+    state: &SlashState,
+    invoker: MemberDisplayInfo,
+    guild_id: Option<Id<GuildMarker>>,
+    colors: CardColors,
+    locale: Option<&str>,
+) -> Result<XpdSlashResponse, Error> {
+    let current =
+        crate::levels::get_customizations_fields(state.clone(), invoker.id, guild_id).await?;
+    let user_stats = crate::manage_card::card_stats(state, invoker.id, guild_id).await?;
+    let card = crate::levels::gen_card_with(
+        state.clone(),
+        invoker,
+        guild_id,
+        colors.apply(current),
+        user_stats,
+        locale,
+    )
+    .await?;
+
+    let embed = EmbedBuilder::new()
+        .description("Here's a random color scheme! Save it to use it on your card.")
+        .image(ImageSource::attachment(state.card_filename())?)
+        .build();
+    let buttons = [
+        Button {
+            custom_id: Some(format!("{SAVE_ID_PREFIX}{}", colors.encode())),
+            disabled: false,
+            emoji: None,
+            label: Some("Save".to_string()),
+            style: ButtonStyle::Success,
+            url: None,
+        },
+        Button {
+            custom_id: Some(REROLL_ID.to_string()),
+            disabled: false,
+            emoji: None,
+            label: Some("Reroll".to_string()),
+            style: ButtonStyle::Secondary,
+            url: None,
+        },
+    ]
+    .map(Component::Button);
+    Ok(XpdSlashResponse::new()
+        .attachments([card])
+        .embeds([embed])
+        .components([Component::ActionRow(ActionRow {
+            components: buttons.to_vec(),
+        })])
+        .ephemeral(true))
+}
+
+/// Every color on a card, which is what random cards and presets pick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CardColors {
+    pub background: Color,
+    pub border: Color,
+    pub username: Color,
+    pub rank: Color,
+    pub level: Color,
+    pub progress_foreground: Color,
+    pub progress_background: Color,
+    pub foreground_xp_count: Color,
+    pub background_xp_count: Color,
+}
+
+impl CardColors {
+    /// Pick colors around one base hue, so the scheme looks intentional rather than noisy.
+    /// Lightness is kept in separate bands for backgrounds and text, so text stays readable.
+    fn generate(rng: &mut impl Rng) -> Self {
+        let hue = rng.gen_range(0.0..360.0);
+        let accent_hue = hue + rng.gen_range(150.0..210.0);
+        let (background_lightness, panel_lightness) = if rng.gen_bool(0.5) {
+            (rng.gen_range(0.08..0.16), rng.gen_range(0.22..0.3))
+        } else {
+            (rng.gen_range(0.88..0.95), rng.gen_range(0.7..0.78))
+        };
+
+        let background = Color::from_hsl(hue, rng.gen_range(0.2..0.45), background_lightness);
+        let border = Color::from_hsl(hue, rng.gen_range(0.3..0.6), panel_lightness);
+        let progress_foreground = Color::from_hsl(
+            accent_hue,
+            rng.gen_range(0.55..0.8),
+            rng.gen_range(0.45..0.6),
+        );
+        let progress_background = Color::from_hsl(hue, rng.gen_range(0.2..0.4), panel_lightness);
+
+        Self {
+            background,
+            border,
+            username: readable_on(hue, rng.gen_range(0.5..0.8), background),
+            rank: readable_on(hue + 30.0, rng.gen_range(0.5..0.8), background),
+            level: readable_on(accent_hue, rng.gen_range(0.5..0.8), background),
+            progress_foreground,
+            progress_background,
+            foreground_xp_count: readable_on(accent_hue, 0.3, progress_foreground),
+            background_xp_count: readable_on(hue, 0.3, progress_background),
+        }
+    }
+
+    const fn colors(&self) -> [Color; 9] {
+        [
+            self.background,
+            self.border,
+            self.username,
+            self.rank,
+            self.level,
+            self.progress_foreground,
+            self.progress_background,
+            self.foreground_xp_count,
+            self.background_xp_count,
+        ]
+    }
+
+    /// Compact form for a component custom ID, which is limited to 100 characters
+    fn encode(&self) -> String {
+        self.colors()
+            .map(|color| color.to_string().trim_start_matches('#').to_owned())
+            .join(",")
+    }
+
+    fn decode(encoded: &str) -> Option<Self> {
+        let colors: Vec<Color> = encoded
+            .split(',')
+            .map(|hex| Color::from_hex(&hex).ok())
+            .collect::<Option<_>>()?;
+        let [background, border, username, rank, level, progress_foreground, progress_background, foreground_xp_count, background_xp_count] =
+            colors.as_slice()
+        else {
+            return None;
+        };
+        Some(Self {
+            background: *background,
+            border: *border,
+            username: *username,
+            rank: *rank,
+            level: *level,
+            progress_foreground: *progress_foreground,
+            progress_background: *progress_background,
+            foreground_xp_count: *foreground_xp_count,
+            background_xp_count: *background_xp_count,
+        })
+    }
+
+    fn apply(self, customizations: Customizations) -> Customizations {
+        Customizations {
+            username: self.username,
+            rank: self.rank,
+            level: self.level,
+            border: self.border,
+            background: self.background,
+            progress_foreground: self.progress_foreground,
+            progress_foreground_2: None,
+            progress_background: self.progress_background,
+            background_xp_count: self.background_xp_count,
+            foreground_xp_count: self.foreground_xp_count,
+            ..customizations
+        }
+    }
+
+    pub fn card_update(self) -> CardUpdate {
+        CardUpdate {
+            username: Some(self.username.to_string()),
+            rank: Some(self.rank.to_string()),
+            level: Some(self.level.to_string()),
+            border: Some(self.border.to_string()),
+            background: Some(self.background.to_string()),
+            progress_background: Some(self.progress_background.to_string()),
+            progress_foreground: Some(self.progress_foreground.to_string()),
+            // a leftover gradient end wouldn't match the new colors
+            progress_foreground_2: Some(String::new()),
+            foreground_xp_count: Some(self.foreground_xp_count.to_string()),
+            background_xp_count: Some(self.background_xp_count.to_string()),
+            font: None,
+            toy_image: None,
+            card_layout: None,
+            card_layout_default: "classic.svg".to_string(),
+            subtitle: None,
+            progress_style: None,
+            card_size: None,
+            background_preset: None,
+        }
+    }
+}
+
+/// Find a color of this hue which is readable on `surface`, by moving the lightness
+/// towards white or black (whichever contrasts more) until the contrast is high enough.
+fn readable_on(hue: f64, saturation: f64, surface: Color) -> Color {
+    let white = Color::new(255, 255, 255);
+    let black = Color::new(0, 0, 0);
+    let (mut lightness, step, extreme) =
+        if surface.contrast_ratio(white) >= surface.contrast_ratio(black) {
+            (0.65, 0.05, white)
+        } else {
+            (0.35, -0.05, black)
+        };
+    while (0.0..=1.0).contains(&lightness) {
+        let candidate = Color::from_hsl(hue, saturation, lightness);
+        if candidate.contrast_ratio(surface) >= MIN_TEXT_CONTRAST {
+            return candidate;
+        }
+        lightness += step;
+    }
+    extreme
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_text_is_readable() {
+        for seed in 0..500 {
+            let colors = CardColors::generate(&mut StdRng::seed_from_u64(seed));
+            for text in [colors.username, colors.rank, colors.level] {
+                assert!(text.contrast_ratio(colors.background) >= MIN_TEXT_CONTRAST);
+            }
+            assert!(
+                colors
+                    .foreground_xp_count
+                    .contrast_ratio(colors.progress_foreground)
+                    >= MIN_TEXT_CONTRAST
+            );
+            assert!(
+                colors
+                    .background_xp_count
+                    .contrast_ratio(colors.progress_background)
+                    >= MIN_TEXT_CONTRAST
+            );
+        }
+    }
+
+    #[test]
+    fn custom_id_roundtrip() {
+        let colors = CardColors::generate(&mut StdRng::seed_from_u64(7));
+        let custom_id = format!("{SAVE_ID_PREFIX}{}", colors.encode());
+        assert!(custom_id.len() <= 100);
+        let decoded = CardColors::decode(custom_id.strip_prefix(SAVE_ID_PREFIX).unwrap());
+        assert_eq!(decoded, Some(colors));
+        assert_eq!(CardColors::decode("ffffff,000000"), None);
+    }
+
+    #[test]
+    fn saving_clears_the_gradient() {
+        let colors = CardColors::generate(&mut StdRng::seed_from_u64(7));
+        assert_eq!(
+            colors.card_update().progress_foreground_2,
+            Some(String::new())
+        );
+    }
+}
@@ -5,6 +5,7 @@ use twilight_model::{
         attachment::Attachment,
         interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
     },
+    id::{marker::UserMarker, Id},
 };
 use twilight_util::builder::embed::EmbedBuilder;
 
@@ -156,6 +157,18 @@ impl XpdSlashResponse {
         self.tts_o(Some(tts))
     }
 
+    /// Don't let this response ping anybody, no matter what its content mentions.
+    #[must_use]
+    pub fn no_pings(self) -> Self {
+        self.allowed_mentions(AllowedMentions::default())
+    }
+
+    /// Only let this response ping `user`, even if it mentions other users or roles.
+    #[must_use]
+    pub fn ping_only_user(self, user: Id<UserMarker>) -> Self {
+        self.allowed_mentions(xpd_common::ping_only_user(user))
+    }
+
     #[must_use]
     pub fn ephemeral(mut self, ephemeral: bool) -> Self {
         if let Some(flags) = &mut self.flags {
@@ -174,7 +187,10 @@ impl XpdSlashResponse {
 impl From<XpdSlashResponse> for InteractionResponseData {
     fn from(value: XpdSlashResponse) -> Self {
         Self {
-            allowed_mentions: value.allowed_mentions,
+            // Discord would parse every mention in the content otherwise
+            allowed_mentions: value
+                .allowed_mentions
+                .or_else(|| Some(AllowedMentions::default())),
             attachments: value.attachments,
             choices: value.choices,
             components: value.components,
@@ -1,43 +1,264 @@
 use std::fmt::Write;
 
-use twilight_model::{
-    channel::message::AllowedMentions,
-    id::{marker::GuildMarker, Id},
+use sqlx::PgConnection;
+use twilight_model::id::{
+    marker::{GuildMarker, RoleMarker, UserMarker},
+    Id,
 };
 use twilight_util::builder::embed::EmbedBuilder;
-use xpd_slash_defs::rewards::{RewardsCommand, RewardsCommandAdd, RewardsCommandRemove};
+use xpd_common::RoleReward;
+use xpd_database::UpdateGuildConfig;
+use xpd_slash_defs::rewards::{
+    RewardsCommand, RewardsCommandAdd, RewardsCommandAlerts, RewardsCommandBulk,
+    RewardsCommandClaims, RewardsCommandOnceAdd, RewardsCommandOnceRemove, RewardsCommandRemove,
+};
 
 use crate::{Error, SlashState, XpdSlashResponse};
 
 pub async fn process_rewards(
     cmd: RewardsCommand,
     guild_id: Id<GuildMarker>,
+    invoker: Id<UserMarker>,
     state: SlashState,
 ) -> Result<XpdSlashResponse, Error> {
     let contents = match cmd {
         RewardsCommand::Add(add) => process_rewards_add(add, state, guild_id).await,
         RewardsCommand::Remove(remove) => process_rewards_rm(remove, state, guild_id).await,
-        RewardsCommand::List(_list) => process_rewards_list(state, guild_id).await,
+        RewardsCommand::List(_list) => process_rewards_list(state, guild_id, invoker).await,
+        RewardsCommand::Bulk(bulk) => process_rewards_bulk(bulk, state, guild_id).await,
+        RewardsCommand::Alerts(alerts) => process_rewards_alerts(alerts, state, guild_id).await,
+        RewardsCommand::OnceAdd(add) => process_once_add(add, state, guild_id).await,
+        RewardsCommand::OnceRemove(remove) => process_once_rm(remove, state, guild_id).await,
+        RewardsCommand::Claims(claims) => process_claims(claims, state, guild_id).await,
     }?;
     Ok(XpdSlashResponse::new()
-        .allowed_mentions(AllowedMentions::default())
+        .no_pings()
         .ephemeral(true)
         .embeds([EmbedBuilder::new().description(contents).build()]))
 }
 
+async fn process_rewards_alerts(
+    options: RewardsCommandAlerts,
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+) -> Result<String, Error> {
+    let enabled = if let Some(enabled) = options.enabled {
+        let new_cfg = UpdateGuildConfig::new().role_alerts(Some(enabled));
+        let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+        state.update_config(guild_id, config).await;
+        enabled
+    } else {
+        xpd_database::guild_config(&state.db, guild_id)
+            .await?
+            .and_then(|config| config.role_alerts)
+            .unwrap_or(false)
+    };
+    Ok(if enabled {
+        "The server owner will get a DM the first time I can't give out each reward role, \
+        like when it's above my highest role."
+            .to_string()
+    } else {
+        "The server owner won't get DMs about reward roles I can't give out. \
+        Use `/config perms_checkup` to check on them yourself."
+            .to_string()
+    })
+}
+
 async fn process_rewards_add(
     options: RewardsCommandAdd,
     state: SlashState,
     guild_id: Id<GuildMarker>,
 ) -> Result<String, Error> {
-    xpd_database::add_reward_role(&state.db, guild_id, options.level, options.role.id).await?;
+    let mut txn = state.db.begin().await?;
+    let before = count_rewards_locked(txn.as_mut(), guild_id).await?;
+    xpd_database::add_reward_role(txn.as_mut(), guild_id, options.level, options.role.id).await?;
+    let rewards = xpd_database::guild_rewards(txn.as_mut(), guild_id)
+        .await?
+        .len();
+    check_reward_limit(&state, before, rewards)?;
+    txn.commit().await?;
+    state.invalidate_rewards(guild_id).await;
+    Ok(format!(
+        "Added role reward <@&{}> at level {}! This server has {rewards} of the {} role rewards it can have.",
+        options.role.id, options.level, state.max_rewards
+    ))
+}
+
+/// Refuse to leave a guild with more reward roles than it's allowed, because every level-up
+/// checks all of them. Call this before committing the transaction that added the rewards, with
+/// `before` from [`count_rewards_locked`].
+///
+/// Changes which don't add any rewards are always fine, so a guild which is over a lowered limit
+/// can still move its rewards around.
+pub const fn check_reward_limit(
+    state: &SlashState,
+    before: usize,
+    after: usize,
+) -> Result<(), Error> {
+    if after > before && after > state.max_rewards {
+        return Err(Error::TooManyRewards(state.max_rewards));
+    }
+    Ok(())
+}
+
+/// Lock the guild's rewards for the rest of the transaction `conn` is in, and count its reward roles.
+pub async fn count_rewards_locked(
+    conn: &mut PgConnection,
+    guild_id: Id<GuildMarker>,
+) -> Result<usize, Error> {
+    xpd_database::lock_guild_rewards(&mut *conn, guild_id).await?;
+    Ok(xpd_database::guild_rewards(conn, guild_id).await?.len())
+}
+
+async fn process_rewards_bulk(
+    options: RewardsCommandBulk,
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+) -> Result<String, Error> {
+    let rewards = bulk_rewards(&options.pairs())?;
+    let replace = options.replace.unwrap_or(false);
+    let mut txn = state.db.begin().await?;
+    let before = count_rewards_locked(txn.as_mut(), guild_id).await?;
+    if replace {
+        xpd_database::delete_guild_rewards(txn.as_mut(), guild_id).await?;
+    }
+    for reward in &rewards {
+        xpd_database::add_reward_role(txn.as_mut(), guild_id, reward.requirement, reward.id)
+            .await?;
+    }
+    let mut ladder = xpd_database::guild_rewards(txn.as_mut(), guild_id).await?;
+    check_reward_limit(&state, before, ladder.len())?;
+    txn.commit().await?;
     state.invalidate_rewards(guild_id).await;
+    ladder.sort_by(xpd_common::compare_rewards_requirement);
+
+    let mut data = String::with_capacity(64 + ladder.len() * 48);
+    let verb = if replace {
+        "Replaced rewards with"
+    } else {
+        "Added"
+    };
+    let pluralizer = if rewards.len() == 1 { "" } else { "s" };
+    writeln!(data, "{verb} {} role reward{pluralizer}.", rewards.len())?;
+    writeln!(data, "### Role rewards")?;
+    for reward in &ladder {
+        writeln!(data, "<@&{}> - Level {}", reward.id, reward.requirement)?;
+    }
+    Ok(data)
+}
+
+async fn process_once_add(
+    options: RewardsCommandOnceAdd,
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+) -> Result<String, Error> {
+    let name = options.name.trim();
+    if name.is_empty() {
+        return Err(Error::EmptyRewardName);
+    }
+    let mut txn = state.db.begin().await?;
+    xpd_database::lock_guild_rewards(txn.as_mut(), guild_id).await?;
+    let before = xpd_database::one_time_rewards(txn.as_mut(), guild_id)
+        .await?
+        .len();
+    xpd_database::add_one_time_reward(txn.as_mut(), guild_id, name, options.level).await?;
+    let rewards = xpd_database::one_time_rewards(txn.as_mut(), guild_id)
+        .await?
+        .len();
+    // These are checked on every level-up too, so they share the limit on reward roles
+    if rewards > before && rewards > state.max_rewards {
+        return Err(Error::TooManyOneTimeRewards(state.max_rewards));
+    }
+    txn.commit().await?;
     Ok(format!(
-        "Added role reward <@&{}> at level {}!",
-        options.role.id, options.level
+        "Added one-time reward **{name}** at level {}! \
+        Members get it the first time they level up to level {} or above, and never again after that, \
+        even if their XP is reset. I'll announce it where level-up messages go.",
+        options.level, options.level
     ))
 }
 
+async fn process_once_rm(
+    options: RewardsCommandOnceRemove,
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+) -> Result<String, Error> {
+    let name = options.name.trim();
+    if xpd_database::delete_one_time_reward(&state.db, guild_id, name).await? {
+        Ok(format!(
+            "Removed one-time reward **{name}**. Members who claimed it stay on record, \
+            so they won't get it again if it's added back."
+        ))
+    } else {
+        Ok(format!(
+            "There's no one-time reward called **{name}**. See them all with `/rewards claims`."
+        ))
+    }
+}
+
+async fn process_claims(
+    options: RewardsCommandClaims,
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+) -> Result<String, Error> {
+    let mut data = String::with_capacity(256);
+    if let Some(user) = options.user {
+        let claimed = xpd_database::claimed_rewards(&state.db, guild_id, user.id).await?;
+        if claimed.is_empty() {
+            return Ok(format!(
+                "<@{}> hasn't claimed any one-time rewards.",
+                user.id
+            ));
+        }
+        writeln!(data, "### One-time rewards claimed by <@{}>", user.id)?;
+        for claim in claimed {
+            writeln!(data, "**{}** - <t:{}:R>", claim.reward, claim.claimed_at)?;
+        }
+        return Ok(data);
+    }
+    let rewards = xpd_database::one_time_rewards(&state.db, guild_id).await?;
+    if rewards.is_empty() {
+        return Ok(
+            "This server doesn't have any one-time rewards yet. Add one with `/rewards once-add`!"
+                .to_string(),
+        );
+    }
+    writeln!(data, "### One-time rewards")?;
+    for reward in rewards {
+        let pluralizer = if reward.claims == 1 { "" } else { "s" };
+        writeln!(
+            data,
+            "**{}** - Level {} - claimed by {} member{pluralizer}",
+            reward.name, reward.requirement, reward.claims
+        )?;
+    }
+    writeln!(data, "\nPick a user to see when they claimed theirs.")?;
+    Ok(data)
+}
+
+/// Check that every role has a level and the other way around, and that no role is given twice.
+fn bulk_rewards(pairs: &[(Option<Id<RoleMarker>>, Option<i64>)]) -> Result<Vec<RoleReward>, Error> {
+    let mut rewards: Vec<RoleReward> = Vec::with_capacity(pairs.len());
+    for pair in pairs {
+        let (role, level) = match *pair {
+            (Some(role), Some(level)) => (role, level),
+            (None, None) => continue,
+            _ => return Err(Error::UnpairedBulkReward),
+        };
+        if rewards.iter().any(|reward| reward.id == role) {
+            return Err(Error::DuplicateBulkReward(role));
+        }
+        rewards.push(RoleReward {
+            id: role,
+            requirement: level,
+        });
+    }
+    if rewards.is_empty() {
+        return Err(Error::NoBulkRewards);
+    }
+    Ok(rewards)
+}
+
 async fn process_rewards_rm(
     options: RewardsCommandRemove,
     state: SlashState,
@@ -59,21 +280,77 @@ async fn process_rewards_rm(
 async fn process_rewards_list(
     state: SlashState,
     guild_id: Id<GuildMarker>,
+    invoker: Id<UserMarker>,
 ) -> Result<String, Error> {
     let mut roles = xpd_database::guild_rewards(&state.db, guild_id).await?;
     if roles.is_empty() {
-        return Ok("No role rewards set for this server".to_string());
+        return Ok(
+            "This server doesn't have any role rewards yet. Add one with `/rewards add`!"
+                .to_string(),
+        );
     }
-    let mut data = String::new();
+    roles.sort_by(xpd_common::compare_rewards_requirement);
 
-    roles.sort_by(|a, b| a.requirement.cmp(&b.requirement));
+    let invoker_xp = xpd_database::user_xp(&state.db, guild_id, invoker)
+        .await?
+        .unwrap_or(0);
+    let invoker_level = mee6::LevelInfo::new(invoker_xp.try_into().unwrap_or(0)).level();
+    let invoker_level = i64::try_from(invoker_level).unwrap_or(i64::MAX);
+    let current_idx = roles
+        .iter()
+        .rposition(|role| role.requirement <= invoker_level);
 
-    for role in roles {
-        writeln!(
+    let mut data = String::with_capacity(64 + roles.len() * 64);
+    writeln!(data, "### Role rewards")?;
+    for (idx, role) in roles.iter().enumerate() {
+        let xp_needed = mee6::xp_needed_for_level(role.requirement.try_into().unwrap_or(0));
+        write!(
             data,
-            "Role reward <@&{}> at level {}",
+            "<@&{}> - Level {} ({xp_needed} XP)",
             role.id, role.requirement
         )?;
+        if current_idx == Some(idx) {
+            write!(data, " - **your current reward**")?;
+        }
+        writeln!(data)?;
+    }
+    if current_idx.is_none() {
+        writeln!(data, "\nYou haven't earned any of these rewards yet.")?;
     }
     Ok(data)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bulk_reward_pairs() {
+        let rewards: Vec<(u64, i64)> = bulk_rewards(&[
+            (Some(Id::new(1)), Some(5)),
+            (None, None),
+            (Some(Id::new(2)), Some(10)),
+        ])
+        .unwrap()
+        .iter()
+        .map(|reward| (reward.id.get(), reward.requirement))
+        .collect();
+        assert_eq!(rewards, [(1, 5), (2, 10)]);
+        assert!(matches!(
+            bulk_rewards(&[(Some(Id::new(1)), None)]),
+            Err(Error::UnpairedBulkReward)
+        ));
+        assert!(matches!(
+            bulk_rewards(&[(None, Some(3))]),
+            Err(Error::UnpairedBulkReward)
+        ));
+        assert!(matches!(
+            bulk_rewards(&[(Some(Id::new(1)), Some(5)), (Some(Id::new(1)), Some(6))]),
+            Err(Error::DuplicateBulkReward(role)) if role == Id::new(1)
+        ));
+        assert!(matches!(
+            bulk_rewards(&[(None, None)]),
+            Err(Error::NoBulkRewards)
+        ));
+    }
+}
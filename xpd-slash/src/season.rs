@@ -0,0 +1,153 @@
+use std::{fmt::Write, time::Duration};
+
+use tokio_util::sync::CancellationToken;
+use twilight_model::{
+    channel::message::AllowedMentions,
+    id::{marker::GuildMarker, Id},
+};
+use twilight_util::builder::embed::EmbedBuilder;
+use xpd_database::EndedSeason;
+use xpd_slash_defs::config::ConfigCommandSeason;
+use xpd_util::LogError;
+
+use crate::{Error, SlashState};
+
+/// How often the worker looks for seasons that are over
+const SEASON_CHECK_INTERVAL: Duration = Duration::from_mins(10);
+/// How many due guilds are loaded from the database at once
+const SEASON_BATCH_SIZE: i64 = 20;
+/// Users named in a new season's announcement
+const ANNOUNCED_WINNERS: i64 = 3;
+
+pub async fn process_season_config(
+    state: SlashState,
+    guild_id: Id<GuildMarker>,
+    options: ConfigCommandSeason,
+) -> Result<String, Error> {
+    let Some(cadence) = options.cadence else {
+        let schedule = xpd_database::season_schedule(&state.db, guild_id).await?;
+        return Ok(schedule.map_or_else(
+            || "This server doesn't have seasons.".to_string(),
+            |schedule| {
+                format!(
+                    "Levels are reset {}. The current season ends <t:{}:R>.",
+                    schedule.cadence, schedule.next_reset
+                )
+            },
+        ));
+    };
+    let Some(cadence) = cadence.into() else {
+        let removed = xpd_database::delete_season_schedule(&state.db, guild_id).await?;
+        return Ok(if removed {
+            "Levels will no longer be reset at the end of each season.".to_string()
+        } else {
+            "This server doesn't have seasons.".to_string()
+        });
+    };
+    let schedule = xpd_database::set_season_schedule(&state.db, guild_id, cadence).await?;
+    Ok(format!(
+        "Levels will now be reset {cadence}. The first season ends <t:{}:R>. \
+        Final standings are announced in the level-up channel, if one is set.",
+        schedule.next_reset
+    ))
+}
+
+/// End every season that is over and announce the new ones, until `shutdown` is cancelled.
+pub async fn season_worker(state: SlashState, shutdown: CancellationToken) {
+    loop {
+        match shutdown.run_until_cancelled(end_due_seasons(&state)).await {
+            None => break,
+            Some(Ok(ended)) if ended > 0 => info!(ended, "Ended seasons"),
+            Some(Ok(_)) => {}
+            Some(Err(source)) => error!(?source, "Failed to end seasons"),
+        }
+        if shutdown
+            .run_until_cancelled(tokio::time::sleep(SEASON_CHECK_INTERVAL))
+            .await
+            .is_none()
+        {
+            break;
+        }
+    }
+}
+
+async fn end_due_seasons(state: &SlashState) -> Result<usize, Error> {
+    let mut ended = 0;
+    // Guilds whose season couldn't be ended are left for the next check,
+    // so they don't hold up everyone due after them
+    let mut failed = Vec::new();
+    loop {
+        let due = xpd_database::due_season_resets(&state.db, SEASON_BATCH_SIZE, &failed).await?;
+        if due.is_empty() {
+            return Ok(ended);
+        }
+        for guild in due {
+            // Ending the season moves the schedule forward, so a failed announcement isn't retried
+            let season = match xpd_database::end_season(&state.db, guild).await {
+                Ok(Some(season)) => season,
+                Ok(None) => continue,
+                Err(source) => {
+                    error!(?source, ?guild, "Failed to end season");
+                    failed.push(guild);
+                    continue;
+                }
+            };
+            ended += 1;
+            // Nothing was archived, so there's no standings to show and the season number is reused
+            if season.archived == 0 {
+                continue;
+            }
+            announce_season(state, guild, season)
+                .await
+                .log_error("Failed to announce new season");
+        }
+    }
+}
+
+async fn announce_season(
+    state: &SlashState,
+    guild: Id<GuildMarker>,
+    season: EndedSeason,
+) -> Result<(), Error> {
+    let Some(channel) = xpd_database::guild_config(&state.db, guild)
+        .await?
+        .and_then(|config| config.level_up_channel)
+    else {
+        return Ok(());
+    };
+    if !xpd_util::can_create_message(&state.cache, state.bot_id, channel)? {
+        debug!(
+            ?guild,
+            ?channel,
+            "Can't announce new season in level-up channel"
+        );
+        return Ok(());
+    }
+    let winners =
+        xpd_database::season_standings(&state.db, guild, season.season, ANNOUNCED_WINNERS).await?;
+    let mut description = String::new();
+    for winner in winners {
+        let _ = writeln!(
+            description,
+            "**#{}** <@{}> with {} XP",
+            winner.rank, winner.id, winner.xp
+        );
+    }
+    let _ = write!(
+        description,
+        "\nEveryone's levels have been reset. Season {} ends <t:{}:R>!",
+        season.season + 1,
+        season.next_reset
+    );
+    let embed = EmbedBuilder::new()
+        .title(format!("Season {} is over!", season.season))
+        .description(description)
+        .build();
+    state
+        .client
+        .create_message(channel)
+        .embeds(&[embed])
+        .allowed_mentions(Some(&AllowedMentions::default()))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,466 @@
+use std::fmt::Write;
+
+use twilight_model::{
+    application::interaction::{
+        message_component::MessageComponentInteractionData, modal::ModalInteractionData,
+    },
+    channel::{
+        message::{
+            component::{
+                ActionRow, Button, ButtonStyle, SelectMenu, SelectMenuOption, SelectMenuType,
+                TextInput, TextInputStyle,
+            },
+            Component,
+        },
+        ChannelType,
+    },
+    http::interaction::{InteractionResponse, InteractionResponseType},
+    id::{
+        marker::{ChannelMarker, GuildMarker, RoleMarker},
+        Id,
+    },
+};
+use twilight_util::builder::{embed::EmbedBuilder, InteractionResponseDataBuilder};
+use xpd_database::UpdateGuildConfig;
+
+use crate::{Error, SlashState, XpdSlashResponse};
+
+/// Every component and modal this module creates has a custom ID starting with this.
+/// The wizard keeps no state of its own, every ID says which step it belongs to.
+pub const COMPONENT_PREFIX: &str = "setup-";
+const CHANNEL_ID: &str = "setup-channel";
+const MESSAGE_ID: &str = "setup-message";
+const MESSAGE_MODAL_ID: &str = "setup-message-modal";
+const MESSAGE_INPUT_ID: &str = "setup-message-input";
+const REWARD_ID: &str = "setup-reward";
+const REWARD_MODAL_ID_PREFIX: &str = "setup-reward-level:";
+const REWARD_INPUT_ID: &str = "setup-reward-level-input";
+const COOLDOWN_ID: &str = "setup-cooldown";
+const SKIP_ID_PREFIX: &str = "setup-skip:";
+/// Cooldowns offered in the cooldown step, in seconds
+const COOLDOWN_PRESETS: [i16; 6] = [0, 15, 30, 60, 120, 300];
+
+/// One page of the wizard, in the order they are shown
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Step {
+    Channel,
+    Message,
+    Rewards,
+    Cooldown,
+    Done,
+}
+
+impl Step {
+    const fn next(self) -> Self {
+        match self {
+            Self::Channel => Self::Message,
+            Self::Message => Self::Rewards,
+            Self::Rewards => Self::Cooldown,
+            Self::Cooldown | Self::Done => Self::Done,
+        }
+    }
+
+    const fn id(self) -> &'static str {
+        match self {
+            Self::Channel => "channel",
+            Self::Message => "message",
+            Self::Rewards => "rewards",
+            Self::Cooldown => "cooldown",
+            Self::Done => "done",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Self> {
+        [
+            Self::Channel,
+            Self::Message,
+            Self::Rewards,
+            Self::Cooldown,
+            Self::Done,
+        ]
+        .into_iter()
+        .find(|step| step.id() == id)
+    }
+
+    const fn prompt(self) -> &'static str {
+        match self {
+            Self::Channel => {
+                "**Step 1 of 4: Level-up channel**\nPick the channel level-up messages get sent in. \
+                 If you skip this, they're sent wherever the member leveled up."
+            }
+            Self::Message => {
+                "**Step 2 of 4: Level-up message**\nWrite the message sent when someone levels up. \
+                 `{user_mention}` and `{level}` are filled in for you."
+            }
+            Self::Rewards => {
+                "**Step 3 of 4: Reward roles**\nPick a role, then the level it's granted at. \
+                 You can add as many as you like before moving on."
+            }
+            Self::Cooldown => {
+                "**Step 4 of 4: Cooldown**\nPick how long members have to wait between messages \
+                 that give XP."
+            }
+            Self::Done => "**All done!** Here's how this server is set up now:",
+        }
+    }
+
+    const fn skip_label(self) -> &'static str {
+        match self {
+            Self::Rewards => "Next",
+            _ => "Skip",
+        }
+    }
+}
+
+pub async fn process_setup(
+    guild_id: Id<GuildMarker>,
+    state: SlashState,
+) -> Result<XpdSlashResponse, Error> {
+    Ok(page(&state, guild_id, Step::Channel, None)
+        .await?
+        .ephemeral(true))
+}
+
+pub async fn process_component(
+    data: MessageComponentInteractionData,
+    guild_id: Id<GuildMarker>,
+    state: SlashState,
+) -> Result<InteractionResponse, Error> {
+    let (step, note) = if let Some(step) = data.custom_id.strip_prefix(SKIP_ID_PREFIX) {
+        let step = Step::from_id(step).ok_or(Error::UnknownComponent)?;
+        (step.next(), None)
+    } else if data.custom_id == CHANNEL_ID {
+        let channel: Id<ChannelMarker> = first_value(&data)?;
+        if !xpd_util::can_create_message(&state.cache, state.bot_id, channel)? {
+            return Err(Error::CantPostInLevelUpChannel);
+        }
+        let new_cfg = UpdateGuildConfig::new().level_up_channel(Some(channel));
+        let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+        state.update_config(guild_id, config).await;
+        let note = format!("Level-up messages will be sent in <#{channel}>.");
+        (Step::Channel.next(), Some(note))
+    } else if data.custom_id == MESSAGE_ID {
+        return message_modal(&state, guild_id).await;
+    } else if data.custom_id == REWARD_ID {
+        let role: Id<RoleMarker> = first_value(&data)?;
+        return Ok(reward_level_modal(role));
+    } else if data.custom_id == COOLDOWN_ID {
+        let cooldown: i16 = first_value(&data)?;
+        let new_cfg = UpdateGuildConfig::new().message_cooldown(Some(cooldown));
+        let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+        state.update_config(guild_id, config).await;
+        (Step::Cooldown.next(), None)
+    } else {
+        return Err(Error::UnknownComponent);
+    };
+    Ok(update(page(&state, guild_id, step, note).await?))
+}
+
+pub async fn process_modal_submit(
+    data: ModalInteractionData,
+    guild_id: Id<GuildMarker>,
+    state: SlashState,
+) -> Result<InteractionResponse, Error> {
+    let (step, note) = if data.custom_id == MESSAGE_MODAL_ID {
+        let template = form_field(&data, MESSAGE_INPUT_ID)?;
+        crate::config::validate_level_up_message(&template)?;
+        let new_cfg = UpdateGuildConfig::new().level_up_message(Some(template));
+        let config = xpd_database::update_guild_config(&state.db, guild_id, new_cfg).await?;
+        state.update_config(guild_id, config).await;
+        let note = "Saved your level-up message.".to_string();
+        (Step::Message.next(), Some(note))
+    } else if let Some(role) = data.custom_id.strip_prefix(REWARD_MODAL_ID_PREFIX) {
+        let role: Id<RoleMarker> = role.parse().map_err(|_| Error::UnknownComponent)?;
+        let level = parse_reward_level(&form_field(&data, REWARD_INPUT_ID)?)?;
+        let mut txn = state.db.begin().await?;
+        let before = crate::rewards::count_rewards_locked(txn.as_mut(), guild_id).await?;
+        xpd_database::add_reward_role(txn.as_mut(), guild_id, level, role).await?;
+        let rewards = xpd_database::guild_rewards(txn.as_mut(), guild_id)
+            .await?
+            .len();
+        crate::rewards::check_reward_limit(&state, before, rewards)?;
+        txn.commit().await?;
+        state.invalidate_rewards(guild_id).await;
+        let note = format!("Added role reward <@&{role}> at level {level}!");
+        // Stay on this step, so more than one reward can be added
+        (Step::Rewards, Some(note))
+    } else {
+        return Err(Error::UnknownComponent);
+    };
+    Ok(update(page(&state, guild_id, step, note).await?))
+}
+
+async fn page(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+    step: Step,
+    note: Option<String>,
+) -> Result<XpdSlashResponse, Error> {
+    let mut description = note.map(|note| format!("{note}\n\n")).unwrap_or_default();
+    description.push_str(step.prompt());
+    if step == Step::Done {
+        let config = xpd_database::guild_config(&state.db, guild_id)
+            .await?
+            .unwrap_or_default();
+        let rewards = xpd_database::guild_rewards(&state.db, guild_id)
+            .await?
+            .len();
+        let pluralizer = if rewards == 1 { "" } else { "s" };
+        let _ = write!(
+            description,
+            "\n{config}\n{rewards} role reward{pluralizer}\n\n\
+             Everything else can be changed with `/config`."
+        );
+    }
+    let embed = EmbedBuilder::new().description(description).build();
+    Ok(XpdSlashResponse::new()
+        .embeds([embed])
+        .components(step_components(step)))
+}
+
+fn step_components(step: Step) -> Vec<Component> {
+    let input = match step {
+        Step::Channel => Component::SelectMenu(SelectMenu {
+            channel_types: Some(vec![ChannelType::GuildText]),
+            custom_id: CHANNEL_ID.to_string(),
+            default_values: None,
+            disabled: false,
+            kind: SelectMenuType::Channel,
+            max_values: Some(1),
+            min_values: Some(1),
+            options: None,
+            placeholder: Some("Level-up channel".to_string()),
+        }),
+        Step::Message => Component::Button(Button {
+            custom_id: Some(MESSAGE_ID.to_string()),
+            disabled: false,
+            emoji: None,
+            label: Some("Write message".to_string()),
+            style: ButtonStyle::Primary,
+            url: None,
+        }),
+        Step::Rewards => Component::SelectMenu(SelectMenu {
+            channel_types: None,
+            custom_id: REWARD_ID.to_string(),
+            default_values: None,
+            disabled: false,
+            kind: SelectMenuType::Role,
+            max_values: Some(1),
+            min_values: Some(1),
+            options: None,
+            placeholder: Some("Reward role".to_string()),
+        }),
+        Step::Cooldown => Component::SelectMenu(SelectMenu {
+            channel_types: None,
+            custom_id: COOLDOWN_ID.to_string(),
+            default_values: None,
+            disabled: false,
+            kind: SelectMenuType::Text,
+            max_values: Some(1),
+            min_values: Some(1),
+            options: Some(COOLDOWN_PRESETS.map(cooldown_option).to_vec()),
+            placeholder: Some("Message cooldown".to_string()),
+        }),
+        Step::Done => return Vec::new(),
+    };
+    let skip = Component::Button(Button {
+        custom_id: Some(format!("{SKIP_ID_PREFIX}{}", step.id())),
+        disabled: false,
+        emoji: None,
+        label: Some(step.skip_label().to_string()),
+        style: ButtonStyle::Secondary,
+        url: None,
+    });
+    // Select menus take up a whole row, buttons can share one
+    if matches!(input, Component::SelectMenu(_)) {
+        vec![
+            Component::ActionRow(ActionRow {
+                components: vec![input],
+            }),
+            Component::ActionRow(ActionRow {
+                components: vec![skip],
+            }),
+        ]
+    } else {
+        vec![Component::ActionRow(ActionRow {
+            components: vec![input, skip],
+        })]
+    }
+}
+
+fn cooldown_option(seconds: i16) -> SelectMenuOption {
+    let label = match seconds {
+        0 => "No cooldown".to_string(),
+        1..60 => format!("{seconds} seconds"),
+        60 => "1 minute".to_string(),
+        _ => format!("{} minutes", seconds / 60),
+    };
+    SelectMenuOption {
+        default: false,
+        description: None,
+        emoji: None,
+        label,
+        value: seconds.to_string(),
+    }
+}
+
+async fn message_modal(
+    state: &SlashState,
+    guild_id: Id<GuildMarker>,
+) -> Result<InteractionResponse, Error> {
+    let current = xpd_database::guild_config(&state.db, guild_id)
+        .await?
+        .and_then(|config| config.level_up_message)
+        .map(|message| message.input_value());
+    let input = TextInput {
+        custom_id: MESSAGE_INPUT_ID.to_string(),
+        label: "Level-up message".to_string(),
+        max_length: Some(crate::config::MAX_LEVEL_UP_MESSAGE_LEN.try_into()?),
+        min_length: Some(1),
+        placeholder: Some("{user_mention} has reached level {level}!".to_string()),
+        required: Some(true),
+        style: TextInputStyle::Paragraph,
+        value: current,
+    };
+    Ok(modal(
+        MESSAGE_MODAL_ID.to_string(),
+        "Level-up message",
+        input,
+    ))
+}
+
+fn reward_level_modal(role: Id<RoleMarker>) -> InteractionResponse {
+    let input = TextInput {
+        custom_id: REWARD_INPUT_ID.to_string(),
+        label: "Level to grant the role at".to_string(),
+        max_length: Some(6),
+        min_length: Some(1),
+        placeholder: Some("10".to_string()),
+        required: Some(true),
+        style: TextInputStyle::Short,
+        value: None,
+    };
+    modal(
+        format!("{REWARD_MODAL_ID_PREFIX}{role}"),
+        "Reward level",
+        input,
+    )
+}
+
+fn modal(custom_id: String, title: &str, input: TextInput) -> InteractionResponse {
+    InteractionResponse {
+        kind: InteractionResponseType::Modal,
+        data: Some(
+            InteractionResponseDataBuilder::new()
+                .components([Component::ActionRow(ActionRow {
+                    components: vec![Component::TextInput(input)],
+                })])
+                .custom_id(custom_id)
+                .title(title)
+                .build(),
+        ),
+    }
+}
+
+/// Wizard pages replace the previous page, rather than piling up new messages
+fn update(response: XpdSlashResponse) -> InteractionResponse {
+    InteractionResponse {
+        kind: InteractionResponseType::UpdateMessage,
+        data: Some(response.into()),
+    }
+}
+
+fn first_value<T: std::str::FromStr>(data: &MessageComponentInteractionData) -> Result<T, Error> {
+    data.values
+        .first()
+        .and_then(|value| value.parse().ok())
+        .ok_or(Error::UnknownComponent)
+}
+
+fn form_field(data: &ModalInteractionData, id: &str) -> Result<String, Error> {
+    data.components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find(|field| field.custom_id == id)
+        .and_then(|field| field.value.clone())
+        .ok_or(Error::NoFormField)
+}
+
+fn parse_reward_level(input: &str) -> Result<i64, Error> {
+    input
+        .trim()
+        .parse()
+        .ok()
+        .filter(|level| *level >= 1)
+        .ok_or(Error::InvalidRewardLevel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_round_trip() {
+        let mut step = Step::Channel;
+        let mut seen = vec![step];
+        while step != Step::Done {
+            assert_eq!(Step::from_id(step.id()), Some(step));
+            step = step.next();
+            seen.push(step);
+        }
+        assert_eq!(
+            seen,
+            [
+                Step::Channel,
+                Step::Message,
+                Step::Rewards,
+                Step::Cooldown,
+                Step::Done
+            ]
+        );
+        assert_eq!(Step::Done.next(), Step::Done);
+        assert_eq!(Step::from_id("nonsense"), None);
+    }
+
+    #[test]
+    fn ids_are_prefixed() {
+        for id in [
+            CHANNEL_ID,
+            MESSAGE_ID,
+            MESSAGE_MODAL_ID,
+            REWARD_ID,
+            REWARD_MODAL_ID_PREFIX,
+            COOLDOWN_ID,
+            SKIP_ID_PREFIX,
+        ] {
+            assert!(id.starts_with(COMPONENT_PREFIX), "{id}");
+            assert!(!id.starts_with(crate::config::COMPONENT_PREFIX), "{id}");
+        }
+    }
+
+    #[test]
+    fn reward_levels() {
+        assert_eq!(parse_reward_level(" 10 ").unwrap(), 10);
+        assert!(parse_reward_level("0").is_err());
+        assert!(parse_reward_level("-3").is_err());
+        assert!(parse_reward_level("ten").is_err());
+    }
+
+    #[test]
+    fn cooldown_labels() {
+        let labels: Vec<String> = COOLDOWN_PRESETS
+            .map(|seconds| cooldown_option(seconds).label)
+            .to_vec();
+        assert_eq!(
+            labels,
+            [
+                "No cooldown",
+                "15 seconds",
+                "30 seconds",
+                "1 minute",
+                "2 minutes",
+                "5 minutes"
+            ]
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::{fmt::Write, time::Duration};
+
+use tokio_util::sync::CancellationToken;
+use twilight_model::id::{
+    marker::{GuildMarker, UserMarker},
+    Id,
+};
+use twilight_util::builder::embed::EmbedBuilder;
+use xpd_database::WeeklyProgress;
+use xpd_slash_defs::summary::WeeklySummaryCommand;
+use xpd_util::LogError;
+
+use crate::{Error, SlashState, XpdSlashResponse};
+
+/// How often the worker looks for users who are due a summary
+const SUMMARY_CHECK_INTERVAL: Duration = Duration::from_hours(1);
+/// How many due users are loaded from the database at once
+const SUMMARY_BATCH_SIZE: i64 = 50;
+/// Time between two summary DMs, to keep well clear of Discord's DM rate limits
+const SUMMARY_SEND_INTERVAL: Duration = Duration::from_secs(1);
+/// Servers listed in one summary, the rest are counted at the end
+const MAX_SUMMARY_GUILDS: usize = 10;
+
+pub async fn process_weekly_summary(
+    command: WeeklySummaryCommand,
+    invoker: Id<UserMarker>,
+    state: SlashState,
+) -> Result<XpdSlashResponse, Error> {
+    let mut txn = state.db.begin().await?;
+    xpd_database::set_weekly_summary(txn.as_mut(), invoker, command.enabled).await?;
+    if command.enabled {
+        // Start counting from now, so the first summary covers a whole week
+        xpd_database::take_weekly_summary(txn.as_mut(), invoker).await?;
+    }
+    txn.commit().await?;
+    let message = if command.enabled {
+        "You'll get a DM every week with the XP you gained. \
+        Make sure you allow DMs from servers you share with me!"
+    } else {
+        "You won't get weekly summaries anymore."
+    };
+    Ok(XpdSlashResponse::with_embed_text(message).ephemeral(true))
+}
+
+/// DM every user who opted in their progress once a week, until `shutdown` is cancelled.
+pub async fn summary_worker(state: SlashState, shutdown: CancellationToken) {
+    loop {
+        match shutdown
+            .run_until_cancelled(send_due_summaries(&state))
+            .await
+        {
+            None => break,
+            Some(Ok(sent)) if sent > 0 => info!(sent, "Sent weekly summaries"),
+            Some(Ok(_)) => {}
+            Some(Err(source)) => error!(?source, "Failed to send weekly summaries"),
+        }
+        if shutdown
+            .run_until_cancelled(tokio::time::sleep(SUMMARY_CHECK_INTERVAL))
+            .await
+            .is_none()
+        {
+            break;
+        }
+    }
+}
+
+async fn send_due_summaries(state: &SlashState) -> Result<usize, Error> {
+    let mut sent = 0;
+    loop {
+        let due = xpd_database::due_weekly_summaries(&state.db, SUMMARY_BATCH_SIZE).await?;
+        if due.is_empty() {
+            return Ok(sent);
+        }
+        for user in due {
+            // Taking the summary marks it as sent, so a user who can't be DMed isn't retried
+            let progress = xpd_database::take_weekly_summary(&state.db, user).await?;
+            let Some(text) = summary_text(&progress, |guild| guild_name(state, guild)) else {
+                continue;
+            };
+            send_summary(state, user, text)
+                .await
+                .log_error("Failed to send weekly summary");
+            sent += 1;
+            tokio::time::sleep(SUMMARY_SEND_INTERVAL).await;
+        }
+    }
+}
+
+async fn send_summary(state: &SlashState, user: Id<UserMarker>, text: String) -> Result<(), Error> {
+    let channel = state
+        .client
+        .create_private_channel(user)
+        .await?
+        .model()
+        .await?;
+    let embed = EmbedBuilder::new()
+        .title("Your week in levels")
+        .description(text)
+        .build();
+    match state
+        .client
+        .create_message(channel.id)
+        .embeds(&[embed])
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(source)
+            if xpd_util::discord_error_code(&source) == Some(xpd_util::CANNOT_MESSAGE_USER) =>
+        {
+            debug!(?user, "Skipping weekly summary for user with closed DMs");
+            Ok(())
+        }
+        Err(source) => Err(source.into()),
+    }
+}
+
+fn guild_name(state: &SlashState, guild_id: Id<GuildMarker>) -> String {
+    state.cache.guild(guild_id).map_or_else(
+        || format!("Server {guild_id}"),
+        |guild| guild.name().to_owned(),
+    )
+}
+
+/// A line for every server the user gained XP in, most XP first.
+/// Returns None if they gained none anywhere, since that isn't worth a DM.
+fn summary_text(
+    progress: &[WeeklyProgress],
+    guild_name: impl Fn(Id<GuildMarker>) -> String,
+) -> Option<String> {
+    let mut gains: Vec<(i64, &WeeklyProgress)> = progress
+        .iter()
+        .map(|guild| (guild.xp - guild.previous.map_or(0, |(xp, _)| xp), guild))
+        .filter(|(gained, _)| *gained > 0)
+        .collect();
+    if gains.is_empty() {
+        return None;
+    }
+    gains.sort_unstable_by_key(|(gained, guild)| (-gained, guild.guild));
+
+    let mut text = String::new();
+    for (gained, guild) in gains.iter().take(MAX_SUMMARY_GUILDS) {
+        let old_level = level(guild.previous.map_or(0, |(xp, _)| xp));
+        let new_level = level(guild.xp);
+        let _ = write!(
+            text,
+            "**{}**: +{} XP",
+            guild_name(guild.guild),
+            xpd_common::format_int(None, *gained)
+        );
+        let _ = if new_level > old_level {
+            write!(text, ", level {old_level} → {new_level}")
+        } else {
+            write!(text, ", level {new_level}")
+        };
+        let _ = match guild.previous {
+            Some((_, old_rank)) if old_rank != guild.rank => {
+                writeln!(text, ", rank #{old_rank} → #{}", guild.rank)
+            }
+            _ => writeln!(text, ", rank #{}", guild.rank),
+        };
+    }
+    if let Some(more) = gains
+        .len()
+        .checked_sub(MAX_SUMMARY_GUILDS)
+        .filter(|more| *more > 0)
+    {
+        let _ = writeln!(text, "…and {more} more servers");
+    }
+    text.push_str("\n-# Stop these DMs with `/weekly-summary enabled:False`");
+    Some(text)
+}
+
+fn level(xp: i64) -> u64 {
+    mee6::LevelInfo::new(u64::try_from(xp).unwrap_or(0)).level()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn progress(
+        guild: u64,
+        xp: i64,
+        rank: i64,
+        previous: Option<(i64, i64)>,
+    ) -> WeeklyProgress {
+        WeeklyProgress {
+            guild: Id::new(guild),
+            xp,
+            rank,
+            previous,
+        }
+    }
+
+    #[test]
+    fn summaries_list_gains() {
+        let name = |guild: Id<GuildMarker>| format!("G{guild}");
+        assert_eq!(summary_text(&[], name), None);
+        assert_eq!(
+            summary_text(&[progress(1, 500, 3, Some((500, 2)))], name),
+            None
+        );
+
+        let text = summary_text(
+            &[
+                progress(1, 200, 3, Some((150, 3))),
+                progress(2, 500, 1, Some((100, 4))),
+                progress(3, 120, 7, None),
+            ],
+            name,
+        )
+        .unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "**G2**: +400 XP, level 1 → 3, rank #4 → #1");
+        assert_eq!(lines[1], "**G3**: +120 XP, level 0 → 1, rank #7");
+        assert_eq!(lines[2], "**G1**: +50 XP, level 1, rank #3");
+        assert!(text.contains("/weekly-summary enabled:False"));
+    }
+
+    #[test]
+    fn summaries_are_capped() {
+        let many: Vec<WeeklyProgress> = (1..=12)
+            .map(|guild| progress(guild, 100, 1, None))
+            .collect();
+        let text = summary_text(&many, |guild| guild.to_string()).unwrap();
+        assert_eq!(
+            text.lines().filter(|line| line.starts_with("**")).count(),
+            MAX_SUMMARY_GUILDS
+        );
+        assert!(text.contains("…and 2 more servers"));
+    }
+}
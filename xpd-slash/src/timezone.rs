@@ -0,0 +1,83 @@
+use chrono::DateTime;
+use chrono_tz::{Tz, TZ_VARIANTS};
+use twilight_model::{
+    application::command::{CommandOptionChoice, CommandOptionChoiceValue},
+    id::{marker::UserMarker, Id},
+};
+use xpd_slash_defs::timezone::TimezoneCommand;
+
+use crate::{Error, SlashState, XpdSlashResponse};
+
+pub async fn process_timezone(
+    command: TimezoneCommand,
+    invoker: Id<UserMarker>,
+    state: SlashState,
+) -> Result<XpdSlashResponse, Error> {
+    let message = if let Some(name) = command.timezone {
+        let timezone: Tz = name
+            .trim()
+            .parse()
+            .map_err(|_| Error::UnknownTimezone(name))?;
+        xpd_database::set_user_timezone(&state.db, invoker, Some(timezone.name())).await?;
+        format!("Times will now be shown in `{}`.", timezone.name())
+    } else {
+        xpd_database::set_user_timezone(&state.db, invoker, None).await?;
+        "Times will now be shown in UTC.".to_string()
+    };
+    Ok(XpdSlashResponse::with_embed_text(message).ephemeral(true))
+}
+
+/// Get a user's preferred timezone, falling back to UTC if it is unset or no longer known.
+pub async fn user_timezone(state: &SlashState, user: Id<UserMarker>) -> Result<Tz, Error> {
+    let timezone = xpd_database::user_timezone(&state.db, user)
+        .await?
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Tz::UTC);
+    Ok(timezone)
+}
+
+/// Format a unix timestamp (in seconds) as a date and time in the given timezone.
+pub fn format_timestamp(unix_seconds: i64, timezone: Tz) -> Option<String> {
+    let time = DateTime::from_timestamp(unix_seconds, 0)?.with_timezone(&timezone);
+    Some(time.format("%Y-%m-%d %H:%M %Z").to_string())
+}
+
+pub fn timezone_choices(input: &str) -> Vec<CommandOptionChoice> {
+    let input = input.to_lowercase();
+    TZ_VARIANTS
+        .iter()
+        .filter(|tz| tz.name().to_lowercase().contains(&input))
+        .take(25)
+        .map(|tz| CommandOptionChoice {
+            name: tz.name().to_owned(),
+            name_localizations: None,
+            value: CommandOptionChoiceValue::String(tz.name().to_owned()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_in_timezone() {
+        // 2024-01-01 12:00:00 UTC
+        let timestamp = 1_704_110_400;
+        assert_eq!(
+            format_timestamp(timestamp, Tz::UTC).unwrap(),
+            "2024-01-01 12:00 UTC"
+        );
+        assert_eq!(
+            format_timestamp(timestamp, Tz::Europe__Stockholm).unwrap(),
+            "2024-01-01 13:00 CET"
+        );
+    }
+
+    #[test]
+    fn choices_match_case_insensitively() {
+        let choices = timezone_choices("stockholm");
+        assert_eq!(choices.len(), 1);
+        assert_eq!(choices[0].name, "Europe/Stockholm");
+    }
+}
@@ -1,4 +1,10 @@
+use std::net::{IpAddr, SocketAddr};
+
 use twilight_cache_inmemory::{CacheableRole, InMemoryCache};
+use twilight_http::{
+    api_error::{ApiError, GeneralApiError},
+    error::ErrorType,
+};
 use twilight_model::{
     guild::Permissions,
     id::{
@@ -107,6 +113,27 @@ pub enum PermissionCheckError {
     UnknownPositionForOwnHighestRole,
 }
 
+/// Discord's JSON error code for a role which does not exist (anymore)
+pub const UNKNOWN_ROLE: u64 = 10011;
+/// Discord's JSON error code for a user who is not a member of the guild
+pub const UNKNOWN_MEMBER: u64 = 10007;
+/// Discord's JSON error code for a request the bot doesn't have the permissions for
+pub const MISSING_PERMISSIONS: u64 = 50013;
+/// Discord's JSON error code for a user who doesn't accept DMs from the bot
+pub const CANNOT_MESSAGE_USER: u64 = 50007;
+
+/// Get the JSON error code from a Discord API error response, if this was one
+#[must_use]
+pub fn discord_error_code(error: &twilight_http::Error) -> Option<u64> {
+    match error.kind() {
+        ErrorType::Response {
+            error: ApiError::General(GeneralApiError { code, .. }),
+            ..
+        } => Some(*code),
+        _ => None,
+    }
+}
+
 pub trait LogError {
     fn log_error(&self, msg: &str);
 }
@@ -125,3 +152,130 @@ pub fn snowflake_to_timestamp<T>(id: Id<T>) -> i64 {
     // this is safe, because dividing an u64 by 1000 ensures it is a valid i64
     ((id.get() >> 22) / 1000).try_into().unwrap_or(0)
 }
+
+/// Whether `ip` is this machine, a private network or otherwise not on the public internet.
+/// Requests guild admins point the bot at must never go to these, or they could reach internal services.
+#[must_use]
+pub fn is_internal_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                // Shared address space for carrier-grade NAT, 100.64.0.0/10
+                || (first == 100 && (second & 0b1100_0000) == 64)
+                // Benchmarking, 198.18.0.0/15
+                || (first == 198 && (second & 0b1111_1110) == 18)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_internal_ip(IpAddr::V4(mapped));
+            }
+            let segments = ip.segments();
+            let first = segments[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, fc00::/7
+                || (first & 0xfe00) == 0xfc00
+                // Link-local, fe80::/10
+                || (first & 0xffc0) == 0xfe80
+                // NAT64, 64:ff9b::/96, which translates to any IPv4 address
+                || segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0]
+        }
+    }
+}
+
+/// Resolve `host` and check that every address it points to is public, see [`is_internal_ip`].
+/// IPv6 hosts may be written with or without the brackets URLs put around them.
+/// # Errors
+/// If the host can't be resolved, or any of its addresses is internal.
+pub async fn resolve_public_host(
+    host: &str,
+    port: u16,
+) -> Result<Vec<SocketAddr>, PublicHostError> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    if addrs.is_empty() {
+        return Err(PublicHostError::NoAddresses);
+    }
+    if let Some(internal) = addrs.iter().find(|addr| is_internal_ip(addr.ip())) {
+        return Err(PublicHostError::Internal(internal.ip()));
+    }
+    Ok(addrs)
+}
+
+/// A DNS resolver for `reqwest` which refuses hosts that resolve to internal addresses, see [`is_internal_ip`].
+/// Checking a host before a request isn't enough on its own, because it can resolve somewhere else by the time
+/// the request connects.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            // reqwest fills in the port itself
+            let addrs = resolve_public_host(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PublicHostError {
+    #[error("Could not resolve host: {0}")]
+    Lookup(#[from] std::io::Error),
+    #[error("Host has no addresses")]
+    NoAddresses,
+    #[error("Host resolves to internal address {0}")]
+    Internal(IpAddr),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_ips() {
+        for internal in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "100.64.0.1",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "198.18.0.1",
+            "198.19.255.255",
+            "64:ff9b::7f00:1",
+        ] {
+            assert!(is_internal_ip(internal.parse().unwrap()), "{internal}");
+        }
+        for public in [
+            "1.1.1.1",
+            "100.128.0.1",
+            "198.20.0.1",
+            "2606:4700:4700::1111",
+            "64:ff9b:1::1",
+        ] {
+            assert!(!is_internal_ip(public.parse().unwrap()), "{public}");
+        }
+    }
+
+    #[tokio::test]
+    async fn resolver_refuses_internal_hosts() {
+        use reqwest::dns::Resolve;
+
+        let name = "localhost".parse().unwrap();
+        assert!(PublicResolver.resolve(name).await.is_err());
+    }
+}